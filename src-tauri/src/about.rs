@@ -0,0 +1,128 @@
+// 关于窗口：显示版本、数据目录、设备标识、当前壁纸来源和接口地址以及最近的日志，
+// 可以打开数据目录，或把这些信息连同脱敏后的配置复制到剪贴板，附在问题反馈中
+use std::{fs, sync::Mutex};
+use log::info;
+use serde::Serialize;
+use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::{
+    api, client_version, get_app_data_dir, get_wallpaper_dir, i18n::tr, load_config, lock_state, logging, market_for, portable,
+    AppError, AppState, Result, DEVICE_ID_OFF, UUID_FILE_NAME,
+};
+
+const ABOUT_WINDOW_LABEL: &str = "about";
+// 窗口中显示的日志行数，诊断信息中附带的行数
+const WINDOW_LOG_LINES: usize = 50;
+const DIAGNOSTICS_LOG_LINES: usize = 200;
+// 复制诊断信息时隐去的配置项
+const REDACTED_FIELDS: [&str; 6] =
+    ["proxy_password", "apod_api_key", "unsplash_access_key", "post_change_hook", "control_api_token", "webhook_url"];
+
+#[derive(Serialize)]
+pub(crate) struct AboutInfo {
+    version: String,
+    client_version: String,
+    data_dir: String,
+    portable: bool,
+    wallpaper_dir: String,
+    // 关闭设备标识或尚未生成时为 None
+    device_uuid: Option<String>,
+    refresh_mode: String,
+    provider: String,
+    endpoint: String,
+    market: Option<String>,
+    recent_log: Vec<String>,
+}
+
+// 只读取已有的设备标识，不为了展示而生成新的
+fn read_device_uuid() -> Result<Option<String>> {
+    if load_config().map(|config| config.device_id_mode).unwrap_or_default() == DEVICE_ID_OFF {
+        return Ok(None);
+    }
+    match fs::read_to_string(get_app_data_dir()?.join(UUID_FILE_NAME)) {
+        Ok(uuid) => Ok(Some(uuid.trim().to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn collect(state: &Mutex<AppState>, log_lines: usize) -> Result<AboutInfo> {
+    let refresh_mode = lock_state(state).refresh_mode;
+    let is_china = refresh_mode.is_china() || !refresh_mode.is_daily();
+    Ok(AboutInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        client_version: client_version(),
+        data_dir: get_app_data_dir()?.to_string_lossy().into_owned(),
+        portable: portable::is_enabled(),
+        wallpaper_dir: get_wallpaper_dir()?.to_string_lossy().into_owned(),
+        device_uuid: read_device_uuid()?,
+        refresh_mode: refresh_mode.as_str().to_string(),
+        provider: api::wallpaper_provider_id(),
+        endpoint: api::current_endpoint(is_china),
+        market: market_for(is_china),
+        recent_log: logging::recent_lines(log_lines)?,
+    })
+}
+
+// 配置转为 JSON 后把密码、API key 和自定义命令替换为占位符，未填写的保持为空
+fn redacted_config() -> Result<String> {
+    let mut config = serde_json::to_value(load_config()?)?;
+    if let Some(fields) = config.as_object_mut() {
+        for field in REDACTED_FIELDS {
+            if let Some(value) = fields.get_mut(field).filter(|value| value.as_str().is_some_and(|s| !s.is_empty())) {
+                *value = "<redacted>".into();
+            }
+        }
+    }
+    Ok(serde_json::to_string_pretty(&config)?)
+}
+
+pub(crate) fn show_window(app: &tauri::AppHandle) -> Result<()> {
+    if let Some(window) = app.get_webview_window(ABOUT_WINDOW_LABEL) {
+        window.set_focus()?;
+        return Ok(());
+    }
+    WebviewWindowBuilder::new(app, ABOUT_WINDOW_LABEL, WebviewUrl::App("about.html".into()))
+        .title(tr("关于 Bing Wallpaper"))
+        .inner_size(560.0, 640.0)
+        .build()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn get_about_info(state: tauri::State<'_, Mutex<AppState>>) -> Result<AboutInfo> {
+    collect(&state, WINDOW_LOG_LINES)
+}
+
+#[tauri::command]
+pub(crate) fn open_data_dir() -> Result<()> {
+    open::that(get_app_data_dir()?)?;
+    Ok(())
+}
+
+// 诊断信息为纯文本：基本信息、脱敏后的配置和最近的日志
+#[tauri::command]
+pub(crate) fn copy_diagnostics(app: tauri::AppHandle, state: tauri::State<'_, Mutex<AppState>>) -> Result<()> {
+    let about = collect(&state, DIAGNOSTICS_LOG_LINES)?;
+    let report = format!(
+        "version: {}\nclient-version: {}\ndata dir: {}\nportable: {}\nwallpaper dir: {}\ndevice uuid: {}\nrefresh mode: {}\nprovider: {}\nendpoint: {}\nmarket: {}\n\nconfig:\n{}\n\nrecent log:\n{}\n",
+        about.version,
+        about.client_version,
+        about.data_dir,
+        about.portable,
+        about.wallpaper_dir,
+        about.device_uuid.as_deref().unwrap_or("-"),
+        about.refresh_mode,
+        about.provider,
+        about.endpoint,
+        about.market.as_deref().unwrap_or("-"),
+        redacted_config()?,
+        about.recent_log.join("\n"),
+    );
+    app.clipboard()
+        .write_text(report)
+        .map_err(|e| AppError::OsSet(format!("Failed to write clipboard: {}", e)))?;
+    info!("Copied diagnostics to clipboard");
+    Ok(())
+}
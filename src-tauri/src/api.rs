@@ -0,0 +1,215 @@
+// 壁纸来源的选择：必应壁纸默认请求 bing.wdbyte.com（或自定义地址），连续失败后改为直接请求必应官方接口；
+// 另外可以切换到 Windows 聚焦、NASA 每日天文图或 Unsplash
+use std::{
+    future::Future,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use chrono::NaiveDate;
+use log::{info, warn};
+use wallpaper_core::{
+    fetch_date as fetch_provider_date, provider, ApodProvider, BingProvider, Provider, Resolution, SpotlightProvider, UnsplashProvider, WallpaperInfo,
+    WdbyteProvider,
+};
+
+use crate::{client_version, device_uuid, http, i18n::tr, load_config, scheduler, AppError, Result};
+
+// 壁纸来源，为空时使用必应
+pub(crate) const PROVIDER_BING: &str = "bing";
+pub(crate) const PROVIDER_SPOTLIGHT: &str = "spotlight";
+pub(crate) const PROVIDER_APOD: &str = "apod";
+pub(crate) const PROVIDER_UNSPLASH: &str = "unsplash";
+pub(crate) const PROVIDERS: [(&str, &str); 4] = [
+    (PROVIDER_BING, "必应每日壁纸"),
+    (PROVIDER_SPOTLIGHT, "Windows 聚焦"),
+    (PROVIDER_APOD, "NASA 每日天文图"),
+    (PROVIDER_UNSPLASH, "Unsplash"),
+];
+
+// 接口来源：auto 优先 wdbyte、失败后切换到必应，wdbyte 和 bing 只使用对应的接口
+pub(crate) const SOURCE_AUTO: &str = "auto";
+pub(crate) const SOURCE_WDBYTE: &str = "wdbyte";
+pub(crate) const SOURCE_BING: &str = "bing";
+// 自动模式下主接口连续失败达到该次数后，请求失败时改用必应官方接口
+const FALLBACK_FAILURE_THRESHOLD: u32 = 2;
+
+// 主接口连续失败的次数，任意一次成功后清零
+static PRIMARY_FAILURES: AtomicU32 = AtomicU32::new(0);
+
+pub(crate) fn wallpaper_provider_id() -> String {
+    let provider = load_config().map(|config| config.wallpaper_provider).unwrap_or_default();
+    if PROVIDERS.iter().any(|(id, _)| *id == provider) { provider } else { PROVIDER_BING.to_string() }
+}
+
+// 今天实际使用的来源：按星期的规则指定了来源时优先使用
+fn active_provider_id() -> String {
+    match scheduler::today_rule() {
+        Some(rule) if PROVIDERS.iter().any(|(id, _)| *id == rule) => rule,
+        _ => wallpaper_provider_id(),
+    }
+}
+
+// 必应以外的来源，各自实现 Provider，这里统一分发
+enum OtherProvider {
+    Spotlight(SpotlightProvider),
+    Apod(ApodProvider),
+    Unsplash(UnsplashProvider),
+}
+
+impl Provider for OtherProvider {
+    fn name(&self) -> &'static str {
+        match self {
+            OtherProvider::Spotlight(provider) => provider.name(),
+            OtherProvider::Apod(provider) => provider.name(),
+            OtherProvider::Unsplash(provider) => provider.name(),
+        }
+    }
+
+    async fn fetch_today(&self, resolution: Resolution) -> wallpaper_core::Result<WallpaperInfo> {
+        match self {
+            OtherProvider::Spotlight(provider) => provider.fetch_today(resolution).await,
+            OtherProvider::Apod(provider) => provider.fetch_today(resolution).await,
+            OtherProvider::Unsplash(provider) => provider.fetch_today(resolution).await,
+        }
+    }
+
+    async fn fetch_archive(&self, days: u32) -> wallpaper_core::Result<Vec<WallpaperInfo>> {
+        match self {
+            OtherProvider::Spotlight(provider) => provider.fetch_archive(days).await,
+            OtherProvider::Apod(provider) => provider.fetch_archive(days).await,
+            OtherProvider::Unsplash(provider) => provider.fetch_archive(days).await,
+        }
+    }
+}
+
+// 当前选择的是必应时返回 None，由 fetch 处理主接口和备用接口
+fn other_provider(is_china: bool) -> Result<Option<OtherProvider>> {
+    let config = load_config().unwrap_or_default();
+    let provider = match active_provider_id().as_str() {
+        PROVIDER_SPOTLIGHT => OtherProvider::Spotlight(SpotlightProvider::new(http::client()?, is_china)),
+        PROVIDER_APOD => OtherProvider::Apod(ApodProvider::new(http::client()?, Some(config.apod_api_key))),
+        PROVIDER_UNSPLASH => {
+            if config.unsplash_access_key.is_empty() {
+                return Err(AppError::Config(tr("请先在设置中填写 Unsplash Access Key").to_string()));
+            }
+            let collection = Some(config.unsplash_collection);
+            OtherProvider::Unsplash(UnsplashProvider::new(http::client()?, &config.unsplash_access_key, collection))
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(provider))
+}
+
+fn api_source() -> String {
+    load_config().map(|config| config.api_source).unwrap_or_default()
+}
+
+fn api_base_url() -> Option<String> {
+    load_config().ok().map(|config| config.api_base_url).filter(|url| !url.is_empty())
+}
+
+fn wdbyte_provider(is_china: bool, market: Option<&str>) -> Result<WdbyteProvider> {
+    let uuid = device_uuid()?;
+    Ok(WdbyteProvider::new(http::client()?, is_china, &client_version(), uuid.as_deref())
+        .with_market(market.map(str::to_string))
+        .with_base_url(api_base_url()))
+}
+
+fn bing_provider(is_china: bool, market: Option<&str>) -> Result<BingProvider> {
+    Ok(BingProvider::new(http::client()?, is_china).with_market(market.map(str::to_string)))
+}
+
+// 当前实际请求的接口地址，供关于窗口和诊断信息展示；自动模式下已切换到备用接口时返回必应官方地址
+pub(crate) fn current_endpoint(is_china: bool) -> String {
+    let bing_url = if is_china { provider::BING_CHINA_URL } else { provider::BING_GLOBAL_URL };
+    match active_provider_id().as_str() {
+        PROVIDER_SPOTLIGHT => provider::SPOTLIGHT_API_URL.to_string(),
+        PROVIDER_APOD => provider::APOD_API_URL.to_string(),
+        PROVIDER_UNSPLASH => provider::UNSPLASH_API_URL.to_string(),
+        _ => {
+            let source = api_source();
+            let fallen_back = PRIMARY_FAILURES.load(Ordering::Relaxed) >= FALLBACK_FAILURE_THRESHOLD;
+            if source == SOURCE_BING || (source != SOURCE_WDBYTE && fallen_back) {
+                bing_url.to_string()
+            } else {
+                api_base_url().unwrap_or_else(|| provider::DEFAULT_BASE_URL.to_string())
+            }
+        }
+    }
+}
+
+// 按必应的接口来源设置发出请求；primary 和 fallback 是对两种接口的同一个操作
+async fn fetch<T, P, PF, F, FF>(is_china: bool, market: Option<&str>, primary: P, fallback: F) -> Result<T>
+where
+    P: FnOnce(WdbyteProvider) -> PF,
+    PF: Future<Output = wallpaper_core::Result<T>>,
+    F: FnOnce(BingProvider) -> FF,
+    FF: Future<Output = wallpaper_core::Result<T>>,
+{
+    let source = api_source();
+    if source == SOURCE_BING {
+        return Ok(fallback(bing_provider(is_china, market)?).await?);
+    }
+    let error = match primary(wdbyte_provider(is_china, market)?).await {
+        Ok(value) => {
+            if PRIMARY_FAILURES.swap(0, Ordering::Relaxed) >= FALLBACK_FAILURE_THRESHOLD {
+                info!("Primary wallpaper API is back");
+            }
+            return Ok(value);
+        }
+        Err(e) => e,
+    };
+    if source == SOURCE_WDBYTE {
+        return Err(error.into());
+    }
+    let failures = PRIMARY_FAILURES.fetch_add(1, Ordering::Relaxed) + 1;
+    if failures < FALLBACK_FAILURE_THRESHOLD {
+        return Err(error.into());
+    }
+    warn!("Primary wallpaper API failed {} times ({}), falling back to Bing", failures, error);
+    Ok(fallback(bing_provider(is_china, market)?).await?)
+}
+
+pub(crate) async fn fetch_today(is_china: bool, market: Option<&str>, resolution: Resolution) -> Result<WallpaperInfo> {
+    if let Some(provider) = other_provider(is_china)? {
+        return Ok(provider.fetch_today(resolution).await?);
+    }
+    fetch(
+        is_china,
+        market,
+        |provider| async move { provider.fetch_today(resolution).await },
+        |provider| async move { provider.fetch_today(resolution).await },
+    )
+    .await
+}
+
+pub(crate) async fn fetch_archive(is_china: bool, market: Option<&str>, days: u32) -> Result<Vec<WallpaperInfo>> {
+    if let Some(provider) = other_provider(is_china)? {
+        return Ok(provider.fetch_archive(days).await?);
+    }
+    fetch(
+        is_china,
+        market,
+        |provider| async move { provider.fetch_archive(days).await },
+        |provider| async move { provider.fetch_archive(days).await },
+    )
+    .await
+}
+
+// 某一天的壁纸，不限于菜单中展示的最近几天，只要来源的归档中还有即可
+pub(crate) async fn fetch_date(
+    is_china: bool,
+    market: Option<&str>,
+    date: NaiveDate,
+    resolution: Resolution,
+) -> Result<WallpaperInfo> {
+    if let Some(provider) = other_provider(is_china)? {
+        return Ok(fetch_provider_date(&provider, date, resolution).await?);
+    }
+    fetch(
+        is_china,
+        market,
+        |provider| async move { fetch_provider_date(&provider, date, resolution).await },
+        |provider| async move { fetch_provider_date(&provider, date, resolution).await },
+    )
+    .await
+}
@@ -0,0 +1,56 @@
+// 深色外观下使用调暗的壁纸：macOS 切换到深色模式时，为当前壁纸生成一张降低亮度的副本并设置，
+// 切回浅色时换回原图。副本保存在数据目录的子目录中，只保留最近几张
+use std::path::{Path, PathBuf};
+use log::{info, warn};
+
+use crate::{cache, get_app_data_dir, load_config, processing, AppError, Result};
+
+const DARK_VARIANT_DIR: &str = "dark";
+// 亮度系数，保留足够的细节同时不在深色菜单栏和窗口后显得刺眼
+const DARK_BRIGHTNESS: f32 = 0.55;
+
+#[cfg(target_os = "macos")]
+pub(crate) fn is_dark_appearance() -> bool {
+    // 浅色模式下该键不存在，命令返回非零退出码
+    std::process::Command::new("defaults")
+        .args(["read", "-g", "AppleInterfaceStyle"])
+        .output()
+        .map(|output| output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "Dark")
+        .unwrap_or(false)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub(crate) fn is_dark_appearance() -> bool {
+    false
+}
+
+fn dark_variant_path(path: &Path) -> Result<PathBuf> {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    Ok(get_app_data_dir()?.join(DARK_VARIANT_DIR).join(format!("{}_dark.jpg", stem)))
+}
+
+fn create_dark_variant(path: &Path, dest: &Path) -> Result<()> {
+    let mut image = processing::decode(path)?;
+    processing::scale_brightness(&mut image, DARK_BRIGHTNESS);
+    processing::save_jpeg(&image, dest)?;
+    info!("Created dark variant {:?}", dest);
+    let dir = dest.parent().ok_or_else(|| AppError::Config(format!("Invalid path: {:?}", dest)))?;
+    cache::keep_newest(dir, processing::MAX_PROCESSED_FILES)
+}
+
+// 返回实际要设置到桌面的图片：开启了该选项且系统为深色外观时使用调暗的副本，其余情况使用原图
+pub(crate) fn display_path(path: &Path) -> PathBuf {
+    if !load_config().map(|config| config.dark_variant_enabled).unwrap_or(false) || !is_dark_appearance() {
+        return path.to_path_buf();
+    }
+    let result = dark_variant_path(path).and_then(|dest| {
+        if !dest.is_file() {
+            create_dark_variant(path, &dest)?;
+        }
+        Ok(dest)
+    });
+    result.unwrap_or_else(|e| {
+        warn!("Failed to prepare dark variant, using original: {}", e);
+        path.to_path_buf()
+    })
+}
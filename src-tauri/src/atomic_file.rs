@@ -0,0 +1,165 @@
+// 崩溃安全的文件写入：先写同目录下的 .part 临时文件并刷到磁盘，再改名替换目标文件。
+// 写到一半时程序被结束，目标文件保持原来的内容；遗留的临时文件在启动和退出时删除
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use log::{info, warn};
+
+use crate::{cache, get_app_data_dir, get_wallpaper_dir, Result};
+
+const PARTIAL_EXTENSION: &str = "part";
+
+// 与下载的临时文件命名相同，在目标文件名后加 .part
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(PARTIAL_EXTENSION);
+    path.with_file_name(name)
+}
+
+fn write_temp<F>(temp: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<()>,
+{
+    let mut writer = BufWriter::new(File::create(temp)?);
+    write(&mut writer)?;
+    let file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+// 由 write 写入临时文件，成功后改名；失败时删除临时文件，目标文件不受影响
+pub(crate) fn write_with<F>(path: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<()>,
+{
+    let temp = temp_path(path);
+    if let Err(e) = write_temp(&temp, write).and_then(|()| Ok(fs::rename(&temp, path)?)) {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+pub(crate) fn write(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    write_with(path, |writer| Ok(writer.write_all(contents.as_ref())?))
+}
+
+// 删除应用数据目录（含子目录）中遗留的 .part 文件，返回删除的数量
+fn remove_partial_files(dir: &Path) -> Result<usize> {
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            removed += remove_partial_files(&path)?;
+            continue;
+        }
+        if !file_type.is_file() || path.extension().and_then(|ext| ext.to_str()) != Some(PARTIAL_EXTENSION) {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => removed += 1,
+            Err(e) => warn!("Failed to remove partial file {:?}: {}", path, e),
+        }
+    }
+    Ok(removed)
+}
+
+// 保存目录中大小为 0 的图片是旧版本写入中断留下的，删除后需要时会重新下载。
+// 自定义的保存目录可能是用户的图片文件夹，与清理临时文件一样只删除由本应用下载的壁纸
+fn remove_empty_images(dir: &Path) -> Result<usize> {
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if !cache::is_image(&path) || !entry.metadata().is_ok_and(|metadata| metadata.is_file() && metadata.len() == 0) {
+            continue;
+        }
+        if !cache::is_app_file(&path) {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => removed += 1,
+            Err(e) => warn!("Failed to remove empty wallpaper {:?}: {}", path, e),
+        }
+    }
+    Ok(removed)
+}
+
+// 启动时调用：清理上次运行被中断时留下的临时文件和空图片
+pub(crate) fn recover() -> Result<()> {
+    let app_dir = get_app_data_dir()?;
+    let wallpaper_dir = get_wallpaper_dir()?;
+    let mut removed = remove_partial_files(&app_dir)?;
+    // 自定义的保存目录可能是用户的文件夹，只清理顶层中由本应用写入的临时文件
+    if !wallpaper_dir.starts_with(&app_dir) {
+        removed += cache::remove_partial_downloads()?;
+    }
+    removed += remove_empty_images(&wallpaper_dir)?;
+    if removed > 0 {
+        info!("Removed {} files left by interrupted writes", removed);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppError;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bing-wallpaper-test-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_write() {
+        let dir = temp_dir();
+        let path = dir.join("config.json");
+        write(&path, "old").unwrap();
+        write(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!temp_path(&path).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_with_failure_keeps_original() {
+        let dir = temp_dir();
+        let path = dir.join("config.json");
+        write(&path, "old").unwrap();
+        let result = write_with(&path, |writer| {
+            writer.write_all(b"half")?;
+            Err(AppError::Parse("interrupted".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        assert!(!temp_path(&path).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_remove_partial_files() {
+        let dir = temp_dir();
+        fs::create_dir_all(dir.join("crash_reports")).unwrap();
+        for name in ["config.json.part", "crash_reports/crash-1.txt.part", "config.json", "crash_reports/crash-1.txt"] {
+            fs::write(dir.join(name), "x").unwrap();
+        }
+        assert_eq!(remove_partial_files(&dir).unwrap(), 2);
+        assert!(!dir.join("config.json.part").exists());
+        assert!(!dir.join("crash_reports/crash-1.txt.part").exists());
+        assert!(dir.join("config.json").exists());
+        assert!(dir.join("crash_reports/crash-1.txt").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_temp_path() {
+        assert_eq!(temp_path(Path::new("/data/config.json")), Path::new("/data/config.json.part"));
+    }
+}
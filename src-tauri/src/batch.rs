@@ -0,0 +1,120 @@
+// 批量下载最近若干天的归档壁纸：限制并发数，已缓存或被跳过的图片不再下载。
+// 进度记录在状态中显示在托盘菜单，同时广播给窗口
+use std::sync::Mutex;
+use futures_util::{stream, StreamExt};
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+use crate::{
+    api, blocklist::Blocklist, download_lock, download_wallpaper, effective_resolution, i18n::{tr, tr_fmt},
+    is_wallpaper_exists, lock_state, market_for, notify, report_error, run_guarded_async, schedule_menu_update, AppState,
+    Result, WallpaperInfo,
+};
+
+pub(crate) const BATCH_DAYS: [u32; 3] = [7, 14, 30];
+// 同时进行的下载数，避免占满带宽或触发接口限流
+const CONCURRENCY: usize = 3;
+const PROGRESS_EVENT: &str = "batch-download-progress";
+
+// total 为 0 表示仍在获取归档列表
+#[derive(Clone, Copy, Default, Serialize)]
+pub(crate) struct Progress {
+    pub(crate) done: usize,
+    pub(crate) failed: usize,
+    pub(crate) total: usize,
+}
+
+// 进度为 None 表示批量下载已结束
+fn set_progress(app: &tauri::AppHandle, progress: Option<Progress>) {
+    lock_state(&app.state::<Mutex<AppState>>()).batch_progress = progress;
+    if let Err(e) = app.emit(PROGRESS_EVENT, progress) {
+        warn!("Failed to emit {}: {}", PROGRESS_EVENT, e);
+    }
+    schedule_menu_update(app);
+}
+
+// 归档中的同一张图片按当前分辨率换算文件名后去重
+fn pending_downloads(items: Vec<WallpaperInfo>, app: &tauri::AppHandle) -> Vec<WallpaperInfo> {
+    let resolution = effective_resolution(app);
+    let blocklist = Blocklist::load();
+    let mut pending: Vec<WallpaperInfo> = Vec::new();
+    for item in items.into_iter().map(|item| item.with_resolution(resolution)) {
+        if is_wallpaper_exists(&item.file_name)
+            || blocklist.contains_file(&item.file_name)
+            || pending.iter().any(|existing| existing.file_name == item.file_name)
+        {
+            continue;
+        }
+        pending.push(item);
+    }
+    pending
+}
+
+// 区域跟随当前刷新模式（自动模式按最近一次探测的结果），未开启每日刷新时使用中国区
+async fn run(app: &tauri::AppHandle, days: u32) -> Result<Progress> {
+    let mode = lock_state(&app.state::<Mutex<AppState>>()).refresh_mode;
+    let is_china = mode.is_china() || !mode.is_daily();
+    let items = api::fetch_archive(is_china, market_for(is_china).as_deref(), days).await?;
+    {
+        let state = app.state::<Mutex<AppState>>();
+        let mut state = lock_state(&state);
+        state.history.merge(items.clone());
+        if let Err(e) = state.history.save() {
+            warn!("Failed to save wallpaper history: {}", e);
+        }
+    }
+    let pending = pending_downloads(items, app);
+    let mut progress = Progress { total: pending.len(), ..Progress::default() };
+    set_progress(app, Some(progress));
+    if pending.is_empty() {
+        return Ok(progress);
+    }
+
+    // 整批只取一次下载锁，批内的并发下载写入的都是不同的文件
+    let _lock = download_lock::acquire().await?;
+    let mut downloads = stream::iter(&pending)
+        .map(|item| async move { (item, download_wallpaper(item).await) })
+        .buffer_unordered(CONCURRENCY);
+    while let Some((item, result)) = downloads.next().await {
+        match result {
+            Ok(_) => progress.done += 1,
+            Err(e) => {
+                warn!("Failed to download {}: {}", item.file_name, e);
+                progress.failed += 1;
+            }
+        }
+        set_progress(app, Some(progress));
+    }
+    info!("Batch download of {} days finished: {} downloaded, {} failed", days, progress.done, progress.failed);
+    Ok(progress)
+}
+
+// 同一时间只进行一批，进行中再次触发时忽略
+pub(crate) fn start(app: &tauri::AppHandle, days: u32) {
+    {
+        let state = app.state::<Mutex<AppState>>();
+        let mut state = lock_state(&state);
+        if state.batch_progress.is_some() {
+            return;
+        }
+        state.batch_progress = Some(Progress::default());
+    }
+    schedule_menu_update(app);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        run_guarded_async(&app, "批量下载", async {
+            match run(&app, days).await {
+                Ok(progress) if progress.total == 0 => notify(&app, tr("批量下载完成"), tr("没有需要下载的新壁纸")),
+                Ok(progress) => notify(
+                    &app,
+                    tr("批量下载完成"),
+                    &tr_fmt("已下载 {} 张壁纸，{} 张失败", &[&progress.done.to_string(), &progress.failed.to_string()]),
+                ),
+                Err(e) => report_error(&app, "批量下载失败", &e),
+            }
+        })
+        .await;
+        set_progress(&app, None);
+    });
+}
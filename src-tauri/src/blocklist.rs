@@ -0,0 +1,59 @@
+use std::path::Path;
+use chrono::NaiveDate;
+use log::{info, warn};
+
+use crate::{store, Resolution, Result, WallpaperInfo};
+
+// 用户跳过的壁纸，保存在元信息数据库中；定时刷新、轮播和离线模式都不会再设置这些图片
+#[derive(Default)]
+pub(crate) struct Blocklist {
+    entries: Vec<WallpaperInfo>,
+}
+
+// 同一张图片在不同分辨率下文件名不同（_1920x1080 / _UHD），比较时去掉分辨率部分
+fn image_key(file_name: &str) -> String {
+    [Resolution::FullHd, Resolution::Uhd]
+        .iter()
+        .fold(file_name.to_string(), |name, resolution| name.replace(&format!("_{}", resolution.as_str()), ""))
+}
+
+impl Blocklist {
+    pub(crate) fn load() -> Self {
+        match Self::read_from_disk() {
+            Ok(blocklist) => blocklist,
+            Err(e) => {
+                warn!("Failed to load blocklist: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    fn read_from_disk() -> Result<Self> {
+        Ok(Blocklist { entries: store::load_marked(store::Mark::Blocked)? })
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        store::save_marked(store::Mark::Blocked, &self.entries)
+    }
+
+    pub(crate) fn contains_file(&self, file_name: &str) -> bool {
+        let key = image_key(file_name);
+        self.entries.iter().any(|entry| image_key(&entry.file_name) == key)
+    }
+
+    pub(crate) fn contains_path(&self, path: &Path) -> bool {
+        path.file_name().is_some_and(|name| self.contains_file(&name.to_string_lossy()))
+    }
+
+    // 最近一张被跳过的壁纸日期；跳过今天的壁纸后，定时器不必再频繁检查今天的更新
+    pub(crate) fn latest_date(&self) -> Option<NaiveDate> {
+        self.entries.iter().filter_map(|entry| entry.parsed_date()).max()
+    }
+
+    pub(crate) fn add(&mut self, wallpaper: &WallpaperInfo) {
+        if !self.contains_file(&wallpaper.file_name) {
+            self.entries.push(wallpaper.clone());
+            info!("Blocked wallpaper {}", wallpaper.file_name);
+        }
+    }
+}
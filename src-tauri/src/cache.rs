@@ -0,0 +1,336 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use log::{info, warn};
+
+use crate::{
+    blocklist::Blocklist, favorites::Favorites, get_app_data_dir, get_wallpaper_dir, history::WallpaperHistory, load_config, random_u64, resolve_wallpaper_dir, store, wallpaper_setter, Result,
+};
+use wallpaper_core::{resolution_variant, Resolution};
+
+const IMAGE_EXTENSIONS: [&str; 8] = ["jpg", "jpeg", "png", "webp", "bmp", "avif", "heic", "heif"];
+pub(crate) const DEFAULT_CACHE_MAX_FILES: usize = 30;
+
+struct CachedFile {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+pub(crate) fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+// 默认保存目录（以及旧版本使用的应用数据目录）里的图片都是自己下载的；自定义保存目录
+// 可能混有用户自己的图片，只管理历史记录或收藏中出现过的文件
+fn list_files_in(dir: &Path) -> Result<Vec<CachedFile>> {
+    let owned = dir == resolve_wallpaper_dir("")? || dir == get_app_data_dir()?;
+    let known = if owned { None } else { Some((WallpaperHistory::load(), Favorites::load())) };
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if !path.is_file() || !is_image(&path) || entry.file_name() == wallpaper_setter::CURRENT_LINK_NAME {
+            continue;
+        }
+        if let Some((history, favorites)) = &known {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if !history.contains_file(&file_name) && !favorites.contains_file(&file_name) {
+                continue;
+            }
+        }
+        let metadata = entry.metadata()?;
+        files.push(CachedFile {
+            path,
+            size: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+    // 最新的在前
+    files.sort_by(|a, b| b.modified.cmp(&a.modified));
+    Ok(files)
+}
+
+fn list_cached_files() -> Result<Vec<CachedFile>> {
+    list_files_in(&get_wallpaper_dir()?)
+}
+
+// 目录中由本应用管理的壁纸，用于修改保存目录时搬运
+pub(crate) fn managed_paths(dir: &Path) -> Result<Vec<PathBuf>> {
+    Ok(list_files_in(dir)?.into_iter().map(|file| file.path).collect())
+}
+
+pub(crate) fn cache_size() -> Result<u64> {
+    Ok(list_cached_files()?.iter().map(|file| file.size).sum())
+}
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    const MB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= MB {
+        format!("{:.1} MB", bytes as f64 / MB)
+    } else {
+        format!("{} KB", bytes / 1024)
+    }
+}
+
+fn remove_files<'a>(files: impl Iterator<Item = &'a CachedFile>) -> usize {
+    let mut removed = 0;
+    for file in files {
+        match fs::remove_file(&file.path) {
+            Ok(()) => {
+                removed += 1;
+                if let Err(e) = store::record_removed(&file.path) {
+                    warn!("Failed to record removed wallpaper {:?}: {}", file.path, e);
+                }
+            }
+            Err(e) => warn!("Failed to remove cached wallpaper {:?}: {}", file.path, e),
+        }
+    }
+    removed
+}
+
+// 清理时不能删除的文件：keep 指定的文件、最近一次设置的壁纸（包括本地文件夹和保存目录之外的收藏），
+// 它们按显示器使用的其它分辨率版本，以及去重时与它们共用内容的文件
+fn protected_paths(keep: &[&str]) -> Result<HashSet<PathBuf>> {
+    let dir = get_wallpaper_dir()?;
+    let mut names: Vec<String> = keep.iter().map(|name| name.to_string()).collect();
+    let mut paths = HashSet::new();
+    // 刚启动还没有设置过壁纸时，使用数据库中记录的上次设置
+    let applied = wallpaper_setter::last_applied().or_else(|| store::load_applied(1).ok().and_then(|mut paths| paths.pop()));
+    if let Some(applied) = applied {
+        names.extend(applied.file_name().map(|name| name.to_string_lossy().into_owned()));
+        paths.insert(applied);
+    }
+    for name in &names {
+        paths.insert(dir.join(name));
+        paths.extend([Resolution::FullHd, Resolution::Uhd].map(|resolution| dir.join(resolution_variant(name, resolution))));
+    }
+    for path in paths.clone() {
+        match store::same_content_paths(&path) {
+            Ok(linked) => paths.extend(linked),
+            Err(e) => warn!("Failed to look up files sharing content with {:?}: {}", path, e),
+        }
+    }
+    Ok(paths)
+}
+
+fn is_kept(file: &CachedFile, protected: &HashSet<PathBuf>) -> bool {
+    protected.contains(&file.path)
+}
+
+fn is_favorite(file: &CachedFile, favorites: &Favorites) -> bool {
+    file.path.file_name().is_some_and(|name| favorites.contains_file(&name.to_string_lossy()))
+}
+
+// files 按从新到旧排列，依次保留直到数量或总大小（max_bytes 为 0 时不限）达到上限，返回其余的文件和保留的数量；
+// protected 中的文件总是保留，同样计入上限
+fn select_expired<'a>(
+    files: impl Iterator<Item = &'a CachedFile>,
+    protected: &HashSet<PathBuf>,
+    max_files: usize,
+    max_bytes: u64,
+) -> (Vec<&'a CachedFile>, usize) {
+    let mut total = 0;
+    let mut kept = 0;
+    let mut expired = Vec::new();
+    for file in files {
+        if is_kept(file, protected) {
+            total += file.size;
+            kept += 1;
+            continue;
+        }
+        if kept >= max_files || (max_bytes > 0 && total + file.size > max_bytes) {
+            expired.push(file);
+        } else {
+            total += file.size;
+            kept += 1;
+        }
+    }
+    (expired, kept)
+}
+
+// 按保留数量和总大小清理旧壁纸，正在使用的文件（见 protected_paths）和收藏的壁纸始终保留
+pub(crate) fn cleanup(keep: &[&str]) -> Result<()> {
+    let config = load_config().unwrap_or_default();
+    let max_files = if config.cache_max_files == 0 { DEFAULT_CACHE_MAX_FILES } else { config.cache_max_files };
+    let max_bytes = config.cache_max_mb * 1024 * 1024;
+
+    let protected = protected_paths(keep)?;
+    let favorites = Favorites::load();
+    let files = list_cached_files()?;
+    let (expired, kept) = select_expired(files.iter().filter(|file| !is_favorite(file, &favorites)), &protected, max_files, max_bytes);
+    if !expired.is_empty() {
+        let removed = remove_files(expired.into_iter());
+        info!("Cache cleanup removed {} wallpapers, {} kept", removed, kept);
+    }
+    Ok(())
+}
+
+// 磁盘空间不足时从最旧的开始删除缓存壁纸，直到腾出 bytes 字节或没有可删的；
+// 与 cleanup 一样保留正在使用的文件和收藏的壁纸，返回删除的总大小
+pub(crate) fn free_up(keep: &[&str], bytes: u64) -> Result<u64> {
+    let protected = protected_paths(keep)?;
+    let favorites = Favorites::load();
+    let files = list_cached_files()?;
+    let mut freed = 0;
+    let mut expired = Vec::new();
+    for file in files.iter().rev().filter(|file| !is_kept(file, &protected) && !is_favorite(file, &favorites)) {
+        if freed >= bytes {
+            break;
+        }
+        freed += file.size;
+        expired.push(file);
+    }
+    if !expired.is_empty() {
+        let removed = remove_files(expired.into_iter());
+        info!("Freed {} by removing {} cached wallpapers", format_size(freed), removed);
+    }
+    Ok(freed)
+}
+
+// 缓存中的壁纸路径，最新的在前
+pub(crate) fn cached_paths() -> Result<Vec<PathBuf>> {
+    Ok(list_cached_files()?.into_iter().map(|file| file.path).collect())
+}
+
+// 与更早下载的壁纸内容相同的文件路径；数据库读取失败时不做去重
+pub(crate) fn duplicate_paths() -> HashSet<PathBuf> {
+    store::duplicate_paths().unwrap_or_else(|e| {
+        warn!("Failed to load duplicate wallpapers: {}", e);
+        HashSet::new()
+    })
+}
+
+// 从缓存中随机挑一张壁纸，尽量避开 exclude 指定的文件（当前壁纸），被跳过的壁纸和重复的图片不参与
+pub(crate) fn random_cached_file(exclude: Option<&str>) -> Result<Option<PathBuf>> {
+    let blocklist = Blocklist::load();
+    let duplicates = duplicate_paths();
+    let files: Vec<CachedFile> = list_cached_files()?
+        .into_iter()
+        .filter(|file| !blocklist.contains_path(&file.path) && !duplicates.contains(&file.path))
+        .collect();
+    let candidates: Vec<&CachedFile> =
+        files.iter().filter(|file| exclude.is_none_or(|name| file.path.file_name().is_none_or(|file_name| file_name != name))).collect();
+    if candidates.is_empty() {
+        return Ok(files.into_iter().next().map(|file| file.path));
+    }
+    let index = (random_u64() % candidates.len() as u64) as usize;
+    Ok(Some(candidates[index].path.clone()))
+}
+
+// 按修改时间只保留目录中最新的 count 个文件，用于处理后的壁纸副本
+pub(crate) fn keep_newest(dir: &Path, count: usize) -> Result<()> {
+    let mut files: Vec<(PathBuf, SystemTime)> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?.modified().ok()?)))
+        .collect();
+    files.sort_by(|a, b| b.1.cmp(&a.1));
+    for (path, _) in files.into_iter().skip(count) {
+        if let Err(e) = fs::remove_file(&path) {
+            warn!("Failed to remove old processed wallpaper {:?}: {}", path, e);
+        }
+    }
+    Ok(())
+}
+
+// 保存目录中的文件是否由本应用写入：默认保存目录只有本应用使用；自定义的保存目录可能是用户自己的文件夹
+// （浏览器下载中的文件同样以 .part 结尾），只认数据库中有记录的壁纸。临时文件按去掉 .part、.link 后的文件名判断
+pub(crate) fn is_app_file(path: &Path) -> bool {
+    let Some(dir) = path.parent() else {
+        return false;
+    };
+    if resolve_wallpaper_dir("").is_ok_and(|default| default == dir) {
+        return true;
+    }
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let name = name.strip_suffix(".part").unwrap_or(name);
+    let name = name.strip_suffix(".link").unwrap_or(name);
+    matches!(store::find_by_file(name), Ok(Some(_)))
+}
+
+// 删除下载中断后遗留的 .part 临时文件，不动用户自己的文件
+pub(crate) fn remove_partial_downloads() -> Result<usize> {
+    let mut removed = 0;
+    for entry in fs::read_dir(get_wallpaper_dir()?)? {
+        let path = entry?.path();
+        if !path.is_file() || path.extension().and_then(|ext| ext.to_str()) != Some("part") || !is_app_file(&path) {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => removed += 1,
+            Err(e) => warn!("Failed to remove partial download {:?}: {}", path, e),
+        }
+    }
+    if removed > 0 {
+        info!("Removed {} partial downloads", removed);
+    }
+    Ok(removed)
+}
+
+// 清空缓存，但保留正在使用的壁纸文件和收藏的壁纸
+pub(crate) fn clear(keep: &[&str]) -> Result<usize> {
+    let protected = protected_paths(keep)?;
+    let favorites = Favorites::load();
+    let files = list_cached_files()?;
+    let removed = remove_files(files.iter().filter(|file| !is_kept(file, &protected) && !is_favorite(file, &favorites)));
+    info!("Cleared {} cached wallpapers", removed);
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 按从新到旧的顺序生成，大小以 MB 为单位
+    fn files(sizes_mb: &[u64]) -> Vec<CachedFile> {
+        sizes_mb
+            .iter()
+            .enumerate()
+            .map(|(i, size)| CachedFile {
+                path: PathBuf::from(format!("/wallpapers/{}.jpg", i)),
+                size: size * 1024 * 1024,
+                modified: SystemTime::UNIX_EPOCH,
+            })
+            .collect()
+    }
+
+    fn names(files: &[&CachedFile]) -> Vec<String> {
+        files.iter().map(|file| file.path.file_name().unwrap().to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_select_expired_by_count() {
+        let files = files(&[1, 1, 1, 1]);
+        let (expired, kept) = select_expired(files.iter(), &HashSet::new(), 2, 0);
+        assert_eq!(names(&expired), ["2.jpg", "3.jpg"]);
+        assert_eq!(kept, 2);
+    }
+
+    #[test]
+    fn test_select_expired_by_size() {
+        let files = files(&[2, 2, 2, 1]);
+        let (expired, kept) = select_expired(files.iter(), &HashSet::new(), 10, 5 * 1024 * 1024);
+        // 放不下的文件删除，之后更旧但更小的文件仍可保留
+        assert_eq!(names(&expired), ["2.jpg"]);
+        assert_eq!(kept, 3);
+    }
+
+    #[test]
+    fn test_select_expired_keeps_protected() {
+        let files = files(&[1, 1, 1, 1]);
+        let protected = HashSet::from([PathBuf::from("/wallpapers/3.jpg")]);
+        let (expired, kept) = select_expired(files.iter(), &protected, 2, 0);
+        // 受保护的文件即使超出数量也保留
+        assert_eq!(names(&expired), ["2.jpg"]);
+        assert_eq!(kept, 3);
+    }
+}
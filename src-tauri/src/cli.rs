@@ -0,0 +1,299 @@
+// 命令行模式：带参数启动时只执行一次操作并退出，不创建托盘和窗口，便于在脚本或 cron 中使用
+use std::path::PathBuf;
+use chrono::NaiveDate;
+
+use crate::{
+    api, cache, commands::SEARCH_LIMIT, daemon::{self, CONTROL_ACTIONS, DAEMON_ARG}, download_lock, download_wallpaper, get_bing_wallpaper_info, get_wallpaper_path, history, http,
+    i18n::{self, tr_with}, is_wallpaper_exists, load_config, market_for, portable::PORTABLE_ARG, service, store, wallpaper_setter, AppError, Resolution, Result, WallpaperInfo, MARKETS,
+};
+
+const USAGE: &str = "\
+用法: bing-wallpaper [选项]
+
+不带参数启动时运行托盘程序；带以下参数时只执行一次操作后退出。
+
+选项:
+  --set                    下载并设置壁纸（默认操作）
+  --download-only          只下载壁纸，不设置为桌面
+  --region <cn|global>     壁纸区域，默认 cn
+  --market <代码>          国际区域使用的必应市场，例如 ja-JP、de-DE
+  --resolution <分辨率>    auto、1920x1080 或 UHD，默认使用设置中的值
+  --date <YYYY-MM-DD>      使用归档中某一天的壁纸
+  --list-cache             列出已缓存的壁纸文件
+  --search <关键词>        按标题和版权搜索记录过的壁纸
+  --control <操作>         控制正在运行的程序：status、refresh、pause 或 resume
+  --portable               便携模式：数据保存在程序旁的 data 文件夹中
+  --daemon                 后台服务模式：不显示托盘图标，只运行定时刷新，通过 --control 或控制接口操作
+  --install-service        注册登录后自动运行的后台服务（systemd 用户服务、LaunchAgent 或计划任务）
+  --uninstall-service      停止并删除后台服务
+  -h, --help               显示本帮助";
+
+const USAGE_EN: &str = "\
+Usage: bing-wallpaper [options]
+
+Without options the tray app starts; with the options below a single action runs and the program exits.
+
+Options:
+  --set                    Download and set the wallpaper (default)
+  --download-only          Download the wallpaper without setting it
+  --region <cn|global>     Wallpaper region, defaults to cn
+  --market <code>          Bing market for the global region, e.g. ja-JP, de-DE
+  --resolution <res>       auto, 1920x1080 or UHD, defaults to the value in settings
+  --date <YYYY-MM-DD>      Use the wallpaper of a past day still in the archive
+  --list-cache             List cached wallpaper files
+  --search <keyword>       Search recorded wallpapers by title and copyright
+  --control <action>       Control the running program: status, refresh, pause or resume
+  --portable               Portable mode: keep data in a data folder next to the program
+  --daemon                 Daemon mode: no tray icon, only scheduled refresh, controlled via --control or the control API
+  --install-service        Register the daemon to run at login (systemd user service, LaunchAgent or scheduled task)
+  --uninstall-service      Stop and remove the daemon service
+  -h, --help               Show this help";
+
+fn usage() -> &'static str {
+    if i18n::is_english() { USAGE_EN } else { USAGE }
+}
+
+enum Action {
+    Set,
+    DownloadOnly,
+    ListCache,
+    Search(String),
+    Control(String),
+    InstallService,
+    UninstallService,
+    Help,
+}
+
+struct Options {
+    action: Action,
+    is_china: bool,
+    market: Option<String>,
+    resolution: Option<Resolution>,
+    date: Option<NaiveDate>,
+}
+
+// 托盘程序自己使用的参数（登录自启动标记、便携模式和后台服务模式开关、旧版 macOS 附带的 -psn_ 进程号）不进入命令行模式
+fn is_cli_invocation(args: &[String]) -> bool {
+    args.iter().any(|arg| {
+        arg.starts_with('-')
+            && arg != crate::AUTOSTART_ARG
+            && arg != PORTABLE_ARG
+            && arg != DAEMON_ARG
+            && !arg.starts_with("-psn_")
+    })
+}
+
+fn parse(args: &[String]) -> Result<Options> {
+    let mut options = Options { action: Action::Set, is_china: true, market: None, resolution: None, date: None };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next().cloned().ok_or_else(|| AppError::Config(tr_with("{} 需要一个参数", &name)))
+        };
+        match arg.as_str() {
+            "--set" => options.action = Action::Set,
+            // 已在启动时处理
+            PORTABLE_ARG | DAEMON_ARG => {}
+            "--download-only" => options.action = Action::DownloadOnly,
+            "--list-cache" => options.action = Action::ListCache,
+            "--search" => options.action = Action::Search(value("--search")?),
+            "--install-service" => options.action = Action::InstallService,
+            "--uninstall-service" => options.action = Action::UninstallService,
+            "--control" => {
+                let action = value("--control")?;
+                if !CONTROL_ACTIONS.contains(&action.as_str()) {
+                    return Err(AppError::Config(tr_with("不支持的控制操作: {}", &action)));
+                }
+                options.action = Action::Control(action);
+            }
+            "-h" | "--help" => options.action = Action::Help,
+            "--region" => {
+                options.is_china = match value("--region")?.as_str() {
+                    "cn" | "china" => true,
+                    "global" => false,
+                    other => return Err(AppError::Config(tr_with("不支持的区域: {}", &other))),
+                }
+            }
+            "--market" => {
+                let market = value("--market")?;
+                if !MARKETS.iter().any(|(code, _)| code.eq_ignore_ascii_case(&market)) {
+                    return Err(AppError::Config(tr_with("不支持的市场: {}", &market)));
+                }
+                options.market = Some(market);
+            }
+            "--resolution" => {
+                let resolution = value("--resolution")?;
+                let parsed = Resolution::from_str(&resolution);
+                if parsed.as_str() != resolution {
+                    return Err(AppError::Config(tr_with("不支持的分辨率: {}", &resolution)));
+                }
+                options.resolution = Some(parsed);
+            }
+            "--date" => {
+                let date = value("--date")?;
+                let parsed = NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                    .map_err(|_| AppError::Config(tr_with("日期格式应为 YYYY-MM-DD: {}", &date)))?;
+                options.date = Some(parsed);
+            }
+            other => return Err(AppError::Config(tr_with("未知参数: {}", &other))),
+        }
+    }
+    if options.market.is_some() {
+        options.is_china = false;
+    }
+    Ok(options)
+}
+
+async fn fetch_info(options: &Options, resolution: Resolution) -> Result<WallpaperInfo> {
+    let market = options.market.clone().or_else(|| market_for(options.is_china));
+    if let Some(date) = options.date {
+        return api::fetch_date(options.is_china, market.as_deref(), date, resolution).await;
+    }
+    http::with_retry("Fetching wallpaper info", || {
+        get_bing_wallpaper_info(options.is_china, market.as_deref(), resolution)
+    })
+    .await
+}
+
+// 调用方需持有下载锁，避免与托盘程序同时写入同一文件
+async fn fetch_wallpaper(options: &Options) -> Result<(WallpaperInfo, PathBuf)> {
+    let resolution = options.resolution.unwrap_or_else(|| {
+        load_config().map(|config| Resolution::from_str(&config.resolution)).unwrap_or(Resolution::Auto)
+    });
+    let info = fetch_info(options, resolution).await?;
+    let path = if is_wallpaper_exists(&info.file_name) {
+        get_wallpaper_path(&info.file_name)?
+    } else {
+        download_wallpaper(&info).await?
+    };
+    Ok((info, path))
+}
+
+async fn execute(options: &Options) -> Result<()> {
+    match &options.action {
+        Action::Help => println!("{}", usage()),
+        Action::ListCache => {
+            for path in cache::cached_paths()? {
+                println!("{}", path.display());
+            }
+        }
+        // 每行一条：日期、标题和文件名，已下载的文件名前带 *
+        Action::Search(keyword) => {
+            for (info, downloaded) in store::search(keyword, SEARCH_LIMIT)? {
+                let marker = if downloaded { "*" } else { " " };
+                println!("{}\t{}\t{}{}", info.date, info.title, marker, info.file_name);
+            }
+        }
+        Action::Control(action) => println!("{}", daemon::control(action).await?),
+        Action::InstallService => println!("{}", tr_with("已安装后台服务: {}", service::install()?)),
+        Action::UninstallService => println!("{}", tr_with("已删除后台服务: {}", service::uninstall()?)),
+        Action::DownloadOnly => {
+            let _lock = download_lock::acquire().await?;
+            let (info, path) = fetch_wallpaper(options).await?;
+            println!("{}", info.display_title());
+            println!("{}", path.display());
+        }
+        Action::Set => {
+            let _lock = download_lock::acquire().await?;
+            let (info, path) = fetch_wallpaper(options).await?;
+            wallpaper_setter::set_wallpaper(&path)?;
+            let mut history = history::WallpaperHistory::load();
+            history.merge(vec![info.clone()]);
+            history.save()?;
+            println!("{}", info.display_title());
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+// Windows 的 release 版本是 GUI 程序，需要挂到父进程的控制台上才能输出
+#[cfg(target_os = "windows")]
+fn attach_console() {
+    use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn attach_console() {}
+
+// 返回 None 表示应继续启动托盘程序，否则返回进程退出码
+pub(crate) fn run(args: &[String]) -> Option<i32> {
+    if !is_cli_invocation(args) {
+        return None;
+    }
+    attach_console();
+    let code = match parse(args) {
+        Ok(options) => match tauri::async_runtime::block_on(execute(&options)) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("{}", tr_with("错误: {}", &e));
+                1
+            }
+        },
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, usage());
+            2
+        }
+    };
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_defaults() {
+        let options = parse(&[]).unwrap();
+        assert!(matches!(options.action, Action::Set));
+        assert!(options.is_china);
+        assert!(options.market.is_none() && options.resolution.is_none() && options.date.is_none());
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse(&args(&["--download-only", "--market", "ja-JP", "--resolution", "UHD", "--date", "2024-06-01"])).unwrap();
+        assert!(matches!(options.action, Action::DownloadOnly));
+        // 指定市场时使用国际壁纸
+        assert!(!options.is_china);
+        assert_eq!(options.market.as_deref(), Some("ja-JP"));
+        assert!(options.resolution == Some(Resolution::Uhd));
+        assert_eq!(options.date, NaiveDate::from_ymd_opt(2024, 6, 1));
+
+        assert!(!parse(&args(&["--region", "global"])).unwrap().is_china);
+        assert!(matches!(parse(&args(&["--search", "lake"])).unwrap().action, Action::Search(keyword) if keyword == "lake"));
+        assert!(matches!(parse(&args(&["--control", "pause"])).unwrap().action, Action::Control(action) if action == "pause"));
+        // 便携模式和后台服务模式的开关在启动时处理，这里忽略
+        assert!(matches!(parse(&args(&[PORTABLE_ARG, "--list-cache"])).unwrap().action, Action::ListCache));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for invalid in [
+            &["--region", "mars"][..],
+            &["--market", "xx-XX"],
+            &["--resolution", "800x600"],
+            &["--date", "2024/06/01"],
+            &["--control", "stop"],
+            &["--search"],
+            &["--unknown"],
+        ] {
+            assert!(parse(&args(invalid)).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_is_cli_invocation() {
+        assert!(!is_cli_invocation(&[]));
+        assert!(!is_cli_invocation(&args(&[crate::AUTOSTART_ARG, PORTABLE_ARG, DAEMON_ARG])));
+        assert!(!is_cli_invocation(&args(&["-psn_0_12345"])));
+        assert!(is_cli_invocation(&args(&["--set"])));
+    }
+}
@@ -0,0 +1,194 @@
+// 供前端窗口调用的命令：查询当前壁纸和缓存、应用缓存中的壁纸、搜索历史壁纸、按日期应用壁纸、切换刷新模式。
+// 当前壁纸变化时还会广播 wallpaper-changed 事件，前端无需轮询
+use std::sync::Mutex;
+use chrono::NaiveDate;
+use serde::Serialize;
+use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
+
+use crate::{
+    apply_cached_wallpaper, cache, effective_resolution, favorites, history, i18n::{tr, tr_with}, lock_state, palette::Palette,
+    schedule_menu_update,
+    store, wallpaper_setter,
+    AppError, AppState, RefreshMode, Result, WallpaperInfo,
+};
+
+const SEARCH_WINDOW_LABEL: &str = "search";
+pub(crate) const SEARCH_LIMIT: usize = 50;
+
+#[derive(Serialize)]
+pub(crate) struct CachedWallpaper {
+    file_name: String,
+    path: String,
+    title: String,
+    date: String,
+    copyright: String,
+    favorite: bool,
+    current: bool,
+}
+
+#[derive(Serialize)]
+pub(crate) struct SearchResult {
+    file_name: String,
+    title: String,
+    date: String,
+    copyright: String,
+    downloaded: bool,
+    favorite: bool,
+}
+
+#[tauri::command]
+pub(crate) fn get_current_wallpaper(state: tauri::State<'_, Mutex<AppState>>) -> Option<WallpaperInfo> {
+    lock_state(&state).current_wallpaper.clone()
+}
+
+// 当前壁纸的主色，壁纸刚更换、尚未提取完成时为 null，完成后另有 wallpaper-palette 事件
+#[tauri::command]
+pub(crate) fn get_wallpaper_palette(state: tauri::State<'_, Mutex<AppState>>) -> Option<Palette> {
+    lock_state(&state).palette.clone()
+}
+
+// 按修改时间从新到旧列出缓存的壁纸，能在历史记录或收藏中找到的附带标题等信息
+#[tauri::command]
+pub(crate) fn list_cached_wallpapers(state: tauri::State<'_, Mutex<AppState>>) -> Result<Vec<CachedWallpaper>> {
+    let paths = cache::cached_paths()?;
+    let state = lock_state(&state);
+    let current = state.current_wallpaper.as_ref().map(|wallpaper| wallpaper.file_name.as_str());
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            let info = state
+                .history
+                .find_by_file(&file_name)
+                .or_else(|| state.favorites.find_by_file(&file_name))
+                .cloned()
+                .unwrap_or_default();
+            CachedWallpaper {
+                path: path.to_string_lossy().into_owned(),
+                title: info.title,
+                date: info.date,
+                copyright: info.copyright,
+                favorite: state.favorites.contains_file(&file_name),
+                current: current == Some(file_name.as_str()),
+                file_name,
+            }
+        })
+        .collect())
+}
+
+// 只接受缓存中已有的文件名，不允许前端传入任意路径
+#[tauri::command]
+pub(crate) fn apply_wallpaper(app: tauri::AppHandle, file: String) -> Result<()> {
+    let path = cache::cached_paths()?
+        .into_iter()
+        .find(|path| path.file_name().is_some_and(|name| name == file.as_str()))
+        .ok_or_else(|| AppError::Config(tr_with("缓存中没有该壁纸: {}", &file)))?;
+    apply_cached_wallpaper(&app, &path)
+}
+
+// 在元信息数据库中按标题和版权搜索，关键词为空时返回最近的壁纸
+#[tauri::command]
+pub(crate) fn search_wallpapers(state: tauri::State<'_, Mutex<AppState>>, query: String) -> Result<Vec<SearchResult>> {
+    let results = store::search(&query, SEARCH_LIMIT)?;
+    let state = lock_state(&state);
+    Ok(results
+        .into_iter()
+        .map(|(info, downloaded)| SearchResult {
+            favorite: state.favorites.contains_file(&info.file_name),
+            file_name: info.file_name,
+            title: info.title,
+            date: info.date,
+            copyright: info.copyright,
+            downloaded,
+        })
+        .collect())
+}
+
+// 应用搜索结果，本地没有图片时按记录的地址重新下载
+#[tauri::command]
+pub(crate) async fn apply_search_result(app: tauri::AppHandle, file: String) -> Result<()> {
+    let wallpaper = store::find_by_file(&file)?.ok_or_else(|| AppError::Config(tr_with("没有找到该壁纸: {}", &file)))?;
+    let path = favorites::ensure_local(&wallpaper).await?;
+    wallpaper_setter::set_wallpaper(&path)?;
+    {
+        let state = app.state::<Mutex<AppState>>();
+        lock_state(&state).set_current_wallpaper(&app, wallpaper);
+    }
+    schedule_menu_update(&app);
+    Ok(())
+}
+
+// 日期选择器选中的某一天，格式为 YYYY-MM-DD；区域跟随当前刷新模式（自动模式按最近一次探测的结果），未开启每日刷新时使用中国区
+#[tauri::command]
+pub(crate) async fn apply_wallpaper_by_date(app: tauri::AppHandle, date: String) -> Result<()> {
+    let parsed = parse_date(&date)?;
+    let mode = lock_state(&app.state::<Mutex<AppState>>()).refresh_mode;
+    apply_date(&app, parsed, mode.is_china() || !mode.is_daily()).await
+}
+
+pub(crate) fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| AppError::Config(tr_with("日期格式应为 YYYY-MM-DD: {}", date)))
+}
+
+// 从归档中获取并设置某一天的壁纸，日期选择器和 bingwallpaper://set 链接共用
+pub(crate) async fn apply_date(app: &tauri::AppHandle, date: NaiveDate, is_china: bool) -> Result<()> {
+    let wallpaper = history::set_wallpaper_of_date(is_china, date, effective_resolution(app)).await?;
+    {
+        let state = app.state::<Mutex<AppState>>();
+        lock_state(&state).set_current_wallpaper(app, wallpaper);
+    }
+    schedule_menu_update(app);
+    Ok(())
+}
+
+pub(crate) fn show_search_window(app: &tauri::AppHandle) -> Result<()> {
+    if let Some(window) = app.get_webview_window(SEARCH_WINDOW_LABEL) {
+        window.set_focus()?;
+        return Ok(());
+    }
+    WebviewWindowBuilder::new(app, SEARCH_WINDOW_LABEL, WebviewUrl::App("search.html".into()))
+        .title(tr("搜索壁纸"))
+        .inner_size(520.0, 640.0)
+        .build()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) async fn copy_wallpaper_image(app: tauri::AppHandle) -> Result<()> {
+    tauri::async_runtime::spawn_blocking(move || crate::copy_wallpaper_image(&app))
+        .await
+        .map_err(|e| AppError::Config(e.to_string()))?
+}
+
+#[tauri::command]
+pub(crate) fn copy_wallpaper_url(app: tauri::AppHandle) -> Result<()> {
+    crate::copy_wallpaper_url(&app)
+}
+
+#[tauri::command]
+pub(crate) fn open_wallpaper_dir() -> Result<()> {
+    crate::open_wallpaper_dir()
+}
+
+#[tauri::command]
+pub(crate) fn open_wallpaper_image() -> Result<()> {
+    crate::open_wallpaper_image()
+}
+
+// 与菜单不同，再次设置已激活的模式不会关闭刷新
+#[tauri::command]
+pub(crate) fn set_refresh_mode(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    mode: String,
+) -> Result<()> {
+    let new_mode = RefreshMode::from_str(&mode);
+    if new_mode.as_str() != mode {
+        return Err(AppError::Config(tr_with("不支持的刷新模式: {}", &mode)));
+    }
+    let mut state = lock_state(&state);
+    if state.refresh_mode == new_mode {
+        return Ok(());
+    }
+    crate::set_refresh_mode(&app, &mut state, new_mode)
+}
@@ -0,0 +1,170 @@
+// 本机 HTTP 控制接口：只监听 127.0.0.1，供家庭自动化、Stream Deck 插件和脚本在不打开界面的情况下控制程序。
+// 默认关闭（后台服务模式下总是开启）；每个请求都要带上设置中的访问令牌（Authorization: Bearer <令牌>，或查询参数 token=<令牌>）
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::json;
+use tauri::{Manager, Url};
+use tiny_http::{Header, Request, Response, Server};
+
+use crate::{
+    commands, daemon, handle_pause, handle_refresh_now, load_config, lock_state, next_local_midnight, run_guarded, AppError, AppState,
+    Result, WallpaperInfo,
+};
+
+pub(crate) const DEFAULT_PORT: u16 = 17890;
+
+// 正在运行的服务，修改设置或关闭时停止
+static SERVER: Mutex<Option<Arc<Server>>> = Mutex::new(None);
+
+#[derive(Serialize)]
+struct Status {
+    version: &'static str,
+    refresh_mode: &'static str,
+    paused_until: Option<String>,
+    offline: bool,
+    last_refresh: Option<String>,
+    next_refresh: Option<String>,
+    current_wallpaper: Option<WallpaperInfo>,
+}
+
+fn status(app: &tauri::AppHandle) -> Status {
+    let state = app.state::<Mutex<AppState>>();
+    let state = lock_state(&state);
+    let format = |time: Option<chrono::DateTime<chrono::Local>>| time.map(|time| time.to_rfc3339());
+    Status {
+        version: env!("CARGO_PKG_VERSION"),
+        refresh_mode: state.refresh_mode.as_str(),
+        paused_until: format(state.paused_until.filter(|_| state.pause_remaining().is_some())),
+        offline: state.offline,
+        last_refresh: format(state.last_refresh),
+        next_refresh: format(state.next_refresh),
+        current_wallpaper: state.current_wallpaper.clone(),
+    }
+}
+
+fn json_response(status: u16, body: serde_json::Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes("Content-Type", "application/json; charset=utf-8").expect("valid header");
+    Response::from_string(body.to_string()).with_status_code(status).with_header(header)
+}
+
+fn is_authorized(request: &Request, url: &Url, token: &str) -> bool {
+    let bearer = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer ").map(str::to_string));
+    let query = url.query_pairs().find(|(key, _)| key == "token").map(|(_, value)| value.into_owned());
+    bearer.or(query).is_some_and(|provided| provided == token)
+}
+
+// 未知的路径返回 None
+fn route(app: &tauri::AppHandle, url: &Url) -> Result<Option<serde_json::Value>> {
+    let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+    let state = app.state::<Mutex<AppState>>();
+    match url.path() {
+        "/status" => Ok(Some(serde_json::to_value(status(app))?)),
+        "/refresh" => {
+            handle_refresh_now(app, &state);
+            Ok(Some(json!({ "ok": true })))
+        }
+        "/set" => {
+            let date = param("date").ok_or_else(|| AppError::Config("Missing date".to_string()))?;
+            let date = commands::parse_date(&date)?;
+            let china = match param("region").as_deref() {
+                Some("china") => true,
+                Some("global") => false,
+                Some(region) => return Err(AppError::Config(format!("Unsupported region: {}", region))),
+                None => {
+                    let mode = lock_state(&state).refresh_mode;
+                    mode.is_china() || !mode.is_daily()
+                }
+            };
+            tauri::async_runtime::block_on(commands::apply_date(app, date, china))?;
+            Ok(Some(json!({ "ok": true })))
+        }
+        // minutes 为暂停的分钟数，省略时暂停到明天零点
+        "/pause" => {
+            let until = match param("minutes") {
+                Some(minutes) => {
+                    let minutes: i64 = minutes.parse().map_err(|_| AppError::Config(format!("Invalid minutes: {}", minutes)))?;
+                    Some(chrono::Local::now() + chrono::Duration::minutes(minutes.max(1)))
+                }
+                None => next_local_midnight(),
+            };
+            handle_pause(app, &state, until)?;
+            Ok(Some(json!({ "ok": true, "paused_until": until.map(|until| until.to_rfc3339()) })))
+        }
+        "/resume" => {
+            handle_pause(app, &state, None)?;
+            Ok(Some(json!({ "ok": true })))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn handle(app: &tauri::AppHandle, request: Request, token: &str) {
+    // 只用于解析路径和查询参数，主机名无关紧要
+    let url = match Url::parse(&format!("http://localhost{}", request.url())) {
+        Ok(url) => url,
+        Err(_) => {
+            let _ = request.respond(json_response(400, json!({ "error": "Invalid URL" })));
+            return;
+        }
+    };
+    if !is_authorized(&request, &url, token) {
+        warn!("Rejected unauthorized control API request to {}", url.path());
+        let _ = request.respond(json_response(401, json!({ "error": "Unauthorized" })));
+        return;
+    }
+    info!("Control API request: {} {}", request.method(), url.path());
+    let response = match route(app, &url) {
+        Ok(Some(body)) => json_response(200, body),
+        Ok(None) => json_response(404, json!({ "error": "Not found" })),
+        Err(e) => json_response(400, json!({ "error": e.to_string() })),
+    };
+    if let Err(e) = request.respond(response) {
+        warn!("Failed to respond to control API request: {}", e);
+    }
+}
+
+pub(crate) fn stop() {
+    if let Some(server) = SERVER.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        server.unblock();
+        info!("Control API stopped");
+    }
+}
+
+// 按配置启动或重启服务；关闭时只停止
+pub(crate) fn restart(app: &tauri::AppHandle) -> Result<()> {
+    stop();
+    let config = load_config()?;
+    // 后台服务模式只能通过接口控制，总是开启
+    if !config.control_api_enabled && !daemon::is_enabled() {
+        return Ok(());
+    }
+    if config.control_api_token.is_empty() {
+        return Err(AppError::Config("Control API token is empty".to_string()));
+    }
+    let port = if config.control_api_port == 0 { DEFAULT_PORT } else { config.control_api_port };
+    let server = Arc::new(
+        Server::http(("127.0.0.1", port)).map_err(|e| AppError::Config(format!("Failed to listen on 127.0.0.1:{}: {}", port, e)))?,
+    );
+    *SERVER.lock().unwrap_or_else(|e| e.into_inner()) = Some(server.clone());
+    info!("Control API listening on 127.0.0.1:{}", port);
+
+    let app = app.clone();
+    let token = config.control_api_token;
+    thread::spawn(move || {
+        // 每个请求单独一个线程，设置壁纸或下载较慢时不阻塞其它请求；stop 后 incoming_requests 结束
+        for request in server.incoming_requests() {
+            let app = app.clone();
+            let token = token.clone();
+            thread::spawn(move || run_guarded(&app, "控制接口", || handle(&app, request, &token)));
+        }
+    });
+    Ok(())
+}
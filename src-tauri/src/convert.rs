@@ -0,0 +1,85 @@
+// 旧版 Windows 等环境无法把 WebP、AVIF、HEIC 等格式设为壁纸：设置前按文件内容识别格式（扩展名不一定可靠），
+// JPEG、PNG、BMP 以外的图片转成 JPEG，保存在应用数据目录的子目录中，原图保持不变；转换结果比原图新时直接复用。
+// AVIF 由 image 调用 dav1d 解码，HEIC 由 libheif 解码
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+use image::{ImageFormat, ImageReader, RgbImage};
+use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+use log::{info, warn};
+
+use crate::{cache, get_app_data_dir, processing, AppError, Result};
+
+const CONVERTED_DIR: &str = "converted";
+// HEIF 容器 ftyp 中的主品牌；AVIF 使用同样的容器，由 image 识别
+const HEIF_BRANDS: [&[u8]; 8] = [b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"mif1", b"msf1"];
+
+fn needs_conversion(format: Option<ImageFormat>) -> bool {
+    !matches!(format, None | Some(ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Bmp))
+}
+
+// image 不识别 HEIC，按文件头的 ftyp 品牌判断
+fn is_heif(path: &Path) -> bool {
+    let mut header = [0; 12];
+    File::open(path).and_then(|mut file| file.read_exact(&mut header)).is_ok()
+        && &header[4..8] == b"ftyp"
+        && HEIF_BRANDS.contains(&&header[8..12])
+}
+
+fn decode_heif(path: &Path) -> Result<RgbImage> {
+    let decode_error = |e: libheif_rs::HeifError| AppError::Parse(format!("Failed to decode {:?}: {}", path, e));
+    let context = HeifContext::read_from_file(&path.to_string_lossy()).map_err(decode_error)?;
+    let handle = context.primary_image_handle().map_err(decode_error)?;
+    let image = LibHeif::new().decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None).map_err(decode_error)?;
+    let plane = image
+        .planes()
+        .interleaved
+        .ok_or_else(|| AppError::Parse(format!("Failed to decode {:?}: no RGB plane", path)))?;
+    // 每行末尾可能有对齐用的填充字节
+    let row_len = plane.width as usize * 3;
+    let mut pixels = Vec::with_capacity(row_len * plane.height as usize);
+    for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+        pixels.extend_from_slice(&row[..row_len]);
+    }
+    RgbImage::from_raw(plane.width, plane.height, pixels).ok_or_else(|| AppError::Parse(format!("Failed to decode {:?}: truncated image", path)))
+}
+
+// 转换结果存在且不比原图旧
+fn is_fresh(source: &Path, dest: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    matches!((modified(source), modified(dest)), (Some(source), Some(dest)) if dest >= source)
+}
+
+fn convert(path: &Path) -> Result<PathBuf> {
+    let reader = ImageReader::open(path)?.with_guessed_format()?;
+    let format = reader.format();
+    let heif = format.is_none() && is_heif(path);
+    if !heif && !needs_conversion(format) {
+        return Ok(path.to_path_buf());
+    }
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let dir = get_app_data_dir()?.join(CONVERTED_DIR);
+    let dest = dir.join(format!("{}.jpg", stem));
+    if is_fresh(path, &dest) {
+        return Ok(dest);
+    }
+    let image = if heif {
+        decode_heif(path)?
+    } else {
+        reader.decode().map_err(|e| AppError::Parse(format!("Failed to decode {:?}: {}", path, e)))?.to_rgb8()
+    };
+    processing::save_jpeg(&image, &dest)?;
+    info!("Converted {:?} into {:?}", path, dest);
+    cache::keep_newest(&dir, processing::MAX_PROCESSED_FILES)?;
+    Ok(dest)
+}
+
+// 返回可以直接设为壁纸的图片路径；不需要转换或转换失败时返回原图
+pub(crate) fn compatible_path(path: &Path) -> PathBuf {
+    convert(path).unwrap_or_else(|e| {
+        warn!("Failed to convert {:?}, using original: {}", path, e);
+        path.to_path_buf()
+    })
+}
@@ -0,0 +1,159 @@
+use std::{
+    backtrace::Backtrace,
+    cell::Cell,
+    fs::{self, File},
+    io::Read,
+    panic::PanicHookInfo,
+    path::PathBuf,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use log::{info, error};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::{atomic_file, client_version, get_app_data_dir, http, i18n::{tr, tr_with}, Result};
+
+const CRASH_REPORT_DIR: &str = "crash_reports";
+const CRASH_REPORT_URL: &str = "https://bing.wdbyte.com/crash-report";
+// 待处理的报告以 .txt 结尾，用户处理后改为以下后缀保留在本地
+const SENT_SUFFIX: &str = "sent";
+const DISMISSED_SUFFIX: &str = "dismissed";
+
+thread_local! {
+    // 当前线程上嵌套的 run_guarded 层数；panic hook 在展开之前运行，此时仍能看到标记
+    static CATCHING: Cell<usize> = const { Cell::new(0) };
+}
+
+struct CatchingGuard;
+
+impl Drop for CatchingGuard {
+    fn drop(&mut self) {
+        CATCHING.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+// 运行 f 期间发生的 panic 会被调用方捕获，应用继续运行，panic hook 只记录日志而不生成崩溃报告
+pub(crate) fn catching<R>(f: impl FnOnce() -> R) -> R {
+    CATCHING.with(|depth| depth.set(depth.get() + 1));
+    let _guard = CatchingGuard;
+    f()
+}
+
+fn crash_report_dir() -> Result<PathBuf> {
+    let dir = get_app_data_dir()?.join(CRASH_REPORT_DIR);
+    if !dir.exists() {
+        fs::create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+fn build_report(info: &PanicHookInfo, version: &str) -> String {
+    let backtrace = Backtrace::force_capture();
+    let thread = thread::current();
+    format!(
+        "version: {}\nos: {} ({})\nthread: {}\npanic: {}\n\nbacktrace:\n{}\n",
+        version,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        thread.name().unwrap_or("<unnamed>"),
+        info,
+        backtrace,
+    )
+}
+
+fn write_report(report: &str) -> Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = crash_report_dir()?.join(format!("crash-{}.txt", timestamp));
+    atomic_file::write(&path, report)?;
+    Ok(path)
+}
+
+// 安装 panic hook：崩溃时把报告写入数据目录，不会自动上传
+pub(crate) fn install_panic_hook(version: &'static str) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let report = build_report(info, version);
+        // 被 run_guarded 捕获的 panic 不会导致退出，下次启动不应提示异常退出
+        if CATCHING.with(|depth| depth.get() > 0) {
+            error!("Panic caught:\n{}", report);
+            return;
+        }
+        error!("Panic captured:\n{}", report);
+        match write_report(&report) {
+            Ok(path) => error!("Crash report written to {:?}", path),
+            Err(e) => error!("Failed to write crash report: {}", e),
+        }
+        default_hook(info);
+    }));
+}
+
+fn pending_reports() -> Result<Vec<PathBuf>> {
+    let mut reports = Vec::new();
+    for entry in fs::read_dir(crash_report_dir()?)? {
+        let path = entry?.path();
+        if path.extension().map(|ext| ext == "txt").unwrap_or(false) {
+            reports.push(path);
+        }
+    }
+    reports.sort();
+    Ok(reports)
+}
+
+fn mark_report(path: &PathBuf, suffix: &str) -> Result<()> {
+    fs::rename(path, path.with_extension(suffix))?;
+    Ok(())
+}
+
+async fn submit_report(path: &PathBuf) -> Result<()> {
+    let mut report = String::new();
+    File::open(path)?.read_to_string(&mut report)?;
+    let request = http::post(CRASH_REPORT_URL)?
+        .header("Content-Type", "text/plain; charset=utf-8")
+        .header("client-version", client_version())
+        .body(report);
+    http::send(request).await?;
+    info!("Submitted crash report {:?}", path);
+    Ok(())
+}
+
+// 启动时检查上次遗留的崩溃报告，征得用户同意后才上传
+pub(crate) fn offer_pending_reports(app: &tauri::AppHandle) {
+    let reports = match pending_reports() {
+        Ok(reports) if !reports.is_empty() => reports,
+        Ok(_) => return,
+        Err(e) => {
+            error!("Failed to read crash reports: {}", e);
+            return;
+        }
+    };
+    app.dialog()
+        .message(tr_with(
+            "Bing Wallpaper 上次运行时异常退出，已在本地生成 {} 份崩溃报告。\n是否将报告发送给开发者以帮助排查问题？\n报告仅包含版本、系统信息和错误堆栈。",
+            reports.len(),
+        ))
+        .title(tr("崩溃报告"))
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(tr("发送").to_string(), tr("不发送").to_string()))
+        .show(move |send| {
+            // 回调可能在主线程执行，网络请求放到异步任务中
+            tauri::async_runtime::spawn(async move {
+                for path in &reports {
+                    let suffix = if send {
+                        if let Err(e) = submit_report(path).await {
+                            error!("Failed to submit crash report {:?}: {}", path, e);
+                            continue;
+                        }
+                        SENT_SUFFIX
+                    } else {
+                        DISMISSED_SUFFIX
+                    };
+                    if let Err(e) = mark_report(path, suffix) {
+                        error!("Failed to mark crash report {:?}: {}", path, e);
+                    }
+                }
+            });
+        });
+}
@@ -0,0 +1,54 @@
+// 后台服务模式：以 --daemon 启动时不创建托盘图标和任何窗口，只运行定时刷新、下载和同步，
+// 通过本机控制接口或命令行的 --control 操作。该模式下控制接口总是开启，没有令牌时自动生成并保存
+use std::sync::OnceLock;
+use log::info;
+use uuid::Uuid;
+use wallpaper_core::{HttpClient, Timeouts};
+
+use crate::{control_api::DEFAULT_PORT, http, load_config, update_config, AppError, RefreshMode, Result};
+
+pub(crate) const DAEMON_ARG: &str = "--daemon";
+// --control 支持的操作，与控制接口的路径相同
+pub(crate) const CONTROL_ACTIONS: [&str; 4] = ["status", "refresh", "pause", "resume"];
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+pub(crate) fn is_enabled() -> bool {
+    *ENABLED.get_or_init(|| std::env::args().any(|arg| arg == DAEMON_ARG))
+}
+
+// 没有托盘菜单可以开启刷新，保存的模式为“不刷新”时按自动区域每日刷新
+pub(crate) fn refresh_mode(saved: RefreshMode) -> RefreshMode {
+    if saved == RefreshMode::None {
+        info!("Refresh is off, daemon falls back to {}", RefreshMode::DailyAuto.as_str());
+        RefreshMode::DailyAuto
+    } else {
+        saved
+    }
+}
+
+// 启动控制接口前调用，令牌为空时生成一个
+pub(crate) fn ensure_control_token() -> Result<()> {
+    if !load_config()?.control_api_token.is_empty() {
+        return Ok(());
+    }
+    update_config(|config| config.control_api_token = Uuid::new_v4().simple().to_string())?;
+    info!("Generated control API token for daemon mode");
+    Ok(())
+}
+
+// 命令行的 --control：按配置中的端口和令牌请求正在运行的程序，返回响应的 JSON
+pub(crate) async fn control(action: &str) -> Result<String> {
+    let config = load_config()?;
+    if config.control_api_token.is_empty() {
+        return Err(AppError::Config("Control API token is empty, start the daemon or enable the control API first".to_string()));
+    }
+    let port = if config.control_api_port == 0 { DEFAULT_PORT } else { config.control_api_port };
+    // 本机请求不走代理
+    let client = HttpClient::new(None, Timeouts::default())?;
+    let request = client
+        .get(&format!("http://127.0.0.1:{}/{}", port, action))
+        .bearer_auth(&config.control_api_token);
+    let response = http::send(request).await?;
+    response.text().await.map_err(|e| AppError::Network { message: e.to_string(), transient: false })
+}
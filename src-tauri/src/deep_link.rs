@@ -0,0 +1,150 @@
+// bingwallpaper:// 链接：网站或脚本可以通过链接让正在运行的程序设置指定日期的壁纸或切换壁纸。
+// Windows 和 Linux 上系统会带着链接启动新进程，由单实例插件把参数转交给已运行的实例；
+// macOS 上链接以事件的形式发给已运行的程序
+use std::sync::Mutex;
+use log::{info, warn};
+use tauri::{Manager, Url};
+
+use crate::{commands, i18n::tr_with, lock_state, navigation, report_error, run_guarded_async, AppError, AppState, Result};
+
+pub(crate) const SCHEME: &str = "bingwallpaper";
+
+enum Action {
+    // 某一天的壁纸，region 为空时跟随当前刷新模式
+    Set { date: chrono::NaiveDate, china: Option<bool> },
+    Next,
+    Previous,
+}
+
+// 支持 bingwallpaper://set?date=2024-06-01&region=global、bingwallpaper://next 和 bingwallpaper://previous
+fn parse(link: &str) -> Result<Action> {
+    let invalid = || AppError::Config(tr_with("无法识别的链接: {}", link));
+    let url = Url::parse(link).map_err(|_| invalid())?;
+    if url.scheme() != SCHEME {
+        return Err(invalid());
+    }
+    match url.host_str().unwrap_or_default() {
+        "set" => {
+            let mut date = None;
+            let mut china = None;
+            for (key, value) in url.query_pairs() {
+                match key.as_ref() {
+                    "date" => date = Some(commands::parse_date(&value)?),
+                    "region" if value == "china" => china = Some(true),
+                    "region" if value == "global" => china = Some(false),
+                    "region" => return Err(AppError::Config(tr_with("不支持的壁纸区域: {}", &value))),
+                    _ => {}
+                }
+            }
+            Ok(Action::Set { date: date.ok_or_else(invalid)?, china })
+        }
+        "next" => Ok(Action::Next),
+        "previous" => Ok(Action::Previous),
+        _ => Err(invalid()),
+    }
+}
+
+// 从启动参数中找出链接，没有时返回 None
+pub(crate) fn find_in_args<'a>(args: impl IntoIterator<Item = &'a String>) -> Option<&'a String> {
+    args.into_iter().find(|arg| arg.starts_with(&format!("{}://", SCHEME)))
+}
+
+async fn run(app: &tauri::AppHandle, action: Action) -> Result<()> {
+    match action {
+        Action::Set { date, china } => {
+            let china = china.unwrap_or_else(|| {
+                let mode = lock_state(&app.state::<Mutex<AppState>>()).refresh_mode;
+                mode.is_china() || !mode.is_daily()
+            });
+            commands::apply_date(app, date, china).await
+        }
+        Action::Next => navigation::next(app),
+        Action::Previous => navigation::previous(app),
+    }
+}
+
+// 在后台处理链接，出错时通知用户
+pub(crate) fn handle(app: &tauri::AppHandle, link: &str) {
+    info!("Handling deep link {}", link);
+    let action = match parse(link) {
+        Ok(action) => action,
+        Err(e) => {
+            warn!("Ignoring deep link {}: {}", link, e);
+            report_error(app, "处理链接失败", &e);
+            return;
+        }
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        run_guarded_async(&app, "处理链接", async {
+            if let Err(e) = run(&app, action).await {
+                report_error(&app, "处理链接失败", &e);
+            }
+        })
+        .await;
+    });
+}
+
+// 启动时调用：开发构建和 AppImage 需要在运行时注册协议（安装包在安装时注册），
+// 并处理以链接启动本程序时带来的链接
+pub(crate) fn setup(app: &tauri::AppHandle) {
+    use tauri_plugin_deep_link::DeepLinkExt;
+
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
+    if let Err(e) = app.deep_link().register_all() {
+        warn!("Failed to register {}:// scheme: {}", SCHEME, e);
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let handle = app.clone();
+        app.deep_link().on_open_url(move |event| {
+            for url in event.urls() {
+                handle(&handle, url.as_str());
+            }
+        });
+    }
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(link) = find_in_args(&args) {
+        handle(app, link);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_set() {
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        assert!(matches!(parse("bingwallpaper://set?date=2024-06-01"), Ok(Action::Set { date: d, china: None }) if d == date));
+        assert!(matches!(parse("bingwallpaper://set?date=2024-06-01&region=china"), Ok(Action::Set { china: Some(true), .. })));
+        assert!(matches!(parse("bingwallpaper://set?region=global&date=2024-06-01"), Ok(Action::Set { china: Some(false), .. })));
+    }
+
+    #[test]
+    fn test_parse_navigation() {
+        assert!(matches!(parse("bingwallpaper://next"), Ok(Action::Next)));
+        assert!(matches!(parse("bingwallpaper://previous"), Ok(Action::Previous)));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for link in [
+            "bingwallpaper://set",
+            "bingwallpaper://set?date=20240601",
+            "bingwallpaper://set?date=2024-06-01&region=mars",
+            "bingwallpaper://delete",
+            "https://set?date=2024-06-01",
+            "not a link",
+        ] {
+            assert!(parse(link).is_err(), "{}", link);
+        }
+    }
+
+    #[test]
+    fn test_find_in_args() {
+        let args = vec!["--autostart".to_string(), "bingwallpaper://next".to_string()];
+        assert_eq!(find_in_args(&args).map(String::as_str), Some("bingwallpaper://next"));
+        assert_eq!(find_in_args(&args[..1]), None);
+    }
+}
@@ -0,0 +1,159 @@
+// 系统从休眠恢复、显示器插拔或远程桌面连接后，macOS/Windows 有时会把壁纸还原，
+// 这里在后台检测这些变化并重新设置最近一次应用的壁纸；
+// 系统或其它程序把壁纸换掉时（macOS 上新建的桌面空间不会继承已设置的壁纸，也属于这种情况）同样重新设置
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+use log::{info, warn};
+use tauri::Manager;
+use wallpaper_core::{detect_setter, resolution_variant, Resolution};
+
+use crate::{get_wallpaper_path, load_config, lock_state, run_guarded, wallpaper_setter, AppState, RefreshMode, Result};
+
+// Tauri 没有显示器变化事件，按该间隔轮询显示器布局
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+// 读取系统当前的壁纸需要启动 osascript、gsettings 等进程，比显示器布局的轮询间隔长得多
+const DRIFT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// 两次轮询之间的墙上时间差超过 POLL_INTERVAL + 该值，认为系统刚从休眠中恢复
+const WAKE_THRESHOLD: Duration = Duration::from_secs(60);
+// 变化发生后等系统稳定再设置，避免显示器尚未就绪或被系统再次覆盖
+const SETTLE_DELAY: Duration = Duration::from_secs(3);
+
+// 每个显示器的名称、位置和分辨率，任一项变化都视为显示配置变化
+type MonitorLayout = Vec<(Option<String>, i32, i32, u32, u32)>;
+
+fn monitor_layout(app: &tauri::AppHandle) -> Option<MonitorLayout> {
+    let monitors = app.available_monitors().ok()?;
+    let mut layout: MonitorLayout = monitors
+        .iter()
+        .map(|monitor| {
+            let position = monitor.position();
+            let size = monitor.size();
+            (monitor.name().cloned(), position.x, position.y, size.width, size.height)
+        })
+        .collect();
+    layout.sort();
+    Some(layout)
+}
+
+// 系统当前显示的壁纸既不是最近一次设置的文件，也不是当前壁纸或它的其它分辨率版本（多显示器时按显示器使用），
+// 说明壁纸被系统或其它程序改掉了。macOS 上新建的桌面空间也会表现为这种情况。
+// 读不到系统当前的壁纸、没有开启刷新或在设置中关闭了纠正时不处理。被改掉时返回系统当前的壁纸路径
+fn drifted(app: &tauri::AppHandle) -> Option<PathBuf> {
+    if load_config().map(|config| config.drift_correction_disabled).unwrap_or(false) {
+        return None;
+    }
+    let expected = {
+        let state = app.state::<Mutex<AppState>>();
+        let state = lock_state(&state);
+        if state.refresh_mode == RefreshMode::None {
+            return None;
+        }
+        match &state.current_wallpaper {
+            Some(wallpaper) => wallpaper.file_name.clone(),
+            None => return None,
+        }
+    };
+    let Some(Ok(Some(current))) = detect_setter().map(|setter| setter.current()) else {
+        return None;
+    };
+    let same_file = |a: &Path, b: &Path| a == b || matches!((fs::canonicalize(a), fs::canonicalize(b)), (Ok(a), Ok(b)) if a == b);
+    if wallpaper_setter::last_displayed().is_some_and(|displayed| same_file(&displayed, &current)) {
+        return None;
+    }
+    let name = current.file_name().and_then(|name| name.to_str())?;
+    // 桌面上可能是处理过或调暗的副本，副本文件名以原图的文件名（不含扩展名）开头
+    let matches = |candidate: &str| {
+        Path::new(candidate).file_stem().and_then(|stem| stem.to_str()).is_some_and(|stem| name.starts_with(stem))
+    };
+    let variants = [Resolution::FullHd, Resolution::Uhd].map(|resolution| resolution_variant(&expected, resolution));
+    let drifted = !matches(&expected) && !variants.iter().any(|variant| matches(variant));
+    drifted.then_some(current)
+}
+
+// 只有开启了自动刷新或轮播时才重新设置，用户关闭刷新或恢复原壁纸后不再干预。
+// 优先使用最近一次设置的文件，本地文件夹轮播和保存目录之外的收藏也能还原；启动后还没有设置过时按当前壁纸的文件名查找。
+// 返回是否重新设置了壁纸
+pub(crate) fn reapply_current_wallpaper(app: &tauri::AppHandle) -> Result<bool> {
+    let file_name = {
+        let state = app.state::<Mutex<AppState>>();
+        let state = lock_state(&state);
+        if state.refresh_mode == RefreshMode::None {
+            return Ok(false);
+        }
+        match &state.current_wallpaper {
+            Some(wallpaper) => wallpaper.file_name.clone(),
+            None => return Ok(false),
+        }
+    };
+    let path = match wallpaper_setter::last_applied() {
+        Some(path) => path,
+        None => get_wallpaper_path(&file_name)?,
+    };
+    if !path.is_file() {
+        warn!("Cannot re-apply wallpaper, {:?} no longer exists", path);
+        return Ok(false);
+    }
+    wallpaper_setter::set_wallpaper(&path)?;
+    info!("Re-applied wallpaper {:?}", path);
+    Ok(true)
+}
+
+// 启动常驻的检测线程，随程序退出
+pub(crate) fn start(app: tauri::AppHandle) {
+    thread::spawn(move || {
+        let mut layout = monitor_layout(&app);
+        let mut last_tick = SystemTime::now();
+        let mut last_drift_check = Instant::now();
+        // 重新设置失败或没有可用的文件时记下系统当前的壁纸，桌面换成其它图片之前不再重复尝试
+        let mut given_up: Option<PathBuf> = None;
+        loop {
+            thread::sleep(POLL_INTERVAL);
+
+            let now = SystemTime::now();
+            let elapsed = now.duration_since(last_tick).unwrap_or_default();
+            last_tick = now;
+            let resumed = elapsed > POLL_INTERVAL + WAKE_THRESHOLD;
+
+            let current_layout = monitor_layout(&app);
+            let layout_changed = current_layout.is_some() && current_layout != layout;
+            if current_layout.is_some() {
+                layout = current_layout;
+            }
+
+            let mut drift = None;
+            if !resumed && !layout_changed && last_drift_check.elapsed() >= DRIFT_CHECK_INTERVAL {
+                last_drift_check = Instant::now();
+                drift = drifted(&app).filter(|current| given_up.as_ref() != Some(current));
+            }
+            if !resumed && !layout_changed && drift.is_none() {
+                continue;
+            }
+            if resumed {
+                info!("Detected resume from sleep after {}s", elapsed.as_secs());
+            } else if layout_changed {
+                info!("Detected display configuration change");
+            } else {
+                info!("Desktop shows a different wallpaper, checking again after settling");
+            }
+            thread::sleep(SETTLE_DELAY);
+            last_tick = SystemTime::now();
+            // 刚设置完新壁纸（例如淡入淡出的过渡帧）时也可能短暂不一致，稳定后再确认一次
+            if drift.is_some() && drifted(&app).is_none() {
+                continue;
+            }
+            run_guarded(&app, "重新设置壁纸", || match reapply_current_wallpaper(&app) {
+                Ok(true) => given_up = None,
+                Ok(false) => given_up = drift.clone(),
+                Err(e) => {
+                    warn!("Failed to re-apply wallpaper: {}", e);
+                    given_up = drift.clone();
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,76 @@
+// 下载前检查保存目录所在磁盘的剩余空间：低于设置的下限时先清理缓存腾出空间，仍然不够就跳过下载，
+// 错误随刷新失败的通知提示用户。读取剩余空间失败时不阻止下载
+use std::path::Path;
+#[cfg(not(target_os = "windows"))]
+use std::process::Command;
+use log::{info, warn};
+
+use crate::{cache, i18n::tr_fmt, load_config, AppError, Result};
+
+pub(crate) const DEFAULT_MIN_FREE_SPACE_MB: u64 = 200;
+
+// 0 表示使用默认值
+fn min_free_bytes() -> u64 {
+    let config = load_config().unwrap_or_default();
+    let mb = if config.min_free_space_mb == 0 { DEFAULT_MIN_FREE_SPACE_MB } else { config.min_free_space_mb };
+    mb * 1024 * 1024
+}
+
+#[cfg(target_os = "windows")]
+fn free_bytes(dir: &Path) -> Result<u64> {
+    use std::{iter::once, os::windows::ffi::OsStrExt, ptr::null_mut};
+    use winapi::{shared::ntdef::ULARGE_INTEGER, um::fileapi::GetDiskFreeSpaceExW};
+
+    let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(once(0)).collect();
+    let mut available: ULARGE_INTEGER = unsafe { std::mem::zeroed() };
+    if unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut available, null_mut(), null_mut()) } == 0 {
+        return Err(AppError::Io(std::io::Error::last_os_error()));
+    }
+    Ok(unsafe { *available.QuadPart() })
+}
+
+// df -P 的输出格式在 Linux 和 macOS 上相同，第二行第 4 列是当前用户可用的 1K 块数
+#[cfg(not(target_os = "windows"))]
+fn free_bytes(dir: &Path) -> Result<u64> {
+    let output = Command::new("df").arg("-Pk").arg(dir).output()?;
+    if !output.status.success() {
+        return Err(AppError::OsSet(format!("df failed: {}", String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .nth(1)
+        .and_then(|line| line.split_whitespace().nth(3))
+        .and_then(|blocks| blocks.parse::<u64>().ok())
+        .map(|blocks| blocks * 1024)
+        .ok_or_else(|| AppError::Parse(format!("Unexpected df output: {}", stdout.trim())))
+}
+
+// keep 是清理时除正在使用的壁纸外额外保留的文件，例如同一次刷新中先下载好的版本
+pub(crate) fn ensure_free_space(dir: &Path, keep: &[&str]) -> Result<()> {
+    let required = min_free_bytes();
+    let free = match free_bytes(dir) {
+        Ok(free) => free,
+        Err(e) => {
+            warn!("Failed to read free disk space of {:?}: {}", dir, e);
+            return Ok(());
+        }
+    };
+    if free >= required {
+        return Ok(());
+    }
+
+    warn!("Only {} free on {:?}, cleaning up the cache", cache::format_size(free), dir);
+    if let Err(e) = cache::free_up(keep, required - free) {
+        warn!("Failed to clean up wallpaper cache: {}", e);
+    }
+    let free = free_bytes(dir).unwrap_or(free);
+    if free >= required {
+        info!("Cache cleanup freed enough space, {} free", cache::format_size(free));
+        return Ok(());
+    }
+    Err(AppError::Io(std::io::Error::other(tr_fmt(
+        "保存目录所在磁盘只剩 {}，低于设置的 {}，已跳过下载",
+        &[&cache::format_size(free), &cache::format_size(required)],
+    ))))
+}
@@ -0,0 +1,46 @@
+// 同一时间只允许一次下载并设置壁纸：快速切换模式、立即刷新和定时刷新可能同时写同一个文件。
+// 进程内用异步互斥锁排队，另在应用数据目录放一个文件锁，防止命令行模式和托盘程序同时下载
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    time::Duration,
+};
+use log::info;
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::{get_app_data_dir, Result};
+
+const LOCK_FILE_NAME: &str = ".download.lock";
+// 文件锁被其它进程持有时的重试间隔
+const RETRY_INTERVAL: Duration = Duration::from_millis(200);
+
+static LOCK: Mutex<()> = Mutex::const_new(());
+
+// 持有期间其它下载等待；离开作用域时关闭文件，系统随之释放文件锁
+pub(crate) struct DownloadLock {
+    _file: File,
+    _guard: MutexGuard<'static, ()>,
+}
+
+pub(crate) async fn acquire() -> Result<DownloadLock> {
+    let guard = LOCK.lock().await;
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(get_app_data_dir()?.join(LOCK_FILE_NAME))?;
+    let mut waiting = false;
+    loop {
+        match file.try_lock() {
+            Ok(()) => break,
+            Err(TryLockError::WouldBlock) => {
+                if !waiting {
+                    info!("Another process is downloading a wallpaper, waiting");
+                    waiting = true;
+                }
+                tokio::time::sleep(RETRY_INTERVAL).await;
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+    }
+    Ok(DownloadLock { _file: file, _guard: guard })
+}
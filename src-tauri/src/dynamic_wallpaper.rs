@@ -0,0 +1,136 @@
+// macOS 动态壁纸：把最近几天下载的每日壁纸打包成一个按时间变化的 HEIC，桌面在一天中依次显示这些图片。
+// HEIC 由 JXA 调用 ImageIO 生成，第一张图片带有 apple_desktop:h24 元数据（二进制 plist 的 base64），
+// 记录每张图片开始显示的时刻；图片按从旧到新平均分布在一天中
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+    thread,
+};
+use log::{info, warn};
+use tauri::Manager;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+
+use crate::{
+    get_wallpaper_path, i18n::{tr, tr_with}, lock_state, notify, report_error, run_guarded, wallpaper_setter, AppError, AppState,
+    Result,
+};
+
+// 打包最近几天的壁纸
+const DAYS: usize = 7;
+const DEFAULT_FILE_NAME: &str = "bing-dynamic.heic";
+
+const SCRIPT: &str = "ObjC.import('Foundation');
+ObjC.import('ImageIO');
+ObjC.import('CoreGraphics');
+var paths = __PATHS__, dest = __DEST__;
+var times = [];
+for (var i = 0; i < paths.length; i++) {
+    times.push({ t: i / paths.length, i: i });
+}
+var plist = $({ ti: times, ap: { l: paths.length - 1, d: paths.length - 1 } });
+var data = $.NSPropertyListSerialization.dataWithPropertyListFormatOptionsError(plist, $.NSPropertyListBinaryFormat_v1_0, 0, null);
+var namespace = $('http://ns.apple.com/namespace/1.0/');
+var metadata = $.CGImageMetadataCreateMutable();
+$.CGImageMetadataRegisterNamespaceForPrefix(metadata, namespace, $('apple_desktop'), null);
+var tag = $.CGImageMetadataTagCreate(namespace, $('apple_desktop'), $('h24'), $.kCGImageMetadataTypeString, data.base64EncodedStringWithOptions(0));
+$.CGImageMetadataSetTagWithPath(metadata, null, $('apple_desktop:h24'), tag);
+var destination = $.CGImageDestinationCreateWithURL($.NSURL.fileURLWithPath(dest), $('public.heic'), paths.length, null);
+if (!destination) throw new Error('Cannot create ' + dest);
+for (var i = 0; i < paths.length; i++) {
+    var source = $.CGImageSourceCreateWithURL($.NSURL.fileURLWithPath(paths[i]), null);
+    var image = $.CGImageSourceCreateImageAtIndex(source, 0, null);
+    if (!image) throw new Error('Cannot read ' + paths[i]);
+    if (i === 0) {
+        $.CGImageDestinationAddImageAndMetadata(destination, image, metadata, null);
+    } else {
+        $.CGImageDestinationAddImage(destination, image, null);
+    }
+}
+if (!$.CGImageDestinationFinalize(destination)) throw new Error('Cannot write ' + dest);";
+
+// 历史记录中最近几天已下载的壁纸，从旧到新
+fn recent_images(app: &tauri::AppHandle) -> Result<Vec<PathBuf>> {
+    let state = app.state::<Mutex<AppState>>();
+    let file_names: Vec<String> =
+        lock_state(&state).history.recent(DAYS).iter().map(|wallpaper| wallpaper.file_name.clone()).collect();
+    let mut images = Vec::new();
+    for file_name in file_names.iter().rev() {
+        let path = get_wallpaper_path(file_name)?;
+        if path.is_file() {
+            images.push(path);
+        }
+    }
+    if images.len() < 2 {
+        return Err(AppError::Config(tr("至少需要两张已下载的每日壁纸").to_string()));
+    }
+    Ok(images)
+}
+
+fn generate(images: &[PathBuf], dest: &Path) -> Result<()> {
+    let paths: Vec<String> = images.iter().map(|path| path.to_string_lossy().into_owned()).collect();
+    let script = SCRIPT
+        .replace("__PATHS__", &serde_json::to_string(&paths)?)
+        .replace("__DEST__", &serde_json::to_string(&dest.to_string_lossy())?);
+    let output = Command::new("osascript").args(["-l", "JavaScript", "-e", &script]).output()?;
+    if !output.status.success() {
+        return Err(AppError::OsSet(format!(
+            "Failed to generate dynamic wallpaper: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    info!("Generated dynamic wallpaper {:?} from {} images", dest, images.len());
+    Ok(())
+}
+
+// 生成后询问是否立即设为壁纸
+fn offer_apply(app: &tauri::AppHandle, dest: PathBuf) {
+    app.dialog()
+        .message(tr_with("动态壁纸已保存到 {}，是否立即设为桌面壁纸？", dest.display()))
+        .title(tr("动态壁纸"))
+        .buttons(MessageDialogButtons::OkCancelCustom(tr("设为壁纸").to_string(), tr("稍后").to_string()))
+        .show({
+            let app = app.clone();
+            move |apply| {
+                if !apply {
+                    return;
+                }
+                thread::spawn(move || {
+                    run_guarded(&app, "设置动态壁纸", || match wallpaper_setter::set_wallpaper(&dest) {
+                        Ok(()) => info!("Applied dynamic wallpaper {:?}", dest),
+                        Err(e) => report_error(&app, "设置动态壁纸失败", &e),
+                    })
+                });
+            }
+        });
+}
+
+// 菜单操作：选择保存位置后在后台生成
+pub(crate) fn export(app: &tauri::AppHandle) {
+    let images = match recent_images(app) {
+        Ok(images) => images,
+        Err(e) => {
+            warn!("Cannot generate dynamic wallpaper: {}", e);
+            report_error(app, "生成动态壁纸失败", &e);
+            return;
+        }
+    };
+    let handler = {
+        let app = app.clone();
+        move |path: Option<tauri_plugin_dialog::FilePath>| {
+            let Some(path) = path.and_then(|path| path.into_path().ok()) else {
+                return;
+            };
+            thread::spawn(move || {
+                run_guarded(&app, "生成动态壁纸", || match generate(&images, &path) {
+                    Ok(()) => {
+                        notify(&app, tr("动态壁纸已生成"), &tr_with("已打包最近 {} 张每日壁纸", images.len()));
+                        offer_apply(&app, path);
+                    }
+                    Err(e) => report_error(&app, "生成动态壁纸失败", &e),
+                })
+            });
+        }
+    };
+    app.dialog().file().add_filter("HEIC", &["heic"]).set_file_name(DEFAULT_FILE_NAME).save_file(handler);
+}
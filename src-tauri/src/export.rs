@@ -0,0 +1,131 @@
+// 导出缓存的壁纸：复制到用户选择的文件夹，或打包成附带 manifest.json 的 ZIP，便于备份和分享
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use log::{info, warn};
+use serde::Serialize;
+use tauri_plugin_dialog::DialogExt;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    cache, i18n::{tr, tr_fmt, tr_with}, notify, report_error, run_guarded, store, AppError, Result, WallpaperInfo,
+};
+
+const MANIFEST_NAME: &str = "manifest.json";
+const DEFAULT_ZIP_NAME: &str = "bing-wallpapers.zip";
+
+#[derive(Serialize)]
+struct Manifest {
+    exported_at: String,
+    wallpapers: Vec<WallpaperInfo>,
+}
+
+fn zip_error(err: zip::result::ZipError) -> AppError {
+    AppError::Io(io::Error::new(io::ErrorKind::Other, err))
+}
+
+// files 为空时导出全部缓存；只接受缓存中已有的文件名，不允许传入任意路径
+fn select(files: &[String]) -> Result<Vec<PathBuf>> {
+    let cached = cache::cached_paths()?;
+    if files.is_empty() {
+        return Ok(cached);
+    }
+    files
+        .iter()
+        .map(|file| {
+            cached
+                .iter()
+                .find(|path| path.file_name().is_some_and(|name| name == file.as_str()))
+                .cloned()
+                .ok_or_else(|| AppError::Config(tr_with("缓存中没有该壁纸: {}", file)))
+        })
+        .collect()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+// 目标文件夹中已有同名文件时跳过，返回实际复制的数量
+fn export_to_dir(paths: &[PathBuf], dir: &Path) -> Result<usize> {
+    fs::create_dir_all(dir)?;
+    let mut copied = 0;
+    for path in paths {
+        let dest = dir.join(file_name(path));
+        if dest.exists() {
+            continue;
+        }
+        fs::copy(path, &dest)?;
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+// 图片本身已经压缩过，直接存储；清单中的元信息取自元信息数据库，找不到时只有文件名
+fn export_to_zip(paths: &[PathBuf], zip_path: &Path) -> Result<usize> {
+    let mut writer = ZipWriter::new(File::create(zip_path)?);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut wallpapers = Vec::new();
+    for path in paths {
+        let name = file_name(path);
+        writer.start_file(name.as_str(), stored).map_err(zip_error)?;
+        io::copy(&mut File::open(path)?, &mut writer)?;
+        let info = store::find_by_file(&name).unwrap_or_else(|e| {
+            warn!("Failed to look up {}: {}", name, e);
+            None
+        });
+        wallpapers.push(info.unwrap_or(WallpaperInfo { file_name: name, ..Default::default() }));
+    }
+    let manifest = Manifest { exported_at: chrono::Local::now().to_rfc3339(), wallpapers };
+    writer.start_file(MANIFEST_NAME, SimpleFileOptions::default()).map_err(zip_error)?;
+    writer.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    writer.finish().map_err(zip_error)?;
+    Ok(paths.len())
+}
+
+fn export(files: &[String], destination: &Path, as_zip: bool) -> Result<usize> {
+    let paths = select(files)?;
+    let exported = if as_zip { export_to_zip(&paths, destination)? } else { export_to_dir(&paths, destination)? };
+    info!("Exported {} wallpapers to {:?}", exported, destination);
+    Ok(exported)
+}
+
+// 供窗口调用：导出选中的壁纸（files 为空时导出全部），zip 为 true 时 destination 是 ZIP 文件路径
+#[tauri::command]
+pub(crate) async fn export_wallpapers(files: Vec<String>, destination: String, zip: bool) -> Result<usize> {
+    tauri::async_runtime::spawn_blocking(move || export(&files, Path::new(&destination), zip))
+        .await
+        .map_err(|e| AppError::Config(e.to_string()))?
+}
+
+fn export_and_notify(app: &tauri::AppHandle, destination: &Path, as_zip: bool) {
+    match export(&[], destination, as_zip) {
+        Ok(count) => notify(
+            app,
+            tr("导出完成"),
+            &tr_fmt("已导出 {} 张壁纸到 {}", &[&count.to_string(), &destination.display().to_string()]),
+        ),
+        Err(e) => report_error(app, "导出壁纸失败", &e),
+    }
+}
+
+// 菜单操作：选择文件夹或 ZIP 保存位置后导出全部缓存，在后台线程中复制
+pub(crate) fn export_all(app: &tauri::AppHandle, as_zip: bool) {
+    let handler = {
+        let app = app.clone();
+        move |path: Option<tauri_plugin_dialog::FilePath>| {
+            let Some(path) = path.and_then(|path| path.into_path().ok()) else {
+                return;
+            };
+            std::thread::spawn(move || run_guarded(&app, "导出壁纸", || export_and_notify(&app, &path, as_zip)));
+        }
+    };
+    let dialog = app.dialog().file();
+    if as_zip {
+        dialog.add_filter("ZIP", &["zip"]).set_file_name(DEFAULT_ZIP_NAME).save_file(handler);
+    } else {
+        dialog.pick_folder(handler);
+    }
+}
@@ -0,0 +1,81 @@
+// Windows 上更换壁纸时的淡入淡出：先在旧壁纸和新壁纸之间生成几张过渡帧，依次设置后再设置新壁纸，
+// 每天更换时不那么突兀。过渡帧按新图片的比例生成（旧图片裁剪缩放到相同尺寸），
+// 保存在应用数据目录的 fade/ 中，每次覆盖
+use std::{
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+use image::{imageops::FilterType, DynamicImage, ImageReader, RgbImage};
+use log::{info, warn};
+use wallpaper_core::WallpaperSetter;
+
+use crate::{get_app_data_dir, load_config, processing, AppError, Result};
+
+const FADE_DIR: &str = "fade";
+// 过渡帧数（不含最后的新壁纸）和相邻两帧的间隔
+const FRAME_COUNT: u32 = 4;
+const FRAME_INTERVAL: Duration = Duration::from_millis(40);
+// 过渡帧只显示一瞬间，限制宽度以加快生成
+const FRAME_MAX_WIDTH: u32 = 1920;
+
+// 系统记录的当前壁纸可能是没有扩展名的 TranscodedWallpaper，按文件内容识别格式
+fn decode(path: &Path) -> Result<DynamicImage> {
+    ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()
+        .map_err(|e| AppError::Parse(format!("Failed to decode {:?}: {}", path, e)))
+}
+
+fn generate_frames(from: &Path, to: &Path) -> Result<Vec<PathBuf>> {
+    let target = decode(to)?;
+    let width = target.width().min(FRAME_MAX_WIDTH);
+    let height = (target.height() as u64 * width as u64 / target.width().max(1) as u64).max(1) as u32;
+    let target = target.resize_exact(width, height, FilterType::Triangle).to_rgb8();
+    let source = decode(from)?.resize_to_fill(width, height, FilterType::Triangle).to_rgb8();
+    let dir = get_app_data_dir()?.join(FADE_DIR);
+    let mut frames = Vec::new();
+    for step in 1..=FRAME_COUNT {
+        let weight = step as f32 / (FRAME_COUNT + 1) as f32;
+        let frame = RgbImage::from_fn(width, height, |x, y| {
+            let (old, new) = (source.get_pixel(x, y), target.get_pixel(x, y));
+            image::Rgb(std::array::from_fn(|channel| {
+                (old[channel] as f32 * (1.0 - weight) + new[channel] as f32 * weight).round() as u8
+            }))
+        });
+        let path = dir.join(format!("frame_{}.jpg", step));
+        processing::save_jpeg(&frame, &path)?;
+        frames.push(path);
+    }
+    Ok(frames)
+}
+
+// 开启淡入淡出时在设置新壁纸之前调用；读不到当前壁纸或生成失败时只记录日志，直接切换
+pub(crate) fn play_from_current(setter: &dyn WallpaperSetter, to: &Path) {
+    if !load_config().map(|config| config.fade_transition).unwrap_or(false) {
+        return;
+    }
+    let from = match setter.current() {
+        Ok(Some(from)) if from != to && from.is_file() => from,
+        Ok(_) => return,
+        Err(e) => {
+            warn!("Failed to read current wallpaper for fade: {}", e);
+            return;
+        }
+    };
+    let frames = match generate_frames(&from, to) {
+        Ok(frames) => frames,
+        Err(e) => {
+            warn!("Failed to generate fade frames: {}", e);
+            return;
+        }
+    };
+    for frame in &frames {
+        if let Err(e) = setter.set(frame) {
+            warn!("Failed to set fade frame {:?}: {}", frame, e);
+            return;
+        }
+        thread::sleep(FRAME_INTERVAL);
+    }
+    info!("Faded from {:?} over {} frames", from, frames.len());
+}
@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+use log::{info, warn};
+
+use crate::{download_lock, download_wallpaper, get_wallpaper_path, is_wallpaper_exists, store, Result, WallpaperInfo};
+
+pub(crate) const FAVORITES_MENU_SIZE: usize = 14;
+
+// 收藏的壁纸元信息，最新收藏的在前，保存在元信息数据库中；收藏的图片不会被缓存清理删除
+#[derive(Default)]
+pub(crate) struct Favorites {
+    entries: Vec<WallpaperInfo>,
+}
+
+impl Favorites {
+    pub(crate) fn load() -> Self {
+        match Self::read_from_disk() {
+            Ok(favorites) => favorites,
+            Err(e) => {
+                warn!("Failed to load favorites: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    fn read_from_disk() -> Result<Self> {
+        Ok(Favorites { entries: store::load_marked(store::Mark::Favorite)? })
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        store::save_marked(store::Mark::Favorite, &self.entries)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn recent(&self, count: usize) -> &[WallpaperInfo] {
+        &self.entries[..count.min(self.entries.len())]
+    }
+
+    pub(crate) fn contains_file(&self, file_name: &str) -> bool {
+        self.entries.iter().any(|entry| entry.file_name == file_name)
+    }
+
+    pub(crate) fn find_by_file(&self, file_name: &str) -> Option<&WallpaperInfo> {
+        self.entries.iter().find(|entry| entry.file_name == file_name)
+    }
+
+    // 已收藏则取消，否则加入收藏；返回操作后是否处于收藏状态
+    pub(crate) fn toggle(&mut self, wallpaper: &WallpaperInfo) -> bool {
+        if self.contains_file(&wallpaper.file_name) {
+            self.entries.retain(|entry| entry.file_name != wallpaper.file_name);
+            info!("Removed {} from favorites", wallpaper.file_name);
+            false
+        } else {
+            self.entries.insert(0, wallpaper.clone());
+            info!("Added {} to favorites", wallpaper.file_name);
+            true
+        }
+    }
+
+    // 本地仍存在的收藏图片，供轮播使用
+    pub(crate) fn local_paths(&self) -> Vec<PathBuf> {
+        self.entries
+            .iter()
+            .filter_map(|entry| get_wallpaper_path(&entry.file_name).ok())
+            .filter(|path| path.is_file())
+            .collect()
+    }
+}
+
+// 取得收藏图片的本地路径，文件已被删除时按记录的地址重新下载
+pub(crate) async fn ensure_local(wallpaper: &WallpaperInfo) -> Result<PathBuf> {
+    let _lock = download_lock::acquire().await?;
+    if is_wallpaper_exists(&wallpaper.file_name) {
+        get_wallpaper_path(&wallpaper.file_name)
+    } else {
+        download_wallpaper(wallpaper).await
+    }
+}
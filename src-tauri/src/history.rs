@@ -0,0 +1,115 @@
+use chrono::NaiveDate;
+use log::{info, warn};
+
+use crate::{
+    api, download_lock, download_wallpaper, get_wallpaper_path, is_wallpaper_exists, market_for, store, wallpaper_setter,
+    Resolution, Result, WallpaperInfo,
+};
+
+// 向接口请求的天数，以及本地最多保留的记录数
+const ARCHIVE_DAYS: u32 = 30;
+const MAX_HISTORY_ENTRIES: usize = 90;
+pub(crate) const HISTORY_MENU_SIZE: usize = 14;
+
+// 历史壁纸元信息，按日期倒序排列，保存在元信息数据库中
+#[derive(Default)]
+pub(crate) struct WallpaperHistory {
+    entries: Vec<WallpaperInfo>,
+}
+
+impl WallpaperHistory {
+    pub(crate) fn load() -> Self {
+        match Self::read_from_disk() {
+            Ok(history) => history,
+            Err(e) => {
+                warn!("Failed to load wallpaper history: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    fn read_from_disk() -> Result<Self> {
+        let mut entries = store::load_marked(store::Mark::History)?;
+        entries.truncate(MAX_HISTORY_ENTRIES);
+        Ok(WallpaperHistory { entries })
+    }
+
+    pub(crate) fn save(&self) -> Result<()> {
+        store::save_marked(store::Mark::History, &self.entries)
+    }
+
+    // 合并新记录，同一天以新数据为准；没有日期的记录无法按日期应用，直接忽略
+    pub(crate) fn merge(&mut self, items: Vec<WallpaperInfo>) {
+        for item in items.into_iter().filter(|item| !item.date.is_empty()) {
+            self.entries.retain(|entry| entry.date != item.date);
+            self.entries.push(item);
+        }
+        self.entries.sort_by(|a, b| b.date.cmp(&a.date));
+        self.entries.truncate(MAX_HISTORY_ENTRIES);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn recent(&self, count: usize) -> &[WallpaperInfo] {
+        &self.entries[..count.min(self.entries.len())]
+    }
+
+    pub(crate) fn contains_file(&self, file_name: &str) -> bool {
+        self.entries.iter().any(|entry| entry.file_name == file_name)
+    }
+
+    pub(crate) fn find_by_file(&self, file_name: &str) -> Option<&WallpaperInfo> {
+        self.entries.iter().find(|entry| entry.file_name == file_name)
+    }
+
+    // 接口返回的日期可能是 20240501 或 2024-05-01，比较时忽略连字符
+    pub(crate) fn find_by_date(&self, date: &str) -> Option<&WallpaperInfo> {
+        let date = date.replace('-', "");
+        self.entries.iter().find(|entry| entry.date.replace('-', "") == date)
+    }
+}
+
+async fn fetch_archive(is_china: bool) -> Result<Vec<WallpaperInfo>> {
+    api::fetch_archive(is_china, market_for(is_china).as_deref(), ARCHIVE_DAYS).await
+}
+
+// 拉取最近的归档并合并进本地缓存
+pub(crate) async fn refresh_history(is_china: bool) -> Result<WallpaperHistory> {
+    let items = fetch_archive(is_china).await?;
+    let mut history = WallpaperHistory::load();
+    info!("Fetched {} archived wallpapers", items.len());
+    history.merge(items);
+    history.save()?;
+    Ok(history)
+}
+
+// 应用历史记录中的一张壁纸，本地没有缓存时先下载
+pub(crate) async fn set_history_wallpaper(wallpaper: WallpaperInfo, resolution: Resolution) -> Result<WallpaperInfo> {
+    let _lock = download_lock::acquire().await?;
+    let wallpaper_info = wallpaper.with_resolution(resolution);
+
+    let wallpaper_path = if is_wallpaper_exists(&wallpaper_info.file_name) {
+        get_wallpaper_path(&wallpaper_info.file_name)?
+    } else {
+        download_wallpaper(&wallpaper_info).await?
+    };
+    wallpaper_setter::set_wallpaper(&wallpaper_path)?;
+    info!("Applied wallpaper of {}", wallpaper_info.date);
+    Ok(wallpaper_info)
+}
+
+// 应用归档中任意一天的壁纸，不要求该日期已在本地历史记录中
+pub(crate) async fn set_wallpaper_of_date(is_china: bool, date: NaiveDate, resolution: Resolution) -> Result<WallpaperInfo> {
+    let wallpaper_info = api::fetch_date(is_china, market_for(is_china).as_deref(), date, resolution).await?;
+    let _lock = download_lock::acquire().await?;
+    let wallpaper_path = if is_wallpaper_exists(&wallpaper_info.file_name) {
+        get_wallpaper_path(&wallpaper_info.file_name)?
+    } else {
+        download_wallpaper(&wallpaper_info).await?
+    };
+    wallpaper_setter::set_wallpaper(&wallpaper_path)?;
+    info!("Applied archived wallpaper of {}", date);
+    Ok(wallpaper_info)
+}
@@ -0,0 +1,54 @@
+// 壁纸更换后执行用户配置的命令，例如用 pywal 重新生成配色、备份图片或通知家庭自动化；
+// 壁纸信息通过环境变量传入，命令在后台运行，不阻塞托盘
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+};
+use log::{info, warn};
+
+use crate::{load_config, palette::Palette, Result, WallpaperInfo};
+
+#[cfg(target_os = "windows")]
+fn shell_command(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    // 不为命令弹出控制台窗口
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command).creation_flags(CREATE_NO_WINDOW);
+    shell
+}
+
+#[cfg(not(target_os = "windows"))]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+// 未配置命令时什么也不做；命令的退出状态只写入日志。提取主色失败时颜色变量为空
+pub(crate) fn run_post_change(wallpaper: &WallpaperInfo, path: &Path, palette: Option<&Palette>) -> Result<()> {
+    let command = load_config()?.post_change_hook.trim().to_string();
+    if command.is_empty() {
+        return Ok(());
+    }
+    let mut child = shell_command(&command)
+        .env("BING_WALLPAPER_PATH", path)
+        .env("BING_WALLPAPER_FILE", &wallpaper.file_name)
+        .env("BING_WALLPAPER_TITLE", &wallpaper.title)
+        .env("BING_WALLPAPER_COPYRIGHT", &wallpaper.copyright)
+        .env("BING_WALLPAPER_DATE", &wallpaper.date)
+        .env("BING_WALLPAPER_URL", &wallpaper.url)
+        .env("BING_WALLPAPER_DOMINANT_COLOR", palette.map(|palette| palette.dominant.as_str()).unwrap_or_default())
+        .env("BING_WALLPAPER_ACCENT_COLOR", palette.map(|palette| palette.accent.as_str()).unwrap_or_default())
+        .env("BING_WALLPAPER_PALETTE", palette.map(|palette| palette.colors.join(",")).unwrap_or_default())
+        .stdin(Stdio::null())
+        .spawn()?;
+    info!("Started post-change hook for {}", wallpaper.file_name);
+    thread::spawn(move || match child.wait() {
+        Ok(status) if status.success() => info!("Post-change hook finished"),
+        Ok(status) => warn!("Post-change hook exited with {}", status),
+        Err(e) => warn!("Failed to wait for post-change hook: {}", e),
+    });
+    Ok(())
+}
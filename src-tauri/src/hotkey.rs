@@ -0,0 +1,119 @@
+// 全局快捷键：切换到下一张、切换回上一张、暂停或恢复更换壁纸，与托盘菜单的“上一张 / 下一张”相同；
+// 快捷键可在设置中修改，被其它程序占用时记录日志并跳过
+use std::sync::Mutex;
+use log::{info, warn};
+use tauri::Manager;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::{
+    handle_pause, i18n::{tr, tr_with}, load_config, lock_state, navigation, next_local_midnight, notify, report_error, run_guarded,
+    AppError, AppState, Result,
+};
+
+pub(crate) const DEFAULT_NEXT: &str = "Ctrl+Alt+N";
+pub(crate) const DEFAULT_PREVIOUS: &str = "Ctrl+Alt+P";
+pub(crate) const DEFAULT_PAUSE: &str = "Ctrl+Alt+Space";
+
+#[derive(Clone, Copy, Debug)]
+enum Action {
+    Next,
+    Previous,
+    TogglePause,
+}
+
+// 当前注册的快捷键 id 与对应的操作
+static BINDINGS: Mutex<Vec<(u32, Action)>> = Mutex::new(Vec::new());
+
+// 解析快捷键，例如 "Ctrl+Alt+N"、"CommandOrControl+Shift+Right"
+pub(crate) fn parse(keys: &str) -> Result<Shortcut> {
+    keys.parse().map_err(|_| AppError::Config(tr_with("无法识别的快捷键: {}", keys)))
+}
+
+// 配置为空时使用默认值
+fn configured() -> Vec<(String, Action)> {
+    let config = load_config().unwrap_or_default();
+    let or_default = |keys: String, default: &str| if keys.is_empty() { default.to_string() } else { keys };
+    vec![
+        (or_default(config.hotkey_next, DEFAULT_NEXT), Action::Next),
+        (or_default(config.hotkey_previous, DEFAULT_PREVIOUS), Action::Previous),
+        (or_default(config.hotkey_pause, DEFAULT_PAUSE), Action::TogglePause),
+    ]
+}
+
+// 按配置重新注册全部快捷键，关闭快捷键时只注销
+pub(crate) fn register(app: &tauri::AppHandle) -> Result<()> {
+    let shortcuts = app.global_shortcut();
+    shortcuts.unregister_all().map_err(|e| AppError::OsSet(e.to_string()))?;
+    BINDINGS.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    if load_config().map(|config| config.hotkeys_disabled).unwrap_or(false) {
+        info!("Global hotkeys disabled");
+        return Ok(());
+    }
+    let mut bindings = Vec::new();
+    for (keys, action) in configured() {
+        let shortcut = parse(&keys)?;
+        match shortcuts.register(shortcut) {
+            Ok(()) => {
+                info!("Registered hotkey {} for {:?}", keys, action);
+                bindings.push((shortcut.id(), action));
+            }
+            Err(e) => warn!("Failed to register hotkey {}: {}", keys, e),
+        }
+    }
+    *BINDINGS.lock().unwrap_or_else(|e| e.into_inner()) = bindings;
+    Ok(())
+}
+
+fn toggle_pause(app: &tauri::AppHandle) -> Result<()> {
+    let state = app.state::<Mutex<AppState>>();
+    let (paused, notifications_enabled) = {
+        let state = lock_state(&state);
+        (state.pause_remaining().is_some(), state.notifications_enabled)
+    };
+    let until = if paused { None } else { next_local_midnight() };
+    handle_pause(app, &state, until)?;
+    if notifications_enabled {
+        match until {
+            Some(until) => notify(app, tr("已暂停更换壁纸"), &tr_with("暂停到 {}", until.format("%m-%d %H:%M"))),
+            None => notify(app, tr("已恢复更换壁纸"), tr("定时刷新和轮播将照常进行")),
+        }
+    }
+    Ok(())
+}
+
+fn run(app: &tauri::AppHandle, action: Action) {
+    let result = match action {
+        Action::Next => navigation::next(app),
+        Action::Previous => navigation::previous(app),
+        Action::TogglePause => toggle_pause(app),
+    };
+    if let Err(e) = result {
+        let context = match action {
+            Action::Next => "切换下一张壁纸失败",
+            Action::Previous => "切换上一张壁纸失败",
+            Action::TogglePause => "暂停更换壁纸失败",
+        };
+        report_error(app, context, &e);
+    }
+}
+
+// 快捷键插件；按下时在后台线程执行，设置壁纸较慢时不阻塞事件循环
+pub(crate) fn plugin() -> tauri::plugin::TauriPlugin<tauri::Wry> {
+    tauri_plugin_global_shortcut::Builder::new()
+        .with_handler(|app, shortcut, event| {
+            if event.state != ShortcutState::Pressed {
+                return;
+            }
+            let action = BINDINGS
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .iter()
+                .find(|(id, _)| *id == shortcut.id())
+                .map(|(_, action)| *action);
+            if let Some(action) = action {
+                let app = app.clone();
+                std::thread::spawn(move || run_guarded(&app, "快捷键", || run(&app, action)));
+            }
+        })
+        .build()
+}
@@ -0,0 +1,148 @@
+// 把代理设置转换为 wallpaper_core 的 HttpClient，所有网络请求都经过这里
+use std::time::Duration;
+use log::warn;
+#[cfg(target_os = "macos")]
+use std::process::Command;
+use wallpaper_core::{http::RequestBuilder, HttpClient, Timeouts};
+pub(crate) use wallpaper_core::{
+    http::{send, DEFAULT_CONNECT_TIMEOUT, DEFAULT_DOWNLOAD_TIMEOUT, DEFAULT_REQUEST_TIMEOUT},
+    with_retry,
+};
+
+use crate::{load_config, AppConfig, AppError, Result};
+
+pub(crate) const PROXY_NONE: &str = "none";
+pub(crate) const PROXY_SYSTEM: &str = "system";
+pub(crate) const PROXY_MANUAL: &str = "manual";
+// 手动配置的代理地址可以带的协议，不带协议时按 HTTP 代理处理；socks5h 由代理服务器解析域名
+pub(crate) const PROXY_SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+
+// 设置窗口中超时的上限（秒）
+pub(crate) const MAX_TIMEOUT_SECS: u64 = 3600;
+
+// 按当前的代理和超时设置创建请求客户端
+pub(crate) fn client() -> Result<HttpClient> {
+    let config = load_config().unwrap_or_default();
+    Ok(HttpClient::new(proxy_url(&config)?, timeouts(&config))?)
+}
+
+pub(crate) fn post(url: &str) -> Result<RequestBuilder> {
+    Ok(client()?.post(url))
+}
+
+// 配置中的超时（秒），0 表示使用默认值
+pub(crate) fn timeout_secs(configured: u64, default: Duration) -> u64 {
+    if configured == 0 {
+        default.as_secs()
+    } else {
+        configured
+    }
+}
+
+fn timeouts(config: &AppConfig) -> Timeouts {
+    Timeouts {
+        connect: Duration::from_secs(timeout_secs(config.connect_timeout_secs, DEFAULT_CONNECT_TIMEOUT)),
+        request: Duration::from_secs(timeout_secs(config.request_timeout_secs, DEFAULT_REQUEST_TIMEOUT)),
+        download: Duration::from_secs(timeout_secs(config.download_timeout_secs, DEFAULT_DOWNLOAD_TIMEOUT)),
+    }
+}
+
+// 按配置得到代理地址；未配置时默认跟随系统代理
+fn proxy_url(config: &AppConfig) -> Result<Option<String>> {
+    match config.proxy_mode.as_str() {
+        PROXY_NONE => Ok(None),
+        PROXY_MANUAL => {
+            if config.proxy_host.is_empty() {
+                return Err(AppError::Config("Manual proxy host is empty".to_string()));
+            }
+            let credentials = if config.proxy_username.is_empty() {
+                String::new()
+            } else if config.proxy_password.is_empty() {
+                format!("{}@", config.proxy_username)
+            } else {
+                format!("{}:{}@", config.proxy_username, config.proxy_password)
+            };
+            let port = if config.proxy_port == 0 { String::new() } else { format!(":{}", config.proxy_port) };
+            let (scheme, host) = config.proxy_host.split_once("://").unwrap_or(("http", &config.proxy_host));
+            Ok(Some(format!("{}://{}{}{}", scheme.to_ascii_lowercase(), credentials, host, port)))
+        }
+        _ => Ok(system_proxy()),
+    }
+}
+
+// 支持 HTTP 和 SOCKS5 代理，socks:// 按 SOCKS5 处理，其它协议（例如 SOCKS4）会被忽略
+fn normalize_system_proxy(proxy: &str) -> Option<String> {
+    let proxy = proxy.trim();
+    if proxy.is_empty() {
+        return None;
+    }
+    let Some((scheme, rest)) = proxy.split_once("://") else {
+        return Some(format!("http://{}", proxy));
+    };
+    let scheme = scheme.to_ascii_lowercase();
+    if scheme == "socks" {
+        return Some(format!("socks5://{}", rest));
+    }
+    if !PROXY_SCHEMES.contains(&scheme.as_str()) {
+        warn!("Ignoring unsupported proxy: {}", proxy);
+        return None;
+    }
+    Some(format!("{}://{}", scheme, rest))
+}
+
+// 读取 Internet 设置中的代理，ProxyServer 可能是 "host:port" 或 "http=host:port;https=host:port"
+#[cfg(target_os = "windows")]
+fn system_proxy() -> Option<String> {
+    const SUBKEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings";
+
+    if crate::registry::read_hkcu_dword(SUBKEY, "ProxyEnable") != Some(1) {
+        return None;
+    }
+    let server = crate::registry::read_hkcu_string(SUBKEY, "ProxyServer")?;
+    if !server.contains('=') {
+        return normalize_system_proxy(&server);
+    }
+    let entries: Vec<(&str, &str)> = server
+        .split(';')
+        .filter_map(|entry| entry.split_once('='))
+        .collect();
+    let find = |scheme: &str| entries.iter().find(|(key, _)| key.eq_ignore_ascii_case(scheme)).map(|(_, value)| value.trim());
+    // 只配置了 SOCKS 代理时使用 SOCKS5
+    find("https")
+        .or_else(|| find("http"))
+        .and_then(normalize_system_proxy)
+        .or_else(|| find("socks").and_then(|value| normalize_system_proxy(&format!("socks5://{}", value))))
+}
+
+// 解析 scutil --proxy 的输出，优先使用 HTTPS 代理，其次 HTTP，最后 SOCKS
+#[cfg(target_os = "macos")]
+fn system_proxy() -> Option<String> {
+    let output = Command::new("scutil").arg("--proxy").output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let value = |key: &str| {
+        text.lines()
+            .filter_map(|line| line.split_once(" : "))
+            .find(|(k, _)| k.trim() == key)
+            .map(|(_, v)| v.trim().to_string())
+    };
+    for (scheme, url_scheme, default_port) in [("HTTPS", "http", "80"), ("HTTP", "http", "80"), ("SOCKS", "socks5", "1080")] {
+        let enable_key = format!("{}Enable", scheme);
+        let proxy_key = format!("{}Proxy", scheme);
+        let port_key = format!("{}Port", scheme);
+        if value(enable_key.as_str()).as_deref() == Some("1") {
+            let host = value(proxy_key.as_str())?;
+            let port = value(port_key.as_str()).unwrap_or_else(|| default_port.to_string());
+            return normalize_system_proxy(&format!("{}://{}:{}", url_scheme, host, port));
+        }
+    }
+    None
+}
+
+// 其它平台沿用常见的代理环境变量
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn system_proxy() -> Option<String> {
+    ["https_proxy", "HTTPS_PROXY", "http_proxy", "HTTP_PROXY", "all_proxy", "ALL_PROXY"]
+        .iter()
+        .find_map(|key| std::env::var(key).ok())
+        .and_then(|proxy| normalize_system_proxy(&proxy))
+}
@@ -0,0 +1,380 @@
+// 界面文字的本地化：代码中直接使用中文原文作为键，英文界面时按下表查找译文，找不到的保持中文。
+// 语言默认跟随系统区域设置，也可以在设置中手动指定
+use std::sync::atomic::{AtomicU8, Ordering};
+use log::info;
+
+pub(crate) const LANGUAGE_AUTO: &str = "";
+pub(crate) const LANGUAGE_ZH_CN: &str = "zh-CN";
+pub(crate) const LANGUAGE_EN_US: &str = "en-US";
+pub(crate) const LANGUAGES: [&str; 3] = [LANGUAGE_AUTO, LANGUAGE_ZH_CN, LANGUAGE_EN_US];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Language {
+    ZhCn,
+    EnUs,
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(Language::ZhCn as u8);
+
+// 格式化参数用 {} 占位，与 format! 的写法一致
+const EN_US: &[(&str, &str)] = &[
+    // 托盘菜单
+    ("今日壁纸：{}", "Today: {}"),
+    ("今日壁纸：暂无", "Today: none yet"),
+    ("离线壁纸：{}", "Offline: {}"),
+    ("离线模式：暂无缓存壁纸", "Offline: no cached wallpapers"),
+    ("最近错误：{}", "Last error: {}"),
+    ("今日：{}", "Today: {}"),
+    ("每日壁纸刷新(中国)", "Daily wallpaper (China)"),
+    ("每日壁纸刷新(国际)", "Daily wallpaper (Global)"),
+    ("每日壁纸刷新(自动选择区域)", "Daily wallpaper (Auto region)"),
+    ("国际壁纸市场", "Global market"),
+    ("默认", "Default"),
+    ("美国", "United States"),
+    ("英国", "United Kingdom"),
+    ("加拿大", "Canada"),
+    ("澳大利亚", "Australia"),
+    ("印度", "India"),
+    ("日本", "Japan"),
+    ("德国", "Germany"),
+    ("法国", "France"),
+    ("意大利", "Italy"),
+    ("西班牙", "Spain"),
+    ("巴西", "Brazil"),
+    ("壁纸来源", "Wallpaper source"),
+    ("必应每日壁纸", "Bing daily wallpaper"),
+    ("Windows 聚焦", "Windows Spotlight"),
+    ("NASA 每日天文图", "NASA Astronomy Picture of the Day"),
+    ("本地壁纸轮播", "Local slideshow"),
+    ("开启轮播", "Enable slideshow"),
+    ("每 {} 小时", "Every {}h"),
+    ("每 {} 分钟", "Every {} min"),
+    ("随机顺序", "Shuffle"),
+    ("按下载顺序", "Download order"),
+    ("只轮播收藏", "Favorites only"),
+    ("轮播本地文件夹", "Local folder"),
+    ("暂停更换壁纸", "Pause wallpaper changes"),
+    ("已暂停至 {}", "Paused until {}"),
+    ("暂停 1 小时", "Pause for 1 hour"),
+    ("暂停到明天", "Pause until tomorrow"),
+    ("恢复", "Resume"),
+    ("立即刷新", "Refresh now"),
+    ("跳过今天的壁纸", "Skip today's wallpaper"),
+    ("历史壁纸", "History"),
+    ("我的收藏", "Favorites"),
+    ("收藏当前壁纸", "Add current wallpaper to favorites"),
+    ("取消收藏当前壁纸", "Remove current wallpaper from favorites"),
+    ("恢复原壁纸", "Restore original wallpaper"),
+    ("开机自启动", "Launch at login"),
+    ("托盘图标", "Tray icon"),
+    ("运行状态", "Status"),
+    ("当前模式：{}", "Mode: {}"),
+    ("上次刷新：{}", "Last refresh: {}"),
+    ("下次刷新：{}", "Next refresh: {}"),
+    ("下次刷新：已推迟（{}）", "Next refresh: deferred ({})"),
+    ("定时任务异常结束，已于 {} 重新启动", "Refresh task stopped unexpectedly, restarted at {}"),
+    ("按流量计费的网络", "metered connection"),
+    ("电池电量 {}%", "battery at {}%"),
+    ("全屏程序运行中", "fullscreen app running"),
+    ("按星期规则今天不刷新", "no refresh today by weekday rule"),
+    ("中国区每日壁纸", "Daily wallpaper (China)"),
+    ("国际区每日壁纸", "Daily wallpaper (International)"),
+    ("自动选择：{}", "Auto: {}"),
+    ("未开启自动刷新", "Auto refresh off"),
+    ("暂无", "None"),
+    ("当前壁纸缩略图", "Current wallpaper thumbnail"),
+    ("跟随系统", "Follow system"),
+    ("彩色", "Color"),
+    ("浅色任务栏", "Light taskbar"),
+    ("深色任务栏", "Dark taskbar"),
+    ("壁纸显示方式", "Wallpaper fit"),
+    ("填充", "Fill"),
+    ("适应", "Fit"),
+    ("拉伸", "Stretch"),
+    ("居中", "Center"),
+    ("平铺", "Tile"),
+    ("跨区", "Span"),
+    ("开机启动延迟", "Startup delay"),
+    ("不延迟", "No delay"),
+    ("{} 秒", "{} seconds"),
+    ("壁纸更换通知", "Wallpaper change notifications"),
+    ("退出时恢复原壁纸", "Restore original wallpaper on quit"),
+    ("同时设置锁屏壁纸", "Also set lock screen"),
+    ("更换时淡入淡出", "Fade between wallpapers"),
+    ("生成动态壁纸...", "Create Dynamic Wallpaper..."),
+    ("不支持的卡片位置: {}", "Unsupported overlay position: {}"),
+    ("卡片不透明度应在 {}% 到 100% 之间", "The overlay opacity must be between {}% and 100%"),
+    ("至少需要两张已下载的每日壁纸", "At least two downloaded daily wallpapers are needed"),
+    ("动态壁纸已保存到 {}，是否立即设为桌面壁纸？", "The dynamic wallpaper was saved to {}. Set it as the desktop wallpaper now?"),
+    ("动态壁纸", "Dynamic wallpaper"),
+    ("设为壁纸", "Set as wallpaper"),
+    ("稍后", "Later"),
+    ("设置动态壁纸", "Set dynamic wallpaper"),
+    ("设置动态壁纸失败", "Failed to set the dynamic wallpaper"),
+    ("生成动态壁纸", "Create dynamic wallpaper"),
+    ("生成动态壁纸失败", "Failed to create the dynamic wallpaper"),
+    ("动态壁纸已生成", "Dynamic wallpaper created"),
+    ("已打包最近 {} 张每日壁纸", "Bundled the latest {} daily wallpapers"),
+    ("设置淡入淡出失败", "Failed to change the fade setting"),
+    ("深色模式下调暗壁纸", "Dim wallpaper in dark mode"),
+    ("壁纸缓存", "Wallpaper cache"),
+    ("已占用 {}", "Using {}"),
+    ("已占用 未知", "Using unknown"),
+    ("清空缓存", "Clear cache"),
+    ("导出到文件夹...", "Export to folder..."),
+    ("导出为 ZIP...", "Export as ZIP..."),
+    ("下载最近壁纸", "Download recent wallpapers"),
+    ("正在获取归档...", "Fetching archive..."),
+    ("正在下载 {}/{}", "Downloading {}/{}"),
+    ("最近 {} 天", "Last {} days"),
+    ("搜索壁纸...", "Search wallpapers..."),
+    ("搜索壁纸", "Search wallpapers"),
+    ("没有找到该壁纸: {}", "Wallpaper not found: {}"),
+    ("猜猜这是哪里...", "Where is this?..."),
+    ("猜猜这是哪里", "Where is this?"),
+    ("至少需要 {} 张不同地点的已下载壁纸才能出题", "At least {} downloaded wallpapers from different places are needed for a quiz"),
+    ("设置...", "Settings..."),
+    ("在文件夹中显示", "Show in folder"),
+    ("复制当前壁纸路径", "Copy current wallpaper path"),
+    ("复制当前壁纸图片", "Copy current wallpaper image"),
+    ("复制当前壁纸地址", "Copy current wallpaper URL"),
+    ("复制壁纸图片", "Copying wallpaper image"),
+    ("复制壁纸图片失败", "Failed to copy wallpaper image"),
+    ("复制壁纸地址失败", "Failed to copy wallpaper URL"),
+    ("当前壁纸没有来源地址", "The current wallpaper has no source URL"),
+    ("查看日志", "View log"),
+    ("打开必应壁纸网站", "Open Bing Wallpaper website"),
+    ("退出", "Quit"),
+    // 窗口标题
+    ("更新内容", "What's new"),
+    ("欢迎使用 Bing Wallpaper", "Welcome to Bing Wallpaper"),
+    ("不支持的壁纸区域: {}", "Unsupported wallpaper region: {}"),
+    ("设置", "Settings"),
+    ("崩溃报告", "Crash report"),
+    // 通知
+    ("壁纸已更新", "Wallpaper updated"),
+    ("Bing Wallpaper 出现错误", "Bing Wallpaper error"),
+    ("{}：{}", "{}: {}"),
+    (
+        "Bing Wallpaper 上次运行时异常退出，已在本地生成 {} 份崩溃报告。\n是否将报告发送给开发者以帮助排查问题？\n报告仅包含版本、系统信息和错误堆栈。",
+        "Bing Wallpaper quit unexpectedly last time and saved {} crash reports.\nSend them to the developer to help track down the problem?\nReports only contain the version, system information and stack traces.",
+    ),
+    ("发送", "Send"),
+    ("不发送", "Don't send"),
+    // 错误类别
+    ("网络错误：{}", "Network error: {}"),
+    ("文件读写错误：{}", "File error: {}"),
+    ("数据库错误：{}", "Database error: {}"),
+    ("数据解析错误：{}", "Parse error: {}"),
+    ("系统设置错误：{}", "System error: {}"),
+    ("配置错误：{}", "Configuration error: {}"),
+    ("界面错误：{}", "UI error: {}"),
+    // 出错时的操作说明
+    ("定时刷新", "Scheduled refresh"),
+    ("定时刷新失败", "Scheduled refresh failed"),
+    ("壁纸刷新失败", "Failed to refresh wallpaper"),
+    ("切换国内壁纸失败", "Failed to switch to the China wallpaper"),
+    ("切换国际壁纸失败", "Failed to switch to the global wallpaper"),
+    ("切换自动选择区域失败", "Failed to switch to automatic region"),
+    ("设置开机启动失败", "Failed to change launch at login"),
+    ("切换托盘图标失败", "Failed to change tray icon"),
+    ("切换壁纸显示方式失败", "Failed to change wallpaper fit"),
+    ("设置启动延迟失败", "Failed to change startup delay"),
+    ("切换壁纸轮播失败", "Failed to toggle slideshow"),
+    ("设置轮播顺序失败", "Failed to change slideshow order"),
+    ("设置轮播间隔失败", "Failed to change slideshow interval"),
+    ("切换壁纸来源失败", "Failed to change wallpaper source"),
+    ("切换壁纸市场失败", "Failed to change market"),
+    ("设置历史壁纸失败", "Failed to apply wallpaper from history"),
+    ("设置轮播范围失败", "Failed to change slideshow source"),
+    ("收藏壁纸失败", "Failed to update favorites"),
+    ("设置收藏壁纸失败", "Failed to apply favorite wallpaper"),
+    ("跳过壁纸失败", "Failed to skip wallpaper"),
+    ("暂停更换壁纸失败", "Failed to pause wallpaper changes"),
+    ("恢复更换壁纸失败", "Failed to resume wallpaper changes"),
+    ("清理缓存失败", "Failed to clear cache"),
+    ("设置通知失败", "Failed to change notifications"),
+    ("恢复原壁纸失败", "Failed to restore original wallpaper"),
+    ("设置退出时恢复失败", "Failed to change restore on quit"),
+    ("设置锁屏壁纸失败", "Failed to set lock screen"),
+    ("设置深色壁纸失败", "Failed to change dark wallpaper"),
+    ("打开壁纸位置失败", "Failed to show wallpaper in folder"),
+    ("复制壁纸路径失败", "Failed to copy wallpaper path"),
+    ("打开设置窗口失败", "Failed to open settings"),
+    ("打开搜索窗口失败", "Failed to open search"),
+    ("打开猜地点窗口失败", "Failed to open quiz"),
+    ("打开关于窗口失败", "Failed to open the About window"),
+    ("打开壁纸文件夹", "Open wallpaper folder"),
+    ("打开当前壁纸图片", "Open current wallpaper"),
+    ("打开壁纸文件夹失败", "Failed to open the wallpaper folder"),
+    ("打开壁纸图片失败", "Failed to open the wallpaper image"),
+    ("无法识别的快捷键: {}", "Unrecognized shortcut: {}"),
+    ("上一张", "Previous wallpaper"),
+    ("无法识别的链接: {}", "Unrecognized link: {}"),
+    ("控制接口端口应在 {} 到 65535 之间", "The control API port must be between {} and 65535"),
+    ("控制接口", "Control API"),
+    ("Webhook 地址无效: {}", "Invalid webhook URL: {}"),
+    ("Webhook 地址必须以 http:// 或 https:// 开头", "The webhook URL must start with http:// or https://"),
+    ("导出收藏和设置...", "Export Favorites and Settings..."),
+    ("导入收藏和设置...", "Import Favorites and Settings..."),
+    ("不支持的同步文件版本: {}", "Unsupported sync file version: {}"),
+    ("同步文件夹正被占用，请稍后再试", "The sync folder is busy, please try again later"),
+    ("同步", "Sync"),
+    ("导出收藏和设置", "Export favorites and settings"),
+    ("导出收藏和设置失败", "Failed to export favorites and settings"),
+    ("已导出收藏和设置到 {}", "Exported favorites and settings to {}"),
+    ("导入收藏和设置", "Import favorites and settings"),
+    ("导入收藏和设置失败", "Failed to import favorites and settings"),
+    ("导入完成", "Import finished"),
+    ("已导入 {} 中的收藏和设置", "Imported favorites and settings from {}"),
+    ("处理链接", "Handling link"),
+    ("处理链接失败", "Failed to handle link"),
+    ("下一张", "Next wallpaper"),
+    ("切换壁纸", "Switching wallpaper"),
+    ("快捷键", "Hotkey"),
+    ("切换下一张壁纸失败", "Failed to switch to the next wallpaper"),
+    ("切换上一张壁纸失败", "Failed to switch to the previous wallpaper"),
+    ("没有可以切换的壁纸", "No wallpapers to switch to"),
+    ("没有可以返回的上一张壁纸", "No previous wallpaper to go back to"),
+    ("已暂停更换壁纸", "Wallpaper changes paused"),
+    ("暂停到 {}", "Paused until {}"),
+    ("已恢复更换壁纸", "Wallpaper changes resumed"),
+    ("定时刷新和轮播将照常进行", "Scheduled refreshes and the slideshow will continue as usual"),
+    ("关于", "About"),
+    ("关于 Bing Wallpaper", "About Bing Wallpaper"),
+    ("移动壁纸", "Move wallpapers"),
+    ("移动壁纸失败", "Failed to move wallpapers"),
+    ("壁纸已移动", "Wallpapers moved"),
+    ("已把 {} 张壁纸移动到 {}", "Moved {} wallpapers to {}"),
+    ("生成缩略图", "Generating thumbnails"),
+    ("提取壁纸主色", "Extracting wallpaper colors"),
+    ("导出壁纸", "Export wallpapers"),
+    ("导出壁纸失败", "Failed to export wallpapers"),
+    ("导出完成", "Export finished"),
+    ("已导出 {} 张壁纸到 {}", "Exported {} wallpapers to {}"),
+    ("批量下载", "Batch download"),
+    ("批量下载失败", "Batch download failed"),
+    ("批量下载完成", "Batch download finished"),
+    ("没有需要下载的新壁纸", "No new wallpapers to download"),
+    ("已下载 {} 张壁纸，{} 张失败", "Downloaded {} wallpapers, {} failed"),
+    ("打开日志失败", "Failed to open log"),
+    ("打开网站失败", "Failed to open website"),
+    ("窗口操作", "Window action"),
+    ("菜单事件", "Menu action"),
+    ("重复启动", "Second instance"),
+    ("重新设置壁纸", "Re-applying wallpaper"),
+    ("设置历史壁纸", "Applying wallpaper from history"),
+    ("设置收藏壁纸", "Applying favorite wallpaper"),
+    ("壁纸轮播", "Slideshow"),
+    ("壁纸轮播失败", "Slideshow failed"),
+    // 错误说明
+    ("请先在设置中填写 Unsplash Access Key", "Enter an Unsplash Access Key in settings first"),
+    ("请先在设置中选择本地壁纸文件夹", "Choose a local wallpaper folder in settings first"),
+    ("本地壁纸文件夹不存在: {}", "Local wallpaper folder does not exist: {}"),
+    ("缓存中没有该壁纸: {}", "Wallpaper is not in the cache: {}"),
+    ("不支持的刷新模式: {}", "Unsupported refresh mode: {}"),
+    ("刷新间隔必须大于 0 分钟", "Refresh interval must be greater than 0 minutes"),
+    ("不支持的星期: {}", "Unsupported weekday: {}"),
+    ("不支持的控制操作: {}", "Unsupported control action: {}"),
+    ("已安装后台服务: {}", "Daemon service installed: {}"),
+    ("已删除后台服务: {}", "Daemon service removed: {}"),
+    ("不支持的星期规则: {}", "Unsupported weekday rule: {}"),
+    ("不支持的刷新方式: {}", "Unsupported schedule: {}"),
+    ("轮播间隔必须大于 0 分钟", "Slideshow interval must be greater than 0 minutes"),
+    ("不支持的轮播顺序: {}", "Unsupported slideshow order: {}"),
+    ("不支持的轮播范围: {}", "Unsupported slideshow source: {}"),
+    ("请选择要轮播的本地文件夹", "Choose a local folder for the slideshow"),
+    ("本地文件夹不存在: {}", "Local folder does not exist: {}"),
+    ("不支持的壁纸市场: {}", "Unsupported market: {}"),
+    ("不支持的分辨率: {}", "Unsupported resolution: {}"),
+    ("至少需要保留 1 张壁纸", "Keep at least 1 wallpaper"),
+    ("磁盘剩余空间下限至少为 1 MB", "Minimum free disk space must be at least 1 MB"),
+    ("保存目录所在磁盘只剩 {}，低于设置的 {}，已跳过下载", "Only {} free on the disk of the save folder, below the minimum of {}; download skipped"),
+    ("不支持的代理模式: {}", "Unsupported proxy mode: {}"),
+    ("请填写代理服务器地址", "Enter the proxy server address"),
+    ("超时应在 1 到 {} 秒之间", "Timeouts must be between 1 and {} seconds"),
+    ("电量阈值应在 0 到 100 之间", "The battery threshold must be between 0 and 100"),
+    ("不支持的设备标识设置: {}", "Unsupported device ID setting: {}"),
+    ("不支持的更新通道: {}", "Unsupported update channel: {}"),
+    ("发现新版本", "Update available"),
+    ("Bing Wallpaper {} 已下载，将在退出时自动安装", "Bing Wallpaper {} has been downloaded and will be installed on quit"),
+    ("新版本 {} 可用，点击安装并重启", "Version {} available, click to install and restart"),
+    ("检查更新", "Checking for updates"),
+    ("安装更新失败", "Failed to install the update"),
+    ("更新包不可用，请稍后重试", "The update package is unavailable, please try again later"),
+    ("不支持的代理协议: {}，请使用 HTTP 或 SOCKS5 代理", "Unsupported proxy scheme: {}, use an HTTP or SOCKS5 proxy"),
+    ("不支持的日志级别: {}", "Unsupported log level: {}"),
+    ("不支持的接口来源: {}", "Unsupported API source: {}"),
+    ("不支持的界面语言: {}", "Unsupported language: {}"),
+    ("模糊强度不能超过 {}", "Blur strength cannot exceed {}"),
+    ("调暗比例不能超过 {}%", "Dimming cannot exceed {}%"),
+    ("饱和度调整应在 ±{}% 之间", "Saturation adjustment must be within ±{}%"),
+    ("不支持的水印位置: {}", "Unsupported watermark position: {}"),
+    ("水印字号应在 {} 到 {} 之间", "Watermark font size must be between {} and {}"),
+    ("水印字体文件不存在: {}", "Watermark font file does not exist: {}"),
+    ("找不到可用的水印字体，请在设置中指定字体文件: {}", "No usable watermark font found, choose a font file in settings: {}"),
+    ("无法使用保存目录 {}: {}", "Cannot use save directory {}: {}"),
+    ("设置已保存，但部分设置未能生效：\n{}", "Settings were saved, but some of them could not be applied:\n{}"),
+    ("接口地址无效: {}", "Invalid API address: {}"),
+    ("接口地址必须以 http:// 或 https:// 开头", "API address must start with http:// or https://"),
+    ("接口地址不能包含查询参数", "API address must not contain a query string"),
+    // 命令行
+    ("错误: {}", "Error: {}"),
+    ("{} 需要一个参数", "{} requires a value"),
+    ("不支持的区域: {}", "Unsupported region: {}"),
+    ("不支持的市场: {}", "Unsupported market: {}"),
+    ("日期格式应为 YYYY-MM-DD: {}", "Date must be in YYYY-MM-DD format: {}"),
+    ("未知参数: {}", "Unknown option: {}"),
+];
+
+impl Language {
+    fn from_code(code: &str) -> Option<Self> {
+        let code = code.to_ascii_lowercase();
+        if code.starts_with("zh") {
+            Some(Language::ZhCn)
+        } else if code.starts_with("en") {
+            Some(Language::EnUs)
+        } else {
+            None
+        }
+    }
+
+    // 系统语言既不是中文也不是英文时使用英文界面
+    fn detect() -> Self {
+        sys_locale::get_locale().map_or(Language::ZhCn, |locale| Language::from_code(&locale).unwrap_or(Language::EnUs))
+    }
+}
+
+// 按设置中的语言（空字符串表示跟随系统）切换界面语言，启动时和保存设置后调用
+pub(crate) fn set_language(setting: &str) {
+    let language = Language::from_code(setting).unwrap_or_else(Language::detect);
+    CURRENT.store(language as u8, Ordering::Relaxed);
+    info!("Using UI language {}", if language == Language::EnUs { LANGUAGE_EN_US } else { LANGUAGE_ZH_CN });
+}
+
+pub(crate) fn is_english() -> bool {
+    CURRENT.load(Ordering::Relaxed) == Language::EnUs as u8
+}
+
+// 当前语言下的文字，没有译文时返回原文
+pub(crate) fn tr(text: &'static str) -> &'static str {
+    if !is_english() {
+        return text;
+    }
+    EN_US.iter().find(|(key, _)| *key == text).map_or(text, |(_, translated)| translated)
+}
+
+// 先翻译再依次替换其中的 {} 占位
+pub(crate) fn tr_fmt(text: &'static str, args: &[&str]) -> String {
+    args.iter().fold(tr(text).to_string(), |result, arg| result.replacen("{}", arg, 1))
+}
+
+// 只有一个占位时的简写，参数可以是任意可显示的值
+pub(crate) fn tr_with(text: &'static str, arg: impl std::fmt::Display) -> String {
+    tr_fmt(text, &[&arg.to_string()])
+}
+
+// 菜单中已选中的选项在文字后加上 ✓
+pub(crate) fn checked(text: &'static str, selected: bool) -> String {
+    if selected { format!("{} ✓", tr(text)) } else { tr(text).to_string() }
+}
@@ -1,60 +1,152 @@
+mod about;
+mod atomic_file;
+mod api;
+mod appearance;
+mod batch;
+mod blocklist;
+mod cache;
+mod cli;
+mod commands;
+mod control_api;
+mod convert;
+mod crash_report;
+mod daemon;
+mod deep_link;
+mod desktop_sync;
+mod disk_space;
+mod download_lock;
+#[cfg(target_os = "macos")]
+mod dynamic_wallpaper;
+mod export;
+#[cfg(target_os = "windows")]
+mod fade;
+mod favorites;
+mod history;
+mod hotkey;
+mod hook;
+mod http;
+mod i18n;
+mod library;
+mod local_folder;
+mod logging;
+mod metadata;
+mod navigation;
+mod onboarding;
+mod overlay;
+mod palette;
+mod portable;
+mod processing;
+mod quiz;
+mod refresh_policy;
+mod region;
+#[cfg(target_os = "windows")]
+mod registry;
+mod scheduler;
+mod service;
+mod settings;
+mod slideshow;
+mod store;
+mod sync;
+mod thumbnail;
+mod tray_thumbnail;
+mod updater;
+mod wallpaper_setter;
+mod watchdog;
+mod watermark;
+mod webhook;
+
 use std::{
+    any::Any,
+    collections::BTreeMap,
     fs::{self, File},
-    io::{Write, Read},
-    path::PathBuf,
+    future::Future,
+    io::Read,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
     process::Command,
-    thread::{self, JoinHandle},
-    time::Duration,
+    time::{Duration, Instant},
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+        Mutex, MutexGuard,
     },
 };
+use futures_util::FutureExt;
 use log::{info, error, warn};
 use tauri::{
-    Manager,
-    menu::{Menu, MenuItem},
-    tray::{TrayIcon, TrayIconBuilder}
+    Emitter, Manager,
+    image::Image,
+    menu::{Menu, MenuItem, Submenu},
+    tray::TrayIconBuilder,
+    WebviewUrl, WebviewWindowBuilder, WindowEvent,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use blocklist::Blocklist;
+use favorites::Favorites;
+use history::WallpaperHistory;
+use i18n::{checked, tr, tr_fmt, tr_with};
+use scheduler::{Scheduler, Trigger};
 use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
-
-#[cfg(target_os = "windows")]
-use winapi::{
-    um::winuser::{SystemParametersInfoA, SPI_SETDESKWALLPAPER, SPIF_UPDATEINIFILE, SPIF_SENDCHANGE},
-    shared::minwindef::TRUE,
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_notification::NotificationExt;
+use wallpaper_core::{
+    random_u64, CancellationToken, Downloader, Fit, HttpDownloader, Resolution, Transient, WallpaperInfo,
 };
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+use wallpaper_core::setter::percent_encode_path;
 
-const REFRESH_INTERVAL: u64 = 600; // 10分钟
-const CHINA_API_URL: &str = "https://bing.wdbyte.com/zh-cn/today";
-const GLOBAL_API_URL: &str = "https://bing.wdbyte.com/today";
+const DEFAULT_REFRESH_INTERVAL_MINUTES: u64 = 10;
+const STARTUP_DELAY_OPTIONS: [u64; 4] = [0, 30, 60, 120];
+// 连续失败多少次刷新后进入离线模式
+const OFFLINE_FAILURE_THRESHOLD: u32 = 3;
+// 退出时等待进行中的下载结束的最长时间，超时后直接退出
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+// 国际模式可选的必应市场，空字符串表示使用接口默认市场
+const MARKETS: [(&str, &str); 12] = [
+    ("", "默认"),
+    ("en-US", "美国"),
+    ("en-GB", "英国"),
+    ("en-CA", "加拿大"),
+    ("en-AU", "澳大利亚"),
+    ("en-IN", "印度"),
+    ("ja-JP", "日本"),
+    ("de-DE", "德国"),
+    ("fr-FR", "法国"),
+    ("it-IT", "意大利"),
+    ("es-ES", "西班牙"),
+    ("pt-BR", "巴西"),
+];
 const UUID_FILE_NAME: &str = "device_uuid.txt";
+// 设备标识的处理方式：persistent 一直使用同一个（默认），rotate 定期重新生成，off 不生成也不发送
+const DEVICE_ID_PERSISTENT: &str = "persistent";
+const DEVICE_ID_ROTATE: &str = "rotate";
+const DEVICE_ID_OFF: &str = "off";
+// rotate 方式下设备标识的有效期
+const DEVICE_ID_ROTATE_INTERVAL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
 const CONFIG_FILE_NAME: &str = "config.json";
-
-// 简单的日志实现
-static LOGGER: SimpleLogger = SimpleLogger;
-
-struct SimpleLogger;
-
-impl log::Log for SimpleLogger {
-    fn enabled(&self, metadata: &log::Metadata) -> bool {
-        metadata.level() <= log::Level::Info
-    }
-
-    fn log(&self, record: &log::Record) {
-        if self.enabled(record.metadata()) {
-            eprintln!("{} - {}", record.level(), record.args());
-        }
-    }
-
-    fn flush(&self) {}
-}
+const DEFAULT_WALLPAPER_DIR_NAME: &str = "wallpapers";
+// 开机自启动时附带的命令行参数，用于区分登录启动和手动启动
+const AUTOSTART_ARG: &str = "--autostart";
+const TRAY_ID: &str = "main";
+const WHATS_NEW_WINDOW_LABEL: &str = "whats-new";
+const CONTROL_WINDOW_LABEL: &str = "control";
+// 当前壁纸变化时向前端广播的事件
+const WALLPAPER_CHANGED_EVENT: &str = "wallpaper-changed";
+const WALLPAPER_PALETTE_EVENT: &str = "wallpaper-palette";
+const CHANGELOG: &str = include_str!("../../CHANGELOG.md");
+const TRAY_ICON_LIGHT: &[u8] = include_bytes!("../icons/tray/light.png");
+const TRAY_ICON_DARK: &[u8] = include_bytes!("../icons/tray/dark.png");
+#[cfg(target_os = "macos")]
+const TRAY_ICON_TEMPLATE: &[u8] = include_bytes!("../icons/tray/template.png");
 
 #[derive(Clone, Copy, PartialEq)]
 enum RefreshMode {
     DailyChina,
     DailyGlobal,
+    // 按系统语言、时区和网络探测自动选择中国区或国际区
+    DailyAuto,
+    // 不联网，轮流展示本地缓存的壁纸
+    Slideshow,
     None,
 }
 
@@ -63,6 +155,8 @@ impl RefreshMode {
         match self {
             RefreshMode::DailyChina => "china",
             RefreshMode::DailyGlobal => "global",
+            RefreshMode::DailyAuto => "auto",
+            RefreshMode::Slideshow => "slideshow",
             RefreshMode::None => "none",
         }
     }
@@ -71,115 +165,582 @@ impl RefreshMode {
         match s {
             "china" => RefreshMode::DailyChina,
             "global" => RefreshMode::DailyGlobal,
+            "auto" => RefreshMode::DailyAuto,
+            "slideshow" => RefreshMode::Slideshow,
             _ => RefreshMode::None,
         }
     }
 
+    // 自动模式返回最近一次探测的结果
     fn is_china(&self) -> bool {
-        matches!(self, RefreshMode::DailyChina)
+        match self {
+            RefreshMode::DailyChina => true,
+            RefreshMode::DailyAuto => region::is_china(),
+            _ => false,
+        }
+    }
+
+    // 是否为联网获取每日壁纸的模式
+    fn is_daily(&self) -> bool {
+        matches!(self, RefreshMode::DailyChina | RefreshMode::DailyGlobal | RefreshMode::DailyAuto)
+    }
+}
+
+// 托盘图标风格：Auto 按系统外观自动选择，Light/Dark 表示菜单栏/任务栏的底色，
+// Wallpaper 显示当前壁纸的缩略图
+#[derive(Clone, Copy, PartialEq)]
+enum TrayIconTheme {
+    Auto,
+    Color,
+    Light,
+    Dark,
+    Wallpaper,
+}
+
+impl TrayIconTheme {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TrayIconTheme::Auto => "auto",
+            TrayIconTheme::Color => "color",
+            TrayIconTheme::Light => "light",
+            TrayIconTheme::Dark => "dark",
+            TrayIconTheme::Wallpaper => "wallpaper",
+        }
+    }
+
+    fn from_str(s: &str) -> Self {
+        match s {
+            "color" => TrayIconTheme::Color,
+            "light" => TrayIconTheme::Light,
+            "dark" => TrayIconTheme::Dark,
+            "wallpaper" => TrayIconTheme::Wallpaper,
+            _ => TrayIconTheme::Auto,
+        }
     }
 }
 
 #[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
 struct AppConfig {
     refresh_mode: String,
+    tray_icon_theme: String,
+    // 启动后首次拉取壁纸前等待的秒数
+    startup_delay_secs: u64,
+    refresh_interval_minutes: u64,
+    // 刷新方式：interval 按间隔轮询，daily 每天在 daily_time 检查一次
+    schedule_mode: String,
+    daily_time: String,
+    // 按星期的规则（键为 mon..sun），见 scheduler::today_rule
+    day_rules: BTreeMap<String, String>,
+    // 壁纸分辨率，auto 表示使用接口默认值
+    resolution: String,
+    // 国际模式使用的必应市场，例如 ja-JP；为空时使用接口默认市场
+    market: String,
+    // 轮播模式的切换间隔（分钟，0 表示默认值）和顺序：shuffle 随机，sequential 按下载先后
+    slideshow_interval_minutes: u64,
+    slideshow_order: String,
+    // 轮播范围：为空或 all 轮播全部缓存，favorites 只轮播收藏，folder 轮播本地文件夹
+    slideshow_source: String,
+    // 本地文件夹来源的目录、是否包含子目录，以及只轮播的格式（例如 "jpg,png"，为空表示全部）
+    local_folder: String,
+    local_folder_recursive: bool,
+    local_folder_formats: String,
+    // 壁纸保存目录，为空时使用应用数据目录
+    save_dir: String,
+    // 代理：none 不使用，system 跟随系统（默认），manual 使用下面的手动配置
+    proxy_mode: String,
+    proxy_host: String,
+    proxy_port: u16,
+    proxy_username: String,
+    proxy_password: String,
+    // 建立连接、普通接口请求和下载一张图片的超时（秒，0 表示默认值）
+    connect_timeout_secs: u64,
+    request_timeout_secs: u64,
+    download_timeout_secs: u64,
+    // 下载图片的速度上限（KB/s，0 表示不限速）
+    download_rate_limit_kbps: u64,
+    // 保存目录所在磁盘至少保留的剩余空间（MB，0 表示默认值），不足时清理缓存，仍不够则跳过下载
+    min_free_space_mb: u64,
+    // 处于按流量计费的网络时推迟定时刷新；使用电池且电量低于该百分比时推迟（0 表示不检查电量）
+    defer_on_metered: bool,
+    battery_min_percent: u8,
+    // 有全屏程序（游戏、视频、演示）在前台时推迟定时刷新和系统通知，退出全屏后补上
+    defer_when_fullscreen: bool,
+    // 关闭壁纸被系统或其它程序换掉后的自动改回
+    drift_correction_disabled: bool,
+    // 更新通道：为空或 stable 只接收正式版，beta 同时接收测试版，off 不检查更新
+    update_channel: String,
+    // 设备标识：为空或 persistent 一直使用同一个，rotate 每 30 天重新生成，off 不发送
+    device_id_mode: String,
+    // 关闭更换壁纸后的系统通知
+    notifications_disabled: bool,
+    // 首次设置必应壁纸前系统壁纸的备份路径，以及退出时是否自动恢复
+    original_wallpaper: String,
+    restore_on_quit: bool,
+    // 缓存保留的壁纸数量（0 表示默认值）和总大小上限（MB，0 表示不限制）
+    cache_max_files: usize,
+    cache_max_mb: u64,
+    // 日志级别：error、warn、info、debug、trace，为空时使用 info
+    log_level: String,
+    // 自建镜像等自定义接口地址，为空时使用 bing.wdbyte.com
+    api_base_url: String,
+    // 接口来源：为空或 auto 时主接口连续失败后改用必应官方接口，wdbyte/bing 固定使用其一
+    api_source: String,
+    // 壁纸来源：bing（默认）、spotlight、apod、unsplash
+    wallpaper_provider: String,
+    // NASA APOD 的 API key，为空时使用有频率限制的 DEMO_KEY
+    apod_api_key: String,
+    // Unsplash 的 Access Key 和可选的合集 ID
+    unsplash_access_key: String,
+    unsplash_collection: String,
+    // 界面语言：zh-CN、en-US，为空时跟随系统
+    language: String,
+    // 壁纸更换后执行的命令，为空时不执行
+    post_change_hook: String,
+    // 壁纸更换后 POST 壁纸信息的地址，为空时不发送
+    webhook_url: String,
+    // 全局快捷键：下一张、上一张、暂停或恢复，为空时使用默认值；hotkeys_disabled 关闭全部快捷键
+    hotkeys_disabled: bool,
+    hotkey_next: String,
+    hotkey_previous: String,
+    hotkey_pause: String,
+    // 本机 HTTP 控制接口：是否开启、端口（0 表示默认值）和访问令牌
+    control_api_enabled: bool,
+    control_api_port: u16,
+    control_api_token: String,
+    // 多台电脑同步收藏、跳过列表和设置使用的文件夹，为空时不同步
+    sync_folder: String,
+    // 同时设置 Windows 锁屏图片
+    lock_screen_enabled: bool,
+    // 桌面信息卡片：是否显示、所在角落（与水印相同的取值）和背景不透明度百分比（0 表示默认值）
+    overlay_enabled: bool,
+    overlay_corner: String,
+    overlay_opacity: u32,
+    // 更换壁纸时在新旧壁纸之间淡入淡出，仅 Windows
+    fade_transition: bool,
+    // macOS 深色外观下使用调暗的壁纸
+    dark_variant_enabled: bool,
+    // 设置前的图片处理：高斯模糊 sigma、调暗百分比、饱和度增减百分比，全为 0 时不处理
+    process_blur: u32,
+    process_dim: u32,
+    process_saturation: i32,
+    // 在壁纸一角绘制标题和版权：位置、字号（0 为默认）、字体文件（为空时使用系统字体）
+    watermark_enabled: bool,
+    watermark_corner: String,
+    watermark_font_size: u32,
+    watermark_font: String,
+    // 各刷新模式（键为 RefreshMode::as_str）使用的壁纸显示方式，未设置的模式保持系统当前的方式
+    wallpaper_fit: BTreeMap<String, String>,
+    // 上次运行的版本号，用于升级后展示更新内容
+    last_run_version: String,
 }
 
-fn save_config(mode: RefreshMode) -> Result<()> {
-    let config = AppConfig {
-        refresh_mode: mode.as_str().to_string(),
-    };
+fn save_config(config: &AppConfig) -> Result<()> {
     let config_path = get_app_data_dir()?.join(CONFIG_FILE_NAME);
-    let json = serde_json::to_string_pretty(&config)?;
-    File::create(&config_path)?.write_all(json.as_bytes())?;
-    info!("Saved config: refresh_mode = {}", mode.as_str());
+    let json = serde_json::to_string_pretty(config)?;
+    atomic_file::write(&config_path, json)?;
+    info!("Saved config: refresh_mode = {}", config.refresh_mode);
     Ok(())
 }
 
-fn load_config() -> Result<RefreshMode> {
+fn load_config() -> Result<AppConfig> {
     let config_path = get_app_data_dir()?.join(CONFIG_FILE_NAME);
     if !config_path.exists() {
-        return Ok(RefreshMode::None);
+        return Ok(AppConfig::default());
     }
     let mut contents = String::new();
     File::open(config_path)?.read_to_string(&mut contents)?;
     let config: AppConfig = serde_json::from_str(&contents)?;
-    let mode = RefreshMode::from_str(&config.refresh_mode);
-    info!("Loaded config: refresh_mode = {}", mode.as_str());
-    Ok(mode)
+    info!("Loaded config: refresh_mode = {}", config.refresh_mode);
+    Ok(config)
+}
+
+// 读取配置、修改后写回，避免覆盖其它字段
+// 配置文件内容损坏时先备份为 config.json.bak 再从默认值重建，否则之后的设置将永远无法保存；
+// 读取失败（例如权限不足）时返回错误，不覆盖原文件
+fn update_config<F: FnOnce(&mut AppConfig)>(f: F) -> Result<()> {
+    let mut config = match load_config() {
+        Ok(config) => config,
+        Err(e) if is_corrupt_config(&e) => {
+            let config_path = get_app_data_dir()?.join(CONFIG_FILE_NAME);
+            let backup_path = config_path.with_file_name(format!("{}.bak", CONFIG_FILE_NAME));
+            fs::copy(&config_path, &backup_path)?;
+            warn!("Config is corrupt ({}), backed up to {:?} and rebuilding from defaults", e, backup_path);
+            AppConfig::default()
+        }
+        Err(e) => return Err(e),
+    };
+    f(&mut config);
+    save_config(&config)
+}
+
+// JSON 无法解析，或内容不是有效的 UTF-8
+fn is_corrupt_config(err: &AppError) -> bool {
+    match err {
+        AppError::Parse(_) => true,
+        AppError::Io(e) => e.kind() == std::io::ErrorKind::InvalidData,
+        _ => false,
+    }
+}
+
+// 记录本次运行的版本号，返回是否为升级后的首次运行（全新安装不算）
+fn check_version_upgrade(current_version: &str) -> Result<bool> {
+    let mut config = load_config()?;
+    if config.last_run_version == current_version {
+        return Ok(false);
+    }
+    let upgraded = !config.last_run_version.is_empty();
+    info!("Version changed: {:?} -> {}", config.last_run_version, current_version);
+    config.last_run_version = current_version.to_string();
+    save_config(&config)?;
+    Ok(upgraded)
+}
+
+#[tauri::command]
+fn get_changelog() -> &'static str {
+    CHANGELOG
+}
+
+fn show_whats_new_window(app: &tauri::AppHandle) -> Result<()> {
+    if let Some(window) = app.get_webview_window(WHATS_NEW_WINDOW_LABEL) {
+        window.set_focus()?;
+        return Ok(());
+    }
+    WebviewWindowBuilder::new(app, WHATS_NEW_WINDOW_LABEL, WebviewUrl::App("whats-new.html".into()))
+        .title(tr("更新内容"))
+        .inner_size(420.0, 520.0)
+        .resizable(false)
+        .build()?;
+    Ok(())
 }
 
+// 定时刷新或轮播的后台任务，取消令牌用于停止任务
+type RefreshTask = (tauri::async_runtime::JoinHandle<()>, CancellationToken);
+
 struct AppState {
     refresh_mode: RefreshMode,
-    timer_handle: Option<(JoinHandle<()>, Arc<AtomicBool>)>,
+    timer_handle: Option<RefreshTask>,
+    tray_icon_theme: TrayIconTheme,
+    current_wallpaper: Option<WallpaperInfo>,
+    startup_delay_secs: u64,
+    history: WallpaperHistory,
+    favorites: Favorites,
+    notifications_enabled: bool,
+    // 连续的网络失败次数，达到阈值后进入离线模式
+    network_failures: u32,
+    offline: bool,
+    restore_on_quit: bool,
+    // 最近一次失败的说明，显示在托盘菜单中，刷新成功后清除
+    last_error: Option<String>,
+    // 暂停更换壁纸的截止时间，期间定时刷新和轮播都不会执行，刷新模式保持不变
+    paused_until: Option<chrono::DateTime<chrono::Local>>,
+    // 正在进行的批量下载进度，没有批量下载时为 None
+    batch_progress: Option<batch::Progress>,
+    // 当前壁纸的主色，壁纸更换后在后台提取
+    palette: Option<palette::Palette>,
+    // 最近一次成功设置壁纸（包括刷新后壁纸未变化）的时间，以及定时任务计划的下一次刷新时间，显示在状态菜单中
+    last_refresh: Option<chrono::DateTime<chrono::Local>>,
+    next_refresh: Option<chrono::DateTime<chrono::Local>>,
+    // 正在进行的手动刷新，切换刷新模式时取消
+    manual_refresh: Option<CancellationToken>,
+    // 定时刷新因计费网络或电量低被推迟时的原因，显示在状态菜单中
+    deferred: Option<String>,
+    // 已在后台下载、等待安装的新版本号
+    update_available: Option<String>,
+    // 按顺序设置过的壁纸文件，供“上一张 / 下一张”使用
+    applied: navigation::AppliedHistory,
+    // 定时任务意外结束、被看门狗重新启动的时间，显示在状态菜单中
+    task_restarted: Option<chrono::DateTime<chrono::Local>>,
+}
+
+impl AppState {
+    // 仍在暂停中时返回剩余的暂停时长
+    fn pause_remaining(&self) -> Option<Duration> {
+        self.paused_until
+            .and_then(|until| (until - chrono::Local::now()).to_std().ok())
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    // 记录当前壁纸，并同步写入历史记录；返回壁纸是否发生了变化
+    fn set_current_wallpaper(&mut self, app: &tauri::AppHandle, wallpaper: WallpaperInfo) -> bool {
+        let changed = self.current_wallpaper.as_ref()
+            .map(|current| current.file_name != wallpaper.file_name)
+            .unwrap_or(true);
+        self.history.merge(vec![wallpaper.clone()]);
+        if let Err(e) = self.history.save() {
+            error!("Failed to save wallpaper history: {}", e);
+        }
+        self.last_refresh = Some(chrono::Local::now());
+        if changed {
+            // 优先使用刚设置的文件，本地文件夹中的图片不在保存目录
+            let path = wallpaper_setter::last_applied()
+                .filter(|path| path.file_name().is_some_and(|name| name == wallpaper.file_name.as_str()))
+                .or_else(|| get_wallpaper_path(&wallpaper.file_name).ok().filter(|path| path.is_file()));
+            if let Some(path) = path {
+                self.applied.record(path);
+            }
+            emit_wallpaper_changed(app, Some(&wallpaper));
+            self.palette = None;
+            run_post_change_in_background(app, wallpaper.clone());
+        }
+        self.current_wallpaper = Some(wallpaper);
+        changed
+    }
+}
+
+// current.jpg 指向刚设置的文件（Windows 上是它的副本），本地文件夹中的图片也能得到正确路径。
+// 主色需要解码图片，在后台线程提取后广播给窗口，再连同壁纸信息传给更换后执行的命令和 Webhook
+fn run_post_change_in_background(app: &tauri::AppHandle, wallpaper: WallpaperInfo) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        run_guarded(&app, "提取壁纸主色", || {
+            let path = match wallpaper_setter::current_link_path().and_then(|link| Ok(fs::canonicalize(link)?)) {
+                Ok(path) => path,
+                Err(e) => {
+                    warn!("Failed to resolve current wallpaper: {}", e);
+                    return;
+                }
+            };
+            let palette = palette::extract(&path, &wallpaper.file_name)
+                .inspect_err(|e| warn!("Failed to extract palette of {:?}: {}", path, e))
+                .ok();
+            if let Some(palette) = &palette {
+                let state = app.state::<Mutex<AppState>>();
+                let mut state = lock_state(&state);
+                // 期间又换了壁纸时丢弃
+                if state.current_wallpaper.as_ref().is_some_and(|current| current.file_name == palette.file_name) {
+                    state.palette = Some(palette.clone());
+                    if let Err(e) = app.emit(WALLPAPER_PALETTE_EVENT, palette) {
+                        warn!("Failed to emit {}: {}", WALLPAPER_PALETTE_EVENT, e);
+                    }
+                }
+            }
+            if lock_state(&app.state::<Mutex<AppState>>()).tray_icon_theme == TrayIconTheme::Wallpaper {
+                if let Err(e) = apply_tray_icon(&app, TrayIconTheme::Wallpaper) {
+                    warn!("Failed to update tray icon: {}", e);
+                }
+            }
+            if let Err(e) = hook::run_post_change(&wallpaper, &path, palette.as_ref()) {
+                warn!("Failed to run post-change hook: {}", e);
+            }
+            if let Err(e) = tauri::async_runtime::block_on(webhook::post_change(&wallpaper, &path)) {
+                warn!("Failed to post webhook: {}", e);
+            }
+        })
+    });
+}
+
+// 通知所有窗口当前壁纸已变化，载荷为新的壁纸信息，恢复原壁纸时为 null
+fn emit_wallpaper_changed(app: &tauri::AppHandle, wallpaper: Option<&WallpaperInfo>) {
+    if let Err(e) = app.emit(WALLPAPER_CHANGED_EVENT, wallpaper) {
+        warn!("Failed to emit {}: {}", WALLPAPER_CHANGED_EVENT, e);
+    }
+}
+
+// 回调中被捕获的 panic 会让锁中毒，这里直接取回内部状态继续使用
+fn lock_state(state: &Mutex<AppState>) -> MutexGuard<'_, AppState> {
+    state.lock().unwrap_or_else(|poisoned| {
+        warn!("State mutex was poisoned, recovering");
+        poisoned.into_inner()
+    })
 }
 
-// 简化的错误类型
-#[derive(Debug)]
-struct AppError(String);
+fn notify(app: &tauri::AppHandle, title: &str, body: &str) {
+    if refresh_policy::hold_notification(app, title, body) {
+        return;
+    }
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        error!("Failed to show notification: {}", e);
+    }
+}
 
-impl From<std::io::Error> for AppError {
-    fn from(err: std::io::Error) -> Self {
-        AppError(err.to_string())
+// 记录最近一次错误并通知用户；同样的错误连续出现时只通知一次，避免定时刷新反复打扰
+fn report_error(app: &tauri::AppHandle, context: &'static str, err: &AppError) {
+    error!("{}: {}", context, err);
+    let message = tr_fmt("{}：{}", &[tr(context), &err.to_string()]);
+    let repeated = {
+        let state = app.state::<Mutex<AppState>>();
+        let mut state = lock_state(&state);
+        let repeated = state.last_error.as_deref() == Some(message.as_str());
+        state.last_error = Some(message.clone());
+        repeated
+    };
+    schedule_menu_update(app);
+    if !repeated {
+        notify(app, tr("Bing Wallpaper 出现错误"), &message);
     }
 }
 
-impl From<minreq::Error> for AppError {
-    fn from(err: minreq::Error) -> Self {
-        AppError(err.to_string())
+fn notify_wallpaper_changed(app: &tauri::AppHandle, wallpaper: &WallpaperInfo) {
+    let title = if wallpaper.title.is_empty() { &wallpaper.file_name } else { &wallpaper.title };
+    let body = if wallpaper.copyright.is_empty() {
+        title.to_string()
+    } else {
+        format!("{}\n{}", title, wallpaper.copyright)
+    };
+    notify(app, tr("壁纸已更新"), &body);
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
     }
 }
 
-impl From<serde_json::Error> for AppError {
-    fn from(err: serde_json::Error) -> Self {
-        AppError(err.to_string())
+// 捕获回调中的 panic 并通知用户，避免一个处理函数出错导致整个托盘程序退出
+// 堆栈已由 panic hook 写入日志和崩溃报告
+fn run_guarded<F: FnOnce()>(app: &tauri::AppHandle, context: &'static str, f: F) {
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| crash_report::catching(f))) {
+        let message = panic_message(payload.as_ref());
+        error!("Panic in {}: {}", context, message);
+        notify(app, tr("Bing Wallpaper 出现错误"), &tr_fmt("{}：{}", &[tr(context), &message]));
     }
 }
 
-impl std::fmt::Display for AppError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+// run_guarded 的异步版本，用于运行在异步任务中的刷新逻辑
+async fn run_guarded_async<F: Future<Output = ()>>(app: &tauri::AppHandle, context: &'static str, f: F) {
+    // 每次 poll 都可能在不同的线程上，标记随 poll 设置
+    let mut f = std::pin::pin!(f);
+    let polled = std::future::poll_fn(|cx| crash_report::catching(|| f.as_mut().poll(cx)));
+    if let Err(payload) = AssertUnwindSafe(polled).catch_unwind().await {
+        let message = panic_message(payload.as_ref());
+        error!("Panic in {}: {}", context, message);
+        notify(app, tr("Bing Wallpaper 出现错误"), &tr_fmt("{}：{}", &[tr(context), &message]));
     }
 }
 
-impl std::error::Error for AppError {}
+// 错误分类，显示给用户时带上类别前缀；Network 中的 transient 标记可以重试的临时错误
+#[derive(Debug, thiserror::Error)]
+enum AppError {
+    #[error("{}", tr_with("网络错误：{}", .message))]
+    Network { message: String, transient: bool },
+    #[error("{}", tr_with("文件读写错误：{}", .0))]
+    Io(#[from] std::io::Error),
+    #[error("{}", tr_with("数据解析错误：{}", .0))]
+    Parse(String),
+    // 设置壁纸、开机自启动、托盘等与操作系统交互的失败
+    #[error("{}", tr_with("系统设置错误：{}", .0))]
+    OsSet(String),
+    #[error("{}", tr_with("配置错误：{}", .0))]
+    Config(String),
+    #[error("{}", tr_with("界面错误：{}", .0))]
+    Tauri(#[from] tauri::Error),
+    #[error("{}", tr_with("数据库错误：{}", .0))]
+    Database(#[from] rusqlite::Error),
+}
 
-type Result<T> = std::result::Result<T, AppError>;
+// 核心库的错误按相同的类别转换，保留可重试标记
+impl From<wallpaper_core::Error> for AppError {
+    fn from(err: wallpaper_core::Error) -> Self {
+        match err {
+            wallpaper_core::Error::Network { message, transient } => AppError::Network { message, transient },
+            wallpaper_core::Error::Io(err) => AppError::Io(err),
+            wallpaper_core::Error::Parse(message) => AppError::Parse(message),
+            wallpaper_core::Error::OsSet(message) => AppError::OsSet(message),
+            wallpaper_core::Error::Config(message) => AppError::Config(message),
+        }
+    }
+}
+
+impl Transient for AppError {
+    fn is_transient(&self) -> bool {
+        matches!(self, AppError::Network { transient: true, .. })
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        AppError::Parse(err.to_string())
+    }
+}
 
-#[derive(Debug, Deserialize)]
-struct WallpaperInfo {
-    file_name: String,
-    url: String,
+// 命令返回错误时以字符串形式传给前端
+impl Serialize for AppError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
 }
 
+type Result<T> = std::result::Result<T, AppError>;
+
 fn get_or_create_uuid() -> Result<String> {
     let uuid_path = get_app_data_dir()?.join(UUID_FILE_NAME);
     
     if uuid_path.exists() {
         let mut contents = String::new();
-        File::open(uuid_path)?.read_to_string(&mut contents)?;
-        Ok(contents.trim().to_string())
-    } else {
-        let new_uuid = Uuid::new_v4().to_string();
-        File::create(&uuid_path)?.write_all(new_uuid.as_bytes())?;
-        info!("Created new UUID: {}", new_uuid);
-        Ok(new_uuid)
+        File::open(&uuid_path)?.read_to_string(&mut contents)?;
+        // 旧版本写入中断时文件可能为空或只有一半，丢弃后重新生成
+        if Uuid::parse_str(contents.trim()).is_ok() {
+            return Ok(contents.trim().to_string());
+        }
+        warn!("Discarding corrupt device UUID file {:?}", uuid_path);
     }
+    let new_uuid = Uuid::new_v4().to_string();
+    atomic_file::write(&uuid_path, &new_uuid)?;
+    info!("Created new UUID: {}", new_uuid);
+    Ok(new_uuid)
 }
 
-fn get_app_data_dir() -> Result<PathBuf> {
+// 请求时上报的客户端版本：程序版本加系统和架构，例如 "0.3.0 (windows; x86_64)"
+fn client_version() -> String {
+    format!("{} ({}; {})", env!("CARGO_PKG_VERSION"), std::env::consts::OS, std::env::consts::ARCH)
+}
+
+// 按隐私设置返回随请求发送的设备标识，关闭时返回 None；rotate 方式下文件超过有效期就重新生成
+fn device_uuid() -> Result<Option<String>> {
+    let mode = load_config().map(|config| config.device_id_mode).unwrap_or_default();
+    if mode == DEVICE_ID_OFF {
+        return Ok(None);
+    }
+    if mode == DEVICE_ID_ROTATE {
+        let age = fs::metadata(get_app_data_dir()?.join(UUID_FILE_NAME))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok());
+        if age.is_some_and(|age| age >= DEVICE_ID_ROTATE_INTERVAL) {
+            reset_device_uuid()?;
+        }
+    }
+    get_or_create_uuid().map(Some)
+}
+
+// 删除 device_uuid.txt，需要时会生成新的标识
+fn reset_device_uuid() -> Result<()> {
+    match fs::remove_file(get_app_data_dir()?.join(UUID_FILE_NAME)) {
+        Ok(()) => info!("Device UUID reset"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+fn system_app_data_dir() -> Result<PathBuf> {
     #[cfg(windows)]
     let app_dir = {
-        let app_data = std::env::var("APPDATA").map_err(|e| AppError(e.to_string()))?;
+        let app_data = std::env::var("APPDATA").map_err(|e| AppError::Config(format!("APPDATA: {}", e)))?;
         PathBuf::from(app_data).join("bing-wallpaper-client")
     };
 
     #[cfg(not(windows))]
     let app_dir = {
-        let home = std::env::var("HOME").map_err(|e| AppError(e.to_string()))?;
+        let home = std::env::var("HOME").map_err(|e| AppError::Config(format!("HOME: {}", e)))?;
         PathBuf::from(home).join(".bing-wallpaper-client")
     };
-    
+
+    Ok(app_dir)
+}
+
+// 便携模式下使用可执行文件旁的 data 文件夹
+fn get_app_data_dir() -> Result<PathBuf> {
+    let app_dir = match portable::data_dir() {
+        Some(dir) => dir.to_path_buf(),
+        None => system_app_data_dir()?,
+    };
+
     if !app_dir.exists() {
         fs::create_dir_all(&app_dir)?;
         info!("Created app directory: {:?}", app_dir);
@@ -188,292 +749,2021 @@ fn get_app_data_dir() -> Result<PathBuf> {
     Ok(app_dir)
 }
 
+// 未设置保存目录时使用应用数据目录下的 wallpapers/，与配置、数据库、日志等内部文件分开
+fn resolve_wallpaper_dir(save_dir: &str) -> Result<PathBuf> {
+    if save_dir.is_empty() {
+        return Ok(get_app_data_dir()?.join(DEFAULT_WALLPAPER_DIR_NAME));
+    }
+    Ok(PathBuf::from(save_dir))
+}
+
+fn get_wallpaper_dir() -> Result<PathBuf> {
+    let save_dir = load_config().map(|config| config.save_dir).unwrap_or_default();
+    let dir = resolve_wallpaper_dir(&save_dir)?;
+    if !dir.exists() {
+        fs::create_dir_all(&dir)?;
+        info!("Created wallpaper directory: {:?}", dir);
+    }
+    Ok(dir)
+}
+
+// 文件名只能是单独一段，不含目录分隔符、盘符或 ..；同步文件等外部来源的文件名不能借此写到保存目录之外
+fn is_plain_file_name(filename: &str) -> bool {
+    let mut components = Path::new(filename).components();
+    !filename.contains(['/', '\\'])
+        && matches!(components.next(), Some(std::path::Component::Normal(name)) if name == filename)
+        && components.next().is_none()
+}
+
 fn get_wallpaper_path(filename: &str) -> Result<PathBuf> {
-    Ok(get_app_data_dir()?.join(filename))
+    if !is_plain_file_name(filename) {
+        return Err(AppError::Config(format!("Invalid wallpaper file name: {:?}", filename)));
+    }
+    Ok(get_wallpaper_dir()?.join(filename))
 }
 
+
 fn is_wallpaper_exists(filename: &str) -> bool {
     get_wallpaper_path(filename).map(|path| path.exists()).unwrap_or(false)
 }
 
+#[cfg(target_os = "windows")]
+fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    let mut arg = std::ffi::OsString::from("/select,");
+    arg.push(path);
+    // explorer 即使成功也可能返回非零退出码，这里只关心能否启动
+    Command::new("explorer").arg(arg).spawn()?;
+    Ok(())
+}
+
 #[cfg(target_os = "macos")]
-fn set_wallpaper(path: &str) -> Result<()> {
-    let script = format!(
-        "tell application \"System Events\" to tell every desktop to set picture to \"{}\"",
-        path
-    );
-    let output = Command::new("osascript")
-        .args(&["-e", &script])
-        .output()?;
-
-    if output.status.success() {
-        info!("Wallpaper set successfully on macOS");
+fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    let status = Command::new("open").arg("-R").arg(path).status()?;
+    if status.success() {
         Ok(())
     } else {
-        let error_msg = String::from_utf8_lossy(&output.stderr);
-        Err(AppError(format!("Failed to set wallpaper on macOS: {}", error_msg)))
+        Err(AppError::OsSet(format!("Failed to reveal {:?} in Finder", path)))
     }
 }
 
-#[cfg(target_os = "windows")]
-fn set_wallpaper(path: &str) -> Result<()> {
-    use std::ffi::CString;
-    
-    let path_cstr = CString::new(path).map_err(|e| AppError(e.to_string()))?;
-    
-    unsafe {
-        if SystemParametersInfoA(
-            SPI_SETDESKWALLPAPER,
-            0,
-            path_cstr.as_ptr() as _,
-            SPIF_UPDATEINIFILE | SPIF_SENDCHANGE,
-        ) == TRUE
-        {
-            info!("Wallpaper set successfully on Windows");
-            Ok(())
-        } else {
-            Err(AppError("Failed to set wallpaper on Windows".to_string()))
+// 优先通过 FileManager1 D-Bus 接口选中文件，不支持时退回打开所在目录
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    let uri = format!("array:string:file://{}", percent_encode_path(&path.to_string_lossy()));
+    let status = Command::new("dbus-send")
+        .args(&[
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+            uri.as_str(),
+            "string:",
+        ])
+        .status();
+    if matches!(status, Ok(status) if status.success()) {
+        return Ok(());
+    }
+
+    let folder = path.parent().ok_or_else(|| AppError::OsSet(format!("Invalid wallpaper path: {:?}", path)))?;
+    open::that(folder)?;
+    Ok(())
+}
+
+// 读取分辨率设置，自动模式下按主显示器物理像素判断是否需要 UHD
+fn effective_resolution(app: &tauri::AppHandle) -> Resolution {
+    let resolution = load_config()
+        .map(|config| Resolution::from_str(&config.resolution))
+        .unwrap_or(Resolution::Auto);
+    if resolution != Resolution::Auto {
+        return resolution;
+    }
+    match app.primary_monitor() {
+        Ok(Some(monitor)) => Resolution::for_size(monitor.size().width, monitor.size().height),
+        Ok(None) => Resolution::FullHd,
+        Err(e) => {
+            warn!("Failed to detect primary monitor: {}", e);
+            Resolution::FullHd
         }
     }
 }
 
-fn get_bing_wallpaper_info(is_china: bool) -> Result<WallpaperInfo> {
-    let api_url = if is_china { CHINA_API_URL } else { GLOBAL_API_URL };
-    
-    // 获取UUID
-    let uuid = get_or_create_uuid()?;
-    
-    let response = minreq::get(api_url)
-        .with_header("client-version", "0.1.0")
-        .with_header("client-device-uuid", &uuid)
-        .send()?;
-    
-    let content = response.as_str().map_err(|e| AppError(e.to_string()))?;
-    Ok(serde_json::from_str(content)?)
+// 中国模式固定使用 zh-CN，国际模式按配置选择市场
+fn market_for(is_china: bool) -> Option<String> {
+    if is_china {
+        return None;
+    }
+    load_config().ok().map(|config| config.market).filter(|market| !market.is_empty())
+}
+
+async fn get_bing_wallpaper_info(is_china: bool, market: Option<&str>, resolution: Resolution) -> Result<WallpaperInfo> {
+    api::fetch_today(is_china, market, resolution).await
 }
 
-fn download_and_set_wallpaper(force: bool, is_china: bool) -> Result<()> {
-    let wallpaper_info = get_bing_wallpaper_info(is_china)?;
+// 返回 None 表示今天的壁纸已被用户跳过，保持当前桌面不变
+async fn download_and_set_wallpaper(force: bool, is_china: bool, resolution: Resolution) -> Result<Option<WallpaperInfo>> {
+    // 排在前面的刷新完成后，后面的刷新会看到文件已存在而跳过下载
+    let _lock = download_lock::acquire().await?;
+    let market = market_for(is_china);
+    let wallpaper_info = http::with_retry("Fetching wallpaper info", || {
+        get_bing_wallpaper_info(is_china, market.as_deref(), resolution)
+    })
+    .await?;
+
+    if Blocklist::load().contains_file(&wallpaper_info.file_name) {
+        info!("Wallpaper {} is blocked, skipping", wallpaper_info.file_name);
+        return Ok(None);
+    }
     
     if !force && is_wallpaper_exists(&wallpaper_info.file_name) {
         info!("Wallpaper {} already exists, skipping download", wallpaper_info.file_name);
-        return Ok(());
+        return Ok(Some(wallpaper_info));
     }
 
-    let wallpaper_path = get_wallpaper_path(&wallpaper_info.file_name)?;
-    
-    let response = minreq::get(&wallpaper_info.url).send()?;
-    let bytes = response.into_bytes();
+    let wallpaper_path = fetch_wallpaper(&wallpaper_info).await?;
+    // 各显示器的版本都下载完后再清理缓存，当前壁纸的所有版本都保留
+    let variants = download_monitor_variants(&wallpaper_info, resolution).await;
+    let keep: Vec<&str> = std::iter::once(wallpaper_info.file_name.as_str()).chain(variants.iter().map(String::as_str)).collect();
+    cleanup_cache(&keep);
 
-    File::create(&wallpaper_path)?.write_all(&bytes)?;
-    
-    info!("Downloaded wallpaper: {}", wallpaper_info.file_name);
-    
-    #[cfg(any(target_os = "windows", target_os = "macos"))]
-    set_wallpaper(wallpaper_path.to_str().unwrap())?;
+    wallpaper_setter::set_wallpaper(&wallpaper_path)?;
 
-    Ok(())
+    Ok(Some(wallpaper_info))
 }
 
-fn create_timer_thread(is_china: bool) -> (JoinHandle<()>, Arc<AtomicBool>) {
-    let running = Arc::new(AtomicBool::new(true));
-    let running_clone = running.clone();
-
-    let handle = thread::spawn(move || {
-        while running_clone.load(Ordering::Relaxed) {
-            thread::sleep(Duration::from_secs(REFRESH_INTERVAL));
-            
-            if !running_clone.load(Ordering::Relaxed) {
-                break;
-            }
-            
-            if let Err(e) = download_and_set_wallpaper(false, is_china) {
-                error!("Failed to update wallpaper: {}", e);
+// 多显示器分辨率不同时，把其它显示器需要的版本也下载到缓存，各版本文件名不同，分别缓存；
+// 下载失败只影响这些显示器的清晰度，不中断刷新。返回缓存中已有的各版本文件名
+async fn download_monitor_variants(wallpaper_info: &WallpaperInfo, resolution: Resolution) -> Vec<String> {
+    let mut variants = Vec::new();
+    for variant_resolution in wallpaper_setter::extra_resolutions(resolution) {
+        let variant = wallpaper_info.clone().with_resolution(variant_resolution);
+        if variant.file_name == wallpaper_info.file_name {
+            continue;
+        }
+        if !is_wallpaper_exists(&variant.file_name) {
+            if let Err(e) = fetch_wallpaper(&variant).await {
+                warn!("Failed to download {} variant: {}", variant_resolution.as_str(), e);
+                continue;
             }
         }
-    });
-
-    (handle, running)
+        variants.push(variant.file_name);
+    }
+    variants
 }
 
-fn update_menu(app: &tauri::AppHandle, tray: &TrayIcon, refresh_mode: RefreshMode, autostart_enabled: bool) -> Result<()> {
-    let new_menu = Menu::with_items(app, &[
-        &MenuItem::with_id(
-            app,
-            "daily_china",
-            if refresh_mode == RefreshMode::DailyChina { "每日壁纸刷新(中国) ✓" } else { "每日壁纸刷新(中国)" },
-            true,
-            None::<&str>,
-        ).map_err(|e| AppError(e.to_string()))?,
-        &MenuItem::with_id(
-            app,
-            "daily_global",
-            if refresh_mode == RefreshMode::DailyGlobal { "每日壁纸刷新(国际) ✓" } else { "每日壁纸刷新(国际)" },
-            true,
-            None::<&str>,
-        ).map_err(|e| AppError(e.to_string()))?,
-        &MenuItem::with_id(app, "separator1", "--------------", false, None::<&str>)
-            .map_err(|e| AppError(e.to_string()))?,
-        &MenuItem::with_id(
-            app,
-            "autostart",
-            if autostart_enabled { "开机自启动 ✓" } else { "开机自启动" },
-            true,
-            None::<&str>,
-        ).map_err(|e| AppError(e.to_string()))?,
-        &MenuItem::with_id(app, "open_website", "打开必应壁纸网站", true, None::<&str>)
-            .map_err(|e| AppError(e.to_string()))?,
-        &MenuItem::with_id(app, "quit", "退出", true, None::<&str>)
-            .map_err(|e| AppError(e.to_string()))?,
-    ]).map_err(|e| AppError(e.to_string()))?;
-    
-    tray.set_menu(Some(new_menu)).map_err(|e| AppError(e.to_string()))?;
-    Ok(())
+// 正在进行的壁纸下载数量，退出时据此等待下载结束
+static ACTIVE_DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+
+// 下载期间持有，离开作用域（包括任务被取消）时计数减一
+struct DownloadGuard;
+
+impl DownloadGuard {
+    fn start() -> Self {
+        ACTIVE_DOWNLOADS.fetch_add(1, Ordering::SeqCst);
+        DownloadGuard
+    }
 }
 
-fn handle_refresh_mode(
-    app: &tauri::AppHandle,
-    tray: &TrayIcon,
-    state: &Mutex<AppState>,
-    new_mode: RefreshMode,
-    is_china: bool,
-) -> Result<()> {
-    let mut state = state.lock().map_err(|_| AppError("Failed to lock state".to_string()))?;
-    
-    if let Some((_handle, running)) = state.timer_handle.take() {
-        running.store(false, Ordering::Relaxed);
+impl Drop for DownloadGuard {
+    fn drop(&mut self) {
+        ACTIVE_DOWNLOADS.fetch_sub(1, Ordering::SeqCst);
     }
+}
 
-    state.refresh_mode = if state.refresh_mode == new_mode {
-        RefreshMode::None
-    } else {
-        new_mode
-    };
+// 下载一张壁纸并清理缓存，保留刚下载的文件
+async fn download_wallpaper(wallpaper_info: &WallpaperInfo) -> Result<PathBuf> {
+    let wallpaper_path = fetch_wallpaper(wallpaper_info).await?;
+    cleanup_cache(&[&wallpaper_info.file_name]);
+    Ok(wallpaper_path)
+}
 
-    // 持久化保存刷新模式
-    if let Err(e) = save_config(state.refresh_mode) {
-        error!("Failed to save config: {}", e);
+fn cleanup_cache(keep: &[&str]) {
+    if let Err(e) = cache::cleanup(keep) {
+        warn!("Failed to clean up wallpaper cache: {}", e);
     }
+}
 
-    let autostart_enabled = app.autolaunch().is_enabled().unwrap_or(false);
-    update_menu(app, tray, state.refresh_mode, autostart_enabled)?;
+// 只下载并记录，不清理缓存；同时下载多个文件时由调用方在全部完成后清理
+async fn fetch_wallpaper(wallpaper_info: &WallpaperInfo) -> Result<PathBuf> {
+    let _guard = DownloadGuard::start();
+    let wallpaper_path = get_wallpaper_path(&wallpaper_info.file_name)?;
+    // 空间不足时腾出空间；下载其它显示器的版本时，同一张壁纸已下载的版本也保留
+    disk_space::ensure_free_space(&get_wallpaper_dir()?, &[&wallpaper_info.file_name])?;
+
+    if let Err(e) = store::record_pending(wallpaper_info) {
+        warn!("Failed to record pending download of {}: {}", wallpaper_info.file_name, e);
+    }
+    let checksum = Some(wallpaper_info.sha256.as_str()).filter(|sha256| !sha256.is_empty());
+    let content_hash = HttpDownloader::new(http::client()?)
+        .with_client_version(&client_version())
+        .with_rate_limit(load_config().unwrap_or_default().download_rate_limit_kbps)
+        .download(&wallpaper_info.url, &wallpaper_path, checksum)
+        .await?;
+    info!("Downloaded wallpaper: {}", wallpaper_info.file_name);
+    // 内容与已有壁纸相同时改为指向已有文件的硬链接，不占用额外空间；已有文件带着原壁纸的元信息，不再写入
+    let deduplicated = match store::find_duplicate(&content_hash, &wallpaper_info.file_name) {
+        Ok(Some(existing)) => link_duplicate(&existing, &wallpaper_path),
+        Ok(None) => false,
+        Err(e) => {
+            warn!("Failed to look up duplicate of {}: {}", wallpaper_info.file_name, e);
+            false
+        }
+    };
+    if !deduplicated {
+        if let Err(e) = metadata::embed(&wallpaper_path, wallpaper_info) {
+            warn!("Failed to write metadata into {}: {}", wallpaper_info.file_name, e);
+        }
+    }
+    if let Err(e) = store::record_download(wallpaper_info, &wallpaper_path, &content_hash) {
+        warn!("Failed to record downloaded wallpaper: {}", e);
+    }
+    Ok(wallpaper_path)
+}
 
-    if state.refresh_mode == new_mode {
-        download_and_set_wallpaper(true, is_china)?;
-        state.timer_handle = Some(create_timer_thread(is_china));
+// 用指向 existing 的硬链接替换刚下载的 path；文件系统不支持硬链接时保留下载的副本
+fn link_duplicate(existing: &Path, path: &Path) -> bool {
+    // 临时文件名以目标文件名开头，清理遗留的临时文件时能认出是本应用的
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".link.part");
+    let temp = path.with_file_name(temp_name);
+    let linked = fs::hard_link(existing, &temp).and_then(|()| fs::rename(&temp, path));
+    match linked {
+        Ok(()) => {
+            info!("Wallpaper {:?} has the same content as {:?}, linked instead of storing twice", path, existing);
+            true
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&temp);
+            warn!("Failed to link duplicate wallpaper {:?} to {:?}: {}", path, existing, e);
+            false
+        }
     }
+}
 
+// 供后台线程调用：下载并设置壁纸后更新状态和菜单
+async fn refresh_wallpaper(app: &tauri::AppHandle, force: bool, is_china: bool) -> Result<()> {
+    let state = app.state::<Mutex<AppState>>();
+    // 离线期间换上的是缓存壁纸，恢复联网后即使文件已存在也要重新设置
+    let force = force || lock_state(&state).offline;
+    let wallpaper = match download_and_set_wallpaper(force, is_china, effective_resolution(app)).await {
+        Ok(wallpaper) => wallpaper,
+        Err(e) => {
+            if e.is_transient() {
+                record_network_failure(app);
+            }
+            return Err(e);
+        }
+    };
+    let (wallpaper, changed, notifications_enabled) = {
+        let mut state = lock_state(&state);
+        state.network_failures = 0;
+        state.last_error = None;
+        if state.offline {
+            info!("Network is back, leaving offline mode");
+            state.offline = false;
+        }
+        // 今天的壁纸已被跳过，保持当前壁纸
+        let Some(wallpaper) = wallpaper else {
+            return Ok(());
+        };
+        let changed = state.set_current_wallpaper(app, wallpaper.clone());
+        (wallpaper, changed, state.notifications_enabled)
+    };
+    schedule_menu_update(app);
+    if changed && notifications_enabled {
+        notify_wallpaper_changed(app, &wallpaper);
+    }
     Ok(())
 }
 
-fn handle_autostart_toggle(
-    app: &tauri::AppHandle,
-    tray: &TrayIcon,
-    state: &Mutex<AppState>,
-) -> Result<()> {
-    let manager = app.autolaunch();
-    let is_enabled = manager.is_enabled().map_err(|e| AppError(e.to_string()))?;
+// 连续多次联网失败后进入离线模式，每次失败都从缓存中随机换一张壁纸
+fn record_network_failure(app: &tauri::AppHandle) {
+    let state = app.state::<Mutex<AppState>>();
+    let (failures, current) = {
+        let mut state = lock_state(&state);
+        state.network_failures += 1;
+        (state.network_failures, state.current_wallpaper.as_ref().map(|w| w.file_name.clone()))
+    };
+    if failures < OFFLINE_FAILURE_THRESHOLD {
+        return;
+    }
 
-    if is_enabled {
-        manager.disable().map_err(|e| AppError(e.to_string()))?;
-        info!("Autostart disabled");
-    } else {
-        manager.enable().map_err(|e| AppError(e.to_string()))?;
-        info!("Autostart enabled");
+    let path = match cache::random_cached_file(current.as_deref()) {
+        Ok(Some(path)) => path,
+        Ok(None) => {
+            warn!("Offline and no cached wallpaper is available");
+            return;
+        }
+        Err(e) => {
+            error!("Failed to pick a cached wallpaper: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = apply_cached_wallpaper(app, &path) {
+        error!("Failed to apply cached wallpaper {:?}: {}", path, e);
+        return;
     }
+    info!("Offline after {} failures, applied cached wallpaper {:?}", failures, path);
 
-    let new_status = manager.is_enabled().unwrap_or(false);
-    let state = state.lock().map_err(|_| AppError("Failed to lock state".to_string()))?;
-    update_menu(app, tray, state.refresh_mode, new_status)?;
+    let mut state = lock_state(&state);
+    if !state.offline {
+        warn!("Entering offline mode");
+        state.offline = true;
+    }
+}
 
+// 把缓存中的壁纸设为桌面并更新当前壁纸；历史记录里查不到时只记录文件名
+fn apply_cached_wallpaper(app: &tauri::AppHandle, path: &Path) -> Result<()> {
+    wallpaper_setter::set_wallpaper(path)?;
+    let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    {
+        let state = app.state::<Mutex<AppState>>();
+        let mut state = lock_state(&state);
+        let wallpaper = state
+            .history
+            .find_by_file(&file_name)
+            .cloned()
+            .unwrap_or_else(|| WallpaperInfo { file_name, ..Default::default() });
+        state.set_current_wallpaper(app, wallpaper);
+    }
+    schedule_menu_update(app);
     Ok(())
 }
 
-pub fn run() {
-    // 初始化日志
-    log::set_logger(&LOGGER).unwrap();
-    log::set_max_level(log::LevelFilter::Info);
+async fn refresh_in_background(app: &tauri::AppHandle, force: bool, is_china: bool) {
+    run_guarded_async(app, "定时刷新", async {
+        if let Err(e) = refresh_wallpaper(app, force, is_china).await {
+            report_error(app, "定时刷新失败", &e);
+        }
+    })
+    .await;
+}
 
-    // 启动时确保UUID已经生成
-    match get_or_create_uuid() {
-        Ok(uuid) => info!("Using device UUID: {}", uuid),
-        Err(e) => error!("Failed to initialize UUID: {}", e),
+// 按刷新模式启动对应的后台任务：轮播模式切换本地壁纸，其余模式定时下载
+fn start_refresh_task(app: tauri::AppHandle, mode: RefreshMode, startup_delay: Option<Duration>) -> RefreshTask {
+    if mode == RefreshMode::Slideshow {
+        slideshow::start(app, startup_delay)
+    } else {
+        create_timer_task(app, mode, startup_delay)
+    }
+}
+
+// 按当前模式重启后台任务；暂停中时任务先等到暂停结束再刷新
+fn restart_refresh_task(app: &tauri::AppHandle, state: &mut AppState) {
+    if let Some((_handle, cancel)) = state.timer_handle.take() {
+        cancel.cancel();
+    }
+    state.deferred = None;
+    if state.refresh_mode != RefreshMode::None {
+        state.timer_handle = Some(start_refresh_task(app.clone(), state.refresh_mode, state.pause_remaining()));
+    }
+}
+
+// 定时任务在每次等待前调用，记录下一次刷新的时间并刷新菜单
+fn set_next_refresh(app: &tauri::AppHandle, delay: Duration) {
+    let next = chrono::Duration::from_std(delay).ok().map(|delay| chrono::Local::now() + delay);
+    lock_state(&app.state::<Mutex<AppState>>()).next_refresh = next;
+    schedule_menu_update(app);
+}
+
+// startup_delay 不为空时，任务会先等待该时长并立即刷新一次，再进入定时循环；
+// 取消时正在进行的下载也会被中断
+fn create_timer_task(app: tauri::AppHandle, mode: RefreshMode, startup_delay: Option<Duration>) -> RefreshTask {
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+
+    let handle = tauri::async_runtime::spawn(async move {
+        if let Some(delay) = startup_delay {
+            if !delay.is_zero() {
+                info!("Delaying first refresh by {}s", delay.as_secs());
+                set_next_refresh(&app, delay);
+                tokio::select! {
+                    _ = token.cancelled() => return,
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            }
+            if !refresh_policy::wait_until_allowed(&app, &token).await {
+                return;
+            }
+            tokio::select! {
+                _ = token.cancelled() => return,
+                _ = async { refresh_in_background(&app, true, region::resolve(mode).await).await } => {}
+            }
+        }
+
+        // 每轮按最新的壁纸日期重新计算，rollover 方式据此决定睡到零点还是短间隔重试
+        loop {
+            let latest = {
+                let state = app.state::<Mutex<AppState>>();
+                let state = lock_state(&state);
+                state.current_wallpaper.as_ref().and_then(|wallpaper| wallpaper.parsed_date())
+            };
+            let latest = latest.max(Blocklist::load().latest_date());
+            let schedule = scheduler::load_schedule(latest);
+            set_next_refresh(&app, schedule.next_delay(chrono::Local::now()));
+            let Some(trigger) = schedule.wait_for_next(&token).await else {
+                break;
+            };
+            if let Trigger::Resumed = trigger {
+                info!("Re-checking wallpaper after wake");
+            }
+            if !refresh_policy::wait_until_allowed(&app, &token).await {
+                break;
+            }
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = async { refresh_in_background(&app, false, region::resolve(mode).await).await } => {}
+            }
+        }
+    });
+
+    (handle, cancel)
+}
+
+fn build_menu(app: &tauri::AppHandle, state: &AppState) -> Result<Menu<tauri::Wry>> {
+    let refresh_mode = state.refresh_mode;
+    let autostart_enabled = app.autolaunch().is_enabled().unwrap_or(false);
+    let theme_label = |theme: TrayIconTheme, label: &'static str| {
+        checked(label, state.tray_icon_theme == theme)
+    };
+
+    let delay_label = |secs: u64| {
+        let label = if secs == 0 { tr("不延迟").to_string() } else { tr_with("{} 秒", secs) };
+        if state.startup_delay_secs == secs { format!("{} ✓", label) } else { label }
+    };
+
+    let startup_delay_menu = Submenu::with_id(app, "startup_delay", tr("开机启动延迟"), true)?;
+    for secs in STARTUP_DELAY_OPTIONS {
+        startup_delay_menu.append(&MenuItem::with_id(
+            app,
+            format!("startup_delay_{}", secs),
+            delay_label(secs),
+            true,
+            None::<&str>,
+        )?)?;
+    }
+
+    let config = load_config().unwrap_or_default();
+    let current_fit = config.wallpaper_fit.get(refresh_mode.as_str()).map(|fit| Fit::from_str(fit));
+    let fit_menu = Submenu::with_id(app, "wallpaper_fit", tr("壁纸显示方式"), true)?;
+    fit_menu.append(&MenuItem::with_id(app, "fit_system", checked("跟随系统", current_fit.is_none()), true, None::<&str>)?)?;
+    for fit in Fit::ALL {
+        fit_menu.append(&MenuItem::with_id(
+            app,
+            format!("fit_{}", fit.as_str()),
+            checked(fit_label(fit), current_fit == Some(fit)),
+            true,
+            None::<&str>,
+        )?)?;
+    }
+
+    let slideshow_minutes = if config.slideshow_interval_minutes == 0 {
+        slideshow::DEFAULT_SLIDESHOW_INTERVAL_MINUTES
+    } else {
+        config.slideshow_interval_minutes
+    };
+    let shuffle = config.slideshow_order != slideshow::ORDER_SEQUENTIAL;
+    let slideshow_menu = Submenu::with_id(app, "slideshow", tr("本地壁纸轮播"), true)?;
+    slideshow_menu.append(&MenuItem::with_id(
+        app,
+        "slideshow_toggle",
+        checked("开启轮播", refresh_mode == RefreshMode::Slideshow),
+        true,
+        None::<&str>,
+    )?)?;
+    for minutes in slideshow::SLIDESHOW_INTERVAL_OPTIONS {
+        let label = if minutes >= 60 { tr_with("每 {} 小时", minutes / 60) } else { tr_with("每 {} 分钟", minutes) };
+        let label = if slideshow_minutes == minutes { format!("{} ✓", label) } else { label };
+        slideshow_menu.append(&MenuItem::with_id(app, format!("slideshow_interval_{}", minutes), label, true, None::<&str>)?)?;
+    }
+    slideshow_menu.append(&MenuItem::with_id(app, "slideshow_shuffle", checked("随机顺序", shuffle), true, None::<&str>)?)?;
+    slideshow_menu.append(&MenuItem::with_id(app, "slideshow_sequential", checked("按下载顺序", !shuffle), true, None::<&str>)?)?;
+    let favorites_only = config.slideshow_source == slideshow::SOURCE_FAVORITES;
+    slideshow_menu.append(&MenuItem::with_id(app, "slideshow_favorites", checked("只轮播收藏", favorites_only), true, None::<&str>)?)?;
+    let folder_only = config.slideshow_source == slideshow::SOURCE_FOLDER;
+    slideshow_menu.append(&MenuItem::with_id(app, "slideshow_folder", checked("轮播本地文件夹", folder_only), !config.local_folder.is_empty(), None::<&str>)?)?;
+
+    let current_market = config.market;
+    let market_menu = Submenu::with_id(app, "market", tr("国际壁纸市场"), true)?;
+    for (code, label) in MARKETS {
+        let id = if code.is_empty() { "market_default".to_string() } else { format!("market_{}", code) };
+        let label = if code.is_empty() { tr(label).to_string() } else { format!("{} ({})", tr(label), code) };
+        let label = if current_market == code { format!("{} ✓", label) } else { label };
+        market_menu.append(&MenuItem::with_id(app, id, label, true, None::<&str>)?)?;
+    }
+
+    let current_provider = api::wallpaper_provider_id();
+    let provider_menu = Submenu::with_id(app, "provider", tr("壁纸来源"), true)?;
+    for (id, label) in api::PROVIDERS {
+        let label = checked(label, current_provider == id);
+        provider_menu.append(&MenuItem::with_id(app, format!("provider_{}", id), label, true, None::<&str>)?)?;
+    }
+
+    let paused = state.pause_remaining().is_some();
+    let pause_menu = Submenu::with_id(
+        app,
+        "pause",
+        checked("暂停更换壁纸", paused),
+        refresh_mode != RefreshMode::None,
+    )?;
+    if let Some(until) = state.paused_until.filter(|_| paused) {
+        let label = tr_with("已暂停至 {}", until.format("%m-%d %H:%M"));
+        pause_menu.append(&MenuItem::with_id(app, "pause_status", label, false, None::<&str>)?)?;
+    }
+    pause_menu.append(&MenuItem::with_id(app, "pause_1h", tr("暂停 1 小时"), true, None::<&str>)?)?;
+    pause_menu.append(&MenuItem::with_id(app, "pause_tomorrow", tr("暂停到明天"), true, None::<&str>)?)?;
+    pause_menu.append(&MenuItem::with_id(app, "pause_resume", tr("恢复"), paused, None::<&str>)?)?;
+
+    let batch_running = state.batch_progress.is_some();
+    let batch_menu = Submenu::with_id(app, "batch_download", tr("下载最近壁纸"), true)?;
+    if let Some(progress) = state.batch_progress {
+        let label = if progress.total == 0 {
+            tr("正在获取归档...").to_string()
+        } else {
+            tr_fmt("正在下载 {}/{}", &[&(progress.done + progress.failed).to_string(), &progress.total.to_string()])
+        };
+        batch_menu.append(&MenuItem::with_id(app, "batch_status", label, false, None::<&str>)?)?;
+    }
+    for days in batch::BATCH_DAYS {
+        batch_menu.append(&MenuItem::with_id(
+            app,
+            format!("batch_download_{}", days),
+            tr_with("最近 {} 天", days),
+            !batch_running,
+            None::<&str>,
+        )?)?;
+    }
+
+    let mode_label = match refresh_mode {
+        RefreshMode::DailyChina => tr("中国区每日壁纸").to_string(),
+        RefreshMode::DailyGlobal if current_market.is_empty() => tr("国际区每日壁纸").to_string(),
+        RefreshMode::DailyGlobal => format!("{} ({})", tr("国际区每日壁纸"), current_market),
+        RefreshMode::DailyAuto if region::is_china() => tr_with("自动选择：{}", tr("中国区每日壁纸")),
+        RefreshMode::DailyAuto => tr_with("自动选择：{}", tr("国际区每日壁纸")),
+        RefreshMode::Slideshow => tr("本地壁纸轮播").to_string(),
+        RefreshMode::None => tr("未开启自动刷新").to_string(),
+    };
+    let time_label = |time: Option<chrono::DateTime<chrono::Local>>| match time {
+        Some(time) => time.format("%m-%d %H:%M").to_string(),
+        None => tr("暂无").to_string(),
+    };
+    // 没有定时任务时不显示过期的计划时间；暂停中的任务计划在暂停结束时刷新
+    let next_refresh = state.next_refresh.filter(|_| refresh_mode != RefreshMode::None && state.timer_handle.is_some());
+    let next_label = match &state.deferred {
+        Some(reason) => tr_with("下次刷新：已推迟（{}）", reason),
+        None => tr_with("下次刷新：{}", time_label(next_refresh)),
+    };
+    let status_menu = Submenu::with_id(app, "status", tr("运行状态"), true)?;
+    status_menu.append_items(&[
+        &MenuItem::with_id(app, "status_mode", tr_with("当前模式：{}", mode_label), false, None::<&str>)?,
+        &MenuItem::with_id(app, "status_last", tr_with("上次刷新：{}", time_label(state.last_refresh)), false, None::<&str>)?,
+        &MenuItem::with_id(app, "status_next", next_label, false, None::<&str>)?,
+    ])?;
+    if let Some(time) = state.task_restarted {
+        let label = tr_with("定时任务异常结束，已于 {} 重新启动", time.format("%m-%d %H:%M"));
+        status_menu.append(&MenuItem::with_id(app, "status_restarted", label, false, None::<&str>)?)?;
+    }
+
+    let history_menu = Submenu::with_id(app, "history", tr("历史壁纸"), !state.history.is_empty())?;
+    for wallpaper in state.history.recent(history::HISTORY_MENU_SIZE) {
+        history_menu.append(&MenuItem::with_id(
+            app,
+            format!("history_{}", wallpaper.date),
+            wallpaper.display_title(),
+            true,
+            None::<&str>,
+        )?)?;
+    }
+
+    let is_favorite = state.current_wallpaper.as_ref()
+        .map(|wallpaper| state.favorites.contains_file(&wallpaper.file_name))
+        .unwrap_or(false);
+    let favorites_menu = Submenu::with_id(app, "favorites", tr("我的收藏"), true)?;
+    favorites_menu.append(&MenuItem::with_id(
+        app,
+        "favorite_toggle",
+        tr(if is_favorite { "取消收藏当前壁纸" } else { "收藏当前壁纸" }),
+        state.current_wallpaper.is_some(),
+        None::<&str>,
+    )?)?;
+    for wallpaper in state.favorites.recent(favorites::FAVORITES_MENU_SIZE) {
+        favorites_menu.append(&MenuItem::with_id(
+            app,
+            format!("favorite_{}", wallpaper.file_name),
+            wallpaper.display_title(),
+            true,
+            None::<&str>,
+        )?)?;
+    }
+
+    let header = match &state.current_wallpaper {
+        Some(wallpaper) if state.offline => tr_with("离线壁纸：{}", wallpaper.display_title()),
+        Some(wallpaper) => tr_with("今日壁纸：{}", wallpaper.display_title()),
+        None if state.offline => tr("离线模式：暂无缓存壁纸").to_string(),
+        None => tr("今日壁纸：暂无").to_string(),
+    };
+
+    let cache_label = match cache::cache_size() {
+        Ok(size) => tr_with("已占用 {}", cache::format_size(size)),
+        Err(_) => tr("已占用 未知").to_string(),
+    };
+
+    let copyright = state.current_wallpaper.as_ref()
+        .map(|wallpaper| wallpaper.copyright.trim().to_string())
+        .filter(|copyright| !copyright.is_empty());
+
+    let menu = Menu::with_items(app, &[
+        &MenuItem::with_id(app, "wallpaper_title", header, false, None::<&str>)?,
+    ])?;
+    if let Some(copyright) = copyright {
+        menu.append(&MenuItem::with_id(app, "wallpaper_copyright", copyright, false, None::<&str>)?)?;
+    }
+    if let Some(err) = &state.last_error {
+        menu.append(&MenuItem::with_id(app, "last_error", tr_with("最近错误：{}", err), false, None::<&str>)?)?;
+    }
+    menu.append_items(&[
+        &status_menu,
+        &MenuItem::with_id(app, "separator0", "--------------", false, None::<&str>)?,
+        &MenuItem::with_id(
+            app,
+            "daily_china",
+            checked("每日壁纸刷新(中国)", refresh_mode == RefreshMode::DailyChina),
+            true,
+            None::<&str>,
+        )?,
+        &MenuItem::with_id(
+            app,
+            "daily_global",
+            checked("每日壁纸刷新(国际)", refresh_mode == RefreshMode::DailyGlobal),
+            true,
+            None::<&str>,
+        )?,
+        &MenuItem::with_id(
+            app,
+            "daily_auto",
+            checked("每日壁纸刷新(自动选择区域)", refresh_mode == RefreshMode::DailyAuto),
+            true,
+            None::<&str>,
+        )?,
+        &market_menu,
+        &provider_menu,
+        &slideshow_menu,
+        &pause_menu,
+        &MenuItem::with_id(app, "refresh_now", tr("立即刷新"), true, None::<&str>)?,
+        &MenuItem::with_id(app, "previous_wallpaper", tr("上一张"), state.applied.has_previous(), None::<&str>)?,
+        &MenuItem::with_id(app, "next_wallpaper", tr("下一张"), true, None::<&str>)?,
+        &MenuItem::with_id(app, "skip_wallpaper", tr("跳过今天的壁纸"), state.current_wallpaper.is_some(), None::<&str>)?,
+        &history_menu,
+        &batch_menu,
+        &favorites_menu,
+        &MenuItem::with_id(app, "restore_original", tr("恢复原壁纸"), wallpaper_setter::has_original(), None::<&str>)?,
+        &fit_menu,
+        &MenuItem::with_id(app, "separator1", "--------------", false, None::<&str>)?,
+        &MenuItem::with_id(
+            app,
+            "autostart",
+            checked("开机自启动", autostart_enabled),
+            true,
+            None::<&str>,
+        )?,
+        &Submenu::with_id_and_items(app, "tray_icon", tr("托盘图标"), true, &[
+            &MenuItem::with_id(app, "tray_icon_auto", theme_label(TrayIconTheme::Auto, "跟随系统"), true, None::<&str>)?,
+            &MenuItem::with_id(app, "tray_icon_color", theme_label(TrayIconTheme::Color, "彩色"), true, None::<&str>)?,
+            &MenuItem::with_id(app, "tray_icon_light", theme_label(TrayIconTheme::Light, "浅色任务栏"), true, None::<&str>)?,
+            &MenuItem::with_id(app, "tray_icon_dark", theme_label(TrayIconTheme::Dark, "深色任务栏"), true, None::<&str>)?,
+            &MenuItem::with_id(app, "tray_icon_wallpaper", theme_label(TrayIconTheme::Wallpaper, "当前壁纸缩略图"), true, None::<&str>)?,
+        ])?,
+        &startup_delay_menu,
+        &MenuItem::with_id(
+            app,
+            "notifications",
+            checked("壁纸更换通知", state.notifications_enabled),
+            true,
+            None::<&str>,
+        )?,
+        &MenuItem::with_id(
+            app,
+            "restore_on_quit",
+            checked("退出时恢复原壁纸", state.restore_on_quit),
+            true,
+            None::<&str>,
+        )?,
+    ])?;
+    #[cfg(target_os = "windows")]
+    menu.append(&MenuItem::with_id(
+        app,
+        "lock_screen",
+        checked("同时设置锁屏壁纸", config.lock_screen_enabled),
+        true,
+        None::<&str>,
+    )?)?;
+    #[cfg(target_os = "windows")]
+    menu.append(&MenuItem::with_id(
+        app,
+        "fade_transition",
+        checked("更换时淡入淡出", config.fade_transition),
+        true,
+        None::<&str>,
+    )?)?;
+    #[cfg(target_os = "macos")]
+    menu.append(&MenuItem::with_id(
+        app,
+        "dark_variant",
+        checked("深色模式下调暗壁纸", config.dark_variant_enabled),
+        true,
+        None::<&str>,
+    )?)?;
+    #[cfg(target_os = "macos")]
+    menu.append(&MenuItem::with_id(app, "dynamic_wallpaper", tr("生成动态壁纸..."), true, None::<&str>)?)?;
+    menu.append_items(&[
+        &Submenu::with_id_and_items(app, "cache", tr("壁纸缓存"), true, &[
+            &MenuItem::with_id(app, "cache_size", cache_label, false, None::<&str>)?,
+            &MenuItem::with_id(app, "clear_cache", tr("清空缓存"), true, None::<&str>)?,
+            &MenuItem::with_id(app, "export_folder", tr("导出到文件夹..."), true, None::<&str>)?,
+            &MenuItem::with_id(app, "export_zip", tr("导出为 ZIP..."), true, None::<&str>)?,
+        ])?,
+        &MenuItem::with_id(app, "search", tr("搜索壁纸..."), true, None::<&str>)?,
+        &MenuItem::with_id(app, "quiz", tr("猜猜这是哪里..."), true, None::<&str>)?,
+        &MenuItem::with_id(app, "settings", tr("设置..."), true, None::<&str>)?,
+        &MenuItem::with_id(app, "export_sync", tr("导出收藏和设置..."), true, None::<&str>)?,
+        &MenuItem::with_id(app, "import_sync", tr("导入收藏和设置..."), true, None::<&str>)?,
+        &MenuItem::with_id(app, "open_wallpaper_dir", tr("打开壁纸文件夹"), true, None::<&str>)?,
+        &MenuItem::with_id(app, "open_wallpaper_image", tr("打开当前壁纸图片"), state.current_wallpaper.is_some(), None::<&str>)?,
+        &MenuItem::with_id(app, "reveal_wallpaper", tr("在文件夹中显示"), state.current_wallpaper.is_some(), None::<&str>)?,
+        &MenuItem::with_id(app, "copy_wallpaper_path", tr("复制当前壁纸路径"), state.current_wallpaper.is_some(), None::<&str>)?,
+        &MenuItem::with_id(app, "copy_wallpaper_image", tr("复制当前壁纸图片"), state.current_wallpaper.is_some(), None::<&str>)?,
+        &MenuItem::with_id(
+            app,
+            "copy_wallpaper_url",
+            tr("复制当前壁纸地址"),
+            state.current_wallpaper.as_ref().is_some_and(|wallpaper| !wallpaper.url.is_empty()),
+            None::<&str>,
+        )?,
+        &MenuItem::with_id(app, "open_log", tr("查看日志"), true, None::<&str>)?,
+        &MenuItem::with_id(app, "open_website", tr("打开必应壁纸网站"), true, None::<&str>)?,
+        &MenuItem::with_id(app, "about", tr("关于"), true, None::<&str>)?,
+        &MenuItem::with_id(app, "quit", tr("退出"), true, None::<&str>)?,
+    ])?;
+    if let Some(version) = &state.update_available {
+        menu.insert(
+            &MenuItem::with_id(app, "install_update", tr_with("新版本 {} 可用，点击安装并重启", version), true, None::<&str>)?,
+            0,
+        )?;
+    }
+    Ok(menu)
+}
+
+// 刷新托盘菜单；托盘不可用时改为通知控制窗口刷新状态
+fn update_menu(app: &tauri::AppHandle, state: &AppState) -> Result<()> {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        tray.set_menu(Some(build_menu(app, state)?))?;
+        let tooltip = state.current_wallpaper.as_ref()
+            .map(|wallpaper| tr_with("今日：{}", wallpaper.summary()))
+            .unwrap_or_else(|| "Bing Wallpaper".to_string());
+        tray.set_tooltip(Some(tooltip))?;
+    }
+    if let Some(window) = app.get_webview_window(CONTROL_WINDOW_LABEL) {
+        window.emit("status-changed", ())?;
+    }
+    Ok(())
+}
+
+// 后台线程不能在持有状态锁时等待主线程，这里把菜单刷新投递到主线程执行
+fn schedule_menu_update(app: &tauri::AppHandle) {
+    let app_handle = app.clone();
+    let result = app.run_on_main_thread(move || {
+        let state = app_handle.state::<Mutex<AppState>>();
+        let state = lock_state(&state);
+        if let Err(e) = update_menu(&app_handle, &state) {
+            error!("Failed to update menu: {}", e);
+        }
+    });
+    if let Err(e) = result {
+        error!("Failed to schedule menu update: {}", e);
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn is_taskbar_light() -> bool {
+    // 读取失败时按 Windows 默认的深色任务栏处理
+    registry::read_hkcu_dword(
+        "Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize",
+        "SystemUsesLightTheme",
+    ) == Some(1)
+}
+
+// 返回托盘图标和是否作为 macOS 模板图标使用
+fn resolve_tray_icon(app: &tauri::AppHandle, theme: TrayIconTheme) -> Result<(Image<'static>, bool)> {
+    let bytes = match theme {
+        TrayIconTheme::Color => {
+            let icon = app.default_window_icon()
+                .map(|icon| icon.clone().to_owned())
+                .ok_or_else(|| AppError::OsSet("Missing default window icon".to_string()))?;
+            return Ok((icon, false));
+        }
+        TrayIconTheme::Light => TRAY_ICON_LIGHT,
+        TrayIconTheme::Dark => TRAY_ICON_DARK,
+        TrayIconTheme::Auto => return resolve_auto_tray_icon(app),
+        TrayIconTheme::Wallpaper => return resolve_wallpaper_tray_icon(app),
+    };
+    Ok((Image::from_bytes(bytes)?, false))
+}
+
+// macOS 使用模板图标，由系统根据菜单栏外观着色
+#[cfg(target_os = "macos")]
+fn resolve_auto_tray_icon(_app: &tauri::AppHandle) -> Result<(Image<'static>, bool)> {
+    Ok((Image::from_bytes(TRAY_ICON_TEMPLATE)?, true))
+}
+
+#[cfg(target_os = "windows")]
+fn resolve_auto_tray_icon(_app: &tauri::AppHandle) -> Result<(Image<'static>, bool)> {
+    let bytes = if is_taskbar_light() { TRAY_ICON_LIGHT } else { TRAY_ICON_DARK };
+    Ok((Image::from_bytes(bytes)?, false))
+}
+
+// 其它平台无法可靠获取面板颜色，沿用彩色图标
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn resolve_auto_tray_icon(app: &tauri::AppHandle) -> Result<(Image<'static>, bool)> {
+    resolve_tray_icon(app, TrayIconTheme::Color)
+}
+
+// 描边颜色跟随菜单栏（macOS）或任务栏（Windows）的深浅；还没有设置过壁纸或解码失败时使用普通图标
+fn resolve_wallpaper_tray_icon(app: &tauri::AppHandle) -> Result<(Image<'static>, bool)> {
+    let path = wallpaper_setter::current_link_path()?;
+    if !path.is_file() {
+        return resolve_auto_tray_icon(app);
+    }
+    #[cfg(target_os = "windows")]
+    let dark_background = !is_taskbar_light();
+    #[cfg(not(target_os = "windows"))]
+    let dark_background = appearance::is_dark_appearance();
+    match tray_thumbnail::render(&path, dark_background) {
+        Ok(icon) => Ok((icon, false)),
+        Err(e) => {
+            warn!("Failed to render tray thumbnail: {}", e);
+            resolve_auto_tray_icon(app)
+        }
+    }
+}
+
+fn apply_tray_icon(app: &tauri::AppHandle, theme: TrayIconTheme) -> Result<()> {
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let (icon, is_template) = resolve_tray_icon(app, theme)?;
+        tray.set_icon(Some(icon))?;
+        tray.set_icon_as_template(is_template)?;
+    }
+    Ok(())
+}
+
+fn handle_tray_icon_theme(app: &tauri::AppHandle, state: &Mutex<AppState>, theme: TrayIconTheme) -> Result<()> {
+    let mut state = lock_state(state);
+    state.tray_icon_theme = theme;
+
+    if let Err(e) = update_config(|config| config.tray_icon_theme = theme.as_str().to_string()) {
+        error!("Failed to save config: {}", e);
+    }
+
+    apply_tray_icon(app, theme)?;
+    update_menu(app, &state)?;
+    info!("Tray icon theme set to {}", theme.as_str());
+    Ok(())
+}
+
+// 切换到指定刷新模式：停止旧定时器和正在进行的手动刷新，保存配置，需要时立即下载并启动新定时器
+fn set_refresh_mode(app: &tauri::AppHandle, state: &mut AppState, mode: RefreshMode) -> Result<()> {
+    if let Some((_handle, cancel)) = state.timer_handle.take() {
+        cancel.cancel();
+    }
+    if let Some(cancel) = state.manual_refresh.take() {
+        cancel.cancel();
+    }
+    state.deferred = None;
+
+    // 主动切换模式视为结束暂停
+    state.paused_until = None;
+    state.refresh_mode = mode;
+
+    // 持久化保存刷新模式
+    if let Err(e) = update_config(|config| config.refresh_mode = mode.as_str().to_string()) {
+        error!("Failed to save config: {}", e);
+    }
+
+    update_menu(app, state)?;
+
+    if mode == RefreshMode::Slideshow {
+        state.timer_handle = Some(start_refresh_task(app.clone(), mode, None));
+    } else if mode != RefreshMode::None {
+        // 首次下载放在定时任务中立即执行，不阻塞菜单；再次切换模式时随任务一起取消
+        state.timer_handle = Some(create_timer_task(app.clone(), mode, Some(Duration::ZERO)));
+    }
+
+    Ok(())
+}
+
+// 菜单中再次点击已激活的模式即取消刷新
+fn handle_refresh_mode(
+    app: &tauri::AppHandle,
+    state: &Mutex<AppState>,
+    new_mode: RefreshMode,
+) -> Result<()> {
+    let mut state = lock_state(state);
+    let mode = if state.refresh_mode == new_mode { RefreshMode::None } else { new_mode };
+    set_refresh_mode(app, &mut state, mode)
+}
+
+fn handle_autostart_toggle(
+    app: &tauri::AppHandle,
+    state: &Mutex<AppState>,
+) -> Result<()> {
+    let manager = app.autolaunch();
+    let is_enabled = manager.is_enabled().map_err(|e| AppError::OsSet(e.to_string()))?;
+
+    if is_enabled {
+        manager.disable().map_err(|e| AppError::OsSet(e.to_string()))?;
+        info!("Autostart disabled");
+    } else {
+        manager.enable().map_err(|e| AppError::OsSet(e.to_string()))?;
+        info!("Autostart enabled");
+    }
+
+    let state = lock_state(state);
+    update_menu(app, &state)?;
+
+    Ok(())
+}
+
+fn handle_startup_delay(app: &tauri::AppHandle, state: &Mutex<AppState>, secs: u64) -> Result<()> {
+    let mut state = lock_state(state);
+    state.startup_delay_secs = secs;
+    update_config(|config| config.startup_delay_secs = secs)?;
+    update_menu(app, &state)?;
+    info!("Startup delay set to {}s", secs);
+    Ok(())
+}
+
+fn fit_label(fit: Fit) -> &'static str {
+    match fit {
+        Fit::Fill => "填充",
+        Fit::Fit => "适应",
+        Fit::Stretch => "拉伸",
+        Fit::Center => "居中",
+        Fit::Tile => "平铺",
+        Fit::Span => "跨区",
+    }
+}
+
+// 显示方式按刷新模式分别保存，只修改当前模式的设置并立即应用到桌面；None 表示跟随系统
+fn handle_wallpaper_fit(app: &tauri::AppHandle, state: &Mutex<AppState>, fit: Option<Fit>) -> Result<()> {
+    let state = lock_state(state);
+    let mode = state.refresh_mode.as_str();
+    update_config(|config| match fit {
+        Some(fit) => {
+            config.wallpaper_fit.insert(mode.to_string(), fit.as_str().to_string());
+        }
+        None => {
+            config.wallpaper_fit.remove(mode);
+        }
+    })?;
+    if let Some(fit) = fit {
+        wallpaper_setter::apply_fit(fit)?;
+    }
+    update_menu(app, &state)?;
+    info!("Wallpaper fit for {} set to {}", mode, fit.map(|fit| fit.as_str()).unwrap_or("system"));
+    Ok(())
+}
+
+// 修改轮播设置后，正在轮播时重启轮播线程使其生效
+fn handle_slideshow_option(app: &tauri::AppHandle, state: &Mutex<AppState>, f: impl FnOnce(&mut AppConfig)) -> Result<()> {
+    update_config(f)?;
+    let mut state = lock_state(state);
+    if state.refresh_mode == RefreshMode::Slideshow {
+        set_refresh_mode(app, &mut state, RefreshMode::Slideshow)
+    } else {
+        update_menu(app, &state)
+    }
+}
+
+// 暂停或恢复（until 为 None）更换壁纸，保留当前的刷新模式
+fn handle_pause(app: &tauri::AppHandle, state: &Mutex<AppState>, until: Option<chrono::DateTime<chrono::Local>>) -> Result<()> {
+    let mut state = lock_state(state);
+    state.paused_until = until;
+    restart_refresh_task(app, &mut state);
+    match until {
+        Some(until) => info!("Wallpaper changes paused until {}", until.format("%Y-%m-%d %H:%M")),
+        None => info!("Wallpaper changes resumed"),
+    }
+    update_menu(app, &state)
+}
+
+// 明天本地时间零点
+fn next_local_midnight() -> Option<chrono::DateTime<chrono::Local>> {
+    let tomorrow = chrono::Local::now().date_naive().succ_opt()?;
+    tomorrow.and_hms_opt(0, 0, 0)?.and_local_timezone(chrono::Local).earliest()
+}
+
+// 切换市场后，国际模式（包括自动选择为国际区时）下立即按新市场刷新一次
+fn handle_market(app: &tauri::AppHandle, state: &Mutex<AppState>, market: &str) -> Result<()> {
+    update_config(|config| config.market = market.to_string())?;
+    info!("Market set to {:?}", market);
+    let mut state = lock_state(state);
+    let mode = state.refresh_mode;
+    if mode.is_daily() && !mode.is_china() {
+        set_refresh_mode(app, &mut state, mode)
+    } else {
+        update_menu(app, &state)
+    }
+}
+
+// 切换壁纸来源后立即按当前刷新模式重新获取，并刷新历史列表
+fn handle_wallpaper_provider(app: &tauri::AppHandle, state: &Mutex<AppState>, provider: &str) -> Result<()> {
+    update_config(|config| config.wallpaper_provider = provider.to_string())?;
+    info!("Wallpaper provider set to {}", provider);
+    let mut state = lock_state(state);
+    let mode = state.refresh_mode;
+    if mode.is_daily() {
+        set_refresh_mode(app, &mut state, mode)?;
+    } else {
+        update_menu(app, &state)?;
+    }
+    refresh_history_in_background(app, mode.is_china());
+    Ok(())
+}
+
+// 下载可能需要较长时间，在后台执行，不阻塞菜单，也不在下载期间持有状态锁
+fn handle_history_wallpaper(app: &tauri::AppHandle, state: &Mutex<AppState>, date: &str) -> Result<()> {
+    let wallpaper = lock_state(state)
+        .history
+        .find_by_date(date)
+        .cloned()
+        .ok_or_else(|| AppError::Config(format!("No wallpaper found for {}", date)))?;
+    let resolution = effective_resolution(app);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        run_guarded_async(&app, "设置历史壁纸", async {
+            match history::set_history_wallpaper(wallpaper, resolution).await {
+                Ok(wallpaper) => {
+                    lock_state(&app.state::<Mutex<AppState>>()).set_current_wallpaper(&app, wallpaper);
+                    schedule_menu_update(&app);
+                }
+                Err(e) => report_error(&app, "设置历史壁纸失败", &e),
+            }
+        })
+        .await;
+    });
+    Ok(())
+}
+
+// 把当前壁纸加入黑名单，并换回历史中最近一张未被跳过且已下载的壁纸；
+// 没有可用的历史壁纸时从缓存随机挑选，仍然没有则恢复原壁纸
+fn handle_skip_wallpaper(app: &tauri::AppHandle, state: &Mutex<AppState>) -> Result<()> {
+    let resolution = effective_resolution(app);
+    let (skipped, previous) = {
+        let state = lock_state(state);
+        let skipped = state
+            .current_wallpaper
+            .clone()
+            .ok_or_else(|| AppError::Config("No wallpaper has been applied yet".to_string()))?;
+        let mut blocklist = Blocklist::load();
+        blocklist.add(&skipped);
+        blocklist.save()?;
+        sync::push_in_background(app);
+        let previous = state
+            .history
+            .recent(usize::MAX)
+            .iter()
+            .map(|wallpaper| wallpaper.clone().with_resolution(resolution).file_name)
+            .find(|file_name| !blocklist.contains_file(file_name) && is_wallpaper_exists(file_name));
+        (skipped, previous)
+    };
+
+    let path = match previous {
+        Some(file_name) => Some(get_wallpaper_path(&file_name)?),
+        None => cache::random_cached_file(Some(&skipped.file_name))?,
+    };
+    match path {
+        Some(path) => apply_cached_wallpaper(app, &path)?,
+        None if wallpaper_setter::has_original() => {
+            wallpaper_setter::restore_original()?;
+            {
+                let mut state = lock_state(state);
+                state.current_wallpaper = None;
+                state.palette = None;
+            }
+            emit_wallpaper_changed(app, None);
+            schedule_menu_update(app);
+        }
+        None => warn!("No other wallpaper to fall back to after skipping {}", skipped.file_name),
+    }
+    info!("Skipped wallpaper {}", skipped.file_name);
+    Ok(())
+}
+
+fn handle_favorite_toggle(app: &tauri::AppHandle, state: &Mutex<AppState>) -> Result<()> {
+    let mut state = lock_state(state);
+    let wallpaper = state
+        .current_wallpaper
+        .clone()
+        .ok_or_else(|| AppError::Config("No wallpaper has been applied yet".to_string()))?;
+    state.favorites.toggle(&wallpaper);
+    state.favorites.save()?;
+    sync::push_in_background(app);
+    update_menu(app, &state)
+}
+
+// 与历史壁纸相同，文件已被清理时在后台重新下载
+fn handle_favorite_wallpaper(app: &tauri::AppHandle, state: &Mutex<AppState>, file_name: &str) -> Result<()> {
+    let wallpaper = lock_state(state)
+        .favorites
+        .find_by_file(file_name)
+        .cloned()
+        .ok_or_else(|| AppError::Config(format!("No favorite wallpaper named {}", file_name)))?;
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        run_guarded_async(&app, "设置收藏壁纸", async {
+            let result = favorites::ensure_local(&wallpaper).await.and_then(|path| wallpaper_setter::set_wallpaper(&path));
+            match result {
+                Ok(()) => {
+                    lock_state(&app.state::<Mutex<AppState>>()).set_current_wallpaper(&app, wallpaper);
+                    schedule_menu_update(&app);
+                }
+                Err(e) => report_error(&app, "设置收藏壁纸失败", &e),
+            }
+        })
+        .await;
+    });
+    Ok(())
+}
+
+// 后台拉取历史归档，完成后刷新菜单
+fn refresh_history_in_background(app: &tauri::AppHandle, is_china: bool) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        run_guarded_async(&app, "历史壁纸", async {
+            match history::refresh_history(is_china).await {
+                Ok(history) => {
+                    let state = app.state::<Mutex<AppState>>();
+                    lock_state(&state).history = history;
+                    schedule_menu_update(&app);
+                }
+                Err(e) => warn!("Failed to refresh wallpaper history: {}", e),
+            }
+        })
+        .await;
+    });
+}
+
+fn handle_notifications_toggle(app: &tauri::AppHandle, state: &Mutex<AppState>) -> Result<()> {
+    let mut state = lock_state(state);
+    state.notifications_enabled = !state.notifications_enabled;
+    let disabled = !state.notifications_enabled;
+    update_config(|config| config.notifications_disabled = disabled)?;
+    update_menu(app, &state)?;
+    info!("Wallpaper change notifications {}", if disabled { "disabled" } else { "enabled" });
+    Ok(())
+}
+
+fn handle_restore_on_quit_toggle(app: &tauri::AppHandle, state: &Mutex<AppState>) -> Result<()> {
+    let mut state = lock_state(state);
+    state.restore_on_quit = !state.restore_on_quit;
+    let enabled = state.restore_on_quit;
+    update_config(|config| config.restore_on_quit = enabled)?;
+    update_menu(app, &state)?;
+    info!("Restore original wallpaper on quit {}", if enabled { "enabled" } else { "disabled" });
+    Ok(())
+}
+
+// 开启时立即把当前壁纸设为锁屏，失败时提示并保持关闭
+#[cfg(target_os = "windows")]
+fn handle_lock_screen_toggle(app: &tauri::AppHandle, state: &Mutex<AppState>) -> Result<()> {
+    let state = lock_state(state);
+    let enabled = !load_config()?.lock_screen_enabled;
+    if enabled {
+        // 与设置桌面壁纸时一样使用最近一次设置的文件（可能是本地文件夹或收藏中的图片），并转换系统不支持的格式
+        let path = match wallpaper_setter::last_applied() {
+            Some(path) => Some(path),
+            None => state.current_wallpaper.as_ref().map(|wallpaper| get_wallpaper_path(&wallpaper.file_name)).transpose()?,
+        };
+        if let Some(path) = path.filter(|path| path.is_file()) {
+            wallpaper_setter::set_lock_screen(&convert::compatible_path(&path))?;
+        }
+    }
+    update_config(|config| config.lock_screen_enabled = enabled)?;
+    update_menu(app, &state)?;
+    info!("Lock screen wallpaper {}", if enabled { "enabled" } else { "disabled" });
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn handle_fade_toggle(app: &tauri::AppHandle, state: &Mutex<AppState>) -> Result<()> {
+    let state = lock_state(state);
+    let enabled = !load_config()?.fade_transition;
+    update_config(|config| config.fade_transition = enabled)?;
+    update_menu(app, &state)?;
+    info!("Fade transition {}", if enabled { "enabled" } else { "disabled" });
+    Ok(())
+}
+
+// 切换后立即按当前外观重新设置壁纸
+#[cfg(target_os = "macos")]
+fn handle_dark_variant_toggle(app: &tauri::AppHandle, state: &Mutex<AppState>) -> Result<()> {
+    let enabled = !load_config()?.dark_variant_enabled;
+    update_config(|config| config.dark_variant_enabled = enabled)?;
+    update_menu(app, &lock_state(state))?;
+    info!("Dark wallpaper variant {}", if enabled { "enabled" } else { "disabled" });
+    reapply_in_background(app);
+    Ok(())
+}
+
+// 图片处理可能需要一两秒，放到后台线程执行，不阻塞菜单和窗口事件
+fn reapply_in_background(app: &tauri::AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        run_guarded(&app, "重新设置壁纸", || {
+            if let Err(e) = desktop_sync::reapply_current_wallpaper(&app) {
+                warn!("Failed to re-apply wallpaper: {}", e);
+            }
+        });
+    });
+}
+
+// 恢复原壁纸后停止自动刷新，否则下一次定时刷新又会换回必应壁纸
+fn handle_restore_original(app: &tauri::AppHandle, state: &Mutex<AppState>) -> Result<()> {
+    wallpaper_setter::restore_original()?;
+    let mut state = lock_state(state);
+    if state.refresh_mode != RefreshMode::None {
+        set_refresh_mode(app, &mut state, RefreshMode::None)?;
+    }
+    update_menu(app, &state)
+}
+
+// 退出前停止定时任务，等待手动触发的下载完成，最后清理中断下载留下的临时文件
+fn shutdown(state: &Mutex<AppState>) {
+    let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+    let task = lock_state(state).timer_handle.take();
+    if let Some((handle, cancel)) = task {
+        cancel.cancel();
+        // 取消后任务在下一个等待点结束，进行中的下载随之中止
+        if tauri::async_runtime::block_on(tokio::time::timeout(SHUTDOWN_TIMEOUT, handle)).is_err() {
+            warn!("Refresh task did not stop within {}s", SHUTDOWN_TIMEOUT.as_secs());
+        }
+    }
+    while ACTIVE_DOWNLOADS.load(Ordering::SeqCst) > 0 {
+        if Instant::now() >= deadline {
+            warn!("Quitting with {} downloads still in progress", ACTIVE_DOWNLOADS.load(Ordering::SeqCst));
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    if let Err(e) = cache::remove_partial_downloads() {
+        warn!("Failed to remove partial downloads: {}", e);
+    }
+    info!("Shutdown complete");
+}
+
+fn handle_quit(app: &tauri::AppHandle, state: &Mutex<AppState>) {
+    shutdown(state);
+    if lock_state(state).restore_on_quit && wallpaper_setter::has_original() {
+        if let Err(e) = wallpaper_setter::restore_original() {
+            error!("Failed to restore original wallpaper on quit: {}", e);
+        }
+    }
+    // 有已下载的更新时在退出前安装
+    updater::install_pending();
+    app.exit(0);
+}
+
+fn handle_clear_cache(app: &tauri::AppHandle, state: &Mutex<AppState>) -> Result<()> {
+    let state = lock_state(state);
+    let current = state.current_wallpaper.as_ref().map(|wallpaper| wallpaper.file_name.as_str());
+    cache::clear(current.as_slice())?;
+    update_menu(app, &state)
+}
+
+// 按当前区域强制重新下载，在后台执行避免阻塞菜单；连续点击时只保留最后一次
+fn handle_refresh_now(app: &tauri::AppHandle, state: &Mutex<AppState>) {
+    let cancel = CancellationToken::new();
+    let is_china = {
+        let mut state = lock_state(state);
+        if let Some(previous) = state.manual_refresh.replace(cancel.clone()) {
+            previous.cancel();
+        }
+        state.refresh_mode.is_china()
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        run_guarded_async(&app, "立即刷新", async {
+            tokio::select! {
+                _ = cancel.cancelled() => info!("Manual refresh cancelled"),
+                result = refresh_wallpaper(&app, true, is_china) => {
+                    if let Err(e) = result {
+                        report_error(&app, "壁纸刷新失败", &e);
+                    }
+                }
+            }
+        })
+        .await;
+    });
+}
+
+fn handle_reveal_wallpaper(state: &Mutex<AppState>) -> Result<()> {
+    let file_name = lock_state(state)
+        .current_wallpaper
+        .as_ref()
+        .map(|wallpaper| wallpaper.file_name.clone())
+        .ok_or_else(|| AppError::Config("No wallpaper has been applied yet".to_string()))?;
+    reveal_in_file_manager(&get_wallpaper_path(&file_name)?)
+}
+
+// 用系统文件管理器打开壁纸保存目录，未自定义时为数据目录下的 wallpapers/
+fn open_wallpaper_dir() -> Result<()> {
+    let dir = get_wallpaper_dir()?;
+    open::that(&dir)?;
+    info!("Opened wallpaper directory {:?}", dir);
+    Ok(())
+}
+
+// 用系统默认的图片查看器打开当前壁纸；通过 current.jpg 打开，本地文件夹轮播的图片同样适用
+fn open_wallpaper_image() -> Result<()> {
+    let path = wallpaper_setter::current_link_path()?;
+    if !path.is_file() {
+        return Err(AppError::Config("No wallpaper has been applied yet".to_string()));
+    }
+    open::that(&path)?;
+    info!("Opened {:?} in the default viewer", path);
+    Ok(())
+}
+
+// 复制的是固定的 current.jpg 路径而不是当前文件，外部工具引用后换壁纸也不用改
+fn handle_copy_wallpaper_path(app: &tauri::AppHandle) -> Result<()> {
+    let path = wallpaper_setter::current_link_path()?;
+    if !path.is_file() {
+        return Err(AppError::Config("No wallpaper has been applied yet".to_string()));
+    }
+    app.clipboard()
+        .write_text(path.to_string_lossy().into_owned())
+        .map_err(|e| AppError::OsSet(format!("Failed to write clipboard: {}", e)))?;
+    info!("Copied {:?} to clipboard", path);
+    Ok(())
+}
+
+// 复制图片内容，可以直接粘贴到聊天软件；本地文件夹轮播的图片同样可以复制
+fn copy_wallpaper_image(app: &tauri::AppHandle) -> Result<()> {
+    let path = wallpaper_setter::current_link_path()?;
+    if !path.is_file() {
+        return Err(AppError::Config("No wallpaper has been applied yet".to_string()));
+    }
+    let image = processing::decode(&path)?;
+    let (width, height) = image.dimensions();
+    let rgba = image::DynamicImage::ImageRgb8(image).into_rgba8().into_raw();
+    app.clipboard()
+        .write_image(&tauri::image::Image::new_owned(rgba, width, height))
+        .map_err(|e| AppError::OsSet(format!("Failed to write clipboard: {}", e)))?;
+    info!("Copied image {:?} to clipboard", path);
+    Ok(())
+}
+
+// 本地文件夹中的图片没有来源地址
+fn copy_wallpaper_url(app: &tauri::AppHandle) -> Result<()> {
+    let url = lock_state(&app.state::<Mutex<AppState>>())
+        .current_wallpaper
+        .as_ref()
+        .map(|wallpaper| wallpaper.url.clone())
+        .filter(|url| !url.is_empty())
+        .ok_or_else(|| AppError::Config(tr("当前壁纸没有来源地址").to_string()))?;
+    app.clipboard()
+        .write_text(url.clone())
+        .map_err(|e| AppError::OsSet(format!("Failed to write clipboard: {}", e)))?;
+    info!("Copied {} to clipboard", url);
+    Ok(())
+}
+
+// 设置壁纸需要一些时间，放到后台线程，不阻塞托盘菜单
+fn handle_step_wallpaper(app: &tauri::AppHandle, forward: bool) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        run_guarded(&app, "切换壁纸", || {
+            let result = if forward { navigation::next(&app) } else { navigation::previous(&app) };
+            if let Err(e) = result {
+                report_error(&app, if forward { "切换下一张壁纸失败" } else { "切换上一张壁纸失败" }, &e);
+            }
+        });
+    });
+}
+
+// 解码大图需要一些时间，放到后台线程，不阻塞托盘菜单
+fn handle_copy_wallpaper_image(app: &tauri::AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        run_guarded(&app, "复制壁纸图片", || {
+            if let Err(e) = copy_wallpaper_image(&app) {
+                report_error(&app, "复制壁纸图片失败", &e);
+            }
+        })
+    });
+}
+
+// 托盘菜单和控制窗口共用的操作分发
+fn handle_menu_action(app: &tauri::AppHandle, id: &str) {
+    let state = app.state::<Mutex<AppState>>();
+
+    match id {
+        "daily_china" => {
+            if let Err(e) = handle_refresh_mode(app, &state, RefreshMode::DailyChina) {
+                report_error(app, "切换国内壁纸失败", &e);
+            }
+        }
+        "daily_global" => {
+            if let Err(e) = handle_refresh_mode(app, &state, RefreshMode::DailyGlobal) {
+                report_error(app, "切换国际壁纸失败", &e);
+            }
+        }
+        "daily_auto" => {
+            if let Err(e) = handle_refresh_mode(app, &state, RefreshMode::DailyAuto) {
+                report_error(app, "切换自动选择区域失败", &e);
+            }
+        }
+        "autostart" => {
+            if let Err(e) = handle_autostart_toggle(app, &state) {
+                report_error(app, "设置开机启动失败", &e);
+            }
+        }
+        "tray_icon_auto" | "tray_icon_color" | "tray_icon_light" | "tray_icon_dark" | "tray_icon_wallpaper" => {
+            let theme = TrayIconTheme::from_str(id.trim_start_matches("tray_icon_"));
+            if let Err(e) = handle_tray_icon_theme(app, &state, theme) {
+                report_error(app, "切换托盘图标失败", &e);
+            }
+        }
+        id if id.starts_with("batch_download_") => {
+            let days = id.trim_start_matches("batch_download_").parse().unwrap_or(0);
+            if batch::BATCH_DAYS.contains(&days) {
+                batch::start(app, days);
+            }
+        }
+        "fit_system" => {
+            if let Err(e) = handle_wallpaper_fit(app, &state, None) {
+                report_error(app, "切换壁纸显示方式失败", &e);
+            }
+        }
+        id if id.starts_with("fit_") => {
+            let fit = Fit::from_str(id.trim_start_matches("fit_"));
+            if let Err(e) = handle_wallpaper_fit(app, &state, Some(fit)) {
+                report_error(app, "切换壁纸显示方式失败", &e);
+            }
+        }
+        id if id.starts_with("startup_delay_") => {
+            let secs = id.trim_start_matches("startup_delay_").parse().unwrap_or(0);
+            if STARTUP_DELAY_OPTIONS.contains(&secs) {
+                if let Err(e) = handle_startup_delay(app, &state, secs) {
+                    report_error(app, "设置启动延迟失败", &e);
+                }
+            }
+        }
+        "slideshow_toggle" => {
+            if let Err(e) = handle_refresh_mode(app, &state, RefreshMode::Slideshow) {
+                report_error(app, "切换壁纸轮播失败", &e);
+            }
+        }
+        "slideshow_shuffle" | "slideshow_sequential" => {
+            let order = id.trim_start_matches("slideshow_").to_string();
+            if let Err(e) = handle_slideshow_option(app, &state, |config| config.slideshow_order = order) {
+                report_error(app, "设置轮播顺序失败", &e);
+            }
+        }
+        id if id.starts_with("slideshow_interval_") => {
+            let minutes = id.trim_start_matches("slideshow_interval_").parse().unwrap_or(0);
+            if slideshow::SLIDESHOW_INTERVAL_OPTIONS.contains(&minutes) {
+                if let Err(e) = handle_slideshow_option(app, &state, |config| config.slideshow_interval_minutes = minutes) {
+                    report_error(app, "设置轮播间隔失败", &e);
+                }
+            }
+        }
+        id if id.starts_with("provider_") => {
+            if let Err(e) = handle_wallpaper_provider(app, &state, id.trim_start_matches("provider_")) {
+                report_error(app, "切换壁纸来源失败", &e);
+            }
+        }
+        id if id.starts_with("market_") => {
+            let market = id.trim_start_matches("market_");
+            let market = if market == "default" { "" } else { market };
+            if MARKETS.iter().any(|(code, _)| *code == market) {
+                if let Err(e) = handle_market(app, &state, market) {
+                    report_error(app, "切换壁纸市场失败", &e);
+                }
+            }
+        }
+        id if id.starts_with("history_") => {
+            if let Err(e) = handle_history_wallpaper(app, &state, id.trim_start_matches("history_")) {
+                report_error(app, "设置历史壁纸失败", &e);
+            }
+        }
+        "slideshow_favorites" => {
+            let source = if load_config().unwrap_or_default().slideshow_source == slideshow::SOURCE_FAVORITES {
+                slideshow::SOURCE_ALL
+            } else {
+                slideshow::SOURCE_FAVORITES
+            };
+            if let Err(e) = handle_slideshow_option(app, &state, |config| config.slideshow_source = source.to_string()) {
+                report_error(app, "设置轮播范围失败", &e);
+            }
+        }
+        "slideshow_folder" => {
+            let source = if load_config().unwrap_or_default().slideshow_source == slideshow::SOURCE_FOLDER {
+                slideshow::SOURCE_ALL
+            } else {
+                slideshow::SOURCE_FOLDER
+            };
+            if let Err(e) = handle_slideshow_option(app, &state, |config| config.slideshow_source = source.to_string()) {
+                report_error(app, "设置轮播范围失败", &e);
+            }
+        }
+        "favorite_toggle" => {
+            if let Err(e) = handle_favorite_toggle(app, &state) {
+                report_error(app, "收藏壁纸失败", &e);
+            }
+        }
+        id if id.starts_with("favorite_") => {
+            if let Err(e) = handle_favorite_wallpaper(app, &state, id.trim_start_matches("favorite_")) {
+                report_error(app, "设置收藏壁纸失败", &e);
+            }
+        }
+        "skip_wallpaper" => {
+            if let Err(e) = handle_skip_wallpaper(app, &state) {
+                report_error(app, "跳过壁纸失败", &e);
+            }
+        }
+        "pause_1h" => {
+            let until = chrono::Local::now() + chrono::Duration::hours(1);
+            if let Err(e) = handle_pause(app, &state, Some(until)) {
+                report_error(app, "暂停更换壁纸失败", &e);
+            }
+        }
+        "pause_tomorrow" => {
+            if let Err(e) = handle_pause(app, &state, next_local_midnight()) {
+                report_error(app, "暂停更换壁纸失败", &e);
+            }
+        }
+        "pause_resume" => {
+            if let Err(e) = handle_pause(app, &state, None) {
+                report_error(app, "恢复更换壁纸失败", &e);
+            }
+        }
+        "refresh_now" => handle_refresh_now(app, &state),
+        "previous_wallpaper" => handle_step_wallpaper(app, false),
+        "next_wallpaper" => handle_step_wallpaper(app, true),
+        "clear_cache" => {
+            if let Err(e) = handle_clear_cache(app, &state) {
+                report_error(app, "清理缓存失败", &e);
+            }
+        }
+        "export_folder" => export::export_all(app, false),
+        "export_zip" => export::export_all(app, true),
+        "export_sync" => sync::export_file(app),
+        "import_sync" => sync::import_file(app),
+        "install_update" => updater::install_now(app),
+        "notifications" => {
+            if let Err(e) = handle_notifications_toggle(app, &state) {
+                report_error(app, "设置通知失败", &e);
+            }
+        }
+        "restore_original" => {
+            if let Err(e) = handle_restore_original(app, &state) {
+                report_error(app, "恢复原壁纸失败", &e);
+            }
+        }
+        "restore_on_quit" => {
+            if let Err(e) = handle_restore_on_quit_toggle(app, &state) {
+                report_error(app, "设置退出时恢复失败", &e);
+            }
+        }
+        #[cfg(target_os = "macos")]
+        "dark_variant" => {
+            if let Err(e) = handle_dark_variant_toggle(app, &state) {
+                report_error(app, "设置深色壁纸失败", &e);
+            }
+        }
+        #[cfg(target_os = "macos")]
+        "dynamic_wallpaper" => dynamic_wallpaper::export(app),
+        #[cfg(target_os = "windows")]
+        "lock_screen" => {
+            if let Err(e) = handle_lock_screen_toggle(app, &state) {
+                report_error(app, "设置锁屏壁纸失败", &e);
+            }
+        }
+        #[cfg(target_os = "windows")]
+        "fade_transition" => {
+            if let Err(e) = handle_fade_toggle(app, &state) {
+                report_error(app, "设置淡入淡出失败", &e);
+            }
+        }
+        "open_wallpaper_dir" => {
+            if let Err(e) = open_wallpaper_dir() {
+                report_error(app, "打开壁纸文件夹失败", &e);
+            }
+        }
+        "open_wallpaper_image" => {
+            if let Err(e) = open_wallpaper_image() {
+                report_error(app, "打开壁纸图片失败", &e);
+            }
+        }
+        "reveal_wallpaper" => {
+            if let Err(e) = handle_reveal_wallpaper(&state) {
+                report_error(app, "打开壁纸位置失败", &e);
+            }
+        }
+        "copy_wallpaper_path" => {
+            if let Err(e) = handle_copy_wallpaper_path(app) {
+                report_error(app, "复制壁纸路径失败", &e);
+            }
+        }
+        "copy_wallpaper_image" => handle_copy_wallpaper_image(app),
+        "copy_wallpaper_url" => {
+            if let Err(e) = copy_wallpaper_url(app) {
+                report_error(app, "复制壁纸地址失败", &e);
+            }
+        }
+        "search" => {
+            if let Err(e) = commands::show_search_window(app) {
+                report_error(app, "打开搜索窗口失败", &e);
+            }
+        }
+        "quiz" => {
+            if let Err(e) = quiz::show_window(app) {
+                report_error(app, "打开猜地点窗口失败", &e);
+            }
+        }
+        "settings" => {
+            if let Err(e) = settings::show_settings_window(app) {
+                report_error(app, "打开设置窗口失败", &e);
+            }
+        }
+        "open_log" => {
+            if let Err(e) = logging::open_log_file() {
+                report_error(app, "打开日志失败", &e);
+            }
+        }
+        "open_website" => {
+            if let Err(e) = open::that("https://bing.wdbyte.com") {
+                report_error(app, "打开网站失败", &AppError::from(e));
+            }
+        }
+        "about" => {
+            if let Err(e) = about::show_window(app) {
+                report_error(app, "打开关于窗口失败", &e);
+            }
+        }
+        "quit" => handle_quit(app, &state),
+        _ => warn!("Unhandled menu item: {:?}", id),
+    }
+}
+
+#[derive(Serialize)]
+struct ControlStatus {
+    refresh_mode: &'static str,
+    autostart_enabled: bool,
+}
+
+#[tauri::command]
+fn get_control_status(app: tauri::AppHandle, state: tauri::State<'_, Mutex<AppState>>) -> ControlStatus {
+    ControlStatus {
+        refresh_mode: lock_state(&state).refresh_mode.as_str(),
+        autostart_enabled: app.autolaunch().is_enabled().unwrap_or(false),
+    }
+}
+
+#[tauri::command]
+fn trigger_action(app: tauri::AppHandle, action: String) {
+    run_guarded(&app, "窗口操作", || handle_menu_action(&app, &action));
+}
+
+// 检测桌面环境是否提供 StatusNotifier 托盘，无法判断时按可用处理
+#[cfg(target_os = "linux")]
+fn is_tray_available() -> bool {
+    let output = Command::new("dbus-send")
+        .args(&[
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus.NameHasOwner",
+            "string:org.kde.StatusNotifierWatcher",
+        ])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).contains("boolean true"),
+        _ => true,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_tray_available() -> bool {
+    true
+}
+
+fn create_tray(app: &tauri::AppHandle, state: &AppState) -> Result<()> {
+    if !is_tray_available() {
+        return Err(AppError::OsSet("No system tray host available".to_string()));
+    }
+
+    let (icon, is_template) = resolve_tray_icon(app, state.tray_icon_theme)?;
+
+    TrayIconBuilder::with_id(TRAY_ID)
+        .icon(icon)
+        .icon_as_template(is_template)
+        .tooltip("Bing Wallpaper")
+        .menu(&build_menu(app, state)?)
+        .on_menu_event(|app, event| {
+            run_guarded(app, "菜单事件", || handle_menu_action(app, &event.id.0));
+        })
+        .build(app)?;
+    Ok(())
+}
+
+// 没有托盘时显示一个小窗口承载同样的操作，关闭窗口即退出程序
+// 再次启动程序时不再创建新的托盘，而是让已运行的实例立即刷新壁纸；
+// 托盘不可用时顺便把控制窗口调到前台
+fn handle_second_instance(app: &tauri::AppHandle, argv: &[String]) {
+    if let Some(link) = deep_link::find_in_args(argv) {
+        deep_link::handle(app, link);
+        return;
+    }
+    if argv.iter().any(|arg| arg == AUTOSTART_ARG) {
+        info!("Ignoring duplicate login launch");
+        return;
+    }
+    info!("Second instance launched, refreshing wallpaper");
+    if app.get_webview_window(CONTROL_WINDOW_LABEL).is_some() {
+        if let Err(e) = show_control_window(app) {
+            error!("Failed to focus control window: {}", e);
+        }
+    }
+    let state = app.state::<Mutex<AppState>>();
+    handle_refresh_now(app, &state);
+}
+
+fn show_control_window(app: &tauri::AppHandle) -> Result<()> {
+    if let Some(window) = app.get_webview_window(CONTROL_WINDOW_LABEL) {
+        window.set_focus()?;
+        return Ok(());
+    }
+    let window = WebviewWindowBuilder::new(app, CONTROL_WINDOW_LABEL, WebviewUrl::App("control.html".into()))
+        .title("Bing Wallpaper")
+        .inner_size(320.0, 360.0)
+        .resizable(false)
+        .build()?;
+
+    let app_handle = app.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::CloseRequested { .. } = event {
+            if app_handle.tray_by_id(TRAY_ID).is_none() {
+                app_handle.exit(0);
+            }
+        }
+    });
+    Ok(())
+}
+
+pub fn run() {
+    // 初始化日志
+    logging::init();
+
+    // 崩溃时在数据目录生成报告
+    crash_report::install_panic_hook(env!("CARGO_PKG_VERSION"));
+
+    // 命令行模式的提示也需要本地化，先确定界面语言
+    i18n::set_language(&load_config().map(|config| config.language).unwrap_or_default());
+
+    // 带命令行参数时执行一次操作后直接退出，不启动托盘
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
+    // 启动时确保UUID已经生成
+    match device_uuid() {
+        Ok(Some(uuid)) => info!("Using device UUID: {}", uuid),
+        Ok(None) => info!("Device UUID disabled by privacy setting"),
+        Err(e) => error!("Failed to initialize UUID: {}", e),
     }
 
     // 启动时加载保存的刷新模式
-    let saved_refresh_mode = load_config().unwrap_or(RefreshMode::None);
-    let saved_is_china = saved_refresh_mode.is_china();
+    let saved_config = load_config().unwrap_or_default();
+    let saved_refresh_mode = RefreshMode::from_str(&saved_config.refresh_mode);
+    let saved_refresh_mode = if daemon::is_enabled() { daemon::refresh_mode(saved_refresh_mode) } else { saved_refresh_mode };
+    let launched_at_login = std::env::args().any(|arg| arg == AUTOSTART_ARG);
+    // 以 --portable 开启便携模式时，登录自启动也要带上，否则会改用系统的数据目录
+    let autostart_args = if portable::is_enabled() { vec![AUTOSTART_ARG, portable::PORTABLE_ARG] } else { vec![AUTOSTART_ARG] };
+    if portable::is_enabled() {
+        info!("Portable mode, data directory: {:?}", portable::data_dir());
+    }
 
     if let Err(e) = tauri::Builder::default()
-        .plugin(tauri_plugin_autostart::init(MacosLauncher::LaunchAgent, None))
+        // 单实例插件需要最先注册，重复启动时由已运行的实例处理
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            run_guarded(app, "重复启动", || handle_second_instance(app, &argv));
+        }))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_autostart::init(MacosLauncher::LaunchAgent, Some(autostart_args)))
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(hotkey::plugin())
         .manage(Mutex::new(AppState {
             refresh_mode: saved_refresh_mode,
             timer_handle: None,
+            tray_icon_theme: TrayIconTheme::from_str(&saved_config.tray_icon_theme),
+            current_wallpaper: None,
+            startup_delay_secs: saved_config.startup_delay_secs,
+            history: WallpaperHistory::load(),
+            favorites: Favorites::load(),
+            notifications_enabled: !saved_config.notifications_disabled,
+            network_failures: 0,
+            offline: false,
+            restore_on_quit: saved_config.restore_on_quit,
+            last_error: None,
+            paused_until: None,
+            batch_progress: None,
+            palette: None,
+            last_refresh: None,
+            next_refresh: None,
+            manual_refresh: None,
+            deferred: None,
+            update_available: None,
+            applied: navigation::AppliedHistory::load(),
+            task_restarted: None,
         }))
+        .invoke_handler(tauri::generate_handler![
+            get_changelog,
+            get_control_status,
+            trigger_action,
+            commands::get_current_wallpaper,
+            commands::get_wallpaper_palette,
+            commands::list_cached_wallpapers,
+            commands::apply_wallpaper,
+            commands::search_wallpapers,
+            commands::apply_search_result,
+            commands::apply_wallpaper_by_date,
+            export::export_wallpapers,
+            thumbnail::get_thumbnail,
+            quiz::get_quiz_question,
+            quiz::answer_quiz,
+            commands::copy_wallpaper_image,
+            commands::copy_wallpaper_url,
+            commands::open_wallpaper_dir,
+            commands::open_wallpaper_image,
+            commands::set_refresh_mode,
+            settings::get_settings,
+            settings::save_settings,
+            settings::get_pictures_save_dir,
+            settings::reset_device_id,
+            onboarding::finish_onboarding,
+            about::get_about_info,
+            about::open_data_dir,
+            overlay::get_overlay_info,
+            about::copy_diagnostics,
+        ])
+        .on_window_event(|window, event| {
+            // 系统切换深浅色时，自动模式下重新选择托盘图标
+            if let WindowEvent::ThemeChanged(_) = event {
+                let app = window.app_handle();
+                let theme = lock_state(&app.state::<Mutex<AppState>>()).tray_icon_theme;
+                if matches!(theme, TrayIconTheme::Auto | TrayIconTheme::Wallpaper) {
+                    if let Err(e) = apply_tray_icon(app, theme) {
+                        error!("Failed to update tray icon: {}", e);
+                    }
+                }
+                // 每个窗口都会收到该事件，只在常驻的主窗口上处理一次
+                if window.label() == "main" && load_config().map(|config| config.dark_variant_enabled).unwrap_or(false) {
+                    reapply_in_background(app);
+                }
+            }
+        })
         .setup(move |app| {
             // 在 macOS 托盘中隐藏
             #[cfg(target_os = "macos")]
             app.set_activation_policy(tauri::ActivationPolicy::Accessory);
 
-            let autostart_enabled = app.autolaunch().is_enabled().unwrap_or(false);
-            let autostart_label = if autostart_enabled { "开机自启动 ✓" } else { "开机自启动" };
-
-            let china_label = if saved_refresh_mode == RefreshMode::DailyChina { "每日壁纸刷新(中国) ✓" } else { "每日壁纸刷新(中国)" };
-            let global_label = if saved_refresh_mode == RefreshMode::DailyGlobal { "每日壁纸刷新(国际) ✓" } else { "每日壁纸刷新(国际)" };
-
-            let tray = TrayIconBuilder::new()
-                .icon(app.default_window_icon().unwrap().clone())
-                .menu(&Menu::with_items(app, &[
-                    &MenuItem::with_id(app, "daily_china", china_label, true, None::<&str>)?,
-                    &MenuItem::with_id(app, "daily_global", global_label, true, None::<&str>)?,
-                    &MenuItem::with_id(app, "separator1", "--------------", false, None::<&str>)?,
-                    &MenuItem::with_id(app, "autostart", autostart_label, true, None::<&str>)?,
-                    &MenuItem::with_id(app, "open_website", "打开必应壁纸网站", true, None::<&str>)?,
-                    &MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?,
-                ])?)
-                .build(app)?;
-
-            // 恢复之前保存的刷新模式：立即下载并启动定时器
-            if saved_refresh_mode != RefreshMode::None {
-                if let Err(e) = download_and_set_wallpaper(true, saved_is_china) {
-                    error!("Failed to restore wallpaper on startup: {}", e);
+            // 重新注册自启动项，使其指向当前程序路径并带上启动参数
+            let autolaunch = app.autolaunch();
+            if autolaunch.is_enabled().unwrap_or(false) {
+                if let Err(e) = autolaunch.enable() {
+                    warn!("Failed to refresh autostart entry: {}", e);
+                }
+            }
+
+            // 上次运行被中断时可能留下写了一半的临时文件
+            if let Err(e) = atomic_file::recover() {
+                warn!("Failed to clean up interrupted writes: {}", e);
+            }
+
+            // 旧版本把图片直接放在应用数据目录，在开始下载前搬到默认保存目录
+            match library::migrate_legacy_layout() {
+                Ok(0) => {}
+                Ok(_) => reapply_in_background(app.handle()),
+                Err(e) => warn!("Failed to migrate wallpapers to the save directory: {}", e),
+            }
+
+            // 后台服务模式不创建托盘，也不打开任何窗口
+            if daemon::is_enabled() {
+                info!("Running in daemon mode without tray");
+            } else {
+                let tray_result = {
+                    let state = app.state::<Mutex<AppState>>();
+                    let state = lock_state(&state);
+                    create_tray(app.handle(), &state)
+                };
+                if let Err(e) = tray_result {
+                    warn!("System tray unavailable, falling back to control window: {}", e);
+                    show_control_window(app.handle())?;
                 }
+            }
+
+            // 恢复之前保存的刷新模式：按启动延迟在后台下载并启动定时器
+            if saved_refresh_mode != RefreshMode::None {
                 let state = app.state::<Mutex<AppState>>();
-                let mut state = state.lock().map_err(|_| AppError("Failed to lock state".to_string()))?;
-                state.timer_handle = Some(create_timer_thread(saved_is_china));
+                let mut state = lock_state(&state);
+                // 启动延迟只在登录自启动时生效，手动启动时立即刷新
+                let startup_delay = if launched_at_login {
+                    Duration::from_secs(state.startup_delay_secs)
+                } else {
+                    Duration::ZERO
+                };
+                state.timer_handle = Some(start_refresh_task(app.handle().clone(), saved_refresh_mode, Some(startup_delay)));
                 info!("Restored refresh mode: {}", saved_refresh_mode.as_str());
             }
 
-            let tray_clone = tray.clone();
+            // 定时任务意外结束时重新启动
+            watchdog::start(app.handle());
 
-            tray.on_menu_event(move |app, event| {
-                let state = app.state::<Mutex<AppState>>();
-                
-                match event.id.0.as_str() {
-                    "daily_china" => {
-                        if let Err(e) = handle_refresh_mode(app, &tray_clone, &state, RefreshMode::DailyChina, true) {
-                            error!("Failed to handle China refresh mode: {}", e);
-                        }
-                    }
-                    "daily_global" => {
-                        if let Err(e) = handle_refresh_mode(app, &tray_clone, &state, RefreshMode::DailyGlobal, false) {
-                            error!("Failed to handle Global refresh mode: {}", e);
-                        }
-                    }
-                    "autostart" => {
-                        if let Err(e) = handle_autostart_toggle(app, &tray_clone, &state) {
-                            error!("Failed to toggle autostart: {}", e);
-                        }
-                    }
-                    "open_website" => {
-                        if let Err(e) = open::that("https://bing.wdbyte.com") {
-                            error!("Failed to open website: {}", e);
+            // 休眠唤醒或显示器变化后，系统可能把壁纸还原，需要重新设置
+            desktop_sync::start(app.handle().clone());
+
+            // 以下窗口、快捷键、更新和桌面卡片都需要有人在桌面前，后台服务模式下跳过；
+            // 版本号也不记录，之后启动托盘程序时照常展示更新内容
+            let interactive = !daemon::is_enabled();
+
+            // 全新安装（从未记录过运行版本）且没有开启刷新时展示引导窗口
+            let first_run = load_config().map(|config| config.last_run_version.is_empty()).unwrap_or(false);
+            if interactive && first_run && saved_refresh_mode == RefreshMode::None {
+                if let Err(e) = onboarding::show_window(app.handle()) {
+                    error!("Failed to show onboarding window: {}", e);
+                }
+            }
+
+            // 升级后首次运行时展示更新内容
+            if interactive {
+                let current_version = app.package_info().version.to_string();
+                match check_version_upgrade(&current_version) {
+                    Ok(true) => {
+                        if let Err(e) = show_whats_new_window(app.handle()) {
+                            error!("Failed to show what's new window: {}", e);
                         }
                     }
-                    "quit" => app.exit(0),
-                    _ => warn!("Unhandled menu item: {:?}", event.id),
+                    Ok(false) => {}
+                    Err(e) => error!("Failed to check version upgrade: {}", e),
                 }
-            });
+            }
+
+            // 图库使用的缩略图
+            thumbnail::generate_in_background(app.handle());
+
+            // 后台更新历史壁纸列表
+            refresh_history_in_background(app.handle(), saved_refresh_mode.is_china());
+
+            if interactive {
+                // 后台检查并下载更新
+                updater::start(app.handle());
+                if let Err(e) = hotkey::register(app.handle()) {
+                    error!("Failed to register hotkeys: {}", e);
+                }
+            }
+
+            // 注册 bingwallpaper:// 协议，处理启动时带来的链接
+            deep_link::setup(app.handle());
+
+            if !interactive {
+                if let Err(e) = daemon::ensure_control_token() {
+                    error!("Failed to generate control API token: {}", e);
+                }
+            }
+            if let Err(e) = control_api::restart(app.handle()) {
+                error!("Failed to start control API: {}", e);
+            }
+
+            if interactive {
+                if let Err(e) = overlay::refresh(app.handle()) {
+                    error!("Failed to show desktop overlay: {}", e);
+                }
+            }
+
+            // 读取同步文件夹中其它电脑写入的收藏和设置，之后定时检查
+            sync::start(app.handle());
+
+            // 上次崩溃遗留的报告，询问用户是否发送
+            if interactive {
+                crash_report::offer_pending_reports(app.handle());
+            }
 
             Ok(())
         })
@@ -481,4 +2771,4 @@ pub fn run() {
     {
         error!("Error running application: {}", e);
     }
-}
\ No newline at end of file
+}
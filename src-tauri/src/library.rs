@@ -0,0 +1,89 @@
+// 壁纸保存目录的迁移：修改保存目录后把已下载的壁纸搬到新目录，并更新元信息数据库中的路径。
+// 旧版本把图片直接放在应用数据目录，与配置、数据库、日志混在一起，启动时搬到默认保存目录
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use log::{info, warn};
+use tauri::Manager;
+
+use crate::{
+    cache, download_lock, get_app_data_dir, i18n::{tr, tr_fmt}, load_config, lock_state, navigation::AppliedHistory, notify,
+    reapply_in_background, report_error, resolve_wallpaper_dir, run_guarded_async, store, AppState, Result,
+};
+
+// 跨磁盘时不能直接改名，复制后删除原文件
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+// 只搬本应用管理的壁纸（规则与缓存清理相同），用户放在旧目录中的其它图片保持不动；
+// 新目录中已有同名文件时保留两边的文件，返回实际移动的数量
+fn move_wallpapers(from: &Path, to: &Path) -> Result<usize> {
+    if from == to || !from.is_dir() {
+        return Ok(0);
+    }
+    fs::create_dir_all(to)?;
+    let mut moved = 0;
+    for path in cache::managed_paths(from)? {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        let dest = to.join(name);
+        if dest.exists() {
+            warn!("Skipped moving {:?}: {:?} already exists", path, dest);
+            continue;
+        }
+        if let Err(e) = move_file(&path, &dest) {
+            warn!("Failed to move {:?} to {:?}: {}", path, dest, e);
+            continue;
+        }
+        moved += 1;
+        if let Err(e) = store::record_moved(&dest) {
+            warn!("Failed to record new path of {:?}: {}", dest, e);
+        }
+    }
+    info!("Moved {} wallpapers from {:?} to {:?}", moved, from, to);
+    Ok(moved)
+}
+
+// 启动时调用，此时还没有开始下载；未自定义保存目录时才搬，返回移动的数量
+pub(crate) fn migrate_legacy_layout() -> Result<usize> {
+    let save_dir = load_config()?.save_dir;
+    if !save_dir.is_empty() {
+        return Ok(0);
+    }
+    move_wallpapers(&get_app_data_dir()?, &resolve_wallpaper_dir(&save_dir)?)
+}
+
+// 保存设置后调用：持有下载锁在后台搬运，完成后重新设置当前壁纸，让系统引用新位置的文件
+pub(crate) fn relocate(app: &tauri::AppHandle, from: PathBuf, to: PathBuf) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        run_guarded_async(&app, "移动壁纸", async {
+            let result = match download_lock::acquire().await {
+                Ok(_lock) => move_wallpapers(&from, &to),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(0) => {}
+                Ok(moved) => {
+                    notify(
+                        &app,
+                        tr("壁纸已移动"),
+                        &tr_fmt("已把 {} 张壁纸移动到 {}", &[&moved.to_string(), &to.display().to_string()]),
+                    );
+                    lock_state(&app.state::<Mutex<AppState>>()).applied = AppliedHistory::load();
+                    reapply_in_background(&app);
+                }
+                Err(e) => report_error(&app, "移动壁纸失败", &e),
+            }
+        })
+        .await;
+    });
+}
@@ -0,0 +1,79 @@
+// 本地文件夹来源：轮播用户指定目录中的图片，可选包含子目录，按扩展名过滤
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use log::warn;
+
+use crate::{cache, i18n::{tr, tr_with}, load_config, AppError, Result};
+
+// 递归扫描的最大深度和最多收集的文件数，避免误选根目录时长时间扫描
+const MAX_SCAN_DEPTH: usize = 8;
+const MAX_SCAN_FILES: usize = 10_000;
+
+// 格式设置形如 "jpg, png"，为空时接受所有支持的图片格式
+fn parse_formats(formats: &str) -> Vec<String> {
+    formats
+        .split([',', ' ', ';'])
+        .map(|format| format.trim().trim_start_matches('.').to_lowercase())
+        .filter(|format| !format.is_empty())
+        .collect()
+}
+
+fn matches_format(path: &Path, formats: &[String]) -> bool {
+    if !cache::is_image(path) {
+        return false;
+    }
+    formats.is_empty()
+        || path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| formats.contains(&ext.to_lowercase()))
+}
+
+// 不跟随符号链接，跳过隐藏文件和目录；无法读取的子目录只记录警告
+fn collect(dir: &Path, recursive: bool, depth: usize, formats: &[String], files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read folder {:?}: {}", dir, e);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        if files.len() >= MAX_SCAN_FILES {
+            return;
+        }
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        if file_type.is_dir() {
+            if recursive && depth < MAX_SCAN_DEPTH {
+                collect(&path, recursive, depth + 1, formats, files);
+            }
+        } else if file_type.is_file() && matches_format(&path, formats) {
+            files.push(path);
+        }
+    }
+}
+
+// 按路径排序，顺序轮播时按文件名依次播放
+pub(crate) fn scan() -> Result<Vec<PathBuf>> {
+    let config = load_config()?;
+    if config.local_folder.is_empty() {
+        return Err(AppError::Config(tr("请先在设置中选择本地壁纸文件夹").to_string()));
+    }
+    let root = PathBuf::from(&config.local_folder);
+    if !root.is_dir() {
+        return Err(AppError::Config(tr_with("本地壁纸文件夹不存在: {}", &config.local_folder)));
+    }
+    let formats = parse_formats(&config.local_folder_formats);
+    let mut files = Vec::new();
+    collect(&root, config.local_folder_recursive, 0, &formats, &mut files);
+    files.sort();
+    Ok(files)
+}
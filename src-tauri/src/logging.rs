@@ -0,0 +1,136 @@
+// 文件日志：托盘程序看不到 stderr，日志同时写入数据目录下的文件，超过大小上限后轮转
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use log::{warn, LevelFilter};
+
+use crate::{get_app_data_dir, load_config, Result};
+
+const LOG_FILE_NAME: &str = "bing-wallpaper.log";
+// 单个日志文件的大小上限，超过后旧文件依次改名为 .1.log、.2.log，最多保留 MAX_ROTATED_FILES 份
+const MAX_LOG_FILE_BYTES: u64 = 1024 * 1024;
+const MAX_ROTATED_FILES: usize = 3;
+pub(crate) const DEFAULT_LOG_LEVEL: &str = "info";
+pub(crate) const LOG_LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+static LOGGER: FileLogger = FileLogger { file: Mutex::new(None) };
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(LogFile { path, file, size })
+    }
+}
+
+struct FileLogger {
+    file: Mutex<Option<LogFile>>,
+}
+
+impl log::Log for FileLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{} {} - {}\n",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+            record.level(),
+            record.args()
+        );
+        eprint!("{}", line);
+
+        // 这里不能再调用日志宏，出错时只输出到 stderr
+        let mut file = self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(path) = file.as_ref().filter(|log_file| log_file.size >= MAX_LOG_FILE_BYTES).map(|log_file| log_file.path.clone()) {
+            // 先关闭当前文件，Windows 上无法重命名仍在打开的文件
+            *file = None;
+            if let Err(e) = rotate(&path) {
+                eprintln!("Failed to rotate log file: {}", e);
+            }
+            *file = LogFile::open(path).map_err(|e| eprintln!("Failed to reopen log file: {}", e)).ok();
+        }
+        if let Some(log_file) = file.as_mut() {
+            if log_file.file.write_all(line.as_bytes()).is_ok() {
+                log_file.size += line.len() as u64;
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(log_file) = self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).as_mut() {
+            let _ = log_file.file.flush();
+        }
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    path.with_extension(format!("{}.log", index))
+}
+
+fn rotate(path: &Path) -> std::io::Result<()> {
+    let oldest = rotated_path(path, MAX_ROTATED_FILES);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for index in (1..MAX_ROTATED_FILES).rev() {
+        let from = rotated_path(path, index);
+        if from.exists() {
+            fs::rename(&from, rotated_path(path, index + 1))?;
+        }
+    }
+    fs::rename(path, rotated_path(path, 1))
+}
+
+pub(crate) fn log_file_path() -> Result<PathBuf> {
+    Ok(get_app_data_dir()?.join(LOG_FILE_NAME))
+}
+
+// 按配置设置日志级别，无法识别时使用默认的 info
+pub(crate) fn set_level(level: &str) {
+    log::set_max_level(level.parse().unwrap_or(LevelFilter::Info));
+}
+
+// 安装日志：先输出到 stderr，确定数据目录后再打开日志文件
+pub(crate) fn init() {
+    log::set_logger(&LOGGER).unwrap();
+    set_level(&load_config().map(|config| config.log_level).unwrap_or_default());
+
+    match log_file_path().and_then(|path| Ok(LogFile::open(path)?)) {
+        Ok(log_file) => *LOGGER.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(log_file),
+        Err(e) => warn!("File logging is disabled: {}", e),
+    }
+}
+
+// 用系统默认程序打开当前日志文件
+pub(crate) fn open_log_file() -> Result<()> {
+    log::logger().flush();
+    open::that(log_file_path()?)?;
+    Ok(())
+}
+
+// 当前日志文件的最后 count 行，日志文件不存在时返回空列表
+pub(crate) fn recent_lines(count: usize) -> Result<Vec<String>> {
+    log::logger().flush();
+    let bytes = match fs::read(log_file_path()?) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let content = String::from_utf8_lossy(&bytes);
+    let lines: Vec<&str> = content.lines().collect();
+    Ok(lines[lines.len().saturating_sub(count)..].iter().map(|line| line.to_string()).collect())
+}
@@ -0,0 +1,145 @@
+// 下载后把标题、版权、日期和来源地址写进 JPEG 的 XMP 和 EXIF，照片管理软件（Lightroom、照片、
+// digiKam 等）可以直接索引。只插入元数据段，不重新编码图片；文件内容的 SHA-256 校验在写入前完成
+use std::{fs, path::Path};
+use img_parts::{
+    jpeg::{markers, Jpeg, JpegSegment},
+    Bytes, ImageEXIF,
+};
+use log::debug;
+
+use crate::{atomic_file, Result, WallpaperInfo};
+
+const XMP_PREFIX: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+// EXIF 中的标签，取值为 ASCII 类型
+const TAG_IMAGE_DESCRIPTION: u16 = 0x010e;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_COPYRIGHT: u16 = 0x8298;
+const TYPE_ASCII: u16 = 2;
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// 日期可能是 20240101 或 2024-01-01，统一拆成年月日
+fn date_parts(date: &str) -> Option<(String, String, String)> {
+    let digits: String = date.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.len() != 8 {
+        return None;
+    }
+    Some((digits[..4].to_string(), digits[4..6].to_string(), digits[6..].to_string()))
+}
+
+fn lang_alt(tag: &str, text: &str) -> String {
+    format!(
+        "<{0}><rdf:Alt><rdf:li xml:lang=\"x-default\">{1}</rdf:li></rdf:Alt></{0}>",
+        tag,
+        escape_xml(text)
+    )
+}
+
+fn xmp_packet(info: &WallpaperInfo) -> String {
+    let mut fields = Vec::new();
+    if !info.title.is_empty() {
+        fields.push(lang_alt("dc:title", &info.title));
+    }
+    if !info.copyright.is_empty() {
+        fields.push(lang_alt("dc:description", &info.copyright));
+        fields.push(lang_alt("dc:rights", &info.copyright));
+    }
+    if let Some((year, month, day)) = date_parts(&info.date) {
+        fields.push(format!("<photoshop:DateCreated>{}-{}-{}</photoshop:DateCreated>", year, month, day));
+    }
+    if !info.url.is_empty() {
+        let url = escape_xml(&info.url);
+        fields.push(format!("<dc:source>{}</dc:source>", url));
+        fields.push(format!("<xmpRights:WebStatement>{}</xmpRights:WebStatement>", url));
+    }
+    format!(
+        concat!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>",
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">",
+            "<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">",
+            "<rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\"",
+            " xmlns:photoshop=\"http://ns.adobe.com/photoshop/1.0/\"",
+            " xmlns:xmpRights=\"http://ns.adobe.com/xap/1.0/rights/\">{}</rdf:Description>",
+            "</rdf:RDF></x:xmpmeta><?xpacket end=\"w\"?>"
+        ),
+        fields.concat()
+    )
+}
+
+// 只含 IFD0 的小端 TIFF 结构；ASCII 字段按 UTF-8 写入，主流软件都能正确读出中文
+fn exif_block(info: &WallpaperInfo) -> Vec<u8> {
+    let mut entries: Vec<(u16, Vec<u8>)> = Vec::new();
+    if !info.title.is_empty() {
+        entries.push((TAG_IMAGE_DESCRIPTION, info.title.as_bytes().to_vec()));
+    }
+    if let Some((year, month, day)) = date_parts(&info.date) {
+        entries.push((TAG_DATE_TIME, format!("{}:{}:{} 00:00:00", year, month, day).into_bytes()));
+    }
+    if !info.copyright.is_empty() {
+        entries.push((TAG_COPYRIGHT, info.copyright.as_bytes().to_vec()));
+    }
+    for (_, value) in entries.iter_mut() {
+        value.push(0);
+    }
+
+    // 头部 8 字节，IFD 为条目数 2 字节 + 每条 12 字节 + 下一个 IFD 的偏移 4 字节，超过 4 字节的值放在 IFD 之后
+    let mut data_offset = 8 + 2 + entries.len() * 12 + 4;
+    let mut ifd = Vec::new();
+    let mut values = Vec::new();
+    for (tag, value) in &entries {
+        ifd.extend_from_slice(&tag.to_le_bytes());
+        ifd.extend_from_slice(&TYPE_ASCII.to_le_bytes());
+        ifd.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        if value.len() <= 4 {
+            let mut inline = value.clone();
+            inline.resize(4, 0);
+            ifd.extend_from_slice(&inline);
+        } else {
+            ifd.extend_from_slice(&(data_offset as u32).to_le_bytes());
+            values.extend_from_slice(value);
+            data_offset += value.len();
+        }
+    }
+
+    let mut block = b"II*\0".to_vec();
+    block.extend_from_slice(&8u32.to_le_bytes());
+    block.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    block.extend_from_slice(&ifd);
+    block.extend_from_slice(&0u32.to_le_bytes());
+    block.extend_from_slice(&values);
+    block
+}
+
+fn is_xmp(segment: &JpegSegment) -> bool {
+    segment.marker() == markers::APP1 && segment.contents().starts_with(XMP_PREFIX)
+}
+
+// 图片自带的 EXIF（例如 Unsplash 照片的相机参数）保留不动，只替换 XMP；不是 JPEG 的图片跳过
+pub(crate) fn embed(path: &Path, info: &WallpaperInfo) -> Result<()> {
+    let mut jpeg = match Jpeg::from_bytes(Bytes::from(fs::read(path)?)) {
+        Ok(jpeg) => jpeg,
+        Err(e) => {
+            debug!("Skipped writing metadata into {:?}: {}", path, e);
+            return Ok(());
+        }
+    };
+    if jpeg.exif().is_none() {
+        jpeg.set_exif(Some(Bytes::from(exif_block(info))));
+    }
+
+    let mut xmp = XMP_PREFIX.to_vec();
+    xmp.extend_from_slice(xmp_packet(info).as_bytes());
+    let segments = jpeg.segments_mut();
+    segments.retain(|segment| !is_xmp(segment));
+    // 放在开头的 APP 段（JFIF、EXIF）之后
+    let index = segments.iter().take_while(|segment| (markers::APP0..=markers::APP15).contains(&segment.marker())).count();
+    segments.insert(index, JpegSegment::new_with_contents(markers::APP1, Bytes::from(xmp)));
+
+    // 先写临时文件再替换，写入中断时原图不受影响
+    atomic_file::write_with(path, |writer| {
+        jpeg.encoder().write_to(writer)?;
+        Ok(())
+    })
+}
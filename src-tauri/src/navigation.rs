@@ -0,0 +1,103 @@
+// 上一张 / 下一张：设置过的壁纸按顺序记录在元信息数据库中，在其中前后移动时直接使用本地文件，不重新下载。
+// 已经位于最新一张时，“下一张”按本地壁纸轮播的设置切换到下一张缓存壁纸
+use std::{path::PathBuf, sync::Mutex};
+use log::{info, warn};
+use tauri::Manager;
+
+use crate::{apply_cached_wallpaper, i18n::tr, lock_state, slideshow, store, AppError, AppState, Result};
+
+// 本地最多保留的记录数
+const MAX_APPLIED_ENTRIES: usize = 100;
+
+#[derive(Default)]
+pub(crate) struct AppliedHistory {
+    // 最早的在前
+    entries: Vec<PathBuf>,
+    // 切换回较早的壁纸后指向它，位于最新一张时为 None
+    cursor: Option<usize>,
+}
+
+impl AppliedHistory {
+    pub(crate) fn load() -> Self {
+        match store::load_applied(MAX_APPLIED_ENTRIES) {
+            Ok(entries) => AppliedHistory { entries, cursor: None },
+            Err(e) => {
+                warn!("Failed to load applied wallpapers: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    // 记录新设置的壁纸并回到最新位置；由上一张、下一张切换时不重复记录
+    pub(crate) fn record(&mut self, path: PathBuf) {
+        if self.cursor.is_some_and(|cursor| self.entries.get(cursor) == Some(&path)) {
+            return;
+        }
+        self.cursor = None;
+        if self.entries.last() == Some(&path) {
+            return;
+        }
+        if let Err(e) = store::record_applied(&path, MAX_APPLIED_ENTRIES) {
+            warn!("Failed to record applied wallpaper: {}", e);
+        }
+        self.entries.push(path);
+        let excess = self.entries.len().saturating_sub(MAX_APPLIED_ENTRIES);
+        self.entries.drain(..excess);
+    }
+
+    fn position(&self) -> usize {
+        self.cursor.unwrap_or(self.entries.len().saturating_sub(1))
+    }
+
+    pub(crate) fn has_previous(&self) -> bool {
+        self.position() > 0
+    }
+
+    // 前后第一张仍存在的图片，已被缓存清理删除的跳过
+    fn find(&self, forward: bool) -> Option<usize> {
+        let exists = |index: &usize| self.entries[*index].is_file();
+        if forward {
+            self.cursor?;
+            (self.position() + 1..self.entries.len()).find(exists)
+        } else {
+            (0..self.position()).rev().find(exists)
+        }
+    }
+}
+
+// 在记录中前后移动一张，返回 false 表示该方向没有可用的图片
+fn step(app: &tauri::AppHandle, forward: bool) -> Result<bool> {
+    let state = app.state::<Mutex<AppState>>();
+    let (path, old_cursor) = {
+        let mut state = lock_state(&state);
+        let applied = &mut state.applied;
+        let Some(index) = applied.find(forward) else {
+            return Ok(false);
+        };
+        let old_cursor = applied.cursor;
+        applied.cursor = (index + 1 < applied.entries.len()).then_some(index);
+        (applied.entries[index].clone(), old_cursor)
+    };
+    if let Err(e) = apply_cached_wallpaper(app, &path) {
+        lock_state(&state).applied.cursor = old_cursor;
+        return Err(e);
+    }
+    info!("Stepped {} to {:?}", if forward { "forward" } else { "back" }, path);
+    Ok(true)
+}
+
+pub(crate) fn previous(app: &tauri::AppHandle) -> Result<()> {
+    if step(app, false)? {
+        Ok(())
+    } else {
+        Err(AppError::Config(tr("没有可以返回的上一张壁纸").to_string()))
+    }
+}
+
+pub(crate) fn next(app: &tauri::AppHandle) -> Result<()> {
+    if step(app, true)? {
+        Ok(())
+    } else {
+        slideshow::next(app)
+    }
+}
@@ -0,0 +1,71 @@
+// 全新安装后首次启动时的引导窗口：选择壁纸区域、是否开机自启动、是否接收通知。
+// 完成后保存选择并立即设置今天的壁纸；直接关闭窗口则保持未开启刷新，之后可在托盘菜单中开启
+use std::sync::Mutex;
+use log::info;
+use serde::Deserialize;
+use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
+use tauri_plugin_autostart::ManagerExt;
+use tauri_plugin_notification::NotificationExt;
+
+use crate::{
+    i18n::{tr, tr_with},
+    lock_state, set_refresh_mode, update_config, update_menu, AppError, AppState, RefreshMode, Result,
+};
+
+const ONBOARDING_WINDOW_LABEL: &str = "onboarding";
+
+#[derive(Deserialize)]
+pub(crate) struct Choices {
+    // china、global 或 auto
+    region: String,
+    autostart: bool,
+    notifications: bool,
+}
+
+pub(crate) fn show_window(app: &tauri::AppHandle) -> Result<()> {
+    if let Some(window) = app.get_webview_window(ONBOARDING_WINDOW_LABEL) {
+        window.set_focus()?;
+        return Ok(());
+    }
+    WebviewWindowBuilder::new(app, ONBOARDING_WINDOW_LABEL, WebviewUrl::App("onboarding.html".into()))
+        .title(tr("欢迎使用 Bing Wallpaper"))
+        .inner_size(420.0, 460.0)
+        .resizable(false)
+        .build()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn finish_onboarding(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    choices: Choices,
+) -> Result<()> {
+    let mode = RefreshMode::from_str(&choices.region);
+    if !mode.is_daily() || mode.as_str() != choices.region {
+        return Err(AppError::Config(tr_with("不支持的壁纸区域: {}", &choices.region)));
+    }
+
+    let autolaunch = app.autolaunch();
+    let result = if choices.autostart { autolaunch.enable() } else { autolaunch.disable() };
+    result.map_err(|e| AppError::OsSet(e.to_string()))?;
+
+    // 同意接收通知时向系统申请权限（macOS 会弹出授权提示），拒绝不影响其它功能
+    if choices.notifications {
+        if let Err(e) = app.notification().request_permission() {
+            info!("Notification permission request failed: {}", e);
+        }
+    }
+    update_config(|config| config.notifications_disabled = !choices.notifications)?;
+
+    let mut state = lock_state(&state);
+    state.notifications_enabled = choices.notifications;
+    // 切换模式会保存配置并在后台立即下载今天的壁纸
+    set_refresh_mode(&app, &mut state, mode)?;
+    update_menu(&app, &state)?;
+    info!(
+        "Onboarding finished: region = {}, autostart = {}, notifications = {}",
+        choices.region, choices.autostart, choices.notifications
+    );
+    Ok(())
+}
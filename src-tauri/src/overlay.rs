@@ -0,0 +1,103 @@
+// 桌面信息卡片：一个置于所有窗口之下、鼠标可以穿透的半透明小窗口，显示当前壁纸的标题、说明和拍摄地点。
+// 位置（主显示器的四个角）和不透明度在设置中调整；窗口收到 wallpaper-changed 事件后重新读取内容
+use std::sync::Mutex;
+use log::info;
+use serde::Serialize;
+use tauri::{LogicalPosition, Manager, WebviewUrl, WebviewWindowBuilder};
+
+use crate::{
+    load_config, lock_state,
+    watermark::{CORNER_BOTTOM_LEFT, CORNER_TOP_LEFT, CORNER_TOP_RIGHT},
+    AppState, Result,
+};
+
+const OVERLAY_WINDOW_LABEL: &str = "overlay";
+const WIDTH: f64 = 420.0;
+const HEIGHT: f64 = 160.0;
+// 与屏幕边缘的距离；底部留出任务栏和程序坞的高度
+const MARGIN: f64 = 24.0;
+const BOTTOM_MARGIN: f64 = 72.0;
+pub(crate) const DEFAULT_OPACITY: u32 = 70;
+pub(crate) const MIN_OPACITY: u32 = 20;
+
+#[derive(Serialize)]
+pub(crate) struct OverlayInfo {
+    title: String,
+    description: String,
+    location: Option<String>,
+    copyright: String,
+    // 背景不透明度百分比
+    opacity: u32,
+}
+
+fn opacity(configured: u32) -> u32 {
+    if configured == 0 {
+        DEFAULT_OPACITY
+    } else {
+        configured.clamp(MIN_OPACITY, 100)
+    }
+}
+
+// 没有壁纸时返回 None，窗口显示为空
+#[tauri::command]
+pub(crate) fn get_overlay_info(state: tauri::State<'_, Mutex<AppState>>) -> Result<Option<OverlayInfo>> {
+    let opacity = opacity(load_config()?.overlay_opacity);
+    Ok(lock_state(&state).current_wallpaper.as_ref().map(|wallpaper| OverlayInfo {
+        title: wallpaper.display_title(),
+        description: wallpaper.description.clone(),
+        location: wallpaper.location(),
+        copyright: wallpaper.copyright.clone(),
+        opacity,
+    }))
+}
+
+// 主显示器上对应角落的位置（逻辑像素）
+fn position(app: &tauri::AppHandle, corner: &str) -> Result<LogicalPosition<f64>> {
+    let Some(monitor) = app.primary_monitor()? else {
+        return Ok(LogicalPosition::new(MARGIN, MARGIN));
+    };
+    let scale = monitor.scale_factor();
+    let origin = monitor.position().to_logical::<f64>(scale);
+    let size = monitor.size().to_logical::<f64>(scale);
+    let left = corner == CORNER_TOP_LEFT || corner == CORNER_BOTTOM_LEFT;
+    let top = corner == CORNER_TOP_LEFT || corner == CORNER_TOP_RIGHT;
+    let x = if left { origin.x + MARGIN } else { origin.x + size.width - WIDTH - MARGIN };
+    let y = if top { origin.y + MARGIN } else { origin.y + size.height - HEIGHT - BOTTOM_MARGIN };
+    Ok(LogicalPosition::new(x, y))
+}
+
+// 按配置显示、移动或关闭卡片；启动时和保存设置后调用
+pub(crate) fn refresh(app: &tauri::AppHandle) -> Result<()> {
+    let config = load_config()?;
+    let existing = app.get_webview_window(OVERLAY_WINDOW_LABEL);
+    if !config.overlay_enabled {
+        if let Some(window) = existing {
+            window.close()?;
+            info!("Desktop overlay closed");
+        }
+        return Ok(());
+    }
+    let position = position(app, &config.overlay_corner)?;
+    if let Some(window) = existing {
+        window.set_position(position)?;
+        // 让页面按新的不透明度重新绘制
+        window.eval("window.location.reload()")?;
+        return Ok(());
+    }
+    let window = WebviewWindowBuilder::new(app, OVERLAY_WINDOW_LABEL, WebviewUrl::App("overlay.html".into()))
+        .title("Bing Wallpaper")
+        .inner_size(WIDTH, HEIGHT)
+        .position(position.x, position.y)
+        .decorations(false)
+        .transparent(true)
+        .shadow(false)
+        .resizable(false)
+        .focused(false)
+        .skip_taskbar(true)
+        .always_on_bottom(true)
+        .visible_on_all_workspaces(true)
+        .build()?;
+    window.set_ignore_cursor_events(true)?;
+    info!("Desktop overlay shown at {:?}", position);
+    Ok(())
+}
@@ -0,0 +1,83 @@
+// 提取壁纸的主色和强调色，供窗口同步界面配色、供壁纸更换后的命令同步终端或编辑器主题。
+// 先缩小图片，再把颜色按每通道 16 级归类计数：占比最大的一类是主色，
+// 占比不太小的类中饱和度最高的是强调色
+use std::{collections::HashMap, path::Path};
+use image::imageops::FilterType;
+use serde::Serialize;
+
+use crate::{processing, Result};
+
+// 缩小后的宽度，足够统计颜色分布，计算量与原图大小无关
+const SAMPLE_WIDTH: u32 = 64;
+const PALETTE_SIZE: usize = 5;
+// 作为强调色候选的最小占比，避免选中零星的噪点
+const MIN_ACCENT_SHARE: f32 = 0.01;
+
+#[derive(Clone, Serialize)]
+pub(crate) struct Palette {
+    pub(crate) file_name: String,
+    // 颜色均为 #rrggbb
+    pub(crate) dominant: String,
+    pub(crate) accent: String,
+    // 按占比从大到小
+    pub(crate) colors: Vec<String>,
+}
+
+#[derive(Default)]
+struct Bucket {
+    count: u32,
+    sum: [u32; 3],
+}
+
+impl Bucket {
+    fn average(&self) -> [u8; 3] {
+        self.sum.map(|sum| (sum / self.count.max(1)) as u8)
+    }
+}
+
+fn hex(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+// HSL 中的饱和度，0 到 1
+fn saturation([r, g, b]: [u8; 3]) -> f32 {
+    let max = r.max(g).max(b) as f32 / 255.0;
+    let min = r.min(g).min(b) as f32 / 255.0;
+    let lightness = (max + min) / 2.0;
+    if max == min {
+        return 0.0;
+    }
+    (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+}
+
+pub(crate) fn extract(path: &Path, file_name: &str) -> Result<Palette> {
+    let image = processing::decode(path)?;
+    let height = (image.height() as u64 * SAMPLE_WIDTH as u64 / image.width().max(1) as u64).max(1) as u32;
+    let sample = image::imageops::resize(&image, SAMPLE_WIDTH, height, FilterType::Triangle);
+
+    let mut buckets: HashMap<[u8; 3], Bucket> = HashMap::new();
+    for pixel in sample.pixels() {
+        let bucket = buckets.entry(pixel.0.map(|channel| channel >> 4)).or_default();
+        bucket.count += 1;
+        for (sum, channel) in bucket.sum.iter_mut().zip(pixel.0) {
+            *sum += channel as u32;
+        }
+    }
+    let mut buckets: Vec<Bucket> = buckets.into_values().collect();
+    buckets.sort_by(|a, b| b.count.cmp(&a.count));
+
+    let total = (sample.width() * sample.height()).max(1) as f32;
+    let dominant = buckets.first().map(Bucket::average).unwrap_or_default();
+    let accent = buckets
+        .iter()
+        .filter(|bucket| bucket.count as f32 / total >= MIN_ACCENT_SHARE)
+        .map(Bucket::average)
+        .max_by(|a, b| saturation(*a).total_cmp(&saturation(*b)))
+        .unwrap_or(dominant);
+    Ok(Palette {
+        file_name: file_name.to_string(),
+        dominant: hex(dominant),
+        accent: hex(accent),
+        colors: buckets.iter().take(PALETTE_SIZE).map(|bucket| hex(bucket.average())).collect(),
+    })
+}
@@ -0,0 +1,43 @@
+// 便携模式：可执行文件旁有 portable.txt，或以 --portable 启动时，配置、设备标识、日志、数据库和缓存的图片
+// 都保存在可执行文件旁的 data 文件夹中，不写入 APPDATA / HOME，便于从 U 盘运行或在没有安装权限的电脑上使用
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+pub(crate) const PORTABLE_ARG: &str = "--portable";
+const MARKER_FILE_NAME: &str = "portable.txt";
+const DATA_DIR_NAME: &str = "data";
+
+// 进程内只判断一次，运行中途创建或删除 portable.txt 不会切换数据目录
+static DATA_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+// 可执行文件所在的文件夹。AppImage 运行时可执行文件位于只读的挂载目录中，以 AppImage 文件所在的文件夹为准；
+// macOS 上可执行文件位于 .app/Contents/MacOS 中，以 .app 所在的文件夹为准
+fn base_dir() -> Option<PathBuf> {
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        return Path::new(&appimage).parent().map(Path::to_path_buf);
+    }
+    let exe = std::env::current_exe().ok()?;
+    let dir = exe.parent()?;
+    #[cfg(target_os = "macos")]
+    if let Some(bundle) = dir.ancestors().find(|dir| dir.extension().is_some_and(|ext| ext == "app")) {
+        return bundle.parent().map(Path::to_path_buf);
+    }
+    Some(dir.to_path_buf())
+}
+
+fn detect() -> Option<PathBuf> {
+    let base = base_dir()?;
+    let enabled = base.join(MARKER_FILE_NAME).is_file() || std::env::args().any(|arg| arg == PORTABLE_ARG);
+    enabled.then(|| base.join(DATA_DIR_NAME))
+}
+
+// 便携模式下的数据目录，未开启时为 None
+pub(crate) fn data_dir() -> Option<&'static Path> {
+    DATA_DIR.get_or_init(detect).as_deref()
+}
+
+pub(crate) fn is_enabled() -> bool {
+    data_dir().is_some()
+}
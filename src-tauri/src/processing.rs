@@ -0,0 +1,130 @@
+// 下载后、设置前对壁纸做可选的处理：高斯模糊、调暗和饱和度调整，让桌面图标在复杂的图片上也清晰可读；
+// 开启水印时还会在角落绘制标题和版权信息。
+// 处理结果按参数命名保存在应用数据目录的子目录中（不放进用户的图片库），参数不变时直接复用
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use image::{codecs::jpeg::JpegEncoder, RgbImage};
+use log::{info, warn};
+
+use crate::{atomic_file, cache, get_app_data_dir, load_config, watermark::Watermark, AppError, Result};
+
+const PROCESSED_DIR: &str = "processed";
+const JPEG_QUALITY: u8 = 90;
+// 多显示器时每个显示器可能各用一张，保留的处理结果需要覆盖常见的显示器数量
+pub(crate) const MAX_PROCESSED_FILES: usize = 4;
+pub(crate) const MAX_BLUR: u32 = 50;
+pub(crate) const MAX_DIM: u32 = 90;
+pub(crate) const MAX_SATURATION: i32 = 100;
+
+// 模糊为高斯模糊的 sigma，调暗为降低的亮度百分比，饱和度为增减的百分比，全为 0 且没有水印表示不处理
+struct Params {
+    blur: u32,
+    dim: u32,
+    saturation: i32,
+    watermark: Option<(Watermark, Vec<String>)>,
+}
+
+impl Params {
+    // 水印文字取自图片对应的壁纸信息，找不到信息的图片不加水印
+    fn load(path: &Path) -> Self {
+        let config = load_config().unwrap_or_default();
+        Params {
+            blur: config.process_blur.min(MAX_BLUR),
+            dim: config.process_dim.min(MAX_DIM),
+            saturation: config.process_saturation.clamp(-MAX_SATURATION, MAX_SATURATION),
+            watermark: Watermark::load().and_then(|watermark| Some((watermark, crate::watermark::caption_for(path)?))),
+        }
+    }
+
+    fn is_identity(&self) -> bool {
+        self.blur == 0 && self.dim == 0 && self.saturation == 0 && self.watermark.is_none()
+    }
+
+    fn file_name(&self, stem: &str) -> String {
+        match &self.watermark {
+            Some((watermark, _)) => {
+                format!("{}_b{}_d{}_s{}_{}.jpg", stem, self.blur, self.dim, self.saturation, watermark.key())
+            }
+            None => format!("{}_b{}_d{}_s{}.jpg", stem, self.blur, self.dim, self.saturation),
+        }
+    }
+}
+
+pub(crate) fn decode(path: &Path) -> Result<RgbImage> {
+    Ok(image::open(path).map_err(|e| AppError::Parse(format!("Failed to decode {:?}: {}", path, e)))?.to_rgb8())
+}
+
+pub(crate) fn save_jpeg(image: &RgbImage, dest: &Path) -> Result<()> {
+    let dir = dest.parent().ok_or_else(|| AppError::Config(format!("Invalid path: {:?}", dest)))?;
+    fs::create_dir_all(dir)?;
+    atomic_file::write_with(dest, |writer| {
+        JpegEncoder::new_with_quality(writer, JPEG_QUALITY)
+            .encode_image(image)
+            .map_err(|e| AppError::Parse(format!("Failed to encode {:?}: {}", dest, e)))
+    })
+}
+
+// 按系数缩放亮度，factor 小于 1 时变暗
+pub(crate) fn scale_brightness(image: &mut RgbImage, factor: f32) {
+    for pixel in image.pixels_mut() {
+        for channel in pixel.0.iter_mut() {
+            *channel = (*channel as f32 * factor).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+// 以亮度为基准拉伸或收缩各通道与亮度的差值，factor 为 0 时是灰度图
+fn scale_saturation(image: &mut RgbImage, factor: f32) {
+    for pixel in image.pixels_mut() {
+        let [r, g, b] = pixel.0.map(f32::from);
+        let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+        for channel in pixel.0.iter_mut() {
+            *channel = (luma + (*channel as f32 - luma) * factor).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+fn process(path: &Path, dest: &Path, params: &Params) -> Result<()> {
+    let mut image = decode(path)?;
+    if params.blur > 0 {
+        image = image::imageops::blur(&image, params.blur as f32);
+    }
+    if params.saturation != 0 {
+        scale_saturation(&mut image, 1.0 + params.saturation as f32 / 100.0);
+    }
+    if params.dim > 0 {
+        scale_brightness(&mut image, 1.0 - params.dim as f32 / 100.0);
+    }
+    // 水印最后绘制，不受模糊和调暗影响
+    if let Some((watermark, lines)) = &params.watermark {
+        if let Err(e) = watermark.render(&mut image, lines) {
+            warn!("Failed to render watermark: {}", e);
+        }
+    }
+    save_jpeg(&image, dest)?;
+    info!("Processed {:?} into {:?}", path, dest);
+    let dir = dest.parent().ok_or_else(|| AppError::Config(format!("Invalid path: {:?}", dest)))?;
+    cache::keep_newest(dir, MAX_PROCESSED_FILES)
+}
+
+// 返回处理后的图片路径；未开启处理或处理失败时返回原图
+pub(crate) fn processed_path(path: &Path) -> PathBuf {
+    let params = Params::load(path);
+    if params.is_identity() {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = params.file_name(&stem);
+    let result = get_app_data_dir().map(|dir| dir.join(PROCESSED_DIR).join(name)).and_then(|dest| {
+        if !dest.is_file() {
+            process(path, &dest, &params)?;
+        }
+        Ok(dest)
+    });
+    result.unwrap_or_else(|e| {
+        warn!("Failed to process wallpaper, using original: {}", e);
+        path.to_path_buf()
+    })
+}
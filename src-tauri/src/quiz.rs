@@ -0,0 +1,88 @@
+// 猜地点小游戏：从元信息数据库中最近已下载的壁纸里随机选一张，隐藏标题，只显示图片和几个候选地点，
+// 选择后再揭晓答案。地点取自版权信息中括号前的部分，干扰项是其他壁纸的地点
+use std::collections::HashSet;
+use log::info;
+use serde::Serialize;
+use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
+
+use crate::{cache, i18n::{tr, tr_with}, random_u64, store, AppError, Result};
+
+const QUIZ_WINDOW_LABEL: &str = "quiz";
+// 出题范围和每题的选项数
+const CANDIDATE_LIMIT: usize = 200;
+const CHOICE_COUNT: usize = 4;
+
+#[derive(Serialize)]
+pub(crate) struct QuizQuestion {
+    // 图片通过 get_thumbnail 按文件名读取，答题时原样传回
+    file_name: String,
+    date: String,
+    choices: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct QuizAnswer {
+    correct: bool,
+    location: String,
+    title: String,
+    copyright: String,
+}
+
+fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        let j = (random_u64() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
+fn new_question() -> Result<QuizQuestion> {
+    let cached: HashSet<String> = cache::cached_paths()?
+        .iter()
+        .filter_map(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()))
+        .collect();
+    // 同一地点只保留最近的一张，选项不会重复
+    let mut seen = HashSet::new();
+    let mut candidates: Vec<_> = store::recent_downloaded(CANDIDATE_LIMIT)?
+        .into_iter()
+        .filter(|wallpaper| cached.contains(&wallpaper.file_name))
+        .filter_map(|wallpaper| wallpaper.location().map(|location| (wallpaper, location)))
+        .filter(|(_, location)| seen.insert(location.to_lowercase()))
+        .collect();
+    if candidates.len() < CHOICE_COUNT {
+        return Err(AppError::Config(tr_with("至少需要 {} 张不同地点的已下载壁纸才能出题", CHOICE_COUNT)));
+    }
+    shuffle(&mut candidates);
+    let (answer, location) = candidates.swap_remove(0);
+    let mut choices: Vec<String> =
+        candidates.into_iter().take(CHOICE_COUNT - 1).map(|(_, location)| location).collect();
+    choices.push(location);
+    shuffle(&mut choices);
+    Ok(QuizQuestion { file_name: answer.file_name, date: answer.date, choices })
+}
+
+#[tauri::command]
+pub(crate) async fn get_quiz_question() -> Result<QuizQuestion> {
+    tauri::async_runtime::spawn_blocking(new_question).await.map_err(|e| AppError::Config(e.to_string()))?
+}
+
+// 揭晓答案：按文件名重新查出壁纸，比较选中的地点
+#[tauri::command]
+pub(crate) fn answer_quiz(file: String, choice: String) -> Result<QuizAnswer> {
+    let wallpaper = store::find_by_file(&file)?.ok_or_else(|| AppError::Config(tr_with("没有找到该壁纸: {}", &file)))?;
+    let location = wallpaper.location().unwrap_or_default();
+    let correct = location.eq_ignore_ascii_case(choice.trim());
+    info!("Quiz answer for {}: {}", file, if correct { "correct" } else { "wrong" });
+    Ok(QuizAnswer { correct, location, title: wallpaper.display_title(), copyright: wallpaper.copyright })
+}
+
+pub(crate) fn show_window(app: &tauri::AppHandle) -> Result<()> {
+    if let Some(window) = app.get_webview_window(QUIZ_WINDOW_LABEL) {
+        window.set_focus()?;
+        return Ok(());
+    }
+    WebviewWindowBuilder::new(app, QUIZ_WINDOW_LABEL, WebviewUrl::App("quiz.html".into()))
+        .title(tr("猜猜这是哪里"))
+        .inner_size(560.0, 620.0)
+        .build()?;
+    Ok(())
+}
@@ -0,0 +1,298 @@
+// 按星期的规则不刷新的日子，以及按流量计费的网络、电池电量低和全屏程序运行时推迟定时刷新，条件恢复后由定时任务自动补上这次刷新。
+// 手动点击“立即刷新”不受影响。全屏期间的系统通知也先保存下来，退出全屏后再依次显示
+use std::{sync::Mutex, thread, time::Duration};
+use log::info;
+#[cfg(not(target_os = "windows"))]
+use std::process::Command;
+use tauri::Manager;
+use wallpaper_core::CancellationToken;
+
+use crate::{
+    i18n::{tr, tr_with},
+    load_config, lock_state, notify, schedule_menu_update,
+    scheduler::{self, DAY_SKIP},
+    AppState,
+};
+
+// 推迟期间重新检查条件的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+enum Deferral {
+    SkippedDay,
+    Metered,
+    LowBattery(u8),
+    Fullscreen,
+}
+
+impl Deferral {
+    fn describe(&self) -> String {
+        match self {
+            Deferral::SkippedDay => tr("按星期规则今天不刷新").to_string(),
+            Deferral::Metered => tr("按流量计费的网络").to_string(),
+            Deferral::LowBattery(percent) => tr_with("电池电量 {}%", percent),
+            Deferral::Fullscreen => tr("全屏程序运行中").to_string(),
+        }
+    }
+}
+
+// 通过 WinRT 的 NetworkInformation 读取当前连接的计费类型，漫游或超出流量上限也视为计费
+#[cfg(target_os = "windows")]
+fn is_metered() -> bool {
+    use windows::Networking::Connectivity::{NetworkCostType, NetworkInformation};
+
+    let Ok(cost) = NetworkInformation::GetInternetConnectionProfile().and_then(|profile| profile.GetConnectionCost())
+    else {
+        return false;
+    };
+    matches!(cost.NetworkCostType(), Ok(NetworkCostType::Fixed | NetworkCostType::Variable))
+        || cost.Roaming().unwrap_or(false)
+        || cost.OverDataLimit().unwrap_or(false)
+}
+
+// 通过 Network.framework 的路径监视器（NWPathMonitor）读取当前网络是否昂贵（蜂窝网络、个人热点）或受限（低数据模式）。
+// 监视器在第一次检查时启动并常驻，回调中更新状态；第一次检查时最多等待片刻拿到初始状态
+#[cfg(target_os = "macos")]
+fn is_metered() -> bool {
+    use std::{
+        ffi::c_void,
+        sync::{
+            atomic::{AtomicU8, Ordering},
+            Once,
+        },
+    };
+    use block2::{Block, RcBlock};
+
+    #[link(name = "Network", kind = "framework")]
+    extern "C" {
+        fn nw_path_monitor_create() -> *mut c_void;
+        fn nw_path_monitor_set_queue(monitor: *mut c_void, queue: *mut c_void);
+        fn nw_path_monitor_set_update_handler(monitor: *mut c_void, handler: &Block<dyn Fn(*mut c_void)>);
+        fn nw_path_monitor_start(monitor: *mut c_void);
+        fn nw_path_is_expensive(path: *mut c_void) -> bool;
+        fn nw_path_is_constrained(path: *mut c_void) -> bool;
+    }
+    extern "C" {
+        fn dispatch_get_global_queue(identifier: isize, flags: usize) -> *mut c_void;
+    }
+
+    const UNKNOWN: u8 = 0;
+    const UNMETERED: u8 = 1;
+    const METERED: u8 = 2;
+    static STATE: AtomicU8 = AtomicU8::new(UNKNOWN);
+    static START: Once = Once::new();
+
+    START.call_once(|| {
+        let handler = RcBlock::new(|path: *mut c_void| {
+            let metered = unsafe { nw_path_is_expensive(path) || nw_path_is_constrained(path) };
+            STATE.store(if metered { METERED } else { UNMETERED }, Ordering::SeqCst);
+        });
+        // 监视器不释放，随程序退出；设置回调时系统会复制 block
+        unsafe {
+            let monitor = nw_path_monitor_create();
+            nw_path_monitor_set_queue(monitor, dispatch_get_global_queue(0, 0));
+            nw_path_monitor_set_update_handler(monitor, &handler);
+            nw_path_monitor_start(monitor);
+        }
+    });
+    for _ in 0..20 {
+        match STATE.load(Ordering::SeqCst) {
+            UNKNOWN => thread::sleep(Duration::from_millis(50)),
+            state => return state == METERED,
+        }
+    }
+    false
+}
+
+// 读取 NetworkManager 的 Metered 属性：1 为计费，3 为推测计费（例如手机热点）
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn is_metered() -> bool {
+    let output = Command::new("dbus-send")
+        .args([
+            "--system",
+            "--print-reply",
+            "--dest=org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.DBus.Properties.Get",
+            "string:org.freedesktop.NetworkManager",
+            "string:Metered",
+        ])
+        .output();
+    let Ok(output) = output else {
+        return false;
+    };
+    if !output.status.success() {
+        return false;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    matches!(stdout.split_whitespace().last(), Some("1" | "3"))
+}
+
+// 使用电池供电时返回剩余电量，接通电源或没有电池时返回 None
+#[cfg(target_os = "windows")]
+fn battery_percent() -> Option<u8> {
+    use winapi::um::winbase::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return None;
+    }
+    // ACLineStatus 为 0 表示未接电源，电量 255 表示未知
+    (status.ACLineStatus == 0 && status.BatteryLifePercent <= 100).then_some(status.BatteryLifePercent)
+}
+
+// pmset 的输出形如 "Now drawing from 'Battery Power'" 和 "-InternalBattery-0 (id=...)	45%; discharging; ..."
+#[cfg(target_os = "macos")]
+fn battery_percent() -> Option<u8> {
+    let output = Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !stdout.contains("'Battery Power'") {
+        return None;
+    }
+    stdout
+        .split(|c: char| c.is_whitespace() || c == ';')
+        .find_map(|field| field.strip_suffix('%')?.parse().ok())
+}
+
+// 读取 /sys/class/power_supply 下的电池，状态为 Discharging 时视为使用电池供电
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn battery_percent() -> Option<u8> {
+    let read = |path: std::path::PathBuf| std::fs::read_to_string(path).map(|value| value.trim().to_string()).ok();
+    std::fs::read_dir("/sys/class/power_supply").ok()?.flatten().find_map(|entry| {
+        let path = entry.path();
+        if read(path.join("type"))? != "Battery" || read(path.join("status"))? != "Discharging" {
+            return None;
+        }
+        read(path.join("capacity"))?.parse().ok()
+    })
+}
+
+// 与系统通知中心判断“请勿打扰”的依据相同：前台是全屏程序、Direct3D 独占全屏或演示模式
+#[cfg(target_os = "windows")]
+fn is_fullscreen() -> bool {
+    use winapi::um::shellapi::{
+        SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_RUNNING_D3D_FULL_SCREEN,
+    };
+
+    let mut state = 0;
+    if unsafe { SHQueryUserNotificationState(&mut state) } != 0 {
+        return false;
+    }
+    matches!(state, QUNS_BUSY | QUNS_RUNNING_D3D_FULL_SCREEN | QUNS_PRESENTATION_MODE)
+}
+
+// 用 JXA 读取屏幕上最前面的普通窗口（layer 0），它盖住整个主屏幕时视为全屏；读取窗口位置不需要额外的权限
+#[cfg(target_os = "macos")]
+fn is_fullscreen() -> bool {
+    const SCRIPT: &str = "ObjC.import('AppKit');
+ObjC.import('CoreGraphics');
+var screen = $.NSScreen.mainScreen.frame.size;
+var windows = ObjC.deepUnwrap(ObjC.castRefToObject($.CGWindowListCopyWindowInfo(
+    $.kCGWindowListOptionOnScreenOnly | $.kCGWindowListExcludeDesktopElements, $.kCGNullWindowID)));
+var front = (windows || []).find(function (w) { return w.kCGWindowLayer === 0; });
+!!front && front.kCGWindowBounds.Width >= screen.width && front.kCGWindowBounds.Height >= screen.height;";
+    let Ok(output) = Command::new("osascript").args(["-l", "JavaScript", "-e", SCRIPT]).output() else {
+        return false;
+    };
+    String::from_utf8_lossy(&output.stdout).trim() == "true"
+}
+
+// X11 下读取活动窗口的 _NET_WM_STATE；Wayland 不向普通程序公开其他窗口的状态，xprop 不可用时视为没有全屏
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn is_fullscreen() -> bool {
+    let xprop = |args: &[&str]| {
+        Command::new("xprop")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    // 输出形如 "_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007"
+    let Some(active) = xprop(&["-root", "_NET_ACTIVE_WINDOW"]) else {
+        return false;
+    };
+    let Some(id) = active.split_whitespace().last().filter(|id| id.starts_with("0x") && *id != "0x0") else {
+        return false;
+    };
+    xprop(&["-id", id, "_NET_WM_STATE"]).is_some_and(|state| state.contains("_NET_WM_STATE_FULLSCREEN"))
+}
+
+fn check() -> Option<Deferral> {
+    if scheduler::today_rule().as_deref() == Some(DAY_SKIP) {
+        return Some(Deferral::SkippedDay);
+    }
+    let config = load_config().unwrap_or_default();
+    if config.defer_on_metered && is_metered() {
+        return Some(Deferral::Metered);
+    }
+    if config.battery_min_percent > 0 {
+        if let Some(percent) = battery_percent().filter(|percent| *percent < config.battery_min_percent) {
+            return Some(Deferral::LowBattery(percent));
+        }
+    }
+    if config.defer_when_fullscreen && is_fullscreen() {
+        return Some(Deferral::Fullscreen);
+    }
+    None
+}
+
+fn set_deferred(app: &tauri::AppHandle, reason: Option<String>) {
+    lock_state(&app.state::<Mutex<AppState>>()).deferred = reason;
+    schedule_menu_update(app);
+}
+
+// 定时任务在每次刷新前调用：条件不满足时每分钟重新检查一次，推迟原因显示在状态菜单中；
+// 返回 false 表示等待期间任务被取消，推迟状态由取消任务的一方清除
+pub(crate) async fn wait_until_allowed(app: &tauri::AppHandle, token: &CancellationToken) -> bool {
+    let mut deferred = false;
+    loop {
+        // 检查条件需要启动外部进程或等待系统回调，放到阻塞线程中执行
+        let reason = tauri::async_runtime::spawn_blocking(check).await.unwrap_or(None);
+        let Some(reason) = reason else {
+            if deferred {
+                info!("Refresh conditions met again, running deferred refresh");
+                set_deferred(app, None);
+            }
+            return true;
+        };
+        if !deferred {
+            let reason = reason.describe();
+            info!("Deferring refresh: {}", reason);
+            set_deferred(app, Some(reason));
+            deferred = true;
+        }
+        tokio::select! {
+            _ = token.cancelled() => return false,
+            _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+        }
+    }
+}
+
+// 全屏期间保存下来的通知（标题、正文）
+static HELD_NOTIFICATIONS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+// 由 notify 在显示通知前调用：开启了全屏推迟且当前有全屏程序时保存通知并返回 true。
+// 第一条通知被保存时启动一个线程，每分钟检查一次，退出全屏后依次显示保存的通知
+pub(crate) fn hold_notification(app: &tauri::AppHandle, title: &str, body: &str) -> bool {
+    if !load_config().is_ok_and(|config| config.defer_when_fullscreen) || !is_fullscreen() {
+        return false;
+    }
+    let mut held = HELD_NOTIFICATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    held.push((title.to_string(), body.to_string()));
+    if held.len() > 1 {
+        return true;
+    }
+    info!("Holding notifications while a fullscreen app is running");
+    let app = app.clone();
+    thread::spawn(move || {
+        while load_config().is_ok_and(|config| config.defer_when_fullscreen) && is_fullscreen() {
+            thread::sleep(CHECK_INTERVAL);
+        }
+        let held = std::mem::take(&mut *HELD_NOTIFICATIONS.lock().unwrap_or_else(|e| e.into_inner()));
+        info!("Fullscreen ended, showing {} held notifications", held.len());
+        for (title, body) in held {
+            notify(&app, &title, &body);
+        }
+    });
+    true
+}
@@ -0,0 +1,76 @@
+// 自动选择中国区或国际区：先按系统语言和时区推测，再探测 cn.bing.com 和 www.bing.com 的可达性和延迟加以修正。
+// 自动模式下每次定时刷新前重新探测，切换网络（例如出差、连接 VPN）后下一次刷新即按新的结果获取
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use log::{info, warn};
+use wallpaper_core::HttpClient;
+
+use crate::{http, RefreshMode};
+
+const CHINA_PROBE_URL: &str = "https://cn.bing.com/";
+const GLOBAL_PROBE_URL: &str = "https://www.bing.com/";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+// 两个地址都可达时，只有一方明显更快才推翻按语言和时区的推测
+const LATENCY_MARGIN: Duration = Duration::from_millis(300);
+const CHINA_UTC_OFFSET_SECS: i32 = 8 * 3600;
+
+// 最近一次探测的结果，尚未探测时为 None
+static DETECTED: Mutex<Option<bool>> = Mutex::new(None);
+
+// 系统区域为 CN（zh-CN、zh-Hans-CN 等），或只有语言为中文且时区为东八区时推测为中国区
+fn guess() -> bool {
+    let locale = sys_locale::get_locale().unwrap_or_default().replace('_', "-").to_lowercase();
+    if locale.split('-').any(|part| part == "cn") {
+        return true;
+    }
+    let china_offset = chrono::Local::now().offset().local_minus_utc() == CHINA_UTC_OFFSET_SECS;
+    china_offset && (locale == "zh" || locale == "zh-hans")
+}
+
+// 返回收到响应头的耗时，不读取页面内容；不可达或超时返回 None
+async fn probe(client: &HttpClient, url: &str) -> Option<Duration> {
+    let started = Instant::now();
+    http::send(client.get(url).timeout(PROBE_TIMEOUT)).await.ok().map(|_| started.elapsed())
+}
+
+// 自动模式下当前是否使用中国区；还没有探测过时使用推测的结果
+pub(crate) fn is_china() -> bool {
+    DETECTED.lock().unwrap_or_else(|e| e.into_inner()).unwrap_or_else(guess)
+}
+
+// 只有一个地址可达时选择它；都可达时一方快出 LATENCY_MARGIN 以上才选择它，否则沿用推测的结果
+pub(crate) async fn detect() -> bool {
+    let guessed = guess();
+    let china = match http::client() {
+        Ok(client) => {
+            let (china, global) = tokio::join!(probe(&client, CHINA_PROBE_URL), probe(&client, GLOBAL_PROBE_URL));
+            match (china, global) {
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (Some(china), Some(global)) if china + LATENCY_MARGIN < global => true,
+                (Some(china), Some(global)) if global + LATENCY_MARGIN < china => false,
+                _ => guessed,
+            }
+        }
+        Err(e) => {
+            warn!("Failed to probe region, using guess: {}", e);
+            guessed
+        }
+    };
+    let previous = DETECTED.lock().unwrap_or_else(|e| e.into_inner()).replace(china);
+    if previous != Some(china) {
+        info!("Detected region: {}", if china { "china" } else { "global" });
+    }
+    china
+}
+
+// 定时任务每次刷新前调用：自动模式下重新探测，其它模式按模式本身决定
+pub(crate) async fn resolve(mode: RefreshMode) -> bool {
+    if mode == RefreshMode::DailyAuto {
+        detect().await
+    } else {
+        mode.is_china()
+    }
+}
@@ -0,0 +1,54 @@
+// Windows 注册表读取的简单封装，只处理当前用户（HKCU）下的值
+use winapi::{
+    shared::minwindef::DWORD,
+    um::winreg::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD, RRF_RT_REG_SZ},
+};
+
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+pub(crate) fn read_hkcu_dword(subkey: &str, value: &str) -> Option<u32> {
+    let subkey = to_wide(subkey);
+    let value = to_wide(value);
+    let mut data: DWORD = 0;
+    let mut size = std::mem::size_of::<DWORD>() as DWORD;
+
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            subkey.as_ptr(),
+            value.as_ptr(),
+            RRF_RT_REG_DWORD,
+            std::ptr::null_mut(),
+            &mut data as *mut DWORD as *mut _,
+            &mut size,
+        )
+    };
+    if status == 0 { Some(data) } else { None }
+}
+
+pub(crate) fn read_hkcu_string(subkey: &str, value: &str) -> Option<String> {
+    let subkey = to_wide(subkey);
+    let value = to_wide(value);
+    let mut buffer = vec![0u16; 1024];
+    let mut size = (buffer.len() * 2) as DWORD;
+
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            subkey.as_ptr(),
+            value.as_ptr(),
+            RRF_RT_REG_SZ,
+            std::ptr::null_mut(),
+            buffer.as_mut_ptr() as *mut _,
+            &mut size,
+        )
+    };
+    if status != 0 {
+        return None;
+    }
+    // size 为字节数并包含结尾的 0
+    let len = (size as usize / 2).saturating_sub(1);
+    Some(String::from_utf16_lossy(&buffer[..len]))
+}
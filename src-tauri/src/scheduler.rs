@@ -0,0 +1,42 @@
+// 从配置读取刷新计划，具体的等待逻辑在 wallpaper_core::scheduler
+use std::time::Duration;
+use chrono::{Datelike, Local, NaiveDate};
+use log::warn;
+pub(crate) use wallpaper_core::scheduler::{parse_daily_time, Schedule, Scheduler, Trigger};
+
+use crate::{load_config, DEFAULT_REFRESH_INTERVAL_MINUTES};
+
+pub(crate) const SCHEDULE_INTERVAL: &str = "interval";
+pub(crate) const SCHEDULE_DAILY: &str = "daily";
+// 默认的刷新方式：按壁纸日期睡到下一次更新
+pub(crate) const SCHEDULE_ROLLOVER: &str = "rollover";
+
+// 按星期的规则：键为星期（周一在前），值为 skip 表示这一天不定时刷新，或当天改用的壁纸来源；没有规则的日子按默认设置
+pub(crate) const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+pub(crate) const DAY_SKIP: &str = "skip";
+
+// 今天的规则，没有时返回 None
+pub(crate) fn today_rule() -> Option<String> {
+    let weekday = WEEKDAYS[Local::now().weekday().num_days_from_monday() as usize];
+    load_config().ok()?.day_rules.remove(weekday).filter(|rule| !rule.is_empty())
+}
+
+// latest 为当前壁纸的日期，只有 rollover 方式会用到
+pub(crate) fn load_schedule(latest: Option<NaiveDate>) -> Schedule {
+    let config = load_config().unwrap_or_default();
+    if config.schedule_mode.is_empty() || config.schedule_mode == SCHEDULE_ROLLOVER {
+        return Schedule::Rollover(latest);
+    }
+    if config.schedule_mode == SCHEDULE_DAILY {
+        match parse_daily_time(&config.daily_time) {
+            Ok(time) => return Schedule::DailyAt(time),
+            Err(e) => warn!("Invalid daily time, falling back to interval: {}", e),
+        }
+    }
+    let minutes = if config.refresh_interval_minutes == 0 {
+        DEFAULT_REFRESH_INTERVAL_MINUTES
+    } else {
+        config.refresh_interval_minutes
+    };
+    Schedule::Interval(Duration::from_secs(minutes * 60))
+}
@@ -0,0 +1,202 @@
+// 把后台服务模式注册为登录后自动运行的系统服务：Linux 写入 systemd 用户服务，macOS 写入 LaunchAgent，
+// Windows 创建登录时运行的计划任务。命令行的 --install-service / --uninstall-service 调用这里，
+// 返回服务文件的路径（Windows 上为计划任务名称）
+use std::{
+    ffi::OsStr,
+    path::PathBuf,
+    process::Command,
+};
+#[cfg(not(target_os = "windows"))]
+use std::fs;
+use log::info;
+
+#[cfg(not(target_os = "windows"))]
+use crate::atomic_file;
+use crate::{daemon::DAEMON_ARG, portable, AppError, Result};
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const UNIT_NAME: &str = "bing-wallpaper.service";
+#[cfg(target_os = "macos")]
+const LAUNCH_AGENT_LABEL: &str = "com.wdbyte.bing.daemon";
+#[cfg(target_os = "windows")]
+const TASK_NAME: &str = "Bing Wallpaper Daemon";
+
+// 服务启动的程序；AppImage 运行时可执行文件位于临时挂载目录，使用 AppImage 文件本身
+fn program() -> Result<PathBuf> {
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        return Ok(PathBuf::from(appimage));
+    }
+    Ok(std::env::current_exe()?)
+}
+
+// 便携模式下服务也要带上 --portable，否则会改用系统的数据目录
+fn daemon_args() -> Vec<&'static str> {
+    if portable::is_enabled() { vec![DAEMON_ARG, portable::PORTABLE_ARG] } else { vec![DAEMON_ARG] }
+}
+
+fn run<I, S>(program: &str, args: I) -> Result<()>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        return Err(AppError::OsSet(format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn home_dir() -> Result<PathBuf> {
+    std::env::var("HOME").map(PathBuf::from).map_err(|e| AppError::Config(format!("HOME: {}", e)))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn unit_path() -> Result<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home_dir()?.join(".config"),
+    };
+    Ok(config_dir.join("systemd").join("user").join(UNIT_NAME))
+}
+
+// systemd 的 ExecStart 按空格分隔参数，路径用双引号括起来
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn unit_content() -> Result<String> {
+    let program = program()?.to_string_lossy().replace('\\', "\\\\").replace('"', "\\\"");
+    Ok(format!(
+        "[Unit]
+Description=Bing Wallpaper daemon
+After=graphical-session.target
+PartOf=graphical-session.target
+
+[Service]
+ExecStart=\"{}\" {}
+Restart=on-failure
+RestartSec=30
+
+[Install]
+WantedBy=graphical-session.target
+",
+        program,
+        daemon_args().join(" ")
+    ))
+}
+
+// 跟随图形会话启动，这样服务能拿到 DISPLAY、DBUS_SESSION_BUS_ADDRESS 等设置壁纸需要的环境变量
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub(crate) fn install() -> Result<String> {
+    let path = unit_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    atomic_file::write(&path, unit_content()?)?;
+    run("systemctl", ["--user", "daemon-reload"])?;
+    run("systemctl", ["--user", "enable", "--now", UNIT_NAME])?;
+    info!("Installed systemd user service {:?}", path);
+    Ok(path.display().to_string())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub(crate) fn uninstall() -> Result<String> {
+    let path = unit_path()?;
+    if !path.exists() {
+        return Err(AppError::Config(format!("Service not installed: {}", path.display())));
+    }
+    run("systemctl", ["--user", "disable", "--now", UNIT_NAME])?;
+    fs::remove_file(&path)?;
+    run("systemctl", ["--user", "daemon-reload"])?;
+    info!("Removed systemd user service {:?}", path);
+    Ok(path.display().to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn plist_path() -> Result<PathBuf> {
+    Ok(home_dir()?.join("Library").join("LaunchAgents").join(format!("{}.plist", LAUNCH_AGENT_LABEL)))
+}
+
+#[cfg(target_os = "macos")]
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// 登录时启动，异常退出后由 launchd 重新拉起，正常退出后不再启动
+#[cfg(target_os = "macos")]
+fn plist_content() -> Result<String> {
+    let arguments: String = std::iter::once(program()?.to_string_lossy().into_owned())
+        .chain(daemon_args().into_iter().map(str::to_string))
+        .map(|arg| format!("        <string>{}</string>\n", xml_escape(&arg)))
+        .collect();
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+<plist version=\"1.0\">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+{}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ProcessType</key>
+    <string>Background</string>
+</dict>
+</plist>
+",
+        LAUNCH_AGENT_LABEL, arguments
+    ))
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn install() -> Result<String> {
+    let path = plist_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // 重新安装时先卸载旧的定义，失败说明之前没有加载过
+    if path.exists() {
+        let _ = run("launchctl", [OsStr::new("unload"), path.as_os_str()]);
+    }
+    atomic_file::write(&path, plist_content()?)?;
+    run("launchctl", [OsStr::new("load"), OsStr::new("-w"), path.as_os_str()])?;
+    info!("Installed LaunchAgent {:?}", path);
+    Ok(path.display().to_string())
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn uninstall() -> Result<String> {
+    let path = plist_path()?;
+    if !path.exists() {
+        return Err(AppError::Config(format!("Service not installed: {}", path.display())));
+    }
+    run("launchctl", [OsStr::new("unload"), OsStr::new("-w"), path.as_os_str()])?;
+    fs::remove_file(&path)?;
+    info!("Removed LaunchAgent {:?}", path);
+    Ok(path.display().to_string())
+}
+
+// 计划任务在当前用户登录时以普通权限运行，创建后立即启动一次
+#[cfg(target_os = "windows")]
+pub(crate) fn install() -> Result<String> {
+    let program = program()?;
+    let command = format!("\"{}\" {}", program.display(), daemon_args().join(" "));
+    run("schtasks", ["/Create", "/F", "/SC", "ONLOGON", "/RL", "LIMITED", "/TN", TASK_NAME, "/TR", command.as_str()])?;
+    run("schtasks", ["/Run", "/TN", TASK_NAME])?;
+    info!("Installed scheduled task {}: {}", TASK_NAME, command);
+    Ok(TASK_NAME.to_string())
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) fn uninstall() -> Result<String> {
+    // 任务没有在运行时 /End 会失败，不影响删除
+    let _ = run("schtasks", ["/End", "/TN", TASK_NAME]);
+    run("schtasks", ["/Delete", "/F", "/TN", TASK_NAME])?;
+    info!("Removed scheduled task {}", TASK_NAME);
+    Ok(TASK_NAME.to_string())
+}
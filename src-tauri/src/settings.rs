@@ -0,0 +1,561 @@
+use std::{collections::BTreeMap, fs, sync::Mutex};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
+use uuid::Uuid;
+
+use crate::{
+    api::{PROVIDERS, SOURCE_AUTO, SOURCE_BING, SOURCE_WDBYTE},
+    cache::DEFAULT_CACHE_MAX_FILES,
+    disk_space::DEFAULT_MIN_FREE_SPACE_MB,
+    http::{
+        self, DEFAULT_CONNECT_TIMEOUT, DEFAULT_DOWNLOAD_TIMEOUT, DEFAULT_REQUEST_TIMEOUT, MAX_TIMEOUT_SECS, PROXY_MANUAL, PROXY_NONE,
+        PROXY_SCHEMES, PROXY_SYSTEM,
+    },
+    i18n::{self, tr, tr_fmt, tr_with, LANGUAGES},
+    processing::{MAX_BLUR, MAX_DIM, MAX_SATURATION},
+    library, load_config, logging::{self, DEFAULT_LOG_LEVEL, LOG_LEVELS},
+    slideshow::{DEFAULT_SLIDESHOW_INTERVAL_MINUTES, ORDER_SEQUENTIAL, ORDER_SHUFFLE, SOURCE_ALL, SOURCE_FAVORITES, SOURCE_FOLDER},
+    hotkey::{self, DEFAULT_NEXT, DEFAULT_PAUSE, DEFAULT_PREVIOUS},
+    control_api,
+    daemon,
+    overlay,
+    sync,
+    restart_refresh_task,
+    updater::{CHANNEL_BETA, CHANNEL_OFF, CHANNEL_STABLE},
+    scheduler::{parse_daily_time, DAY_SKIP, SCHEDULE_DAILY, SCHEDULE_INTERVAL, SCHEDULE_ROLLOVER, WEEKDAYS}, lock_state, set_refresh_mode, update_config, AppError, AppState,
+    reapply_in_background, reset_device_uuid, resolve_wallpaper_dir, DEVICE_ID_OFF, DEVICE_ID_PERSISTENT, DEVICE_ID_ROTATE, update_menu, RefreshMode, Resolution, Result, DEFAULT_REFRESH_INTERVAL_MINUTES, MARKETS,
+    watermark::{CORNERS, CORNER_BOTTOM_RIGHT, CORNER_TOP_RIGHT, DEFAULT_FONT_SIZE, MAX_FONT_SIZE, MIN_FONT_SIZE},
+};
+
+const SETTINGS_WINDOW_LABEL: &str = "settings";
+const DEFAULT_DAILY_TIME: &str = "08:00";
+const PICTURES_SAVE_DIR_NAME: &str = "BingWallpapers";
+// 控制接口不使用需要管理员权限的端口
+const MIN_CONTROL_API_PORT: u16 = 1024;
+
+// 设置窗口读写的配置项，region 即刷新模式，market 只在国际模式下生效
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Settings {
+    schedule_mode: String,
+    daily_time: String,
+    day_rules: BTreeMap<String, String>,
+    refresh_interval_minutes: u64,
+    region: String,
+    market: String,
+    slideshow_interval_minutes: u64,
+    slideshow_order: String,
+    slideshow_source: String,
+    local_folder: String,
+    local_folder_recursive: bool,
+    local_folder_formats: String,
+    resolution: String,
+    save_dir: String,
+    cache_max_files: usize,
+    cache_max_mb: u64,
+    min_free_space_mb: u64,
+    proxy_mode: String,
+    proxy_host: String,
+    proxy_port: u16,
+    proxy_username: String,
+    proxy_password: String,
+    connect_timeout_secs: u64,
+    request_timeout_secs: u64,
+    download_timeout_secs: u64,
+    download_rate_limit_kbps: u64,
+    defer_on_metered: bool,
+    battery_min_percent: u8,
+    defer_when_fullscreen: bool,
+    drift_correction_enabled: bool,
+    device_id_mode: String,
+    update_channel: String,
+    log_level: String,
+    api_base_url: String,
+    api_source: String,
+    apod_api_key: String,
+    unsplash_access_key: String,
+    unsplash_collection: String,
+    // 界面语言，空字符串表示跟随系统
+    language: String,
+    post_change_hook: String,
+    webhook_url: String,
+    hotkeys_enabled: bool,
+    hotkey_next: String,
+    hotkey_previous: String,
+    hotkey_pause: String,
+    control_api_enabled: bool,
+    control_api_port: u16,
+    control_api_token: String,
+    sync_folder: String,
+    overlay_enabled: bool,
+    overlay_corner: String,
+    overlay_opacity: u32,
+    process_blur: u32,
+    process_dim: u32,
+    process_saturation: i32,
+    watermark_enabled: bool,
+    watermark_corner: String,
+    watermark_font_size: u32,
+    watermark_font: String,
+}
+
+#[tauri::command]
+pub(crate) fn get_settings(state: tauri::State<'_, Mutex<AppState>>) -> Result<Settings> {
+    let config = load_config()?;
+    let refresh_interval_minutes = if config.refresh_interval_minutes == 0 {
+        DEFAULT_REFRESH_INTERVAL_MINUTES
+    } else {
+        config.refresh_interval_minutes
+    };
+    let resolution = Resolution::from_str(&config.resolution).as_str().to_string();
+    let schedule_mode = if config.schedule_mode.is_empty() { SCHEDULE_ROLLOVER.to_string() } else { config.schedule_mode };
+    let daily_time = if config.daily_time.is_empty() { DEFAULT_DAILY_TIME.to_string() } else { config.daily_time };
+
+    Ok(Settings {
+        schedule_mode,
+        daily_time,
+        day_rules: config.day_rules,
+        refresh_interval_minutes,
+        region: lock_state(&state).refresh_mode.as_str().to_string(),
+        market: config.market,
+        slideshow_interval_minutes: if config.slideshow_interval_minutes == 0 {
+            DEFAULT_SLIDESHOW_INTERVAL_MINUTES
+        } else {
+            config.slideshow_interval_minutes
+        },
+        slideshow_order: if config.slideshow_order == ORDER_SEQUENTIAL {
+            ORDER_SEQUENTIAL.to_string()
+        } else {
+            ORDER_SHUFFLE.to_string()
+        },
+        slideshow_source: if [SOURCE_FAVORITES, SOURCE_FOLDER].contains(&config.slideshow_source.as_str()) {
+            config.slideshow_source
+        } else {
+            SOURCE_ALL.to_string()
+        },
+        local_folder: config.local_folder,
+        local_folder_recursive: config.local_folder_recursive,
+        local_folder_formats: config.local_folder_formats,
+        resolution,
+        save_dir: config.save_dir,
+        cache_max_files: if config.cache_max_files == 0 { DEFAULT_CACHE_MAX_FILES } else { config.cache_max_files },
+        cache_max_mb: config.cache_max_mb,
+        min_free_space_mb: if config.min_free_space_mb == 0 { DEFAULT_MIN_FREE_SPACE_MB } else { config.min_free_space_mb },
+        proxy_mode: if config.proxy_mode.is_empty() { PROXY_SYSTEM.to_string() } else { config.proxy_mode },
+        proxy_host: config.proxy_host,
+        proxy_port: config.proxy_port,
+        proxy_username: config.proxy_username,
+        proxy_password: config.proxy_password,
+        connect_timeout_secs: http::timeout_secs(config.connect_timeout_secs, DEFAULT_CONNECT_TIMEOUT),
+        request_timeout_secs: http::timeout_secs(config.request_timeout_secs, DEFAULT_REQUEST_TIMEOUT),
+        download_timeout_secs: http::timeout_secs(config.download_timeout_secs, DEFAULT_DOWNLOAD_TIMEOUT),
+        download_rate_limit_kbps: config.download_rate_limit_kbps,
+        defer_on_metered: config.defer_on_metered,
+        battery_min_percent: config.battery_min_percent,
+        defer_when_fullscreen: config.defer_when_fullscreen,
+        drift_correction_enabled: !config.drift_correction_disabled,
+        update_channel: if config.update_channel.is_empty() { CHANNEL_STABLE.to_string() } else { config.update_channel },
+        device_id_mode: if config.device_id_mode.is_empty() { DEVICE_ID_PERSISTENT.to_string() } else { config.device_id_mode },
+        log_level: if config.log_level.is_empty() { DEFAULT_LOG_LEVEL.to_string() } else { config.log_level },
+        api_base_url: config.api_base_url,
+        api_source: if config.api_source.is_empty() { SOURCE_AUTO.to_string() } else { config.api_source },
+        apod_api_key: config.apod_api_key,
+        unsplash_access_key: config.unsplash_access_key,
+        unsplash_collection: config.unsplash_collection,
+        language: config.language,
+        post_change_hook: config.post_change_hook,
+        webhook_url: config.webhook_url,
+        hotkeys_enabled: !config.hotkeys_disabled,
+        hotkey_next: if config.hotkey_next.is_empty() { DEFAULT_NEXT.to_string() } else { config.hotkey_next },
+        hotkey_previous: if config.hotkey_previous.is_empty() { DEFAULT_PREVIOUS.to_string() } else { config.hotkey_previous },
+        hotkey_pause: if config.hotkey_pause.is_empty() { DEFAULT_PAUSE.to_string() } else { config.hotkey_pause },
+        control_api_enabled: config.control_api_enabled,
+        control_api_port: if config.control_api_port == 0 { control_api::DEFAULT_PORT } else { config.control_api_port },
+        control_api_token: config.control_api_token,
+        sync_folder: config.sync_folder,
+        overlay_enabled: config.overlay_enabled,
+        overlay_corner: if CORNERS.contains(&config.overlay_corner.as_str()) {
+            config.overlay_corner
+        } else {
+            CORNER_TOP_RIGHT.to_string()
+        },
+        overlay_opacity: if config.overlay_opacity == 0 { overlay::DEFAULT_OPACITY } else { config.overlay_opacity },
+        process_blur: config.process_blur,
+        process_dim: config.process_dim,
+        process_saturation: config.process_saturation,
+        watermark_enabled: config.watermark_enabled,
+        watermark_corner: if config.watermark_corner.is_empty() { CORNER_BOTTOM_RIGHT.to_string() } else { config.watermark_corner },
+        watermark_font_size: if config.watermark_font_size == 0 { DEFAULT_FONT_SIZE } else { config.watermark_font_size },
+        watermark_font: config.watermark_font,
+    })
+}
+
+// 设置窗口中“图片文件夹”按钮填入的保存目录：系统图片文件夹下的 BingWallpapers
+#[tauri::command]
+pub(crate) fn get_pictures_save_dir(app: tauri::AppHandle) -> Result<String> {
+    let pictures = app.path().picture_dir().map_err(|e| AppError::Config(e.to_string()))?;
+    Ok(pictures.join(PICTURES_SAVE_DIR_NAME).to_string_lossy().into_owned())
+}
+
+// 设置窗口中“重置设备标识”按钮：删除 device_uuid.txt，下次请求时生成新的标识
+#[tauri::command]
+pub(crate) fn reset_device_id() -> Result<()> {
+    reset_device_uuid()
+}
+
+// 去掉值为空（按默认设置）的星期，其余必须是 skip 或已知的壁纸来源
+fn normalize_day_rules(rules: &BTreeMap<String, String>) -> Result<BTreeMap<String, String>> {
+    let mut normalized = BTreeMap::new();
+    for (day, rule) in rules {
+        if rule.is_empty() {
+            continue;
+        }
+        if !WEEKDAYS.contains(&day.as_str()) {
+            return Err(AppError::Config(tr_with("不支持的星期: {}", day)));
+        }
+        if rule != DAY_SKIP && !PROVIDERS.iter().any(|(id, _)| id == rule) {
+            return Err(AppError::Config(tr_with("不支持的星期规则: {}", rule)));
+        }
+        normalized.insert(day.clone(), rule.clone());
+    }
+    Ok(normalized)
+}
+
+// 校验设置窗口提交的内容，不修改配置；同步文件中的设置也按此逐项检查
+pub(crate) fn validate(settings: &Settings) -> Result<()> {
+    if settings.refresh_interval_minutes == 0 {
+        return Err(AppError::Config(tr("刷新间隔必须大于 0 分钟").to_string()));
+    }
+    if ![SCHEDULE_ROLLOVER, SCHEDULE_INTERVAL, SCHEDULE_DAILY].contains(&settings.schedule_mode.as_str()) {
+        return Err(AppError::Config(tr_with("不支持的刷新方式: {}", &settings.schedule_mode)));
+    }
+    parse_daily_time(&settings.daily_time)?;
+    normalize_day_rules(&settings.day_rules)?;
+    if settings.slideshow_interval_minutes == 0 {
+        return Err(AppError::Config(tr("轮播间隔必须大于 0 分钟").to_string()));
+    }
+    if settings.slideshow_order != ORDER_SHUFFLE && settings.slideshow_order != ORDER_SEQUENTIAL {
+        return Err(AppError::Config(tr_with("不支持的轮播顺序: {}", &settings.slideshow_order)));
+    }
+    if ![SOURCE_ALL, SOURCE_FAVORITES, SOURCE_FOLDER].contains(&settings.slideshow_source.as_str()) {
+        return Err(AppError::Config(tr_with("不支持的轮播范围: {}", &settings.slideshow_source)));
+    }
+    let local_folder = settings.local_folder.trim().to_string();
+    if settings.slideshow_source == SOURCE_FOLDER && local_folder.is_empty() {
+        return Err(AppError::Config(tr("请选择要轮播的本地文件夹").to_string()));
+    }
+    if !local_folder.is_empty() && !std::path::Path::new(&local_folder).is_dir() {
+        return Err(AppError::Config(tr_with("本地文件夹不存在: {}", &local_folder)));
+    }
+    if !MARKETS.iter().any(|(code, _)| *code == settings.market) {
+        return Err(AppError::Config(tr_with("不支持的壁纸市场: {}", &settings.market)));
+    }
+    if Resolution::from_str(&settings.resolution).as_str() != settings.resolution {
+        return Err(AppError::Config(tr_with("不支持的分辨率: {}", &settings.resolution)));
+    }
+    if settings.cache_max_files == 0 {
+        return Err(AppError::Config(tr("至少需要保留 1 张壁纸").to_string()));
+    }
+    if settings.min_free_space_mb == 0 {
+        return Err(AppError::Config(tr("磁盘剩余空间下限至少为 1 MB").to_string()));
+    }
+    if ![PROXY_NONE, PROXY_SYSTEM, PROXY_MANUAL].contains(&settings.proxy_mode.as_str()) {
+        return Err(AppError::Config(tr_with("不支持的代理模式: {}", &settings.proxy_mode)));
+    }
+    let proxy_host = settings.proxy_host.trim().to_string();
+    if settings.proxy_mode == PROXY_MANUAL {
+        if proxy_host.is_empty() {
+            return Err(AppError::Config(tr("请填写代理服务器地址").to_string()));
+        }
+        if let Some((scheme, _)) = proxy_host.split_once("://") {
+            if !PROXY_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()) {
+                return Err(AppError::Config(tr_with("不支持的代理协议: {}，请使用 HTTP 或 SOCKS5 代理", scheme)));
+            }
+        }
+    }
+    for timeout in [settings.connect_timeout_secs, settings.request_timeout_secs, settings.download_timeout_secs] {
+        if timeout == 0 || timeout > MAX_TIMEOUT_SECS {
+            return Err(AppError::Config(tr_with("超时应在 1 到 {} 秒之间", MAX_TIMEOUT_SECS)));
+        }
+    }
+    if settings.battery_min_percent > 100 {
+        return Err(AppError::Config(tr("电量阈值应在 0 到 100 之间").to_string()));
+    }
+    if ![DEVICE_ID_PERSISTENT, DEVICE_ID_ROTATE, DEVICE_ID_OFF].contains(&settings.device_id_mode.as_str()) {
+        return Err(AppError::Config(tr_with("不支持的设备标识设置: {}", &settings.device_id_mode)));
+    }
+    if ![CHANNEL_STABLE, CHANNEL_BETA, CHANNEL_OFF].contains(&settings.update_channel.as_str()) {
+        return Err(AppError::Config(tr_with("不支持的更新通道: {}", &settings.update_channel)));
+    }
+    if !LOG_LEVELS.contains(&settings.log_level.as_str()) {
+        return Err(AppError::Config(tr_with("不支持的日志级别: {}", &settings.log_level)));
+    }
+    if ![SOURCE_AUTO, SOURCE_WDBYTE, SOURCE_BING].contains(&settings.api_source.as_str()) {
+        return Err(AppError::Config(tr_with("不支持的接口来源: {}", &settings.api_source)));
+    }
+    for keys in [&settings.hotkey_next, &settings.hotkey_previous, &settings.hotkey_pause] {
+        if !keys.trim().is_empty() {
+            hotkey::parse(keys.trim())?;
+        }
+    }
+    if !CORNERS.contains(&settings.overlay_corner.as_str()) {
+        return Err(AppError::Config(tr_with("不支持的卡片位置: {}", &settings.overlay_corner)));
+    }
+    if !(overlay::MIN_OPACITY..=100).contains(&settings.overlay_opacity) {
+        return Err(AppError::Config(tr_with("卡片不透明度应在 {}% 到 100% 之间", overlay::MIN_OPACITY)));
+    }
+    if settings.control_api_port < MIN_CONTROL_API_PORT {
+        return Err(AppError::Config(tr_with("控制接口端口应在 {} 到 65535 之间", MIN_CONTROL_API_PORT)));
+    }
+    if settings.process_blur > MAX_BLUR {
+        return Err(AppError::Config(tr_with("模糊强度不能超过 {}", MAX_BLUR)));
+    }
+    if settings.process_dim > MAX_DIM {
+        return Err(AppError::Config(tr_with("调暗比例不能超过 {}%", MAX_DIM)));
+    }
+    if settings.process_saturation.abs() > MAX_SATURATION {
+        return Err(AppError::Config(tr_with("饱和度调整应在 ±{}% 之间", MAX_SATURATION)));
+    }
+    if !CORNERS.contains(&settings.watermark_corner.as_str()) {
+        return Err(AppError::Config(tr_with("不支持的水印位置: {}", &settings.watermark_corner)));
+    }
+    if !(MIN_FONT_SIZE..=MAX_FONT_SIZE).contains(&settings.watermark_font_size) {
+        return Err(AppError::Config(tr_fmt(
+            "水印字号应在 {} 到 {} 之间",
+            &[&MIN_FONT_SIZE.to_string(), &MAX_FONT_SIZE.to_string()],
+        )));
+    }
+    let watermark_font = settings.watermark_font.trim().to_string();
+    if !watermark_font.is_empty() && !std::path::Path::new(&watermark_font).is_file() {
+        return Err(AppError::Config(tr_with("水印字体文件不存在: {}", &watermark_font)));
+    }
+    if !LANGUAGES.contains(&settings.language.as_str()) {
+        return Err(AppError::Config(tr_with("不支持的界面语言: {}", &settings.language)));
+    }
+    normalize_api_base_url(&settings.api_base_url)?;
+    normalize_webhook_url(&settings.webhook_url)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn save_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<AppState>>,
+    settings: Settings,
+) -> Result<()> {
+    validate(&settings)?;
+    let day_rules = normalize_day_rules(&settings.day_rules)?;
+    let local_folder = settings.local_folder.trim().to_string();
+    let proxy_host = settings.proxy_host.trim().to_string();
+    let watermark_font = settings.watermark_font.trim().to_string();
+    let api_base_url = normalize_api_base_url(&settings.api_base_url)?;
+    let webhook_url = normalize_webhook_url(&settings.webhook_url)?;
+    let save_dir = settings.save_dir.trim().to_string();
+    if !save_dir.is_empty() {
+        fs::create_dir_all(&save_dir).map_err(|e| AppError::Config(tr_fmt("无法使用保存目录 {}: {}", &[&save_dir, &e.to_string()])))?;
+    }
+
+    let old_config = load_config().unwrap_or_default();
+    let old_wallpaper_dir = resolve_wallpaper_dir(&old_config.save_dir)?;
+    let new_wallpaper_dir = resolve_wallpaper_dir(&save_dir)?;
+    let schedule_changed = old_config.refresh_interval_minutes != settings.refresh_interval_minutes
+        || old_config.schedule_mode != settings.schedule_mode
+        || old_config.daily_time != settings.daily_time
+        || old_config.slideshow_interval_minutes != settings.slideshow_interval_minutes
+        || old_config.slideshow_order != settings.slideshow_order
+        || old_config.slideshow_source != settings.slideshow_source
+        || old_config.local_folder != local_folder
+        || old_config.local_folder_recursive != settings.local_folder_recursive
+        || old_config.local_folder_formats != settings.local_folder_formats;
+    let market_changed = old_config.market != settings.market;
+    let language_changed = old_config.language != settings.language;
+    let processing_changed = old_config.process_blur != settings.process_blur
+        || old_config.process_dim != settings.process_dim
+        || old_config.process_saturation != settings.process_saturation
+        || old_config.watermark_enabled != settings.watermark_enabled
+        || old_config.watermark_corner != settings.watermark_corner
+        || old_config.watermark_font_size != settings.watermark_font_size
+        || old_config.watermark_font != watermark_font;
+    // 开启控制接口但没有填写令牌时生成一个随机令牌
+    let mut control_api_token = settings.control_api_token.trim().to_string();
+    if settings.control_api_enabled && control_api_token.is_empty() {
+        control_api_token = Uuid::new_v4().simple().to_string();
+    }
+    let sync_folder = settings.sync_folder.trim().to_string();
+    let sync_folder_changed = old_config.sync_folder != sync_folder;
+    let control_api_changed = old_config.control_api_enabled != settings.control_api_enabled
+        || old_config.control_api_port != settings.control_api_port
+        || old_config.control_api_token != control_api_token;
+    let apod_api_key = settings.apod_api_key.trim().to_string();
+    let unsplash_access_key = settings.unsplash_access_key.trim().to_string();
+    let unsplash_collection = settings.unsplash_collection.trim().to_string();
+    let api_changed = old_config.api_base_url != api_base_url
+        || old_config.api_source != settings.api_source
+        || old_config.apod_api_key != apod_api_key
+        || old_config.unsplash_access_key != unsplash_access_key
+        || old_config.unsplash_collection != unsplash_collection;
+    update_config(|config| {
+        config.schedule_mode = settings.schedule_mode.clone();
+        config.daily_time = settings.daily_time.trim().to_string();
+        config.day_rules = day_rules;
+        config.refresh_interval_minutes = settings.refresh_interval_minutes;
+        config.resolution = settings.resolution.clone();
+        config.market = settings.market.clone();
+        config.slideshow_interval_minutes = settings.slideshow_interval_minutes;
+        config.slideshow_order = settings.slideshow_order.clone();
+        config.slideshow_source = settings.slideshow_source.clone();
+        config.local_folder = local_folder.clone();
+        config.local_folder_recursive = settings.local_folder_recursive;
+        config.local_folder_formats = settings.local_folder_formats.trim().to_string();
+        config.save_dir = save_dir.clone();
+        config.cache_max_files = settings.cache_max_files;
+        config.cache_max_mb = settings.cache_max_mb;
+        config.min_free_space_mb = settings.min_free_space_mb;
+        config.proxy_mode = settings.proxy_mode.clone();
+        config.proxy_host = proxy_host.clone();
+        config.proxy_port = settings.proxy_port;
+        config.proxy_username = settings.proxy_username.clone();
+        config.proxy_password = settings.proxy_password.clone();
+        config.connect_timeout_secs = settings.connect_timeout_secs;
+        config.request_timeout_secs = settings.request_timeout_secs;
+        config.download_timeout_secs = settings.download_timeout_secs;
+        config.download_rate_limit_kbps = settings.download_rate_limit_kbps;
+        config.defer_on_metered = settings.defer_on_metered;
+        config.battery_min_percent = settings.battery_min_percent;
+        config.defer_when_fullscreen = settings.defer_when_fullscreen;
+        config.drift_correction_disabled = !settings.drift_correction_enabled;
+        config.device_id_mode = settings.device_id_mode.clone();
+        config.update_channel = settings.update_channel.clone();
+        config.log_level = settings.log_level.clone();
+        config.api_base_url = api_base_url.clone();
+        config.api_source = settings.api_source.clone();
+        config.apod_api_key = apod_api_key.clone();
+        config.unsplash_access_key = unsplash_access_key.clone();
+        config.unsplash_collection = unsplash_collection.clone();
+        config.language = settings.language.clone();
+        config.post_change_hook = settings.post_change_hook.trim().to_string();
+        config.webhook_url = webhook_url.clone();
+        config.hotkeys_disabled = !settings.hotkeys_enabled;
+        config.hotkey_next = settings.hotkey_next.trim().to_string();
+        config.hotkey_previous = settings.hotkey_previous.trim().to_string();
+        config.hotkey_pause = settings.hotkey_pause.trim().to_string();
+        config.control_api_enabled = settings.control_api_enabled;
+        config.control_api_port = settings.control_api_port;
+        config.control_api_token = control_api_token.clone();
+        config.sync_folder = sync_folder.clone();
+        config.overlay_enabled = settings.overlay_enabled;
+        config.overlay_corner = settings.overlay_corner.clone();
+        config.overlay_opacity = settings.overlay_opacity;
+        config.process_blur = settings.process_blur;
+        config.process_dim = settings.process_dim;
+        config.process_saturation = settings.process_saturation;
+        config.watermark_enabled = settings.watermark_enabled;
+        config.watermark_corner = settings.watermark_corner.clone();
+        config.watermark_font_size = settings.watermark_font_size;
+        config.watermark_font = watermark_font.clone();
+    })?;
+    logging::set_level(&settings.log_level);
+    // 配置已经保存，其中一项生效失败（例如控制接口端口被占用）不能跳过其余各项，错误收集起来最后一起返回
+    let mut errors = Vec::new();
+    // 后台服务模式没有窗口，也不注册快捷键
+    if !daemon::is_enabled() {
+        errors.extend(hotkey::register(&app).err());
+    }
+    if control_api_changed {
+        errors.extend(control_api::restart(&app).err());
+    }
+    if !daemon::is_enabled() {
+        errors.extend(overlay::refresh(&app).err());
+    }
+    // 关闭设备标识时同时删除已保存的标识
+    if settings.device_id_mode == DEVICE_ID_OFF {
+        errors.extend(reset_device_uuid().err());
+    }
+    if old_wallpaper_dir != new_wallpaper_dir {
+        library::relocate(&app, old_wallpaper_dir, new_wallpaper_dir);
+    }
+    if language_changed {
+        i18n::set_language(&settings.language);
+    }
+    info!(
+        "Saved settings: schedule = {} ({} / {}min), region = {}, market = {:?}, resolution = {}, save_dir = {:?}",
+        settings.schedule_mode,
+        settings.daily_time,
+        settings.refresh_interval_minutes,
+        settings.region,
+        settings.market,
+        settings.resolution,
+        save_dir
+    );
+
+    let mode = RefreshMode::from_str(&settings.region);
+    let mut state = lock_state(&state);
+    let source_changed = api_changed || (market_changed && mode.is_daily() && !mode.is_china());
+    let is_daily = mode.is_daily();
+    if mode != state.refresh_mode || (source_changed && is_daily) {
+        errors.extend(set_refresh_mode(&app, &mut state, mode).err());
+    } else if schedule_changed && mode != RefreshMode::None {
+        // 只修改了刷新计划时重启定时器，不重新下载
+        restart_refresh_task(&app, &mut state);
+    }
+    if language_changed {
+        errors.extend(update_menu(&app, &state).err());
+    }
+    if processing_changed {
+        reapply_in_background(&app);
+    }
+    if sync_folder_changed {
+        errors.extend(sync::forget_seen().err());
+    }
+    sync::push_in_background(&app);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    for e in &errors {
+        warn!("Failed to apply saved settings: {}", e);
+    }
+    let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    Err(AppError::Config(tr_with("设置已保存，但部分设置未能生效：\n{}", messages.join("\n"))))
+}
+
+// 接口地址只接受 http/https，去掉末尾的 /；留空表示使用默认地址
+fn normalize_api_base_url(url: &str) -> Result<String> {
+    let url = url.trim().trim_end_matches('/');
+    if url.is_empty() {
+        return Ok(String::new());
+    }
+    let parsed = tauri::Url::parse(url).map_err(|e| AppError::Config(tr_with("接口地址无效: {}", &e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(AppError::Config(tr("接口地址必须以 http:// 或 https:// 开头").to_string()));
+    }
+    if parsed.query().is_some() || parsed.fragment().is_some() {
+        return Err(AppError::Config(tr("接口地址不能包含查询参数").to_string()));
+    }
+    Ok(url.to_string())
+}
+
+// Webhook 地址只接受 http/https，可以带查询参数（部分服务把令牌放在其中）；留空表示不发送
+fn normalize_webhook_url(url: &str) -> Result<String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Ok(String::new());
+    }
+    let parsed = tauri::Url::parse(url).map_err(|e| AppError::Config(tr_with("Webhook 地址无效: {}", &e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(AppError::Config(tr("Webhook 地址必须以 http:// 或 https:// 开头").to_string()));
+    }
+    Ok(url.to_string())
+}
+
+pub(crate) fn show_settings_window(app: &tauri::AppHandle) -> Result<()> {
+    if let Some(window) = app.get_webview_window(SETTINGS_WINDOW_LABEL) {
+        window.set_focus()?;
+        return Ok(());
+    }
+    WebviewWindowBuilder::new(app, SETTINGS_WINDOW_LABEL, WebviewUrl::App("settings.html".into()))
+        .title(tr("设置"))
+        .inner_size(460.0, 1320.0)
+        .resizable(false)
+        .build()?;
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use std::{path::PathBuf, sync::Mutex, time::Duration};
+use log::{info, warn};
+use tauri::Manager;
+use wallpaper_core::CancellationToken;
+
+use crate::{
+    apply_cached_wallpaper, blocklist::Blocklist, cache, favorites::Favorites, i18n::tr, load_config, local_folder, lock_state, random_u64,
+    report_error, run_guarded,
+    set_next_refresh,
+    scheduler::{Schedule, Scheduler},
+    AppError, AppState, RefreshTask, Result,
+};
+
+pub(crate) const ORDER_SHUFFLE: &str = "shuffle";
+pub(crate) const ORDER_SEQUENTIAL: &str = "sequential";
+// 轮播范围：all 轮播全部缓存壁纸，favorites 只轮播收藏的壁纸，folder 轮播用户指定的本地文件夹
+pub(crate) const SOURCE_ALL: &str = "all";
+pub(crate) const SOURCE_FAVORITES: &str = "favorites";
+pub(crate) const SOURCE_FOLDER: &str = "folder";
+pub(crate) const DEFAULT_SLIDESHOW_INTERVAL_MINUTES: u64 = 30;
+pub(crate) const SLIDESHOW_INTERVAL_OPTIONS: [u64; 5] = [5, 15, 30, 60, 180];
+// 轮播任务和快捷键共用的队列，设置中的顺序或范围变化后重新创建
+static ROTATION: Mutex<Option<Rotation>> = Mutex::new(None);
+
+// 轮播队列：每轮把缓存中的壁纸排好序依次播放，播完一轮再重新读取缓存
+struct Rotation {
+    shuffle: bool,
+    source: &'static str,
+    queue: Vec<PathBuf>,
+}
+
+impl Rotation {
+    fn new(shuffle: bool, source: &'static str) -> Self {
+        Rotation { shuffle, source, queue: Vec::new() }
+    }
+
+    fn next(&mut self, current: Option<&str>) -> Result<Option<PathBuf>> {
+        if self.queue.is_empty() {
+            self.refill(current)?;
+        }
+        Ok(self.queue.pop())
+    }
+
+    // 队列从末尾弹出：顺序模式按从旧到新播放，随机模式用 Fisher-Yates 打乱
+    fn refill(&mut self, current: Option<&str>) -> Result<()> {
+        let mut files = match self.source {
+            SOURCE_FAVORITES => Favorites::load().local_paths(),
+            SOURCE_FOLDER => local_folder::scan()?,
+            _ => cache::cached_paths()?,
+        };
+        let blocklist = Blocklist::load();
+        let duplicates = cache::duplicate_paths();
+        files.retain(|path| !blocklist.contains_path(path) && !duplicates.contains(path));
+        if self.shuffle {
+            for i in (1..files.len()).rev() {
+                let j = (random_u64() % (i as u64 + 1)) as usize;
+                files.swap(i, j);
+            }
+        }
+        // 新一轮的第一张不要和正在显示的重复
+        if files.len() > 1 {
+            let is_current = |path: &PathBuf| {
+                current.is_some_and(|name| path.file_name().is_some_and(|f| f == name))
+            };
+            if files.last().is_some_and(is_current) {
+                files.swap(0, files.len() - 1);
+            }
+        }
+        self.queue = files;
+        Ok(())
+    }
+}
+
+fn interval() -> Duration {
+    let config = load_config().unwrap_or_default();
+    let minutes = if config.slideshow_interval_minutes == 0 {
+        DEFAULT_SLIDESHOW_INTERVAL_MINUTES
+    } else {
+        config.slideshow_interval_minutes
+    };
+    Duration::from_secs(minutes * 60)
+}
+
+fn is_shuffle() -> bool {
+    load_config().map(|config| config.slideshow_order != ORDER_SEQUENTIAL).unwrap_or(true)
+}
+
+fn source() -> &'static str {
+    let source = load_config().map(|config| config.slideshow_source).unwrap_or_default();
+    [SOURCE_FAVORITES, SOURCE_FOLDER].into_iter().find(|s| *s == source).unwrap_or(SOURCE_ALL)
+}
+
+fn current_file(app: &tauri::AppHandle) -> Option<String> {
+    let state = app.state::<Mutex<AppState>>();
+    let state = lock_state(&state);
+    state.current_wallpaper.as_ref().map(|wallpaper| wallpaper.file_name.clone())
+}
+
+fn next_path(current: Option<&str>) -> Result<Option<PathBuf>> {
+    let (shuffle, source) = (is_shuffle(), source());
+    let mut rotation = ROTATION.lock().unwrap_or_else(|e| e.into_inner());
+    if rotation.as_ref().is_some_and(|rotation| rotation.shuffle != shuffle || rotation.source != source) {
+        *rotation = None;
+    }
+    rotation.get_or_insert_with(|| Rotation::new(shuffle, source)).next(current)
+}
+
+// 切换到队列中的下一张，返回 false 表示没有可以切换的图片
+fn advance(app: &tauri::AppHandle) -> Result<bool> {
+    let current = current_file(app);
+    let Some(path) = next_path(current.as_deref())? else {
+        match source() {
+            SOURCE_FAVORITES => warn!("Slideshow has no favorite wallpapers to show"),
+            SOURCE_FOLDER => warn!("Slideshow found no images in the local folder"),
+            _ => warn!("Slideshow has no cached wallpapers to show"),
+        }
+        return Ok(false);
+    };
+    apply_cached_wallpaper(app, &path)?;
+    Ok(true)
+}
+
+// “下一张”已位于最新一张时调用：不在轮播模式时同样按轮播的设置选择下一张
+pub(crate) fn next(app: &tauri::AppHandle) -> Result<()> {
+    if advance(app)? {
+        Ok(())
+    } else {
+        Err(AppError::Config(tr("没有可以切换的壁纸").to_string()))
+    }
+}
+
+// 启动轮播任务，立即切换一张，之后按设置的间隔轮换
+pub(crate) fn start(app: tauri::AppHandle, startup_delay: Option<Duration>) -> RefreshTask {
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+
+    let handle = tauri::async_runtime::spawn(async move {
+        if let Some(delay) = startup_delay.filter(|delay| !delay.is_zero()) {
+            info!("Delaying slideshow by {}s", delay.as_secs());
+            set_next_refresh(&app, delay);
+            tokio::select! {
+                _ = token.cancelled() => return,
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+
+        let schedule = Schedule::Interval(interval());
+        let (shuffle, source) = (is_shuffle(), source());
+        *ROTATION.lock().unwrap_or_else(|e| e.into_inner()) = Some(Rotation::new(shuffle, source));
+        info!("Slideshow started ({}, {})", if shuffle { ORDER_SHUFFLE } else { ORDER_SEQUENTIAL }, source);
+        while !token.is_cancelled() {
+            run_guarded(&app, "壁纸轮播", || {
+                if let Err(e) = advance(&app) {
+                    report_error(&app, "壁纸轮播失败", &e);
+                }
+            });
+            set_next_refresh(&app, schedule.next_delay(chrono::Local::now()));
+            if schedule.wait_for_next(&token).await.is_none() {
+                break;
+            }
+        }
+        info!("Slideshow stopped");
+    });
+
+    (handle, cancel)
+}
@@ -0,0 +1,375 @@
+// 壁纸元信息数据库：每张见过的壁纸一行，记录标题、版权、地址、本地路径，以及是否在历史记录、
+// 收藏和黑名单中。历史记录、收藏和黑名单都读写这里，首次打开时导入旧版本的 JSON 文件。
+// 另有一张表按顺序记录设置过的壁纸文件，供“上一张 / 下一张”使用
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use chrono::Local;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Deserialize;
+
+use crate::{cache, get_app_data_dir, get_wallpaper_dir, Result, WallpaperInfo};
+
+const DATABASE_FILE_NAME: &str = "wallpapers.db";
+// 版本 2 增加了 applied 表，版本 3 增加了 description 列，版本 4 增加了 content_hash 列；
+// 建表语句都带 IF NOT EXISTS，升级时重新执行即可，已有的表另外补上新增的列
+const SCHEMA_VERSION: i32 = 4;
+// 托盘程序和命令行模式可能同时写入，等待对方的事务结束
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS wallpapers (
+    file_name TEXT PRIMARY KEY,
+    url TEXT NOT NULL DEFAULT '',
+    title TEXT NOT NULL DEFAULT '',
+    date TEXT NOT NULL DEFAULT '',
+    copyright TEXT NOT NULL DEFAULT '',
+    sha256 TEXT NOT NULL DEFAULT '',
+    description TEXT NOT NULL DEFAULT '',
+    content_hash TEXT NOT NULL DEFAULT '',
+    path TEXT,
+    downloaded_at INTEGER,
+    history_at INTEGER,
+    favorited_at INTEGER,
+    blocked_at INTEGER
+);
+CREATE INDEX IF NOT EXISTS wallpapers_date ON wallpapers (date);
+CREATE TABLE IF NOT EXISTS applied (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    path TEXT NOT NULL,
+    applied_at INTEGER NOT NULL
+);
+";
+
+const UPSERT: &str = "
+INSERT INTO wallpapers (file_name, url, title, date, copyright, sha256, description) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+ON CONFLICT (file_name) DO UPDATE SET
+    url = excluded.url, title = excluded.title, date = excluded.date,
+    copyright = excluded.copyright, sha256 = excluded.sha256,
+    description = COALESCE(NULLIF(excluded.description, ''), description)
+";
+
+const ADD_DESCRIPTION: &str = "ALTER TABLE wallpapers ADD COLUMN description TEXT NOT NULL DEFAULT ''";
+const ADD_CONTENT_HASH: &str = "ALTER TABLE wallpapers ADD COLUMN content_hash TEXT NOT NULL DEFAULT ''";
+// 旧表补上 content_hash 列之后才能建索引，不放在 SCHEMA 中
+const CONTENT_HASH_INDEX: &str = "CREATE INDEX IF NOT EXISTS wallpapers_content_hash ON wallpapers (content_hash)";
+
+const COLUMNS: &str = "file_name, url, title, date, copyright, sha256, description";
+
+// 历史记录、收藏和黑名单分别用一列时间戳（毫秒）标记，为空表示不在其中
+#[derive(Clone, Copy)]
+pub(crate) enum Mark {
+    History,
+    Favorite,
+    Blocked,
+}
+
+impl Mark {
+    fn column(&self) -> &'static str {
+        match self {
+            Mark::History => "history_at",
+            Mark::Favorite => "favorited_at",
+            Mark::Blocked => "blocked_at",
+        }
+    }
+
+    // 历史记录按日期倒序，收藏最新的在前，黑名单按加入顺序
+    fn order(&self) -> &'static str {
+        match self {
+            Mark::History => "REPLACE(date, '-', '') DESC",
+            Mark::Favorite => "favorited_at DESC",
+            Mark::Blocked => "blocked_at ASC",
+        }
+    }
+
+    fn legacy_file(&self) -> &'static str {
+        match self {
+            Mark::History => "history.json",
+            Mark::Favorite => "favorites.json",
+            Mark::Blocked => "blocklist.json",
+        }
+    }
+}
+
+// 旧版本的 history.json、favorites.json、blocklist.json 格式相同
+#[derive(Deserialize)]
+struct LegacyFile {
+    entries: Vec<WallpaperInfo>,
+}
+
+fn open() -> Result<Connection> {
+    let mut conn = Connection::open(get_app_data_dir()?.join(DATABASE_FILE_NAME))?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version < SCHEMA_VERSION {
+        let tx = conn.transaction()?;
+        tx.execute_batch(SCHEMA)?;
+        // 新建的表已经带有新增的列
+        if (1..3).contains(&version) {
+            tx.execute_batch(ADD_DESCRIPTION)?;
+        }
+        if (1..4).contains(&version) {
+            tx.execute_batch(ADD_CONTENT_HASH)?;
+        }
+        tx.execute_batch(CONTENT_HASH_INDEX)?;
+        let imported = import_legacy_files(&tx);
+        tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        tx.commit()?;
+        // 提交成功后再改名，避免导入的数据没写进数据库文件却已被改名
+        for path in imported {
+            if let Err(e) = fs::rename(&path, path.with_extension("json.migrated")) {
+                warn!("Failed to rename {:?}: {}", path, e);
+            }
+        }
+    }
+    Ok(conn)
+}
+
+fn to_info(row: &Row) -> rusqlite::Result<WallpaperInfo> {
+    Ok(WallpaperInfo {
+        file_name: row.get(0)?,
+        url: row.get(1)?,
+        title: row.get(2)?,
+        date: row.get(3)?,
+        copyright: row.get(4)?,
+        sha256: row.get(5)?,
+        description: row.get(6)?,
+    })
+}
+
+fn upsert(conn: &Connection, info: &WallpaperInfo) -> Result<()> {
+    conn.execute(UPSERT, params![info.file_name, info.url, info.title, info.date, info.copyright, info.sha256, info.description])?;
+    Ok(())
+}
+
+// 导入失败的文件保留原样并记录日志，不影响数据库的创建；返回成功导入的文件，由调用方改名避免再次导入
+fn import_legacy_files(conn: &Connection) -> Vec<PathBuf> {
+    let mut imported = Vec::new();
+    for mark in [Mark::History, Mark::Favorite, Mark::Blocked] {
+        match import_legacy_file(conn, mark) {
+            Ok(Some(path)) => imported.push(path),
+            Ok(None) => {}
+            Err(e) => warn!("Failed to import {}: {}", mark.legacy_file(), e),
+        }
+    }
+    if let Err(e) = import_downloaded_files(conn) {
+        warn!("Failed to record downloaded wallpapers: {}", e);
+    }
+    imported
+}
+
+fn import_legacy_file(conn: &Connection, mark: Mark) -> Result<Option<PathBuf>> {
+    let path = get_app_data_dir()?.join(mark.legacy_file());
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let legacy: LegacyFile = serde_json::from_str(&fs::read_to_string(&path)?)?;
+    save_marked_in(conn, mark, &legacy.entries)?;
+    info!("Imported {} entries from {:?}", legacy.entries.len(), path);
+    Ok(Some(path))
+}
+
+// 保存目录中已有的图片按文件名补上本地路径
+fn import_downloaded_files(conn: &Connection) -> Result<()> {
+    for entry in fs::read_dir(get_wallpaper_dir()?)? {
+        let path = entry?.path();
+        if !path.is_file() || !cache::is_image(&path) {
+            continue;
+        }
+        let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        conn.execute(
+            "UPDATE wallpapers SET path = ?1 WHERE file_name = ?2",
+            params![path.to_string_lossy().into_owned(), file_name],
+        )?;
+    }
+    Ok(())
+}
+
+pub(crate) fn load_marked(mark: Mark) -> Result<Vec<WallpaperInfo>> {
+    let conn = open()?;
+    let sql = format!("SELECT {} FROM wallpapers WHERE {} IS NOT NULL ORDER BY {}", COLUMNS, mark.column(), mark.order());
+    let mut statement = conn.prepare(&sql)?;
+    let entries = statement.query_map([], to_info)?.collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(entries)
+}
+
+// 用 entries 整体替换某个标记下的壁纸；已在其中的保留原来的时间，新加入的记为当前时间，
+// entries 中靠前的新条目时间更晚
+fn save_marked_in(conn: &Connection, mark: Mark, entries: &[WallpaperInfo]) -> Result<()> {
+    let column = mark.column();
+    let existing: HashMap<String, i64> = conn
+        .prepare(&format!("SELECT file_name, {0} FROM wallpapers WHERE {0} IS NOT NULL", column))?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    conn.execute(&format!("UPDATE wallpapers SET {} = NULL", column), [])?;
+    let now = Local::now().timestamp_millis();
+    for (index, entry) in entries.iter().enumerate() {
+        upsert(conn, entry)?;
+        let marked_at = existing.get(&entry.file_name).copied().unwrap_or(now - index as i64);
+        conn.execute(
+            &format!("UPDATE wallpapers SET {} = ?1 WHERE file_name = ?2", column),
+            params![marked_at, entry.file_name],
+        )?;
+    }
+    Ok(())
+}
+
+pub(crate) fn save_marked(mark: Mark, entries: &[WallpaperInfo]) -> Result<()> {
+    let mut conn = open()?;
+    let tx = conn.transaction()?;
+    save_marked_in(&tx, mark, entries)?;
+    tx.commit()?;
+    Ok(())
+}
+
+// 按文件名查找壁纸信息，不限于历史记录和收藏，下载过的壁纸都能找到
+pub(crate) fn find_by_file(file_name: &str) -> Result<Option<WallpaperInfo>> {
+    let conn = open()?;
+    let sql = format!("SELECT {} FROM wallpapers WHERE file_name = ?1", COLUMNS);
+    Ok(conn.query_row(&sql, [file_name], to_info).optional()?)
+}
+
+// 开始下载前记录壁纸信息（还没有本地路径），下载中断后遗留的临时文件据此认出是本应用的
+pub(crate) fn record_pending(info: &WallpaperInfo) -> Result<()> {
+    let conn = open()?;
+    upsert(&conn, info)
+}
+
+// content_hash 是下载内容的 SHA-256，与接口提供的 sha256 不同，所有来源的图片都有
+pub(crate) fn record_download(info: &WallpaperInfo, path: &Path, content_hash: &str) -> Result<()> {
+    let conn = open()?;
+    upsert(&conn, info)?;
+    conn.execute(
+        "UPDATE wallpapers SET path = ?1, downloaded_at = ?2, content_hash = ?3 WHERE file_name = ?4",
+        params![path.to_string_lossy().into_owned(), Local::now().timestamp_millis(), content_hash, info.file_name],
+    )?;
+    Ok(())
+}
+
+// 内容与 file_name 相同、本地文件还在的另一张壁纸，最早下载的优先
+pub(crate) fn find_duplicate(content_hash: &str, file_name: &str) -> Result<Option<PathBuf>> {
+    let conn = open()?;
+    let mut statement = conn.prepare(
+        "SELECT path FROM wallpapers
+         WHERE content_hash = ?1 AND file_name <> ?2 AND path IS NOT NULL
+         ORDER BY downloaded_at ASC",
+    )?;
+    let paths = statement
+        .query_map(params![content_hash, file_name], |row| Ok(PathBuf::from(row.get::<_, String>(0)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(paths.into_iter().find(|path| path.is_file()))
+}
+
+// 与更早下载的壁纸内容相同的本地文件，随机挑选和轮播时跳过，同一张图片不会连着出现两次
+pub(crate) fn duplicate_paths() -> Result<HashSet<PathBuf>> {
+    let conn = open()?;
+    let mut statement = conn.prepare(
+        "SELECT path FROM wallpapers AS w
+         WHERE path IS NOT NULL AND content_hash <> '' AND EXISTS (
+             SELECT 1 FROM wallpapers AS o
+             WHERE o.content_hash = w.content_hash AND o.path IS NOT NULL
+               AND (o.downloaded_at < w.downloaded_at OR (o.downloaded_at = w.downloaded_at AND o.file_name < w.file_name))
+         )",
+    )?;
+    let paths = statement
+        .query_map([], |row| Ok(PathBuf::from(row.get::<_, String>(0)?)))?
+        .collect::<rusqlite::Result<HashSet<_>>>()?;
+    Ok(paths)
+}
+
+// 与 path 内容相同的其它本地文件（去重时用硬链接共用内容的文件）
+pub(crate) fn same_content_paths(path: &Path) -> Result<Vec<PathBuf>> {
+    let conn = open()?;
+    let mut statement = conn.prepare(
+        "SELECT o.path FROM wallpapers AS w JOIN wallpapers AS o ON o.content_hash = w.content_hash
+         WHERE w.path = ?1 AND w.content_hash <> '' AND o.path IS NOT NULL AND o.path <> w.path",
+    )?;
+    let paths = statement
+        .query_map([path.to_string_lossy().into_owned()], |row| Ok(PathBuf::from(row.get::<_, String>(0)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(paths)
+}
+
+// 修改保存目录后文件被移动到 path
+pub(crate) fn record_moved(path: &Path) -> Result<()> {
+    let conn = open()?;
+    let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    conn.execute(
+        "UPDATE applied SET path = ?1 WHERE path = (SELECT path FROM wallpapers WHERE file_name = ?2)",
+        params![path.to_string_lossy().into_owned(), file_name],
+    )?;
+    conn.execute(
+        "UPDATE wallpapers SET path = ?1 WHERE file_name = ?2",
+        params![path.to_string_lossy().into_owned(), file_name],
+    )?;
+    Ok(())
+}
+
+// 文件被缓存清理删除后清空路径，元信息和收藏等标记保留
+pub(crate) fn record_removed(path: &Path) -> Result<()> {
+    let conn = open()?;
+    conn.execute("UPDATE wallpapers SET path = NULL WHERE path = ?1", [path.to_string_lossy().into_owned()])?;
+    Ok(())
+}
+
+// 按关键词匹配标题和版权，按日期倒序返回，附带本地是否已有图片；被跳过的壁纸不出现在结果中
+pub(crate) fn search(keyword: &str, limit: usize) -> Result<Vec<(WallpaperInfo, bool)>> {
+    let conn = open()?;
+    let pattern = format!("%{}%", keyword.trim().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+    let sql = format!(
+        "SELECT {}, path IS NOT NULL FROM wallpapers
+         WHERE blocked_at IS NULL AND (title LIKE ?1 ESCAPE '\\' OR copyright LIKE ?1 ESCAPE '\\')
+         ORDER BY {} LIMIT ?2",
+        COLUMNS,
+        Mark::History.order()
+    );
+    let mut statement = conn.prepare(&sql)?;
+    let results = statement
+        .query_map(params![pattern, limit as i64], |row| Ok((to_info(row)?, row.get(7)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(results)
+}
+
+// 最近已下载、带版权信息的 limit 张壁纸（不含黑名单），按日期倒序；猜地点小游戏从中出题
+pub(crate) fn recent_downloaded(limit: usize) -> Result<Vec<WallpaperInfo>> {
+    let conn = open()?;
+    let sql = format!(
+        "SELECT {} FROM wallpapers
+         WHERE blocked_at IS NULL AND path IS NOT NULL AND copyright <> ''
+         ORDER BY {} LIMIT ?1",
+        COLUMNS,
+        Mark::History.order()
+    );
+    let mut statement = conn.prepare(&sql)?;
+    let results = statement.query_map([limit as i64], to_info)?.collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(results)
+}
+
+// 最近设置过的 limit 张壁纸文件，最早的在前
+pub(crate) fn load_applied(limit: usize) -> Result<Vec<PathBuf>> {
+    let conn = open()?;
+    let mut statement = conn.prepare("SELECT path FROM applied ORDER BY id DESC LIMIT ?1")?;
+    let mut paths = statement
+        .query_map([limit as i64], |row| Ok(PathBuf::from(row.get::<_, String>(0)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    paths.reverse();
+    Ok(paths)
+}
+
+// 追加一条设置记录，只保留最近的 keep 条
+pub(crate) fn record_applied(path: &Path, keep: usize) -> Result<()> {
+    let conn = open()?;
+    conn.execute(
+        "INSERT INTO applied (path, applied_at) VALUES (?1, ?2)",
+        params![path.to_string_lossy().into_owned(), Local::now().timestamp_millis()],
+    )?;
+    conn.execute(
+        "DELETE FROM applied WHERE id NOT IN (SELECT id FROM applied ORDER BY id DESC LIMIT ?1)",
+        [keep as i64],
+    )?;
+    Ok(())
+}
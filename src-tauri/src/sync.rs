@@ -0,0 +1,466 @@
+// 多台电脑之间同步收藏、跳过列表和设置。可以手动导出、导入同步文件，
+// 也可以在设置中指定一个由 Dropbox、OneDrive 等同步的文件夹，程序在其中读写同一个同步文件：
+// 本机修改后写入，定时检查其它电脑写入的新内容。保存路径、代理等只属于本机的设置不参与同步
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::Manager;
+use tauri_plugin_dialog::DialogExt;
+
+use crate::{
+    atomic_file, favorites::Favorites, get_app_data_dir, i18n::{tr, tr_with}, is_plain_file_name, load_config, lock_state, notify, report_error, run_guarded,
+    schedule_menu_update, settings, slideshow, store, AppError, AppState, Result, WallpaperInfo,
+};
+
+const SYNC_FILE_NAME: &str = "bing-wallpaper-sync.json";
+const LOCK_FILE_NAME: &str = ".bing-wallpaper-sync.lock";
+// 应用数据目录中记录上次读到或写入的同步文件时间，用来判断其它电脑是否写入过
+const SEEN_FILE_NAME: &str = "sync_seen.txt";
+// 上次读到或写入的同步文件中的设置，写入前据此区分哪些设置是其它电脑改的、哪些是本机改的
+const BASE_FILE_NAME: &str = "sync_base.json";
+const FORMAT_VERSION: u32 = 1;
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+// 文件锁被占用时的重试间隔和最长等待时间
+const RETRY_INTERVAL: Duration = Duration::from_millis(200);
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+// 只属于本机的设置，导出和同步时去掉
+const LOCAL_SETTINGS: [&str; 13] = [
+    "save_dir",
+    "local_folder",
+    "watermark_font",
+    "post_change_hook",
+    "proxy_mode",
+    "proxy_host",
+    "proxy_port",
+    "proxy_username",
+    "proxy_password",
+    "control_api_enabled",
+    "control_api_port",
+    "control_api_token",
+    "sync_folder",
+];
+
+// 应用同步文件期间保存设置不再回写，避免读写同一个文件时互相等待
+static APPLYING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize, Deserialize)]
+struct Bundle {
+    version: u32,
+    modified_at: String,
+    settings: Map<String, Value>,
+    favorites: Vec<WallpaperInfo>,
+    blocklist: Vec<WallpaperInfo>,
+}
+
+// 本机参与同步的设置
+fn local_settings(app: &tauri::AppHandle) -> Result<Map<String, Value>> {
+    let mut settings = match serde_json::to_value(settings::get_settings(app.state::<Mutex<AppState>>())?)? {
+        Value::Object(settings) => settings,
+        _ => Map::new(),
+    };
+    for field in LOCAL_SETTINGS {
+        settings.remove(field);
+    }
+    Ok(settings)
+}
+
+fn collect(app: &tauri::AppHandle) -> Result<Bundle> {
+    Ok(Bundle {
+        version: FORMAT_VERSION,
+        modified_at: chrono::Local::now().to_rfc3339(),
+        settings: local_settings(app)?,
+        favorites: store::load_marked(store::Mark::Favorite)?,
+        blocklist: store::load_marked(store::Mark::Blocked)?,
+    })
+}
+
+fn parse(contents: &str) -> Result<Bundle> {
+    let bundle: Bundle = serde_json::from_str(contents)?;
+    if bundle.version > FORMAT_VERSION {
+        return Err(AppError::Config(tr_with("不支持的同步文件版本: {}", bundle.version)));
+    }
+    Ok(bundle)
+}
+
+// 本机的条目在前，再加上对方有而本机没有的
+fn union(local: Vec<WallpaperInfo>, remote: &[WallpaperInfo]) -> Vec<WallpaperInfo> {
+    let mut merged = local;
+    for entry in remote {
+        if !merged.iter().any(|local| local.file_name == entry.file_name) {
+            merged.push(entry.clone());
+        }
+    }
+    merged
+}
+
+// 同步文件可能来自共享文件夹中的其它人，文件名不是单独的一段（例如 ../ 或绝对路径）的条目丢弃
+fn valid_entries(entries: &[WallpaperInfo]) -> Vec<WallpaperInfo> {
+    entries
+        .iter()
+        .filter(|entry| {
+            let valid = is_plain_file_name(&entry.file_name);
+            if !valid {
+                warn!("Ignored synced entry with invalid file name {:?}", entry.file_name);
+            }
+            valid
+        })
+        .cloned()
+        .collect()
+}
+
+// merge 为 true 时与本机的收藏和跳过列表合并，否则以同步文件为准（其它电脑上的删除也会生效）
+fn apply_lists(app: &tauri::AppHandle, bundle: &Bundle, merge: bool) -> Result<()> {
+    for (mark, remote) in [(store::Mark::Favorite, &bundle.favorites), (store::Mark::Blocked, &bundle.blocklist)] {
+        let remote = valid_entries(remote);
+        let entries = if merge { union(store::load_marked(mark)?, &remote) } else { remote };
+        store::save_marked(mark, &entries)?;
+    }
+    lock_state(&app.state::<Mutex<AppState>>()).favorites = Favorites::load();
+    schedule_menu_update(app);
+    Ok(())
+}
+
+// 同步文件中的设置逐项覆盖本机的同名设置，按设置窗口的规则校验，本机用不了的值（例如其它电脑上新版本才有的选项）跳过并保留本机的值，
+// 其余照常生效；没有变化时不保存
+fn apply_settings(app: &tauri::AppHandle, remote: &Map<String, Value>) -> Result<()> {
+    let state = app.state::<Mutex<AppState>>();
+    let Value::Object(local) = serde_json::to_value(settings::get_settings(state.clone())?)? else {
+        return Ok(());
+    };
+    let validate = |candidate: &Map<String, Value>| {
+        serde_json::from_value(Value::Object(candidate.clone())).map_err(AppError::from).and_then(|settings| settings::validate(&settings))
+    };
+    let Some(merged) = merge_settings(local, remote, validate) else {
+        return Ok(());
+    };
+    let settings = serde_json::from_value(Value::Object(merged))?;
+    APPLYING.store(true, Ordering::SeqCst);
+    let result = settings::save_settings(app.clone(), state, settings);
+    APPLYING.store(false, Ordering::SeqCst);
+    result
+}
+
+// 把 remote 中的设置逐项合并到 local，每一项合并后由 validate 检查整体是否有效；返回合并结果，没有变化时返回 None
+fn merge_settings(
+    mut merged: Map<String, Value>,
+    remote: &Map<String, Value>,
+    validate: impl Fn(&Map<String, Value>) -> Result<()>,
+) -> Option<Map<String, Value>> {
+    let mut changed = false;
+    for (field, value) in remote {
+        if LOCAL_SETTINGS.contains(&field.as_str()) {
+            continue;
+        }
+        let Some(local) = merged.get(field) else {
+            continue;
+        };
+        let mut value = value.clone();
+        // 本地文件夹只属于本机，本机没有选择文件夹时改为轮播全部壁纸
+        if field == "slideshow_source"
+            && value.as_str() == Some(slideshow::SOURCE_FOLDER)
+            && merged.get("local_folder").and_then(Value::as_str).is_none_or(str::is_empty)
+        {
+            value = Value::from(slideshow::SOURCE_ALL);
+        }
+        if *local == value {
+            continue;
+        }
+        let mut candidate = merged.clone();
+        candidate.insert(field.clone(), value);
+        match validate(&candidate) {
+            Ok(()) => {
+                merged = candidate;
+                changed = true;
+            }
+            Err(e) => warn!("Skipped synced setting {}: {}", field, e),
+        }
+    }
+    changed.then_some(merged)
+}
+
+// 同步文件中相对上次同步被其它电脑改过的设置；本机也改过的以本机为准。
+// 没有上次同步的记录时无法区分，同步文件中与本机不同的设置都算作对方的修改
+fn remote_changes(remote: &Map<String, Value>, base: Option<&Map<String, Value>>, local: &Map<String, Value>) -> Map<String, Value> {
+    remote
+        .iter()
+        .filter(|(field, value)| match base {
+            Some(base) => base.get(*field) != Some(*value) && local.get(*field) == base.get(*field),
+            None => local.get(*field) != Some(*value),
+        })
+        .map(|(field, value)| (field.clone(), value.clone()))
+        .collect()
+}
+
+fn apply(app: &tauri::AppHandle, bundle: &Bundle, merge: bool) -> Result<()> {
+    apply_lists(app, bundle, merge)?;
+    apply_settings(app, &bundle.settings)
+}
+
+fn sync_folder() -> Result<Option<PathBuf>> {
+    let folder = load_config()?.sync_folder;
+    Ok((!folder.is_empty()).then(|| PathBuf::from(folder)))
+}
+
+// 同一台电脑上的多个进程通过文件锁排队；不同电脑之间依靠先写临时文件再改名，对方不会读到写了一半的文件
+fn lock(folder: &Path) -> Result<File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(folder.join(LOCK_FILE_NAME))?;
+    let started = Instant::now();
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(file),
+            Err(TryLockError::WouldBlock) if started.elapsed() < LOCK_TIMEOUT => thread::sleep(RETRY_INTERVAL),
+            Err(TryLockError::WouldBlock) => return Err(AppError::Config(tr("同步文件夹正被占用，请稍后再试").to_string())),
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+    }
+}
+
+fn read_bundle(path: &Path) -> Result<Option<Bundle>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(parse(&contents)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_bundle(path: &Path, bundle: &Bundle) -> Result<()> {
+    atomic_file::write(path, serde_json::to_string_pretty(bundle)?)
+}
+
+fn read_seen() -> Result<Option<String>> {
+    match fs::read_to_string(get_app_data_dir()?.join(SEEN_FILE_NAME)) {
+        Ok(seen) => Ok(Some(seen.trim().to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn read_base() -> Result<Option<Map<String, Value>>> {
+    match fs::read_to_string(get_app_data_dir()?.join(BASE_FILE_NAME)) {
+        Ok(contents) => Ok(serde_json::from_str(&contents).ok()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// 记录已经同步过的同步文件：修改时间和其中的设置
+fn write_seen(bundle: &Bundle) -> Result<()> {
+    let dir = get_app_data_dir()?;
+    atomic_file::write(&dir.join(BASE_FILE_NAME), serde_json::to_string(&bundle.settings)?)?;
+    atomic_file::write(&dir.join(SEEN_FILE_NAME), &bundle.modified_at)
+}
+
+// 更换同步文件夹后调用：下次同步时把本机内容与新文件夹中的合并，而不是直接被覆盖
+pub(crate) fn forget_seen() -> Result<()> {
+    let dir = get_app_data_dir()?;
+    for name in [SEEN_FILE_NAME, BASE_FILE_NAME] {
+        match fs::remove_file(dir.join(name)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// 读取其它电脑写入的新内容；本机从未同步过时与之合并，之后以同步文件为准
+fn pull(app: &tauri::AppHandle) -> Result<()> {
+    let Some(folder) = sync_folder()? else {
+        return Ok(());
+    };
+    let _lock = lock(&folder)?;
+    let Some(remote) = read_bundle(&folder.join(SYNC_FILE_NAME))? else {
+        return Ok(());
+    };
+    let seen = read_seen()?;
+    if seen.as_deref() == Some(remote.modified_at.as_str()) {
+        return Ok(());
+    }
+    info!("Applying sync file written at {}", remote.modified_at);
+    apply(app, &remote, seen.is_none())?;
+    write_seen(&remote)
+}
+
+// 把本机的内容写入同步文件；其它电脑在本机上次读取后写入过时，先合并对方新增的收藏和跳过，
+// 再应用对方修改过而本机没有改动的设置，避免覆盖对方的修改
+fn push(app: &tauri::AppHandle) -> Result<()> {
+    let Some(folder) = sync_folder()? else {
+        return Ok(());
+    };
+    fs::create_dir_all(&folder)?;
+    let _lock = lock(&folder)?;
+    let path = folder.join(SYNC_FILE_NAME);
+    if let Some(remote) = read_bundle(&path)? {
+        if read_seen()?.as_deref() != Some(remote.modified_at.as_str()) {
+            apply_lists(app, &remote, true)?;
+            let changes = remote_changes(&remote.settings, read_base()?.as_ref(), &local_settings(app)?);
+            apply_settings(app, &changes)?;
+        }
+    }
+    let bundle = collect(app)?;
+    write_bundle(&path, &bundle)?;
+    write_seen(&bundle)?;
+    info!("Wrote sync file {:?}", path);
+    Ok(())
+}
+
+// 收藏、跳过或设置变化后调用，在后台写入同步文件；未配置同步文件夹时什么也不做
+pub(crate) fn push_in_background(app: &tauri::AppHandle) {
+    if APPLYING.load(Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    thread::spawn(move || {
+        run_guarded(&app, "同步", || {
+            if let Err(e) = push(&app) {
+                warn!("Failed to write sync file: {}", e);
+            }
+        })
+    });
+}
+
+// 启动时调用：先读取一次，之后定时检查；同步文件夹暂时不可用时只记录日志
+pub(crate) fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || loop {
+        run_guarded(&app, "同步", || {
+            if let Err(e) = pull(&app) {
+                warn!("Failed to read sync file: {}", e);
+            }
+        });
+        thread::sleep(POLL_INTERVAL);
+    });
+}
+
+// 菜单操作：把收藏、跳过列表和设置导出为同步文件
+pub(crate) fn export_file(app: &tauri::AppHandle) {
+    let handler = {
+        let app = app.clone();
+        move |path: Option<tauri_plugin_dialog::FilePath>| {
+            let Some(path) = path.and_then(|path| path.into_path().ok()) else {
+                return;
+            };
+            thread::spawn(move || {
+                run_guarded(&app, "导出收藏和设置", || match collect(&app).and_then(|bundle| write_bundle(&path, &bundle)) {
+                    Ok(()) => {
+                        info!("Exported favorites and settings to {:?}", path);
+                        notify(&app, tr("导出完成"), &tr_with("已导出收藏和设置到 {}", path.display()));
+                    }
+                    Err(e) => report_error(&app, "导出收藏和设置失败", &e),
+                })
+            });
+        }
+    };
+    app.dialog().file().add_filter("JSON", &["json"]).set_file_name(SYNC_FILE_NAME).save_file(handler);
+}
+
+// 菜单操作：导入其它电脑导出的同步文件，收藏和跳过列表与本机合并，设置以文件为准
+pub(crate) fn import_file(app: &tauri::AppHandle) {
+    let handler = {
+        let app = app.clone();
+        move |path: Option<tauri_plugin_dialog::FilePath>| {
+            let Some(path) = path.and_then(|path| path.into_path().ok()) else {
+                return;
+            };
+            thread::spawn(move || {
+                run_guarded(&app, "导入收藏和设置", || {
+                    let result = fs::read_to_string(&path).map_err(AppError::from).and_then(|contents| parse(&contents));
+                    match result.and_then(|bundle| apply(&app, &bundle, true)) {
+                        Ok(()) => {
+                            info!("Imported favorites and settings from {:?}", path);
+                            notify(&app, tr("导入完成"), &tr_with("已导入 {} 中的收藏和设置", path.display()));
+                            push_in_background(&app);
+                        }
+                        Err(e) => report_error(&app, "导入收藏和设置失败", &e),
+                    }
+                })
+            });
+        }
+    };
+    app.dialog().file().add_filter("JSON", &["json"]).pick_file(handler);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(file_name: &str, title: &str) -> WallpaperInfo {
+        WallpaperInfo { file_name: file_name.to_string(), title: title.to_string(), ..Default::default() }
+    }
+
+    fn map(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_union() {
+        let local = vec![entry("a.jpg", "local"), entry("b.jpg", "")];
+        let merged = union(local, &[entry("a.jpg", "remote"), entry("c.jpg", "")]);
+        let names: Vec<&str> = merged.iter().map(|entry| entry.file_name.as_str()).collect();
+        assert_eq!(names, ["a.jpg", "b.jpg", "c.jpg"]);
+        // 两边都有的以本机为准
+        assert_eq!(merged[0].title, "local");
+    }
+
+    #[test]
+    fn test_valid_entries() {
+        let entries = [entry("a.jpg", ""), entry("../a.jpg", ""), entry("/etc/passwd", ""), entry("dir/a.jpg", ""), entry("..", ""), entry("", "")];
+        let valid = valid_entries(&entries);
+        assert_eq!(valid.len(), 1);
+        assert_eq!(valid[0].file_name, "a.jpg");
+    }
+
+    #[test]
+    fn test_remote_changes() {
+        let base = map(json!({ "market": "en-US", "resolution": "UHD", "language": "zh-CN" }));
+        // 本机改了 resolution，对方改了 market 和 resolution
+        let local = map(json!({ "market": "en-US", "resolution": "1920x1080", "language": "zh-CN" }));
+        let remote = map(json!({ "market": "ja-JP", "resolution": "auto", "language": "zh-CN" }));
+        assert_eq!(remote_changes(&remote, Some(&base), &local), map(json!({ "market": "ja-JP" })));
+        // 没有上次同步的记录时，与本机不同的都算对方的修改
+        assert_eq!(remote_changes(&remote, None, &local), map(json!({ "market": "ja-JP", "resolution": "auto" })));
+    }
+
+    #[test]
+    fn test_merge_settings() {
+        let local = map(json!({ "market": "en-US", "resolution": "UHD", "proxy_host": "local", "local_folder": "" }));
+        let remote = map(json!({ "market": "ja-JP", "resolution": "800x600", "proxy_host": "remote", "unknown_field": true }));
+        let validate = |candidate: &Map<String, Value>| match candidate["resolution"].as_str() {
+            Some("800x600") => Err(AppError::Config("unsupported".to_string())),
+            _ => Ok(()),
+        };
+        let merged = merge_settings(local.clone(), &remote, validate).unwrap();
+        // 无效的值和只属于本机的设置保留本机的值，本机没有的字段忽略
+        assert_eq!(merged, map(json!({ "market": "ja-JP", "resolution": "UHD", "proxy_host": "local", "local_folder": "" })));
+        assert!(merge_settings(local.clone(), &local, |_| Ok(())).is_none());
+    }
+
+    #[test]
+    fn test_merge_settings_without_local_folder() {
+        let local = map(json!({ "slideshow_source": "favorites", "local_folder": "" }));
+        let remote = map(json!({ "slideshow_source": "folder" }));
+        let merged = merge_settings(local, &remote, |_| Ok(())).unwrap();
+        assert_eq!(merged["slideshow_source"], json!("all"));
+
+        let local = map(json!({ "slideshow_source": "all", "local_folder": "/home/user/Pictures" }));
+        let merged = merge_settings(local, &remote, |_| Ok(())).unwrap();
+        assert_eq!(merged["slideshow_source"], json!("folder"));
+    }
+}
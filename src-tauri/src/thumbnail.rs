@@ -0,0 +1,91 @@
+// 缓存壁纸的缩略图：图库窗口只加载 320 像素宽的小图，不必解码完整的 4K 原图。
+// 缩略图保存在应用数据目录的 thumbs/ 下，原图比缩略图新（例如重新下载）时重新生成
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use image::imageops::FilterType;
+use log::{info, warn};
+
+use crate::{cache, get_app_data_dir, i18n::tr_with, processing, run_guarded, AppError, Result};
+
+const THUMBNAIL_DIR: &str = "thumbs";
+const THUMBNAIL_WIDTH: u32 = 320;
+
+// 原文件名加 .jpg，同名不同格式的图片不会冲突
+fn thumbnail_name(path: &Path) -> Option<String> {
+    Some(format!("{}.jpg", path.file_name()?.to_string_lossy()))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+fn ensure(path: &Path) -> Result<PathBuf> {
+    let name = thumbnail_name(path).ok_or_else(|| AppError::Config(format!("Invalid path: {:?}", path)))?;
+    let thumbnail = get_app_data_dir()?.join(THUMBNAIL_DIR).join(name);
+    if modified(&thumbnail).is_some_and(|time| modified(path).is_some_and(|source| source <= time)) {
+        return Ok(thumbnail);
+    }
+    let image = processing::decode(path)?;
+    let height = (image.height() as u64 * THUMBNAIL_WIDTH as u64 / image.width().max(1) as u64).max(1) as u32;
+    let resized = image::imageops::resize(&image, THUMBNAIL_WIDTH, height, FilterType::Triangle);
+    processing::save_jpeg(&resized, &thumbnail)?;
+    Ok(thumbnail)
+}
+
+// 为缓存中还没有缩略图的壁纸生成缩略图，删除原图已不在缓存中的缩略图
+fn generate_all() -> Result<()> {
+    let paths = cache::cached_paths()?;
+    let mut generated = 0;
+    for path in &paths {
+        match ensure(path) {
+            Ok(_) => generated += 1,
+            Err(e) => warn!("Failed to generate thumbnail for {:?}: {}", path, e),
+        }
+    }
+    let dir = get_app_data_dir()?.join(THUMBNAIL_DIR);
+    if dir.is_dir() {
+        let expected: HashSet<String> = paths.iter().filter_map(|path| thumbnail_name(path)).collect();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if expected.contains(entry.file_name().to_string_lossy().as_ref()) {
+                continue;
+            }
+            if let Err(e) = fs::remove_file(entry.path()) {
+                warn!("Failed to remove stale thumbnail {:?}: {}", entry.path(), e);
+            }
+        }
+    }
+    info!("Thumbnails ready for {} of {} cached wallpapers", generated, paths.len());
+    Ok(())
+}
+
+// 启动时在后台补齐缩略图，之后由 get_thumbnail 按需生成
+pub(crate) fn generate_in_background(app: &tauri::AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        run_guarded(&app, "生成缩略图", || {
+            if let Err(e) = generate_all() {
+                warn!("Failed to generate thumbnails: {}", e);
+            }
+        })
+    });
+}
+
+// 供图库窗口调用：返回缓存中某张壁纸的 JPEG 缩略图内容，只接受缓存中已有的文件名
+#[tauri::command]
+pub(crate) async fn get_thumbnail(file: String) -> Result<tauri::ipc::Response> {
+    let thumbnail = tauri::async_runtime::spawn_blocking(move || {
+        let path = cache::cached_paths()?
+            .into_iter()
+            .find(|path| path.file_name().is_some_and(|name| name == file.as_str()))
+            .ok_or_else(|| AppError::Config(tr_with("缓存中没有该壁纸: {}", &file)))?;
+        Ok::<_, AppError>(fs::read(ensure(&path)?)?)
+    })
+    .await
+    .map_err(|e| AppError::Config(e.to_string()))??;
+    Ok(tauri::ipc::Response::new(thumbnail))
+}
@@ -0,0 +1,45 @@
+// 托盘图标显示当前壁纸的缩略图：居中裁成正方形后缩小、切圆角，外加一圈描边。
+// 深色菜单栏或任务栏上用浅色描边，浅色上用深色描边，缩略图与底色相近时也能看清轮廓
+use std::path::Path;
+use image::imageops::FilterType;
+use tauri::image::Image;
+
+use crate::{processing, Result};
+
+const ICON_SIZE: u32 = 64;
+const CORNER_RADIUS: f32 = 14.0;
+const BORDER_WIDTH: f32 = 2.0;
+const LIGHT_BORDER: [u8; 4] = [255, 255, 255, 220];
+const DARK_BORDER: [u8; 4] = [0, 0, 0, 160];
+
+// 像素中心到圆角矩形边缘的有向距离，内部为负
+fn edge_distance(x: u32, y: u32) -> f32 {
+    let half = ICON_SIZE as f32 / 2.0;
+    let inner = half - CORNER_RADIUS;
+    let dx = (x as f32 + 0.5 - half).abs() - inner;
+    let dy = (y as f32 + 0.5 - half).abs() - inner;
+    let outside = (dx.max(0.0).powi(2) + dy.max(0.0).powi(2)).sqrt();
+    outside + dx.max(dy).min(0.0) - CORNER_RADIUS
+}
+
+pub(crate) fn render(path: &Path, dark_background: bool) -> Result<Image<'static>> {
+    let image = processing::decode(path)?;
+    let side = image.width().min(image.height());
+    let square = image::imageops::crop_imm(&image, (image.width() - side) / 2, (image.height() - side) / 2, side, side).to_image();
+    let small = image::imageops::resize(&square, ICON_SIZE, ICON_SIZE, FilterType::Triangle);
+    let border = if dark_background { LIGHT_BORDER } else { DARK_BORDER };
+
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for (x, y, pixel) in small.enumerate_pixels() {
+        let distance = edge_distance(x, y);
+        // 边缘按覆盖比例做抗锯齿；inside 为 1 时完全是图片，为 0 时完全是描边
+        let coverage = (0.5 - distance).clamp(0.0, 1.0);
+        let inside = (-(distance + BORDER_WIDTH) + 0.5).clamp(0.0, 1.0);
+        for channel in 0..3 {
+            rgba.push((pixel.0[channel] as f32 * inside + border[channel] as f32 * (1.0 - inside)).round() as u8);
+        }
+        let alpha = 255.0 * inside + border[3] as f32 * (1.0 - inside);
+        rgba.push((alpha * coverage).round() as u8);
+    }
+    Ok(Image::new_owned(rgba, ICON_SIZE, ICON_SIZE))
+}
@@ -0,0 +1,124 @@
+// 应用内更新：启动时和之后每天检查一次发布源，发现新版本后在后台静默下载，退出时自动安装；
+// 托盘菜单显示“新版本可用”，点击可立即安装并重启。更新包用构建时注入的公钥校验签名，
+// 构建时没有提供公钥（例如本地开发构建）时不检查更新
+use std::{sync::Mutex, time::Duration};
+use log::{error, info};
+use tauri::Manager;
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::{
+    i18n::{tr, tr_with},
+    load_config, lock_state, notify, portable, report_error, run_guarded_async, schedule_menu_update, AppError, AppState, Result,
+};
+
+// 更新通道：stable 只接收正式版（默认），beta 同时接收测试版，off 不检查更新
+pub(crate) const CHANNEL_STABLE: &str = "stable";
+pub(crate) const CHANNEL_BETA: &str = "beta";
+pub(crate) const CHANNEL_OFF: &str = "off";
+
+// 发布时由 tauri-action 上传的 latest.json；测试版发布在固定的 beta 标签下
+const STABLE_ENDPOINT: &str = "https://github.com/niumoo/bing-wallpaper-client/releases/latest/download/latest.json";
+const BETA_ENDPOINT: &str = "https://github.com/niumoo/bing-wallpaper-client/releases/download/beta/latest.json";
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+// 启动后稍等再检查，避免与首次刷新壁纸争抢网络
+const STARTUP_DELAY: Duration = Duration::from_secs(60);
+const PUBKEY: Option<&str> = option_env!("BING_WALLPAPER_UPDATER_PUBKEY");
+
+// 已下载、等待安装的更新包
+static PENDING: Mutex<Option<(Update, Vec<u8>)>> = Mutex::new(None);
+
+fn update_error(err: tauri_plugin_updater::Error) -> AppError {
+    AppError::Network { message: err.to_string(), transient: true }
+}
+
+fn endpoint(channel: &str) -> &'static str {
+    if channel == CHANNEL_BETA {
+        BETA_ENDPOINT
+    } else {
+        STABLE_ENDPOINT
+    }
+}
+
+async fn check_and_download(app: &tauri::AppHandle, pubkey: &str) -> Result<()> {
+    let channel = load_config().map(|config| config.update_channel).unwrap_or_default();
+    if channel == CHANNEL_OFF {
+        return Ok(());
+    }
+    let url = tauri::Url::parse(endpoint(&channel)).map_err(|e| AppError::Config(e.to_string()))?;
+    let updater = app
+        .updater_builder()
+        .pubkey(pubkey)
+        .endpoints(vec![url])
+        .map_err(update_error)?
+        .build()
+        .map_err(update_error)?;
+    let Some(update) = updater.check().await.map_err(update_error)? else {
+        info!("No update available on the {} channel", if channel.is_empty() { CHANNEL_STABLE } else { &channel });
+        return Ok(());
+    };
+    let downloaded = PENDING.lock().unwrap_or_else(|e| e.into_inner()).as_ref().map(|(pending, _)| pending.version.clone());
+    if downloaded.as_deref() == Some(update.version.as_str()) {
+        return Ok(());
+    }
+
+    info!("Downloading update {}", update.version);
+    let bytes = update.download(|_, _| {}, || {}).await.map_err(update_error)?;
+    let version = update.version.clone();
+    *PENDING.lock().unwrap_or_else(|e| e.into_inner()) = Some((update, bytes));
+    lock_state(&app.state::<Mutex<AppState>>()).update_available = Some(version.clone());
+    schedule_menu_update(app);
+    notify(app, tr("发现新版本"), &tr_with("Bing Wallpaper {} 已下载，将在退出时自动安装", &version));
+    info!("Update {} downloaded, will be installed on quit", version);
+    Ok(())
+}
+
+// 启动后在后台定期检查更新
+pub(crate) fn start(app: &tauri::AppHandle) {
+    let Some(pubkey) = PUBKEY else {
+        info!("Updater public key not configured, skipping update checks");
+        return;
+    };
+    // 安装包会装到系统目录而不是替换便携版本身，便携模式下不检查更新
+    if portable::is_enabled() {
+        info!("Portable mode, skipping update checks");
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(STARTUP_DELAY).await;
+        loop {
+            run_guarded_async(&app, "检查更新", async {
+                if let Err(e) = check_and_download(&app, pubkey).await {
+                    error!("Failed to check for updates: {}", e);
+                }
+            })
+            .await;
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+// 安装已下载的更新，返回是否安装了更新；Windows 上安装程序启动后当前进程会直接退出
+pub(crate) fn install_pending() -> bool {
+    let Some((update, bytes)) = PENDING.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return false;
+    };
+    info!("Installing update {}", update.version);
+    match update.install(bytes) {
+        Ok(()) => true,
+        Err(e) => {
+            error!("Failed to install update {}: {}", update.version, e);
+            false
+        }
+    }
+}
+
+// 托盘菜单中点击“新版本可用”：安装后按正常退出的流程清理，再重启到新版本
+pub(crate) fn install_now(app: &tauri::AppHandle) {
+    if !install_pending() {
+        report_error(app, "安装更新失败", &AppError::Config(tr("更新包不可用，请稍后重试").to_string()));
+        return;
+    }
+    crate::shutdown(&app.state::<Mutex<AppState>>());
+    app.restart();
+}
@@ -0,0 +1,232 @@
+// 在 wallpaper_core 的各平台实现之上，负责备份和恢复用户原来的壁纸
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use log::{info, warn};
+use wallpaper_core::{detect_setter, resolution_variant, Fit, Monitor, Resolution, WallpaperSetter};
+
+use crate::{appearance, convert, get_app_data_dir, processing, get_wallpaper_dir, load_config, update_config, AppError, Result};
+
+// 原壁纸备份在应用数据目录的子目录中，避免被缓存清理当作旧壁纸删除
+const ORIGINAL_WALLPAPER_DIR: &str = "original";
+// 始终指向当前壁纸的固定路径，供 OBS、终端主题等外部工具引用
+pub(crate) const CURRENT_LINK_NAME: &str = "current.jpg";
+// 最近一次设置的原图路径（处理或调暗前），记录“上一张 / 下一张”时使用
+static LAST_APPLIED: Mutex<Option<PathBuf>> = Mutex::new(None);
+// 最近一次实际设置到桌面的文件（处理、转换后的副本），检测壁纸是否被改掉时与系统当前的壁纸比较；
+// 按显示器分别设置或恢复原壁纸后为 None
+static LAST_DISPLAYED: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+pub(crate) fn set_wallpaper(path: &Path) -> Result<()> {
+    let setter = detect_setter()
+        .ok_or_else(|| AppError::OsSet("Unsupported desktop environment".to_string()))?;
+    if let Err(e) = backup_original(setter.as_ref()) {
+        warn!("Failed to back up original wallpaper: {}", e);
+    }
+    let displayed = match per_monitor_paths(setter.as_ref(), path) {
+        Some(assignments) => {
+            for (monitor, variant) in &assignments {
+                setter.set_monitor(monitor, &display_path(variant))?;
+                info!("Set {:?} on monitor {} ({}x{})", variant.file_name(), monitor.id, monitor.width, monitor.height);
+            }
+            info!("Wallpaper set per monitor via {}", setter.name());
+            None
+        }
+        None => {
+            let display = display_path(path);
+            #[cfg(target_os = "windows")]
+            crate::fade::play_from_current(setter.as_ref(), &display);
+            setter.set(&display)?;
+            info!("Wallpaper set successfully via {}", setter.name());
+            Some(display)
+        }
+    };
+    *LAST_DISPLAYED.lock().unwrap_or_else(|e| e.into_inner()) = displayed;
+    if let Some(fit) = configured_fit() {
+        if let Err(e) = setter.set_fit(fit) {
+            warn!("Failed to set wallpaper fit: {}", e);
+        }
+    }
+    if let Err(e) = update_current_link(path) {
+        warn!("Failed to update {}: {}", CURRENT_LINK_NAME, e);
+    }
+    *LAST_APPLIED.lock().unwrap_or_else(|e| e.into_inner()) = Some(path.to_path_buf());
+    #[cfg(target_os = "windows")]
+    if load_config().map(|config| config.lock_screen_enabled).unwrap_or(false) {
+        if let Err(e) = set_lock_screen(&convert::compatible_path(path)) {
+            warn!("Failed to set lock screen: {}", e);
+        }
+    }
+    Ok(())
+}
+
+// 通过 WinRT 的 LockScreen.SetImageFileAsync 设置当前用户的锁屏图片，不需要管理员权限；同步等待异步操作完成
+#[cfg(target_os = "windows")]
+pub(crate) fn set_lock_screen(path: &Path) -> Result<()> {
+    use windows::{core::HSTRING, Storage::StorageFile, System::UserProfile::LockScreen};
+
+    let os_error = |e: windows::core::Error| AppError::OsSet(format!("Failed to set lock screen: {}", e));
+    // StorageFile 只接受不带 \\?\ 前缀的绝对路径
+    let path = std::path::absolute(path)?;
+    let file = StorageFile::GetFileFromPathAsync(&HSTRING::from(path.as_path())).and_then(|operation| operation.get()).map_err(os_error)?;
+    LockScreen::SetImageFileAsync(&file).and_then(|action| action.get()).map_err(os_error)?;
+    info!("Lock screen set to {:?}", path);
+    Ok(())
+}
+
+// 当前刷新模式设置的显示方式，未设置时返回 None，不改动系统的设置
+fn configured_fit() -> Option<Fit> {
+    let config = load_config().ok()?;
+    config.wallpaper_fit.get(&config.refresh_mode).map(|fit| Fit::from_str(fit))
+}
+
+pub(crate) fn apply_fit(fit: Fit) -> Result<()> {
+    let setter = detect_setter()
+        .ok_or_else(|| AppError::OsSet("Unsupported desktop environment".to_string()))?;
+    setter.set_fit(fit)?;
+    info!("Wallpaper fit set to {} via {}", fit.as_str(), setter.name());
+    Ok(())
+}
+
+// 实际设置到桌面的图片：系统不支持的格式先转成 JPEG，再按设置做模糊、调暗等处理，深色外观下再换成调暗的副本
+fn display_path(path: &Path) -> PathBuf {
+    appearance::display_path(&processing::processed_path(&convert::compatible_path(path)))
+}
+
+pub(crate) fn last_applied() -> Option<PathBuf> {
+    LAST_APPLIED.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub(crate) fn last_displayed() -> Option<PathBuf> {
+    LAST_DISPLAYED.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub(crate) fn current_link_path() -> Result<PathBuf> {
+    Ok(get_app_data_dir()?.join(CURRENT_LINK_NAME))
+}
+
+// 先在旁边生成临时链接（Windows 上创建符号链接需要管理员权限，改为复制文件），
+// 再重命名覆盖，外部工具不会读到不存在或写了一半的文件
+fn update_current_link(path: &Path) -> Result<()> {
+    let link = current_link_path()?;
+    let target = fs::canonicalize(path)?;
+    if target == link {
+        return Ok(());
+    }
+    let temp = link.with_file_name(format!("{}.tmp", CURRENT_LINK_NAME));
+    if fs::symlink_metadata(&temp).is_ok() {
+        fs::remove_file(&temp)?;
+    }
+    #[cfg(windows)]
+    fs::copy(&target, &temp)?;
+    #[cfg(not(windows))]
+    std::os::unix::fs::symlink(&target, &temp)?;
+    fs::rename(&temp, &link)?;
+    info!("Updated {:?} to point at {:?}", link, target);
+    Ok(())
+}
+
+fn is_auto_resolution() -> bool {
+    load_config().map(|config| Resolution::from_str(&config.resolution) == Resolution::Auto).unwrap_or(true)
+}
+
+// 自动分辨率下按显示器列出需要的版本；只有一个显示器或设置了固定分辨率时返回空列表
+fn monitor_assignments(setter: &dyn WallpaperSetter) -> Vec<(Monitor, Resolution)> {
+    if !is_auto_resolution() {
+        return Vec::new();
+    }
+    let monitors = setter.monitors().unwrap_or_else(|e| {
+        warn!("Failed to list monitors: {}", e);
+        Vec::new()
+    });
+    if monitors.len() < 2 {
+        return Vec::new();
+    }
+    monitors
+        .into_iter()
+        .map(|monitor| {
+            let resolution = Resolution::for_size(monitor.width, monitor.height);
+            (monitor, resolution)
+        })
+        .collect()
+}
+
+// 混合分辨率的多显示器环境下，除了主显示器的版本外还需要下载的版本
+pub(crate) fn extra_resolutions(primary: Resolution) -> Vec<Resolution> {
+    let Some(setter) = detect_setter() else {
+        return Vec::new();
+    };
+    let mut resolutions: Vec<Resolution> = Vec::new();
+    for (_, resolution) in monitor_assignments(setter.as_ref()) {
+        if resolution != primary && !resolutions.contains(&resolution) {
+            resolutions.push(resolution);
+        }
+    }
+    resolutions
+}
+
+// 为每个显示器挑选缓存中与其分辨率匹配的版本，找不到时使用 path 本身；
+// 所有显示器最终用的是同一个文件时返回 None，按普通方式统一设置
+fn per_monitor_paths(setter: &dyn WallpaperSetter, path: &Path) -> Option<Vec<(Monitor, PathBuf)>> {
+    let file_name = path.file_name()?.to_str()?;
+    let assignments: Vec<(Monitor, PathBuf)> = monitor_assignments(setter)
+        .into_iter()
+        .map(|(monitor, resolution)| {
+            let variant = path.with_file_name(resolution_variant(file_name, resolution));
+            (monitor, if variant.is_file() { variant } else { path.to_path_buf() })
+        })
+        .collect();
+    let first = assignments.first()?;
+    if assignments.iter().all(|(_, variant)| *variant == first.1) {
+        return None;
+    }
+    Some(assignments)
+}
+
+// 首次换上必应壁纸前复制一份系统原来的壁纸；Windows 上读到的可能是会被覆盖的
+// TranscodedWallpaper，所以保存副本而不是只记路径
+fn backup_original(setter: &dyn WallpaperSetter) -> Result<()> {
+    if has_original() {
+        return Ok(());
+    }
+    let Some(current) = setter.current()? else {
+        return Ok(());
+    };
+    // 当前已经是本应用下载或生成的壁纸（例如旧版本设置的），没有可备份的原壁纸
+    if !current.is_file() || current.starts_with(get_wallpaper_dir()?) || current.starts_with(get_app_data_dir()?) {
+        return Ok(());
+    }
+
+    let dir = get_app_data_dir()?.join(ORIGINAL_WALLPAPER_DIR);
+    fs::create_dir_all(&dir)?;
+    let extension = current.extension().and_then(|ext| ext.to_str()).unwrap_or("jpg");
+    let backup = dir.join(format!("wallpaper.{}", extension));
+    fs::copy(&current, &backup)?;
+    update_config(|config| config.original_wallpaper = backup.to_string_lossy().into_owned())?;
+    info!("Backed up original wallpaper {:?} to {:?}", current, backup);
+    Ok(())
+}
+
+fn original_path() -> Option<PathBuf> {
+    let config = load_config().ok()?;
+    if config.original_wallpaper.is_empty() {
+        return None;
+    }
+    Some(PathBuf::from(config.original_wallpaper)).filter(|path| path.is_file())
+}
+
+pub(crate) fn has_original() -> bool {
+    original_path().is_some()
+}
+
+pub(crate) fn restore_original() -> Result<()> {
+    let path = original_path().ok_or_else(|| AppError::Config("No original wallpaper to restore".to_string()))?;
+    let setter = detect_setter()
+        .ok_or_else(|| AppError::OsSet("Unsupported desktop environment".to_string()))?;
+    setter.set(&path)?;
+    *LAST_DISPLAYED.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    info!("Restored original wallpaper {:?}", path);
+    Ok(())
+}
@@ -0,0 +1,50 @@
+// 定时任务的看门狗：定时刷新或轮播任务意外结束（例如任务中 panic）后，刷新会一直停止而没有任何提示。
+// 每分钟检查一次，任务没有被取消却已经结束时记录原因并重新启动，状态菜单中显示重启的时间
+use std::{sync::Mutex, time::Duration};
+use log::error;
+use tauri::Manager;
+
+use crate::{lock_state, restart_refresh_task, schedule_menu_update, AppState};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+async fn check(app: &tauri::AppHandle) {
+    // 取消后正常结束的任务由取消的一方负责，这里只处理没有被取消的
+    let dead = {
+        let state = app.state::<Mutex<AppState>>();
+        let mut state = lock_state(&state);
+        let finished = state
+            .timer_handle
+            .as_ref()
+            .is_some_and(|(handle, cancel)| handle.inner().is_finished() && !cancel.is_cancelled());
+        if finished { state.timer_handle.take() } else { None }
+    };
+    let Some((handle, _)) = dead else {
+        return;
+    };
+    let reason = match handle.await {
+        Ok(()) => "task returned".to_string(),
+        Err(e) => e.to_string(),
+    };
+    error!("Refresh task stopped unexpectedly ({}), restarting", reason);
+    {
+        let state = app.state::<Mutex<AppState>>();
+        let mut state = lock_state(&state);
+        // 等待期间可能已经切换了模式，新任务已在运行
+        if state.timer_handle.is_none() {
+            restart_refresh_task(app, &mut state);
+        }
+        state.task_restarted = Some(chrono::Local::now());
+    }
+    schedule_menu_update(app);
+}
+
+pub(crate) fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            check(&app).await;
+        }
+    });
+}
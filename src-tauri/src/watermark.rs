@@ -0,0 +1,161 @@
+// 壁纸水印：把图片标题和版权信息绘制到壁纸的一角，不必打开网页也能知道拍摄地点。
+// 字体可以指定字体文件，未指定时依次尝试系统自带的中文字体
+use std::{fs, path::Path};
+use ab_glyph::{FontVec, PxScale};
+use image::{Rgb, RgbImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+
+use crate::{i18n::tr_with, load_config, store, AppError, Result};
+
+pub(crate) const CORNER_BOTTOM_RIGHT: &str = "bottom_right";
+pub(crate) const CORNER_BOTTOM_LEFT: &str = "bottom_left";
+pub(crate) const CORNER_TOP_RIGHT: &str = "top_right";
+pub(crate) const CORNER_TOP_LEFT: &str = "top_left";
+pub(crate) const CORNERS: [&str; 4] = [CORNER_BOTTOM_RIGHT, CORNER_BOTTOM_LEFT, CORNER_TOP_RIGHT, CORNER_TOP_LEFT];
+pub(crate) const DEFAULT_FONT_SIZE: u32 = 20;
+pub(crate) const MIN_FONT_SIZE: u32 = 8;
+pub(crate) const MAX_FONT_SIZE: u32 = 96;
+// 字号按 1080 像素高的屏幕设定，更高分辨率的图片等比放大
+const REFERENCE_HEIGHT: f32 = 1080.0;
+// 文字背后的衬底把原图亮度降到该比例，浅色图片上的白字也能看清
+const BACKDROP_BRIGHTNESS: f32 = 0.5;
+const TEXT_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+
+#[cfg(target_os = "windows")]
+const SYSTEM_FONTS: &[&str] = &["C:\\Windows\\Fonts\\msyh.ttc", "C:\\Windows\\Fonts\\simhei.ttf", "C:\\Windows\\Fonts\\segoeui.ttf"];
+
+#[cfg(target_os = "macos")]
+const SYSTEM_FONTS: &[&str] = &[
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Medium.ttc",
+    "/System/Library/Fonts/Helvetica.ttc",
+];
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const SYSTEM_FONTS: &[&str] = &[
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+];
+
+pub(crate) struct Watermark {
+    corner: String,
+    font_size: u32,
+    font: String,
+}
+
+impl Watermark {
+    // 未开启水印时返回 None
+    pub(crate) fn load() -> Option<Self> {
+        let config = load_config().unwrap_or_default();
+        if !config.watermark_enabled {
+            return None;
+        }
+        let corner = if CORNERS.contains(&config.watermark_corner.as_str()) {
+            config.watermark_corner
+        } else {
+            CORNER_BOTTOM_RIGHT.to_string()
+        };
+        let font_size = match config.watermark_font_size {
+            0 => DEFAULT_FONT_SIZE,
+            size => size.clamp(MIN_FONT_SIZE, MAX_FONT_SIZE),
+        };
+        Some(Watermark { corner, font_size, font: config.watermark_font })
+    }
+
+    // 用于区分处理结果的文件名片段，字体文件只取哈希避免路径字符进入文件名
+    pub(crate) fn key(&self) -> String {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.font.hash(&mut hasher);
+        format!("w{}{}_{:08x}", self.corner_code(), self.font_size, hasher.finish() as u32)
+    }
+
+    fn corner_code(&self) -> &'static str {
+        match self.corner.as_str() {
+            CORNER_BOTTOM_LEFT => "bl",
+            CORNER_TOP_RIGHT => "tr",
+            CORNER_TOP_LEFT => "tl",
+            _ => "br",
+        }
+    }
+
+    pub(crate) fn render(&self, image: &mut RgbImage, lines: &[String]) -> Result<()> {
+        let font = load_font(&self.font)?;
+        let scale = PxScale::from(self.font_size as f32 * image.height() as f32 / REFERENCE_HEIGHT);
+        let line_height = scale.y.ceil() as u32;
+        let spacing = line_height / 3;
+        let margin = line_height * 2;
+        let width = lines.iter().map(|line| text_size(scale, &font, line).0).max().unwrap_or(0);
+        let height = lines.len() as u32 * (line_height + spacing) - spacing;
+        if width + margin * 2 > image.width() || height + margin * 2 > image.height() {
+            return Err(AppError::Config(format!("Image too small for watermark: {}x{}", image.width(), image.height())));
+        }
+
+        let left = match self.corner.as_str() {
+            CORNER_BOTTOM_LEFT | CORNER_TOP_LEFT => margin,
+            _ => image.width() - margin - width,
+        };
+        let top = match self.corner.as_str() {
+            CORNER_TOP_RIGHT | CORNER_TOP_LEFT => margin,
+            _ => image.height() - margin - height,
+        };
+        darken_area(image, left.saturating_sub(spacing), top.saturating_sub(spacing), width + spacing * 2, height + spacing * 2);
+        for (index, line) in lines.iter().enumerate() {
+            // 右侧的水印按行右对齐
+            let x = match self.corner.as_str() {
+                CORNER_BOTTOM_LEFT | CORNER_TOP_LEFT => left,
+                _ => left + width - text_size(scale, &font, line).0,
+            };
+            let y = top + index as u32 * (line_height + spacing);
+            draw_text_mut(image, TEXT_COLOR, x as i32, y as i32, scale, &font, line);
+        }
+        Ok(())
+    }
+}
+
+fn darken_area(image: &mut RgbImage, left: u32, top: u32, width: u32, height: u32) {
+    let right = (left + width).min(image.width());
+    let bottom = (top + height).min(image.height());
+    for y in top..bottom {
+        for x in left..right {
+            let pixel = image.get_pixel_mut(x, y);
+            for channel in pixel.0.iter_mut() {
+                *channel = (*channel as f32 * BACKDROP_BRIGHTNESS) as u8;
+            }
+        }
+    }
+}
+
+// 字体集合（.ttc）只使用其中的第一个字体
+fn read_font(path: &Path) -> Result<FontVec> {
+    let data = fs::read(path)?;
+    FontVec::try_from_vec_and_index(data, 0).map_err(|e| AppError::Parse(format!("Invalid font {:?}: {}", path, e)))
+}
+
+fn load_font(font: &str) -> Result<FontVec> {
+    let font = font.trim();
+    if !font.is_empty() {
+        return read_font(Path::new(font));
+    }
+    SYSTEM_FONTS
+        .iter()
+        .map(Path::new)
+        .filter(|path| path.is_file())
+        .find_map(|path| read_font(path).ok())
+        .ok_or_else(|| AppError::Config(tr_with("找不到可用的水印字体，请在设置中指定字体文件: {}", SYSTEM_FONTS.join(", "))))
+}
+
+// 按文件名在元信息数据库中查找标题和版权；本地文件夹等来源的图片找不到时不绘制水印
+pub(crate) fn caption_for(path: &Path) -> Option<Vec<String>> {
+    let file_name = path.file_name()?.to_string_lossy().into_owned();
+    let info = store::find_by_file(&file_name).ok().flatten()?;
+    let lines: Vec<String> = [info.title.trim(), info.copyright.trim()]
+        .into_iter()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    (!lines.is_empty()).then_some(lines)
+}
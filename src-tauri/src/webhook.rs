@@ -0,0 +1,47 @@
+// 壁纸更换后向用户配置的地址 POST 一段 JSON，可用于记录日志、Discord/Slack 机器人或同步其它电脑。
+// 载荷同时带有 content 和 text 字段，可以直接填写 Discord 或 Slack 的 Webhook 地址
+use std::path::Path;
+use log::info;
+use serde::Serialize;
+
+use crate::{http, load_config, Result, WallpaperInfo};
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    event: &'static str,
+    date: &'a str,
+    title: &'a str,
+    copyright: &'a str,
+    file_name: &'a str,
+    path: String,
+    url: &'a str,
+    // 一行摘要，分别供 Discord 和 Slack 显示
+    content: String,
+    text: String,
+}
+
+// 未配置地址时什么也不做；失败时由调用方记录日志，不重试
+pub(crate) async fn post_change(wallpaper: &WallpaperInfo, path: &Path) -> Result<()> {
+    let url = load_config()?.webhook_url;
+    if url.is_empty() {
+        return Ok(());
+    }
+    let summary = format!("{} {} ({})", wallpaper.date, wallpaper.title, wallpaper.copyright);
+    let payload = Payload {
+        event: "wallpaper_changed",
+        date: &wallpaper.date,
+        title: &wallpaper.title,
+        copyright: &wallpaper.copyright,
+        file_name: &wallpaper.file_name,
+        path: path.to_string_lossy().into_owned(),
+        url: &wallpaper.url,
+        content: summary.clone(),
+        text: summary,
+    };
+    let request = http::post(&url)?
+        .header("Content-Type", "application/json; charset=utf-8")
+        .body(serde_json::to_string(&payload)?);
+    http::send(request).await?;
+    info!("Posted webhook for {}", wallpaper.file_name);
+    Ok(())
+}
@@ -0,0 +1,270 @@
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use futures_util::StreamExt;
+use log::{info, warn};
+use reqwest::{
+    header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE},
+    StatusCode,
+};
+use sha2::{Digest, Sha256};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncReadExt, AsyncWriteExt},
+};
+
+use crate::{http, with_retry, Error, HttpClient, Result};
+
+// 判断文件类型需要的最少字节数（WebP 的标识在第 8~12 字节）
+const MAGIC_LEN: usize = 12;
+
+// 把壁纸图片保存到指定路径；expected_sha256 不为空时校验下载内容。返回下载内容的 SHA-256（小写十六进制），
+// 调用方据此识别内容相同的图片
+pub trait Downloader {
+    fn download(&self, url: &str, dest: &Path, expected_sha256: Option<&str>) -> impl Future<Output = Result<String>> + Send;
+}
+
+pub struct HttpDownloader {
+    client: HttpClient,
+    // 下载速度上限（字节/秒），为空表示不限速
+    rate_limit: Option<u64>,
+    // 随图片请求发送的 client-version，与获取壁纸信息的请求一致
+    client_version: Option<String>,
+}
+
+// AVIF 和 HEIC 共用 ISO 媒体文件的容器，ftyp 盒子中的主品牌区分具体格式
+const ISOBMFF_IMAGE_BRANDS: [&[u8]; 10] = [b"avif", b"avis", b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"mif1", b"msf1"];
+
+// 按文件头识别常见的图片格式，代理或网关返回的 HTML 错误页不会通过
+fn is_image(header: &[u8]) -> bool {
+    header.starts_with(&[0xFF, 0xD8, 0xFF])
+        || header.starts_with(b"\x89PNG\r\n\x1a\n")
+        || header.starts_with(b"BM")
+        || (header.starts_with(b"RIFF") && header.get(8..12) == Some(b"WEBP".as_slice()))
+        || (header.get(4..8) == Some(b"ftyp".as_slice()) && header.get(8..12).is_some_and(|brand| ISOBMFF_IMAGE_BRANDS.contains(&brand)))
+}
+
+// 续传时读取的缓冲区大小
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+// 下载中的临时文件放在目标文件旁边，保证重命名在同一文件系统内完成
+fn temp_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+// 续传前读取临时文件中已有的内容，补上摘要和文件头，返回已有的字节数
+async fn read_partial(temp: &Path, hasher: &mut Sha256, header: &mut Vec<u8>) -> Result<u64> {
+    let mut file = File::open(temp).await?;
+    let mut buffer = vec![0; READ_BUFFER_SIZE];
+    let mut total: u64 = 0;
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(total);
+        }
+        let chunk = &buffer[..read];
+        if header.len() < MAGIC_LEN {
+            let take = (MAGIC_LEN - header.len()).min(chunk.len());
+            header.extend_from_slice(&chunk[..take]);
+        }
+        hasher.update(chunk);
+        total += read as u64;
+    }
+}
+
+// 丢弃临时文件，下次尝试从头下载
+async fn discard_partial(temp: &Path) {
+    if let Err(e) = tokio::fs::remove_file(temp).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("Failed to remove partial download {:?}: {}", temp, e);
+        }
+    }
+}
+
+impl HttpDownloader {
+    pub fn new(client: HttpClient) -> Self {
+        HttpDownloader { client, rate_limit: None, client_version: None }
+    }
+
+    pub fn with_client_version(mut self, client_version: &str) -> Self {
+        self.client_version = Some(client_version.to_string());
+        self
+    }
+
+    // kb_per_sec 为 0 时不限速
+    pub fn with_rate_limit(mut self, kb_per_sec: u64) -> Self {
+        self.rate_limit = (kb_per_sec > 0).then(|| kb_per_sec * 1024);
+        self
+    }
+
+    // 边接收边写入临时文件并计算摘要，不把整张图片放在内存中。
+    // validator 是首次完整响应的 ETag 或 Last-Modified：重试时若临时文件已有内容，
+    // 带 Range 和 If-Range 请求剩余部分，服务端返回 206 时续写，文件已变化返回 200 时重新下载
+    async fn download_once(
+        &self,
+        url: &str,
+        temp: &Path,
+        expected_sha256: Option<&str>,
+        validator: &Mutex<Option<String>>,
+    ) -> Result<String> {
+        // 图片可能有几 MB，下载请求使用比普通接口更长的超时
+        let timeouts = self.client.timeouts();
+        let offset = tokio::fs::metadata(temp).await.map(|metadata| metadata.len()).unwrap_or(0);
+        let resume_validator = validator.lock().unwrap_or_else(|e| e.into_inner()).clone().filter(|_| offset > 0);
+        let mut request = self.client.get(url).timeout(timeouts.download);
+        if let Some(client_version) = &self.client_version {
+            request = request.header("client-version", client_version.as_str());
+        }
+        if let Some(validator) = &resume_validator {
+            request = request.header(RANGE, format!("bytes={}-", offset)).header(IF_RANGE, validator.as_str());
+        }
+        let response = request.send().await?;
+        // 已有的部分超出了文件长度（例如服务端换了图片），从头重新下载
+        if resume_validator.is_some() && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            discard_partial(temp).await;
+            return Err(Error::network("Cannot resume download: range not satisfiable", true));
+        }
+        let response = http::check_status(response)?;
+        if let Some(content_type) = response.headers().get(reqwest::header::CONTENT_TYPE) {
+            let content_type = content_type.to_str().unwrap_or_default();
+            if !content_type.starts_with("image/") && !content_type.starts_with("application/octet-stream") {
+                return Err(Error::Parse(format!("Unexpected content type: {}", content_type)));
+            }
+        }
+
+        let mut hasher = Sha256::new();
+        let mut header = Vec::with_capacity(MAGIC_LEN);
+        let resumed = resume_validator.is_some() && response.status() == StatusCode::PARTIAL_CONTENT;
+        let (mut file, mut received) = if resumed {
+            let content_range = http::header_value(&response, CONTENT_RANGE).unwrap_or_default();
+            if !content_range.starts_with(&format!("bytes {}-", offset)) {
+                discard_partial(temp).await;
+                return Err(Error::network(format!("Unexpected Content-Range: {}", content_range), true));
+            }
+            let existing = read_partial(temp, &mut hasher, &mut header).await?;
+            info!("Resuming download of {} from byte {}", url, existing);
+            (OpenOptions::new().append(true).open(temp).await?, existing)
+        } else {
+            // 弱 ETag 不能用于 If-Range，没有可用的校验值时重试只能从头下载
+            let etag = http::header_value(&response, ETAG).filter(|etag| !etag.starts_with("W/"));
+            *validator.lock().unwrap_or_else(|e| e.into_inner()) = etag.or_else(|| http::header_value(&response, LAST_MODIFIED));
+            (File::create(temp).await?, 0)
+        };
+        let expected_len = response.content_length().map(|len| len + received);
+        let mut stream = response.bytes_stream();
+        // 中途失败时也先把已收到的内容写入磁盘，下次尝试从这里续传
+        let started = Instant::now();
+        let mut transferred: u64 = 0;
+        let streamed: Result<()> = async {
+            loop {
+                let Ok(next) = tokio::time::timeout(timeouts.request, stream.next()).await else {
+                    return Err(Error::network(
+                        format!("Download stalled: no data for {} seconds", timeouts.request.as_secs()),
+                        true,
+                    ));
+                };
+                let Some(chunk) = next else {
+                    return Ok(());
+                };
+                let chunk = chunk?;
+                if header.len() < MAGIC_LEN {
+                    let take = (MAGIC_LEN - header.len()).min(chunk.len());
+                    header.extend_from_slice(&chunk[..take]);
+                }
+                hasher.update(&chunk);
+                received += chunk.len() as u64;
+                file.write_all(&chunk).await?;
+                // 限速：本次传输的字节数超出按上限应花的时间时，睡到平均速度回到上限以内
+                transferred += chunk.len() as u64;
+                if let Some(rate) = self.rate_limit {
+                    let expected = Duration::from_secs_f64(transferred as f64 / rate as f64);
+                    if let Some(wait) = expected.checked_sub(started.elapsed()) {
+                        tokio::time::sleep(wait).await;
+                    }
+                }
+            }
+        }
+        .await;
+        file.flush().await?;
+        streamed?;
+        file.sync_all().await?;
+
+        // 连接中途断开时内容不完整，属于可以重试的临时错误
+        if let Some(expected) = expected_len.filter(|expected| *expected != received) {
+            return Err(Error::network(format!("Download truncated: received {} of {} bytes", received, expected), true));
+        }
+        if !is_image(&header) {
+            return Err(Error::Parse("Downloaded file is not an image".to_string()));
+        }
+        let actual = format!("{:x}", hasher.finalize());
+        if let Some(expected) = expected_sha256 {
+            if !actual.eq_ignore_ascii_case(expected) {
+                discard_partial(temp).await;
+                return Err(Error::network(format!("Checksum mismatch: expected {}, got {}", expected, actual), true));
+            }
+        }
+        Ok(actual)
+    }
+}
+
+impl Downloader for HttpDownloader {
+    // 校验全部通过后才把临时文件重命名为目标文件，失败时不会留下损坏的壁纸。
+    // 重试之间保留临时文件以便续传，最终失败后才删除
+    async fn download(&self, url: &str, dest: &Path, expected_sha256: Option<&str>) -> Result<String> {
+        let temp = temp_path(dest);
+        // 上次运行遗留的临时文件没有对应的校验值，不能续传
+        discard_partial(&temp).await;
+        let validator = Mutex::new(None);
+        let result =
+            with_retry("Downloading wallpaper", || self.download_once(url, &temp, expected_sha256, &validator)).await;
+        let sha256 = match result {
+            Ok(sha256) => sha256,
+            Err(e) => {
+                discard_partial(&temp).await;
+                return Err(e);
+            }
+        };
+        tokio::fs::rename(&temp, dest).await?;
+        info!("Downloaded {} to {:?}", url, dest);
+        Ok(sha256)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_image() {
+        assert!(is_image(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F', 0x00, 0x01]));
+        assert!(is_image(b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0d"));
+        assert!(is_image(b"BM6\x00\x0c\x00\x00\x00\x00\x00\x36\x00"));
+        assert!(is_image(b"RIFF\x24\x00\x00\x00WEBP"));
+        assert!(is_image(b"\x00\x00\x00\x1cftypavif\x00\x00\x00\x00"));
+        assert!(is_image(b"\x00\x00\x00\x18ftypheic\x00\x00\x00\x00"));
+        assert!(is_image(b"\x00\x00\x00\x18ftypmif1\x00\x00\x00\x00"));
+    }
+
+    #[test]
+    fn test_is_not_image() {
+        assert!(!is_image(b""));
+        assert!(!is_image(b"<!DOCTYPE html>"));
+        assert!(!is_image(b"{\"error\":\"x\"}"));
+        assert!(!is_image(b"RIFF\x24\x00\x00\x00WAVE"));
+        // 文件头不完整时不能确认是 WebP
+        assert!(!is_image(b"RIFF\x24\x00"));
+        // MP4 视频使用同样的容器
+        assert!(!is_image(b"\x00\x00\x00\x20ftypisom\x00\x00\x02\x00"));
+        assert!(!is_image(b"\x00\x00\x00\x18ftyp"));
+    }
+
+    #[test]
+    fn test_temp_path() {
+        assert_eq!(temp_path(Path::new("/tmp/a_UHD.jpg")), Path::new("/tmp/a_UHD.jpg.part"));
+    }
+}
@@ -0,0 +1,47 @@
+// 核心库的错误分类；Network 中的 transient 标记网络超时、服务端 5xx 等可重试的临时错误
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{message}")]
+    Network { message: String, transient: bool },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Parse(String),
+    #[error("{0}")]
+    OsSet(String),
+    #[error("{0}")]
+    Config(String),
+}
+
+impl Error {
+    pub fn network(message: impl Into<String>, transient: bool) -> Self {
+        Error::Network { message: message.into(), transient }
+    }
+}
+
+// 重试逻辑只关心错误能否重试，调用方自己的错误类型实现该 trait 后即可复用
+pub trait Transient {
+    fn is_transient(&self) -> bool;
+}
+
+impl Transient for Error {
+    fn is_transient(&self) -> bool {
+        matches!(self, Error::Network { transient: true, .. })
+    }
+}
+
+// 连接失败、超时和传输中断通常是网络波动，其余视为永久错误
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        let transient = err.is_connect() || err.is_timeout() || err.is_body();
+        Error::network(err.to_string(), transient)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Parse(err.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,28 @@
+// 壁纸核心逻辑：获取壁纸信息、下载、设置桌面壁纸和定时调度。
+// 不依赖 Tauri，托盘程序和命令行模式共用，配置的读取由调用方负责。
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+pub mod downloader;
+pub mod error;
+pub mod http;
+pub mod model;
+pub mod provider;
+pub mod scheduler;
+pub mod setter;
+
+pub use downloader::{Downloader, HttpDownloader};
+pub use error::{Error, Result, Transient};
+pub use http::{with_retry, HttpClient, Timeouts};
+pub use model::{resolution_variant, Resolution, WallpaperInfo};
+pub use provider::{fetch_date, ApodProvider, BingProvider, Provider, SpotlightProvider, UnsplashProvider, WdbyteProvider};
+pub use scheduler::{Schedule, Scheduler, Trigger};
+pub use tokio_util::sync::CancellationToken;
+pub use setter::{detect_setter, Fit, Monitor, WallpaperSetter};
+
+// 不需要密码学强度的随机数（退避抖动、随机挑选壁纸），用标准库的随机哈希种子即可
+pub fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
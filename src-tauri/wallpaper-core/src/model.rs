@@ -0,0 +1,205 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+// 壁纸分辨率，Auto 表示由调用方决定（托盘程序按主显示器尺寸选择）
+#[derive(Clone, Copy, PartialEq)]
+pub enum Resolution {
+    Auto,
+    FullHd,
+    Uhd,
+}
+
+impl Resolution {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Resolution::Auto => "auto",
+            Resolution::FullHd => "1920x1080",
+            Resolution::Uhd => "UHD",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "1920x1080" => Resolution::FullHd,
+            "UHD" => Resolution::Uhd,
+            _ => Resolution::Auto,
+        }
+    }
+
+    // 按显示器的物理像素选择版本，超过 1080p 的显示器使用 UHD
+    pub fn for_size(width: u32, height: u32) -> Self {
+        if width > 1920 || height > 1080 {
+            Resolution::Uhd
+        } else {
+            Resolution::FullHd
+        }
+    }
+}
+
+// 把文件名或地址中的分辨率标记换成指定版本，例如 a_1920x1080.jpg -> a_UHD.jpg；没有标记时原样返回
+pub fn resolution_variant(name: &str, resolution: Resolution) -> String {
+    if resolution == Resolution::Auto {
+        return name.to_string();
+    }
+    let target = format!("_{}", resolution.as_str());
+    [Resolution::FullHd, Resolution::Uhd]
+        .map(|r| format!("_{}", r.as_str()))
+        .iter()
+        .fold(name.to_string(), |name, token| if *token == target { name } else { name.replace(token, &target) })
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WallpaperInfo {
+    pub file_name: String,
+    pub url: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub date: String,
+    #[serde(default)]
+    pub copyright: String,
+    // 接口可选返回的图片 SHA-256（十六进制），下载后据此校验文件完整性
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub sha256: String,
+    // 图片背后的故事或说明，只有部分来源提供
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+impl WallpaperInfo {
+    // 托盘提示文字，例如 "标题 © 作者"，前缀由调用方按界面语言添加；接口返回的版权信息可能自带 © 或括号
+    pub fn summary(&self) -> String {
+        let title = if self.title.is_empty() { &self.file_name } else { &self.title };
+        let copyright = self.copyright
+            .trim()
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .trim_start_matches('©')
+            .trim();
+        if copyright.is_empty() {
+            title.to_string()
+        } else {
+            format!("{} © {}", title, copyright)
+        }
+    }
+
+    // 必应的版权信息形如 "Lake Louise, Alberta, Canada (© Paul Zizka/Minden Pictures)"，
+    // 括号前的部分是拍摄地点；没有括号时无法区分，返回 None
+    pub fn location(&self) -> Option<String> {
+        let (location, _) = self.copyright.split_once(['(', '（'])?;
+        let location = location.trim().trim_end_matches([',', '，']).trim();
+        (!location.is_empty()).then(|| location.to_string())
+    }
+
+    // 必应图片地址以 _1920x1080.jpg / _UHD.jpg 区分分辨率，替换后得到对应版本
+    pub fn with_resolution(mut self, resolution: Resolution) -> Self {
+        let url = resolution_variant(&self.url, resolution);
+        if url != self.url {
+            self.url = url;
+            self.file_name = resolution_variant(&self.file_name, resolution);
+            // 校验值只对应接口返回的那个分辨率
+            self.sha256.clear();
+        }
+        self
+    }
+
+    // 接口返回的日期可能是 20240101 或 2024-01-01
+    pub fn parsed_date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date.replace('-', ""), "%Y%m%d").ok()
+    }
+
+    // 菜单头部展示的标题，接口未返回标题时退回文件名
+    pub fn display_title(&self) -> String {
+        let title = if self.title.is_empty() { &self.file_name } else { &self.title };
+        if self.date.is_empty() {
+            title.to_string()
+        } else {
+            format!("{} ({})", title, self.date)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wallpaper(title: &str, copyright: &str) -> WallpaperInfo {
+        WallpaperInfo {
+            file_name: "OHR.LakeLouise_ZH-CN123_1920x1080.jpg".to_string(),
+            url: "https://cn.bing.com/th?id=OHR.LakeLouise_ZH-CN123_1920x1080.jpg".to_string(),
+            title: title.to_string(),
+            date: "20240101".to_string(),
+            copyright: copyright.to_string(),
+            sha256: "abc".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolution_variant() {
+        assert_eq!(resolution_variant("a_1920x1080.jpg", Resolution::Uhd), "a_UHD.jpg");
+        assert_eq!(resolution_variant("a_UHD.jpg", Resolution::FullHd), "a_1920x1080.jpg");
+        assert_eq!(resolution_variant("a_UHD.jpg", Resolution::Uhd), "a_UHD.jpg");
+        assert_eq!(resolution_variant("a_1920x1080.jpg", Resolution::Auto), "a_1920x1080.jpg");
+        assert_eq!(resolution_variant("photo.jpg", Resolution::Uhd), "photo.jpg");
+    }
+
+    #[test]
+    fn test_with_resolution() {
+        let uhd = wallpaper("", "").with_resolution(Resolution::Uhd);
+        assert_eq!(uhd.file_name, "OHR.LakeLouise_ZH-CN123_UHD.jpg");
+        assert_eq!(uhd.url, "https://cn.bing.com/th?id=OHR.LakeLouise_ZH-CN123_UHD.jpg");
+        assert!(uhd.sha256.is_empty());
+
+        // 已经是目标分辨率或 Auto 时保留校验值
+        let same = wallpaper("", "").with_resolution(Resolution::FullHd);
+        assert_eq!(same.file_name, "OHR.LakeLouise_ZH-CN123_1920x1080.jpg");
+        assert_eq!(same.sha256, "abc");
+        assert_eq!(wallpaper("", "").with_resolution(Resolution::Auto).sha256, "abc");
+    }
+
+    #[test]
+    fn test_with_resolution_without_marker() {
+        let info = WallpaperInfo {
+            file_name: "apod-2024-01-01.jpg".to_string(),
+            url: "https://apod.nasa.gov/image/2401/photo.jpg".to_string(),
+            sha256: "abc".to_string(),
+            ..Default::default()
+        };
+        let info = info.with_resolution(Resolution::Uhd);
+        assert_eq!(info.file_name, "apod-2024-01-01.jpg");
+        assert_eq!(info.url, "https://apod.nasa.gov/image/2401/photo.jpg");
+        assert_eq!(info.sha256, "abc");
+    }
+
+    #[test]
+    fn test_location() {
+        let info = wallpaper("", "Lake Louise, Alberta, Canada (© Paul Zizka/Minden Pictures)");
+        assert_eq!(info.location().as_deref(), Some("Lake Louise, Alberta, Canada"));
+        let info = wallpaper("", "西湖，杭州，中国（© Getty Images）");
+        assert_eq!(info.location().as_deref(), Some("西湖，杭州，中国"));
+        assert_eq!(wallpaper("", "© Getty Images").location(), None);
+        assert_eq!(wallpaper("", "(© Getty Images)").location(), None);
+        assert_eq!(wallpaper("", "").location(), None);
+    }
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(wallpaper("Lake Louise", "(© Paul Zizka)").summary(), "Lake Louise © Paul Zizka");
+        assert_eq!(wallpaper("Lake Louise", "© Paul Zizka").summary(), "Lake Louise © Paul Zizka");
+        assert_eq!(wallpaper("Lake Louise", " ").summary(), "Lake Louise");
+        assert_eq!(wallpaper("", "").summary(), "OHR.LakeLouise_ZH-CN123_1920x1080.jpg");
+    }
+
+    #[test]
+    fn test_parsed_date() {
+        let expected = NaiveDate::from_ymd_opt(2024, 1, 1);
+        assert_eq!(wallpaper("", "").parsed_date(), expected);
+        let info = WallpaperInfo { date: "2024-01-01".to_string(), ..Default::default() };
+        assert_eq!(info.parsed_date(), expected);
+        assert_eq!(WallpaperInfo::default().parsed_date(), None);
+        let info = WallpaperInfo { date: "2024-13-01".to_string(), ..Default::default() };
+        assert_eq!(info.parsed_date(), None);
+    }
+}
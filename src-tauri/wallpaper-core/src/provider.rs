@@ -0,0 +1,547 @@
+use std::future::Future;
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::{http::{self, RequestBuilder}, Error, HttpClient, Resolution, Result, WallpaperInfo};
+
+// 默认的接口地址；自建镜像需要提供相同的 /today、/archive 以及 /zh-cn 前缀的中国区接口
+pub const DEFAULT_BASE_URL: &str = "https://bing.wdbyte.com";
+const CHINA_PREFIX: &str = "/zh-cn";
+const TODAY_PATH: &str = "/today";
+const ARCHIVE_PATH: &str = "/archive";
+
+// 壁纸来源：提供当天的壁纸和最近若干天的归档
+pub trait Provider {
+    fn name(&self) -> &'static str;
+    fn fetch_today(&self, resolution: Resolution) -> impl Future<Output = Result<WallpaperInfo>> + Send;
+    fn fetch_archive(&self, days: u32) -> impl Future<Output = Result<Vec<WallpaperInfo>>> + Send;
+}
+
+// 取某一天的壁纸：请求刚好覆盖到该日期的归档后按日期查找，能取到多早取决于来源的归档范围
+pub async fn fetch_date<P: Provider>(provider: &P, date: NaiveDate, resolution: Resolution) -> Result<WallpaperInfo> {
+    let today = chrono::Local::now().date_naive();
+    if date > today {
+        return Err(Error::Config(format!("{} is in the future", date)));
+    }
+    let days = (today - date).num_days() as u32 + 1;
+    provider
+        .fetch_archive(days)
+        .await?
+        .into_iter()
+        .find(|info| info.parsed_date() == Some(date))
+        .map(|info| info.with_resolution(resolution))
+        .ok_or_else(|| Error::Config(format!("No wallpaper for {} in the {} archive", date, provider.name())))
+}
+
+// bing.wdbyte.com 提供的接口，中国区和国际区使用不同的地址，国际区可以指定市场
+pub struct WdbyteProvider {
+    client: HttpClient,
+    base_url: String,
+    is_china: bool,
+    market: Option<String>,
+    client_version: String,
+    device_uuid: Option<String>,
+}
+
+impl WdbyteProvider {
+    // device_uuid 为空时不发送 client-device-uuid
+    pub fn new(client: HttpClient, is_china: bool, client_version: &str, device_uuid: Option<&str>) -> Self {
+        WdbyteProvider {
+            client,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            is_china,
+            market: None,
+            client_version: client_version.to_string(),
+            device_uuid: device_uuid.map(str::to_string),
+        }
+    }
+
+    pub fn with_market(mut self, market: Option<String>) -> Self {
+        self.market = market;
+        self
+    }
+
+    // base_url 为空时使用默认地址，末尾的 / 会被去掉
+    pub fn with_base_url(mut self, base_url: Option<String>) -> Self {
+        if let Some(base_url) = base_url.filter(|url| !url.trim().is_empty()) {
+            self.base_url = base_url.trim().trim_end_matches('/').to_string();
+        }
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        let prefix = if self.is_china { CHINA_PREFIX } else { "" };
+        format!("{}{}{}", self.base_url, prefix, path)
+    }
+
+    fn request(&self, url: &str) -> RequestBuilder {
+        let mut request = self.client.get(url).header("client-version", self.client_version.as_str());
+        if let Some(device_uuid) = &self.device_uuid {
+            request = request.header("client-device-uuid", device_uuid.as_str());
+        }
+        if let Some(market) = &self.market {
+            request = request.query(&[("mkt", market.as_str())]);
+        }
+        request
+    }
+}
+
+impl Provider for WdbyteProvider {
+    fn name(&self) -> &'static str {
+        if self.is_china { "wdbyte-china" } else { "wdbyte-global" }
+    }
+
+    async fn fetch_today(&self, resolution: Resolution) -> Result<WallpaperInfo> {
+        let mut request = self.request(&self.url(TODAY_PATH));
+        if resolution != Resolution::Auto {
+            request = request.query(&[("resolution", resolution.as_str())]);
+        }
+        let body = http::send_conditional(request).await?;
+        let wallpaper_info: WallpaperInfo = serde_json::from_str(&body)?;
+        Ok(wallpaper_info.with_resolution(resolution))
+    }
+
+    async fn fetch_archive(&self, days: u32) -> Result<Vec<WallpaperInfo>> {
+        let request = self
+            .request(&self.url(ARCHIVE_PATH))
+            .query(&[("days", days)]);
+        let body = http::send_conditional(request).await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+}
+
+pub const BING_GLOBAL_URL: &str = "https://www.bing.com";
+pub const BING_CHINA_URL: &str = "https://cn.bing.com";
+const BING_ARCHIVE_PATH: &str = "/HPImageArchive.aspx";
+const BING_DEFAULT_MARKET: &str = "en-US";
+// HPImageArchive 每次最多返回 8 张，idx 最大为 7，因此最多能取到最近 15 天
+const BING_PAGE_SIZE: u32 = 8;
+const BING_MAX_INDEX: u32 = 7;
+
+#[derive(Deserialize)]
+struct BingArchive {
+    images: Vec<BingImage>,
+}
+
+#[derive(Deserialize)]
+struct BingImage {
+    startdate: String,
+    // 例如 /th?id=OHR.Name_ZH-CN1234567890，拼上 _1920x1080.jpg 等后缀即为图片地址
+    urlbase: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    copyright: String,
+}
+
+// 直接请求必应官方的 HPImageArchive 接口，作为 bing.wdbyte.com 不可用时的备用来源
+pub struct BingProvider {
+    client: HttpClient,
+    is_china: bool,
+    market: Option<String>,
+}
+
+impl BingProvider {
+    pub fn new(client: HttpClient, is_china: bool) -> Self {
+        BingProvider { client, is_china, market: None }
+    }
+
+    pub fn with_market(mut self, market: Option<String>) -> Self {
+        self.market = market;
+        self
+    }
+
+    async fn fetch_images(&self, index: u32, count: u32) -> Result<Vec<BingImage>> {
+        let host = if self.is_china { BING_CHINA_URL } else { BING_GLOBAL_URL };
+        let market = if self.is_china { "zh-CN" } else { self.market.as_deref().unwrap_or(BING_DEFAULT_MARKET) };
+        let request = self.client.get(&format!("{}{}", host, BING_ARCHIVE_PATH)).query(&[
+            ("format", "js"),
+            ("idx", index.to_string().as_str()),
+            ("n", count.to_string().as_str()),
+            ("mkt", market),
+        ]);
+        let body = http::send(request).await?.text().await?;
+        let archive: BingArchive = serde_json::from_str(&body)?;
+        Ok(archive.images)
+    }
+
+    // 自动分辨率时使用 1920x1080，与 wdbyte 接口的默认值一致
+    fn to_info(&self, image: BingImage, resolution: Resolution) -> WallpaperInfo {
+        let resolution = if resolution == Resolution::Auto { Resolution::FullHd } else { resolution };
+        let host = if self.is_china { BING_CHINA_URL } else { BING_GLOBAL_URL };
+        let name = image.urlbase.split_once("id=").map_or(image.urlbase.as_str(), |(_, name)| name);
+        WallpaperInfo {
+            file_name: format!("{}_{}.jpg", name, resolution.as_str()),
+            url: format!("{}{}_{}.jpg", host, image.urlbase, resolution.as_str()),
+            title: image.title,
+            date: image.startdate,
+            copyright: image.copyright,
+            ..Default::default()
+        }
+    }
+}
+
+impl Provider for BingProvider {
+    fn name(&self) -> &'static str {
+        if self.is_china { "bing-china" } else { "bing-global" }
+    }
+
+    async fn fetch_today(&self, resolution: Resolution) -> Result<WallpaperInfo> {
+        let image = self
+            .fetch_images(0, 1)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::Parse("Bing returned no images".to_string()))?;
+        Ok(self.to_info(image, resolution))
+    }
+
+    // 分两页请求，第二页与第一页有一天重叠，按日期去重
+    async fn fetch_archive(&self, days: u32) -> Result<Vec<WallpaperInfo>> {
+        let mut images = self.fetch_images(0, days.min(BING_PAGE_SIZE)).await?;
+        if days > BING_PAGE_SIZE {
+            for image in self.fetch_images(BING_MAX_INDEX, BING_PAGE_SIZE).await? {
+                if !images.iter().any(|existing| existing.startdate == image.startdate) {
+                    images.push(image);
+                }
+            }
+        }
+        images.truncate(days as usize);
+        Ok(images.into_iter().map(|image| self.to_info(image, Resolution::Auto)).collect())
+    }
+}
+
+// 今天的本地日期，用于没有日期字段的来源，格式与必应一致
+fn today() -> String {
+    chrono::Local::now().format("%Y%m%d").to_string()
+}
+
+// 取地址最后一段作为文件名的一部分，去掉查询参数
+fn url_file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+pub const SPOTLIGHT_API_URL: &str = "https://fd.api.iris.microsoft.com/v4/api/selection";
+// Windows 聚焦桌面壁纸使用的投放位置
+const SPOTLIGHT_PLACEMENT: &str = "88000820";
+
+#[derive(Deserialize)]
+struct SpotlightResponse {
+    batchrsp: SpotlightBatch,
+}
+
+#[derive(Deserialize)]
+struct SpotlightBatch {
+    items: Vec<SpotlightItem>,
+}
+
+// item 字段本身是一段 JSON 字符串，需要再解析一次
+#[derive(Deserialize)]
+struct SpotlightItem {
+    item: String,
+}
+
+#[derive(Deserialize)]
+struct SpotlightAdItem {
+    ad: SpotlightAd,
+}
+
+#[derive(Deserialize)]
+struct SpotlightAd {
+    #[serde(rename = "landscapeImage")]
+    landscape_image: SpotlightImage,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    copyright: String,
+}
+
+#[derive(Deserialize)]
+struct SpotlightImage {
+    asset: String,
+}
+
+// Windows 聚焦（Spotlight）的锁屏图片，每次请求随机返回一张，没有历史归档
+pub struct SpotlightProvider {
+    client: HttpClient,
+    is_china: bool,
+}
+
+impl SpotlightProvider {
+    pub fn new(client: HttpClient, is_china: bool) -> Self {
+        SpotlightProvider { client, is_china }
+    }
+}
+
+impl Provider for SpotlightProvider {
+    fn name(&self) -> &'static str {
+        "spotlight"
+    }
+
+    // 接口只提供一种尺寸的横屏图片，忽略分辨率设置
+    async fn fetch_today(&self, _resolution: Resolution) -> Result<WallpaperInfo> {
+        let (country, locale) = if self.is_china { ("CN", "zh-CN") } else { ("US", "en-US") };
+        let request = self.client.get(SPOTLIGHT_API_URL).query(&[
+            ("placement", SPOTLIGHT_PLACEMENT),
+            ("bcnt", "1"),
+            ("country", country),
+            ("locale", locale),
+            ("fmt", "json"),
+        ]);
+        let body = http::send(request).await?.text().await?;
+        let response: SpotlightResponse = serde_json::from_str(&body)?;
+        let item = response
+            .batchrsp
+            .items
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::Parse("Spotlight returned no images".to_string()))?;
+        let ad = serde_json::from_str::<SpotlightAdItem>(&item.item)?.ad;
+        Ok(WallpaperInfo {
+            file_name: format!("spotlight_{}", url_file_name(&ad.landscape_image.asset)),
+            url: ad.landscape_image.asset,
+            title: ad.title,
+            date: today(),
+            copyright: ad.copyright,
+            description: ad.description.trim().to_string(),
+            ..Default::default()
+        })
+    }
+
+    async fn fetch_archive(&self, _days: u32) -> Result<Vec<WallpaperInfo>> {
+        Ok(Vec::new())
+    }
+}
+
+pub const APOD_API_URL: &str = "https://api.nasa.gov/planetary/apod";
+// NASA 提供的公共演示 key，每个 IP 每小时有请求次数限制
+pub const APOD_DEMO_KEY: &str = "DEMO_KEY";
+
+#[derive(Deserialize)]
+struct ApodEntry {
+    date: String,
+    #[serde(default)]
+    title: String,
+    // media_type 为 video 时 url 是视频地址，不能作为壁纸
+    media_type: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    hdurl: Option<String>,
+    #[serde(default)]
+    copyright: String,
+    #[serde(default)]
+    explanation: String,
+}
+
+// NASA 每日天文图（APOD），部分日期发布的是视频，这些日期会被跳过
+pub struct ApodProvider {
+    client: HttpClient,
+    api_key: String,
+}
+
+impl ApodProvider {
+    pub fn new(client: HttpClient, api_key: Option<String>) -> Self {
+        let api_key = api_key.filter(|key| !key.is_empty()).unwrap_or_else(|| APOD_DEMO_KEY.to_string());
+        ApodProvider { client, api_key }
+    }
+
+    // UHD 使用高清原图，其它分辨率使用网页展示用的图片
+    fn to_info(entry: ApodEntry, resolution: Resolution) -> Option<WallpaperInfo> {
+        if entry.media_type != "image" {
+            return None;
+        }
+        let url = match entry.hdurl {
+            Some(hdurl) if resolution == Resolution::Uhd => hdurl,
+            _ => entry.url,
+        };
+        Some(WallpaperInfo {
+            file_name: format!("apod_{}_{}", entry.date.replace('-', ""), url_file_name(&url)),
+            url,
+            title: entry.title,
+            date: entry.date,
+            copyright: entry.copyright.trim().to_string(),
+            description: entry.explanation.trim().to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+impl Provider for ApodProvider {
+    fn name(&self) -> &'static str {
+        "nasa-apod"
+    }
+
+    async fn fetch_today(&self, resolution: Resolution) -> Result<WallpaperInfo> {
+        let request = self.client.get(APOD_API_URL).query(&[("api_key", self.api_key.as_str())]);
+        let body = http::send(request).await?.text().await?;
+        let entry: ApodEntry = serde_json::from_str(&body)?;
+        Self::to_info(entry, resolution).ok_or_else(|| Error::Parse("Today's APOD is not an image".to_string()))
+    }
+
+    async fn fetch_archive(&self, days: u32) -> Result<Vec<WallpaperInfo>> {
+        let end = chrono::Local::now().date_naive();
+        let start = end - chrono::Duration::days(days.saturating_sub(1) as i64);
+        let request = self.client.get(APOD_API_URL).query(&[
+            ("api_key", self.api_key.as_str()),
+            ("start_date", start.format("%Y-%m-%d").to_string().as_str()),
+            ("end_date", end.format("%Y-%m-%d").to_string().as_str()),
+        ]);
+        let body = http::send(request).await?.text().await?;
+        let entries: Vec<ApodEntry> = serde_json::from_str(&body)?;
+        // 接口按日期升序返回，历史记录按日期倒序保存
+        Ok(entries.into_iter().rev().filter_map(|entry| Self::to_info(entry, Resolution::Auto)).collect())
+    }
+}
+
+pub const UNSPLASH_API_URL: &str = "https://api.unsplash.com";
+const UNSPLASH_ARCHIVE_MAX: u32 = 30;
+
+#[derive(Deserialize)]
+struct UnsplashPhoto {
+    id: String,
+    created_at: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    alt_description: Option<String>,
+    urls: UnsplashUrls,
+    user: UnsplashUser,
+}
+
+#[derive(Deserialize)]
+struct UnsplashUrls {
+    raw: String,
+}
+
+#[derive(Deserialize)]
+struct UnsplashUser {
+    name: String,
+}
+
+// Unsplash 图片，需要在 Unsplash 开发者后台申请 Access Key；指定合集时只从该合集中挑选
+pub struct UnsplashProvider {
+    client: HttpClient,
+    access_key: String,
+    collection: Option<String>,
+}
+
+impl UnsplashProvider {
+    pub fn new(client: HttpClient, access_key: &str, collection: Option<String>) -> Self {
+        UnsplashProvider {
+            client,
+            access_key: access_key.to_string(),
+            collection: collection.filter(|collection| !collection.is_empty()),
+        }
+    }
+
+    fn request(&self, path: &str) -> RequestBuilder {
+        self.client
+            .get(&format!("{}{}", UNSPLASH_API_URL, path))
+            .header("Accept-Version", "v1")
+            .header("Authorization", format!("Client-ID {}", self.access_key))
+    }
+
+    // raw 地址支持按宽度裁剪，按分辨率设置请求对应宽度的图片
+    fn to_info(photo: UnsplashPhoto, resolution: Resolution) -> WallpaperInfo {
+        let width = if resolution == Resolution::Uhd { 3840 } else { 1920 };
+        let separator = if photo.urls.raw.contains('?') { '&' } else { '?' };
+        let title = photo.description.or(photo.alt_description).unwrap_or_default();
+        WallpaperInfo {
+            file_name: format!("unsplash_{}_{}w.jpg", photo.id, width),
+            url: format!("{}{}w={}&fm=jpg&q=85", photo.urls.raw, separator, width),
+            title: title.trim().to_string(),
+            date: photo.created_at.get(..10).unwrap_or_default().replace('-', ""),
+            copyright: format!("{} / Unsplash", photo.user.name),
+            ..Default::default()
+        }
+    }
+}
+
+impl Provider for UnsplashProvider {
+    fn name(&self) -> &'static str {
+        "unsplash"
+    }
+
+    // 每次请求随机返回一张横屏图片；日期记为今天，便于定时器按天判断是否已更新
+    async fn fetch_today(&self, resolution: Resolution) -> Result<WallpaperInfo> {
+        let mut request = self.request("/photos/random").query(&[("orientation", "landscape")]);
+        if let Some(collection) = &self.collection {
+            request = request.query(&[("collections", collection.as_str())]);
+        }
+        let body = http::send(request).await?.text().await?;
+        let photo: UnsplashPhoto = serde_json::from_str(&body)?;
+        let mut info = Self::to_info(photo, resolution);
+        info.date = today();
+        Ok(info)
+    }
+
+    // 只有指定了合集时才有可浏览的列表
+    async fn fetch_archive(&self, days: u32) -> Result<Vec<WallpaperInfo>> {
+        let Some(collection) = &self.collection else {
+            return Ok(Vec::new());
+        };
+        let request = self
+            .request(&format!("/collections/{}/photos", collection))
+            .query(&[("per_page", days.min(UNSPLASH_ARCHIVE_MAX))]);
+        let body = http::send(request).await?.text().await?;
+        let photos: Vec<UnsplashPhoto> = serde_json::from_str(&body)?;
+        Ok(photos.into_iter().map(|photo| Self::to_info(photo, Resolution::Auto)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Timeouts;
+
+    fn bing(is_china: bool) -> BingProvider {
+        BingProvider::new(HttpClient::new(None, Timeouts::default()).unwrap(), is_china)
+    }
+
+    fn image() -> BingImage {
+        BingImage {
+            startdate: "20240101".to_string(),
+            urlbase: "/th?id=OHR.LakeLouise_ZH-CN1234567890".to_string(),
+            title: "Lake Louise".to_string(),
+            copyright: "Lake Louise, Alberta, Canada (© Paul Zizka/Minden Pictures)".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_bing_to_info_auto_resolution() {
+        let info = bing(true).to_info(image(), Resolution::Auto);
+        assert_eq!(info.file_name, "OHR.LakeLouise_ZH-CN1234567890_1920x1080.jpg");
+        assert_eq!(info.url, "https://cn.bing.com/th?id=OHR.LakeLouise_ZH-CN1234567890_1920x1080.jpg");
+        assert_eq!(info.title, "Lake Louise");
+        assert_eq!(info.date, "20240101");
+        assert_eq!(info.location().as_deref(), Some("Lake Louise, Alberta, Canada"));
+        assert!(info.sha256.is_empty());
+    }
+
+    #[test]
+    fn test_bing_to_info_global_uhd() {
+        let info = bing(false).to_info(image(), Resolution::Uhd);
+        assert_eq!(info.file_name, "OHR.LakeLouise_ZH-CN1234567890_UHD.jpg");
+        assert_eq!(info.url, "https://www.bing.com/th?id=OHR.LakeLouise_ZH-CN1234567890_UHD.jpg");
+        // 换成其它分辨率时文件名和地址一起变化
+        let info = info.with_resolution(Resolution::FullHd);
+        assert_eq!(info.file_name, "OHR.LakeLouise_ZH-CN1234567890_1920x1080.jpg");
+        assert_eq!(info.url, "https://www.bing.com/th?id=OHR.LakeLouise_ZH-CN1234567890_1920x1080.jpg");
+    }
+
+    #[test]
+    fn test_bing_archive_response() {
+        let body = r#"{"images":[{"startdate":"20240101","urlbase":"/th?id=OHR.A_EN-US1","copyright":"A (© B)","title":"A","hsh":"x"},
+            {"startdate":"20231231","urlbase":"/th?id=OHR.C_EN-US2"}],"tooltips":{}}"#;
+        let archive: BingArchive = serde_json::from_str(body).unwrap();
+        assert_eq!(archive.images.len(), 2);
+        let info = bing(false).to_info(archive.images.into_iter().nth(1).unwrap(), Resolution::FullHd);
+        assert_eq!(info.file_name, "OHR.C_EN-US2_1920x1080.jpg");
+        assert_eq!(info.date, "20231231");
+        assert!(info.title.is_empty() && info.copyright.is_empty());
+    }
+}
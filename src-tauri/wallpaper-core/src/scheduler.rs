@@ -0,0 +1,264 @@
+use std::{
+    future::Future,
+    time::{Duration, SystemTime},
+};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use log::info;
+use tokio_util::sync::CancellationToken;
+
+use crate::{Error, Result};
+
+// 每次最多睡眠的时长，用于检测休眠唤醒
+const TICK: Duration = Duration::from_secs(30);
+// 两次 tick 之间的墙上时间差超过 TICK + 该值，认为系统刚从休眠中恢复
+const WAKE_THRESHOLD: Duration = Duration::from_secs(120);
+// 每日定时在夏令时切换等无法换算的情况下的重试间隔
+const DAILY_FALLBACK: Duration = Duration::from_secs(3600);
+// 过了零点后多等一会再请求，给接口留出更新的时间
+const ROLLOVER_GRACE: Duration = Duration::from_secs(5 * 60);
+// 已过零点但接口还没有返回新图片时的轮询间隔
+const ROLLOVER_RETRY: Duration = Duration::from_secs(15 * 60);
+
+pub enum Schedule {
+    Interval(Duration),
+    DailyAt(NaiveTime),
+    // 跟随必应每日更新：参数为当前壁纸的日期，未知时按尚未更新处理
+    Rollover(Option<NaiveDate>),
+}
+
+pub enum Trigger {
+    Scheduled,
+    Resumed,
+}
+
+pub fn parse_daily_time(s: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(s.trim(), "%H:%M").map_err(|_| Error::Config(format!("时间格式应为 HH:MM: {}", s)))
+}
+
+// 决定下一次刷新的时间；wait_for_next 提供了按 tick 睡眠并检测休眠唤醒的默认实现
+pub trait Scheduler {
+    fn next_delay(&self, now: DateTime<Local>) -> Duration;
+
+    // 等待到下一次需要刷新；返回 None 表示定时任务已被取消
+    fn wait_for_next(&self, cancel: &CancellationToken) -> impl Future<Output = Option<Trigger>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let delay = self.next_delay(Local::now());
+            let deadline = SystemTime::now() + delay;
+            info!("Next refresh in {}s", delay.as_secs());
+
+            let mut last_tick = SystemTime::now();
+            loop {
+                let remaining = deadline.duration_since(SystemTime::now()).unwrap_or_default();
+                tokio::select! {
+                    _ = cancel.cancelled() => return None,
+                    _ = tokio::time::sleep(TICK.min(remaining).max(Duration::from_secs(1))) => {}
+                }
+
+                // sleep 使用的单调时钟在休眠期间不走，墙上时间却会跳变
+                let now = SystemTime::now();
+                let elapsed = now.duration_since(last_tick).unwrap_or_default();
+                if elapsed > TICK + WAKE_THRESHOLD {
+                    info!("Detected resume from sleep after {}s", elapsed.as_secs());
+                    return Some(Trigger::Resumed);
+                }
+                if now >= deadline {
+                    return Some(Trigger::Scheduled);
+                }
+                last_tick = now;
+            }
+        }
+    }
+}
+
+// 本地时间落在夏令时跳过的区间时（例如调快时钟那天的 02:30 不存在），顺延一小时
+fn resolve_local<Tz: TimeZone>(tz: &Tz, local: NaiveDateTime) -> Option<DateTime<Tz>> {
+    tz.from_local_datetime(&local)
+        .earliest()
+        .or_else(|| tz.from_local_datetime(&(local + chrono::Duration::hours(1))).earliest())
+}
+
+impl Schedule {
+    // 与时区无关的计算，Scheduler::next_delay 按本地时区调用
+    fn delay_from<Tz: TimeZone>(&self, now: DateTime<Tz>) -> Duration {
+        let tz = now.timezone();
+        match self {
+            Schedule::Interval(interval) => *interval,
+            Schedule::DailyAt(time) => {
+                let today = now.date_naive().and_time(*time);
+                let next = match resolve_local(&tz, today) {
+                    Some(next) if next > now => Some(next),
+                    _ => resolve_local(&tz, today + chrono::Duration::days(1)),
+                };
+                next.and_then(|next| (next - now.clone()).to_std().ok()).unwrap_or(DAILY_FALLBACK)
+            }
+            Schedule::Rollover(latest) => {
+                // 当前壁纸不是今天的，说明新图片还没出现，改为短间隔重试
+                if !latest.is_some_and(|date| date >= now.date_naive()) {
+                    return ROLLOVER_RETRY;
+                }
+                // 否则睡到本地的下一个零点；接口日期比本地超前（例如在美国使用国内区域）时同样等到零点
+                let midnight = now.date_naive().succ_opt().map(|date| date.and_time(NaiveTime::MIN));
+                midnight
+                    .and_then(|midnight| resolve_local(&tz, midnight))
+                    .and_then(|next| (next - now.clone()).to_std().ok())
+                    .map(|delay| delay + ROLLOVER_GRACE)
+                    .unwrap_or(DAILY_FALLBACK)
+            }
+        }
+    }
+}
+
+impl Scheduler for Schedule {
+    fn next_delay(&self, now: DateTime<Local>) -> Duration {
+        self.delay_from(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, LocalResult};
+
+    use super::*;
+
+    fn hours(hours: u64) -> Duration {
+        Duration::from_secs(hours * 3600)
+    }
+
+    fn at(date: (i32, u32, u32), time: (u32, u32)) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap().and_hms_opt(time.0, time.1, 0).unwrap()
+    }
+
+    fn beijing(date: (i32, u32, u32), time: (u32, u32)) -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(8 * 3600).unwrap().from_local_datetime(&at(date, time)).unwrap()
+    }
+
+    // 2024-03-31 本地 02:00 起从 UTC+1 调快到 UTC+2（与中欧夏令时相同），02:00~03:00 不存在
+    #[derive(Clone, Copy, Debug)]
+    struct SpringForward;
+
+    impl SpringForward {
+        fn utc_plus(hours: i32) -> FixedOffset {
+            FixedOffset::east_opt(hours * 3600).unwrap()
+        }
+
+        fn local(date: (i32, u32, u32), time: (u32, u32)) -> DateTime<SpringForward> {
+            SpringForward.from_local_datetime(&at(date, time)).unwrap()
+        }
+    }
+
+    impl TimeZone for SpringForward {
+        type Offset = FixedOffset;
+
+        fn from_offset(_offset: &FixedOffset) -> Self {
+            SpringForward
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            if *local < at((2024, 3, 31), (2, 0)) {
+                LocalResult::Single(Self::utc_plus(1))
+            } else if *local < at((2024, 3, 31), (3, 0)) {
+                LocalResult::None
+            } else {
+                LocalResult::Single(Self::utc_plus(2))
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            if *utc < at((2024, 3, 31), (1, 0)) { Self::utc_plus(1) } else { Self::utc_plus(2) }
+        }
+    }
+
+    #[test]
+    fn test_spring_forward_zone() {
+        assert_eq!(*SpringForward::local((2024, 3, 31), (1, 0)).offset(), SpringForward::utc_plus(1));
+        assert_eq!(*SpringForward::local((2024, 3, 31), (3, 0)).offset(), SpringForward::utc_plus(2));
+        assert!(SpringForward.from_local_datetime(&at((2024, 3, 31), (2, 30))).earliest().is_none());
+    }
+
+    #[test]
+    fn test_interval() {
+        let schedule = Schedule::Interval(Duration::from_secs(90));
+        assert_eq!(schedule.delay_from(beijing((2024, 1, 1), (12, 0))), Duration::from_secs(90));
+    }
+
+    #[test]
+    fn test_daily_at_later_today() {
+        let schedule = Schedule::DailyAt(NaiveTime::from_hms_opt(8, 0, 0).unwrap());
+        assert_eq!(schedule.delay_from(beijing((2024, 1, 1), (7, 0))), hours(1));
+    }
+
+    #[test]
+    fn test_daily_at_already_passed() {
+        let schedule = Schedule::DailyAt(NaiveTime::from_hms_opt(8, 0, 0).unwrap());
+        assert_eq!(schedule.delay_from(beijing((2024, 1, 1), (9, 0))), hours(23));
+        // 恰好到点时等到明天，不会立即重复触发
+        assert_eq!(schedule.delay_from(beijing((2024, 1, 1), (8, 0))), hours(24));
+    }
+
+    #[test]
+    fn test_daily_at_dst_gap_today() {
+        // 02:30 在当天不存在，顺延到 03:30（UTC+2），距 01:00（UTC+1）1.5 小时
+        let schedule = Schedule::DailyAt(NaiveTime::from_hms_opt(2, 30, 0).unwrap());
+        let delay = schedule.delay_from(SpringForward::local((2024, 3, 31), (1, 0)));
+        assert_eq!(delay, Duration::from_secs(90 * 60));
+    }
+
+    #[test]
+    fn test_daily_at_dst_gap_tomorrow() {
+        // 前一天 12:00（UTC+1）到次日顺延后的 03:30（UTC+2）共 14.5 小时
+        let schedule = Schedule::DailyAt(NaiveTime::from_hms_opt(2, 30, 0).unwrap());
+        let delay = schedule.delay_from(SpringForward::local((2024, 3, 30), (12, 0)));
+        assert_eq!(delay, Duration::from_secs(14 * 3600 + 30 * 60));
+    }
+
+    #[test]
+    fn test_daily_at_across_dst_change() {
+        // 调快时钟的那一夜只有 23 小时
+        let schedule = Schedule::DailyAt(NaiveTime::from_hms_opt(8, 0, 0).unwrap());
+        assert_eq!(schedule.delay_from(SpringForward::local((2024, 3, 30), (8, 0))), hours(23));
+    }
+
+    #[test]
+    fn test_rollover_waits_for_new_image() {
+        let now = beijing((2024, 1, 2), (10, 0));
+        let yesterday = NaiveDate::from_ymd_opt(2024, 1, 1);
+        assert_eq!(Schedule::Rollover(None).delay_from(now), ROLLOVER_RETRY);
+        assert_eq!(Schedule::Rollover(yesterday).delay_from(now), ROLLOVER_RETRY);
+    }
+
+    #[test]
+    fn test_rollover_sleeps_until_midnight() {
+        let now = beijing((2024, 1, 2), (22, 0));
+        let today = NaiveDate::from_ymd_opt(2024, 1, 2);
+        assert_eq!(Schedule::Rollover(today).delay_from(now), hours(2) + ROLLOVER_GRACE);
+        // 接口日期比本地超前时同样等到本地零点
+        let tomorrow = NaiveDate::from_ymd_opt(2024, 1, 3);
+        assert_eq!(Schedule::Rollover(tomorrow).delay_from(now), hours(2) + ROLLOVER_GRACE);
+    }
+
+    #[test]
+    fn test_rollover_across_dst_change() {
+        // 01:00（UTC+1）到次日零点（UTC+2）只有 22 小时
+        let now = SpringForward::local((2024, 3, 31), (1, 0));
+        let today = NaiveDate::from_ymd_opt(2024, 3, 31);
+        assert_eq!(Schedule::Rollover(today).delay_from(now), hours(22) + ROLLOVER_GRACE);
+    }
+
+    #[test]
+    fn test_parse_daily_time() {
+        assert_eq!(parse_daily_time(" 08:30 ").unwrap(), NaiveTime::from_hms_opt(8, 30, 0).unwrap());
+        assert!(parse_daily_time("8点").is_err());
+        assert!(parse_daily_time("25:00").is_err());
+    }
+}
@@ -0,0 +1,925 @@
+use std::path::{Path, PathBuf};
+#[cfg(not(target_os = "windows"))]
+use std::process::Command;
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+use std::process::Stdio;
+
+#[cfg(target_os = "windows")]
+use winapi::{
+    um::{
+        combaseapi::{CoCreateInstance, CoTaskMemFree, CoUninitialize, CLSCTX_ALL},
+        objbase::{CoInitializeEx, COINIT_APARTMENTTHREADED},
+        shobjidl_core::{
+            DesktopWallpaper, IDesktopWallpaper, DESKTOP_WALLPAPER_POSITION, DWPOS_CENTER, DWPOS_FILL, DWPOS_FIT,
+            DWPOS_SPAN, DWPOS_STRETCH, DWPOS_TILE,
+        },
+        winuser::{
+            SystemParametersInfoW, SPI_GETDESKWALLPAPER, SPI_SETDESKWALLPAPER,
+            SPIF_UPDATEINIFILE, SPIF_SENDCHANGE,
+        },
+    },
+    shared::{minwindef::TRUE, windef::RECT, winerror::SUCCEEDED},
+    Class, Interface,
+};
+
+use crate::{Error, Result};
+
+// 可以单独设置壁纸的显示器；id 由各实现自己定义，只用于传回 set_monitor，宽高为物理像素
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    pub id: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+// 图片与屏幕比例不同时的显示方式；Fill 裁剪铺满，Fit 完整显示并留边，Span 在多显示器上拼成一张
+#[derive(Clone, Copy, PartialEq)]
+pub enum Fit {
+    Fill,
+    Fit,
+    Stretch,
+    Center,
+    Tile,
+    Span,
+}
+
+impl Fit {
+    pub const ALL: [Fit; 6] = [Fit::Fill, Fit::Fit, Fit::Stretch, Fit::Center, Fit::Tile, Fit::Span];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Fit::Fill => "fill",
+            Fit::Fit => "fit",
+            Fit::Stretch => "stretch",
+            Fit::Center => "center",
+            Fit::Tile => "tile",
+            Fit::Span => "span",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "fit" => Fit::Fit,
+            "stretch" => Fit::Stretch,
+            "center" => Fit::Center,
+            "tile" => Fit::Tile,
+            "span" => Fit::Span,
+            _ => Fit::Fill,
+        }
+    }
+
+    // GNOME、Cinnamon、MATE 的 picture-options 取值
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    fn gsettings_option(&self) -> &'static str {
+        match self {
+            Fit::Fill => "zoom",
+            Fit::Fit => "scaled",
+            Fit::Stretch => "stretched",
+            Fit::Center => "centered",
+            Fit::Tile => "wallpaper",
+            Fit::Span => "spanned",
+        }
+    }
+
+    // swww img --resize 的取值：crop 裁剪铺满、fit 完整显示、no 不缩放居中；不支持拉伸和平铺
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    fn swww_resize(&self) -> &'static str {
+        match self {
+            Fit::Fill | Fit::Stretch | Fit::Span => "crop",
+            Fit::Fit => "fit",
+            Fit::Center | Fit::Tile => "no",
+        }
+    }
+
+    // hyprpaper 在图片路径前加 contain: 或 tile: 前缀，不加时裁剪铺满
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    fn hyprpaper_prefix(&self) -> &'static str {
+        match self {
+            Fit::Fit => "contain:",
+            Fit::Tile => "tile:",
+            Fit::Fill | Fit::Stretch | Fit::Center | Fit::Span => "",
+        }
+    }
+
+    // swaybg -m 的取值，没有跨屏模式
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    fn swaybg_mode(&self) -> &'static str {
+        match self {
+            Fit::Fill | Fit::Span => "fill",
+            Fit::Fit => "fit",
+            Fit::Stretch => "stretch",
+            Fit::Center => "center",
+            Fit::Tile => "tile",
+        }
+    }
+}
+
+// 各平台/桌面环境设置壁纸的方式
+pub trait WallpaperSetter {
+    fn name(&self) -> &'static str;
+    fn set(&self, path: &Path) -> Result<()>;
+    // 读取当前桌面壁纸的文件路径，无法确定时返回 None
+    fn current(&self) -> Result<Option<PathBuf>>;
+
+    // 列出可以单独设置壁纸的显示器，不支持按显示器设置的实现返回空列表
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        Ok(Vec::new())
+    }
+
+    fn set_monitor(&self, _monitor: &Monitor, path: &Path) -> Result<()> {
+        self.set(path)
+    }
+
+    // 修改已设置壁纸的显示方式，需在 set 之后调用；不支持的实现忽略
+    fn set_fit(&self, _fit: Fit) -> Result<()> {
+        Ok(())
+    }
+}
+
+// 解析 "1920x1080" 形式的尺寸
+#[cfg(not(target_os = "windows"))]
+fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.trim().split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+// gsettings get 的输出带有单引号，例如 'file:///home/me/a.jpg'
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn parse_gsettings_path(output: &str) -> Option<PathBuf> {
+    let value = output.trim().trim_matches('\'');
+    if value.is_empty() {
+        return None;
+    }
+    let path = match value.strip_prefix("file://") {
+        Some(path) => percent_decode_path(path),
+        None => value.to_string(),
+    };
+    Some(PathBuf::from(path))
+}
+
+fn path_str(path: &Path) -> Result<&str> {
+    path.to_str().ok_or_else(|| Error::OsSet(format!("Invalid wallpaper path: {:?}", path)))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn run_command(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program).args(args).output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        Err(Error::OsSet(format!("{} failed: {}", program, error_msg.trim())))
+    }
+}
+
+#[cfg(target_os = "macos")]
+struct MacosSetter;
+
+#[cfg(target_os = "macos")]
+impl WallpaperSetter for MacosSetter {
+    fn name(&self) -> &'static str {
+        "macos"
+    }
+
+    // 路径作为 osascript 的参数传入，不拼进脚本，文件名中的引号等字符不会被当作脚本执行
+    fn set(&self, path: &Path) -> Result<()> {
+        run_command(
+            "osascript",
+            &[
+                "-e",
+                "on run argv",
+                "-e",
+                "tell application \"System Events\" to tell every desktop to set picture to (item 1 of argv)",
+                "-e",
+                "end run",
+                path_str(path)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn current(&self) -> Result<Option<PathBuf>> {
+        let output = run_command(
+            "osascript",
+            &["-e", "tell application \"System Events\" to get picture of current desktop"],
+        )?;
+        let path = output.trim();
+        Ok(if path.is_empty() { None } else { Some(PathBuf::from(path)) })
+    }
+
+    // System Events 拿不到显示器尺寸，这里通过 JXA 调用 NSScreen，id 为屏幕序号
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        let script = "ObjC.import('AppKit');\
+                      var screens = $.NSScreen.screens, lines = [];\
+                      for (var i = 0; i < screens.count; i++) {\
+                          var screen = screens.objectAtIndex(i), size = screen.frame.size, scale = screen.backingScaleFactor;\
+                          lines.push(i + ' ' + Math.round(size.width * scale) + 'x' + Math.round(size.height * scale));\
+                      }\
+                      lines.join('\\n');";
+        let output = run_command("osascript", &["-l", "JavaScript", "-e", script])?;
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let (id, size) = line.split_once(' ')?;
+                let (width, height) = parse_size(size)?;
+                Some(Monitor { id: id.to_string(), width, height })
+            })
+            .collect())
+    }
+
+    // 与 set 一样通过参数传入屏幕序号和路径
+    fn set_monitor(&self, monitor: &Monitor, path: &Path) -> Result<()> {
+        let script = "ObjC.import('AppKit');\
+                      function run(argv) {\
+                          var screen = $.NSScreen.screens.objectAtIndex(parseInt(argv[0], 10));\
+                          $.NSWorkspace.sharedWorkspace.setDesktopImageURLForScreenOptionsError(\
+                              $.NSURL.fileURLWithPath(argv[1]), screen, $({}), null);\
+                      }";
+        run_command("osascript", &["-l", "JavaScript", "-e", script, &monitor.id, path_str(path)?])?;
+        Ok(())
+    }
+
+    // 缩放方式只能随图片一起设置，这里把每个屏幕当前的图片带上选项重新设置一次；
+    // 系统不支持平铺和跨屏，分别按居中和铺满处理
+    fn set_fit(&self, fit: Fit) -> Result<()> {
+        // NSImageScaling：1 拉伸，2 不缩放，3 等比缩放
+        let (scaling, clipping) = match fit {
+            Fit::Fill | Fit::Span => (3, true),
+            Fit::Fit => (3, false),
+            Fit::Stretch => (1, false),
+            Fit::Center | Fit::Tile => (2, false),
+        };
+        let script = format!(
+            "ObjC.import('AppKit');\
+             var workspace = $.NSWorkspace.sharedWorkspace, screens = $.NSScreen.screens;\
+             var options = $.NSMutableDictionary.dictionary;\
+             options.setObjectForKey($.NSNumber.numberWithInt({}), $.NSWorkspaceDesktopImageScalingKey);\
+             options.setObjectForKey($.NSNumber.numberWithBool({}), $.NSWorkspaceDesktopImageAllowClippingKey);\
+             for (var i = 0; i < screens.count; i++) {{\
+                 var screen = screens.objectAtIndex(i);\
+                 workspace.setDesktopImageURLForScreenOptionsError(workspace.desktopImageURLForScreen(screen), screen, options, null);\
+             }}",
+            scaling, clipping
+        );
+        run_command("osascript", &["-l", "JavaScript", "-e", &script])?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+struct WindowsSetter;
+
+#[cfg(target_os = "windows")]
+impl WallpaperSetter for WindowsSetter {
+    fn name(&self) -> &'static str {
+        "windows"
+    }
+
+    // 优先通过 IDesktopWallpaper 设置（monitor 为空时对所有显示器生效），资源管理器会平滑过渡，
+    // 不像 SystemParametersInfo 那样先闪一下纯色背景；创建 COM 对象失败时退回 SystemParametersInfoW
+    fn set(&self, path: &Path) -> Result<()> {
+        let path = to_wide(path_str(path)?);
+        let result = with_desktop_wallpaper(|wallpaper| unsafe {
+            check_hresult(wallpaper.SetWallpaper(std::ptr::null(), path.as_ptr()), "SetWallpaper")
+        });
+        let Err(e) = result else {
+            return Ok(());
+        };
+        log::warn!("Falling back to SystemParametersInfoW: {}", e);
+        unsafe {
+            if SystemParametersInfoW(
+                SPI_SETDESKWALLPAPER,
+                0,
+                path.as_ptr() as _,
+                SPIF_UPDATEINIFILE | SPIF_SENDCHANGE,
+            ) == TRUE
+            {
+                Ok(())
+            } else {
+                Err(Error::OsSet("Failed to set wallpaper on Windows".to_string()))
+            }
+        }
+    }
+
+    fn current(&self) -> Result<Option<PathBuf>> {
+        let mut buffer = [0u16; 1024];
+        let ok = unsafe {
+            SystemParametersInfoW(SPI_GETDESKWALLPAPER, buffer.len() as u32, buffer.as_mut_ptr() as _, 0)
+        };
+        if ok != TRUE {
+            return Err(Error::OsSet("Failed to read wallpaper on Windows".to_string()));
+        }
+        let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        let path = String::from_utf16_lossy(&buffer[..len]);
+        Ok(if path.is_empty() { None } else { Some(PathBuf::from(path)) })
+    }
+
+    // 通过 IDesktopWallpaper 枚举显示器，id 为显示器的设备路径
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        with_desktop_wallpaper(|wallpaper| unsafe {
+            let mut count = 0;
+            check_hresult(wallpaper.GetMonitorDevicePathCount(&mut count), "GetMonitorDevicePathCount")?;
+            let mut monitors = Vec::new();
+            for index in 0..count {
+                let mut id = std::ptr::null_mut();
+                if !SUCCEEDED(wallpaper.GetMonitorDevicePathAt(index, &mut id)) || id.is_null() {
+                    continue;
+                }
+                let mut rect: RECT = std::mem::zeroed();
+                let ok = SUCCEEDED(wallpaper.GetMonitorRECT(id, &mut rect));
+                let len = (0..).take_while(|&i| *id.add(i) != 0).count();
+                let device_path = String::from_utf16_lossy(std::slice::from_raw_parts(id, len));
+                CoTaskMemFree(id as _);
+                // 未连接的显示器也会列出，但矩形为空
+                if ok && rect.right > rect.left && rect.bottom > rect.top {
+                    monitors.push(Monitor {
+                        id: device_path,
+                        width: (rect.right - rect.left) as u32,
+                        height: (rect.bottom - rect.top) as u32,
+                    });
+                }
+            }
+            Ok(monitors)
+        })
+    }
+
+    fn set_monitor(&self, monitor: &Monitor, path: &Path) -> Result<()> {
+        let id = to_wide(&monitor.id);
+        let path = to_wide(path_str(path)?);
+        with_desktop_wallpaper(|wallpaper| unsafe {
+            check_hresult(wallpaper.SetWallpaper(id.as_ptr(), path.as_ptr()), "SetWallpaper")
+        })
+    }
+
+    // 显示方式对所有显示器生效，等同于设置中“选择契合度”
+    fn set_fit(&self, fit: Fit) -> Result<()> {
+        let position: DESKTOP_WALLPAPER_POSITION = match fit {
+            Fit::Fill => DWPOS_FILL,
+            Fit::Fit => DWPOS_FIT,
+            Fit::Stretch => DWPOS_STRETCH,
+            Fit::Center => DWPOS_CENTER,
+            Fit::Tile => DWPOS_TILE,
+            Fit::Span => DWPOS_SPAN,
+        };
+        with_desktop_wallpaper(|wallpaper| unsafe { check_hresult(wallpaper.SetPosition(position), "SetPosition") })
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn to_wide(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+#[cfg(target_os = "windows")]
+fn check_hresult(hr: i32, call: &str) -> Result<()> {
+    if SUCCEEDED(hr) {
+        Ok(())
+    } else {
+        Err(Error::OsSet(format!("{} failed: 0x{:08X}", call, hr)))
+    }
+}
+
+// 在当前线程初始化 COM 并创建 DesktopWallpaper 对象，用完后释放
+#[cfg(target_os = "windows")]
+fn with_desktop_wallpaper<T>(f: impl FnOnce(&IDesktopWallpaper) -> Result<T>) -> Result<T> {
+    unsafe {
+        // 线程已按其它模式初始化过 COM 时返回失败，此时不需要也不能调用 CoUninitialize
+        let initialized = SUCCEEDED(CoInitializeEx(std::ptr::null_mut(), COINIT_APARTMENTTHREADED));
+        let mut wallpaper: *mut IDesktopWallpaper = std::ptr::null_mut();
+        let hr = CoCreateInstance(
+            &DesktopWallpaper::uuidof(),
+            std::ptr::null_mut(),
+            CLSCTX_ALL,
+            &IDesktopWallpaper::uuidof(),
+            &mut wallpaper as *mut _ as *mut _,
+        );
+        let result = if SUCCEEDED(hr) && !wallpaper.is_null() {
+            let result = f(&*wallpaper);
+            (*wallpaper).Release();
+            result
+        } else {
+            Err(Error::OsSet(format!("CoCreateInstance(DesktopWallpaper) failed: 0x{:08X}", hr)))
+        };
+        if initialized {
+            CoUninitialize();
+        }
+        result
+    }
+}
+
+// GNOME 与 Cinnamon 共用 gsettings 的 picture-uri 键，只是 schema 不同
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+struct GsettingsSetter {
+    schema: &'static str,
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+impl WallpaperSetter for GsettingsSetter {
+    fn name(&self) -> &'static str {
+        self.schema
+    }
+
+    fn set(&self, path: &Path) -> Result<()> {
+        let uri = format!("file://{}", percent_encode_path(path_str(path)?));
+        run_command("gsettings", &["set", self.schema, "picture-uri", &uri])?;
+        // GNOME 42 起深色模式使用单独的键，旧版本没有该键，忽略失败
+        let _ = run_command("gsettings", &["set", self.schema, "picture-uri-dark", &uri]);
+        Ok(())
+    }
+
+    fn current(&self) -> Result<Option<PathBuf>> {
+        let output = run_command("gsettings", &["get", self.schema, "picture-uri"])?;
+        Ok(parse_gsettings_path(&output))
+    }
+
+    fn set_fit(&self, fit: Fit) -> Result<()> {
+        run_command("gsettings", &["set", self.schema, "picture-options", fit.gsettings_option()])?;
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+struct MateSetter;
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+impl WallpaperSetter for MateSetter {
+    fn name(&self) -> &'static str {
+        "mate"
+    }
+
+    fn set(&self, path: &Path) -> Result<()> {
+        run_command("gsettings", &["set", "org.mate.background", "picture-filename", path_str(path)?])?;
+        Ok(())
+    }
+
+    fn current(&self) -> Result<Option<PathBuf>> {
+        let output = run_command("gsettings", &["get", "org.mate.background", "picture-filename"])?;
+        Ok(parse_gsettings_path(&output))
+    }
+
+    fn set_fit(&self, fit: Fit) -> Result<()> {
+        run_command("gsettings", &["set", "org.mate.background", "picture-options", fit.gsettings_option()])?;
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+struct KdeSetter;
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+impl WallpaperSetter for KdeSetter {
+    fn name(&self) -> &'static str {
+        "kde"
+    }
+
+    // 通过 Plasma 脚本接口修改所有桌面的壁纸，不同发行版的 qdbus 命令名不同
+    fn set(&self, path: &Path) -> Result<()> {
+        let escaped = path_str(path)?.replace('\\', "\\\\").replace('"', "\\\"");
+        let script = format!(
+            "var allDesktops = desktops();\
+             for (var i = 0; i < allDesktops.length; i++) {{\
+                 var d = allDesktops[i];\
+                 d.wallpaperPlugin = \"org.kde.image\";\
+                 d.currentConfigGroup = [\"Wallpaper\", \"org.kde.image\", \"General\"];\
+                 d.writeConfig(\"Image\", \"file://{}\");\
+             }}",
+            escaped
+        );
+        evaluate_plasma_script(&script)?;
+        Ok(())
+    }
+
+    // 以第一个桌面的壁纸为准
+    fn current(&self) -> Result<Option<PathBuf>> {
+        let script = "var d = desktops()[0];\
+                      d.currentConfigGroup = [\"Wallpaper\", \"org.kde.image\", \"General\"];\
+                      print(d.readConfig(\"Image\"));";
+        let output = evaluate_plasma_script(script)?;
+        Ok(parse_gsettings_path(&output))
+    }
+
+    // FillMode 取 Qt 的 Image.fillMode：0 拉伸、1 等比完整显示、2 等比裁剪、3 平铺、6 居中；没有跨屏模式，按裁剪处理
+    fn set_fit(&self, fit: Fit) -> Result<()> {
+        let mode = match fit {
+            Fit::Stretch => 0,
+            Fit::Fit => 1,
+            Fit::Fill | Fit::Span => 2,
+            Fit::Tile => 3,
+            Fit::Center => 6,
+        };
+        let script = format!(
+            "var allDesktops = desktops();\
+             for (var i = 0; i < allDesktops.length; i++) {{\
+                 var d = allDesktops[i];\
+                 d.currentConfigGroup = [\"Wallpaper\", \"org.kde.image\", \"General\"];\
+                 d.writeConfig(\"FillMode\", {});\
+             }}",
+            mode
+        );
+        evaluate_plasma_script(&script)?;
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn evaluate_plasma_script(script: &str) -> Result<String> {
+    let mut last_error = Error::OsSet("qdbus not found".to_string());
+    for program in ["qdbus6", "qdbus", "qdbus-qt5"] {
+        match run_command(program, &["org.kde.plasmashell", "/PlasmaShell", "org.kde.PlasmaShell.evaluateScript", script]) {
+            Ok(output) => return Ok(output),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+struct XfceSetter;
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+impl WallpaperSetter for XfceSetter {
+    fn name(&self) -> &'static str {
+        "xfce"
+    }
+
+    // XFCE 每个显示器/工作区都有独立的 last-image 属性，需要逐个设置
+    fn set(&self, path: &Path) -> Result<()> {
+        let path = path_str(path)?;
+        let properties = run_command("xfconf-query", &["-c", "xfce4-desktop", "-l"])?;
+        let mut updated = 0;
+        for property in properties.lines().filter(|p| p.ends_with("/last-image")) {
+            run_command("xfconf-query", &["-c", "xfce4-desktop", "-p", property, "-s", path])?;
+            updated += 1;
+        }
+        if updated == 0 {
+            return Err(Error::OsSet("No XFCE desktop image property found".to_string()));
+        }
+        Ok(())
+    }
+
+    fn current(&self) -> Result<Option<PathBuf>> {
+        let properties = run_command("xfconf-query", &["-c", "xfce4-desktop", "-l"])?;
+        let Some(property) = properties.lines().find(|p| p.ends_with("/last-image")) else {
+            return Ok(None);
+        };
+        let output = run_command("xfconf-query", &["-c", "xfce4-desktop", "-p", property])?;
+        Ok(parse_gsettings_path(&output))
+    }
+
+    // image-style 与 last-image 一样按显示器/工作区分开保存：1 居中、2 平铺、3 拉伸、4 等比缩放、5 裁剪铺满、6 跨屏
+    fn set_fit(&self, fit: Fit) -> Result<()> {
+        let style = match fit {
+            Fit::Center => "1",
+            Fit::Tile => "2",
+            Fit::Stretch => "3",
+            Fit::Fit => "4",
+            Fit::Fill => "5",
+            Fit::Span => "6",
+        };
+        let properties = run_command("xfconf-query", &["-c", "xfce4-desktop", "-l"])?;
+        for property in properties.lines().filter(|p| p.ends_with("/image-style")) {
+            run_command("xfconf-query", &["-c", "xfce4-desktop", "-p", property, "-s", style])?;
+        }
+        Ok(())
+    }
+}
+
+// swww、hyprpaper 和 swaybg 只能在设置图片时指定显示方式，记住最近一次 set_fit 的取值，之后的 set 沿用
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+static WAYLAND_FIT: std::sync::Mutex<Fit> = std::sync::Mutex::new(Fit::Fill);
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn wayland_fit() -> Fit {
+    *WAYLAND_FIT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// 返回显示方式是否改变，没有改变时不必重新设置图片
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn remember_wayland_fit(fit: Fit) -> bool {
+    let mut current = WAYLAND_FIT.lock().unwrap_or_else(|e| e.into_inner());
+    let changed = *current != fit;
+    *current = fit;
+    changed
+}
+
+// swww 由常驻的 swww-daemon 显示壁纸，切换时带过渡动画
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+struct SwwwSetter;
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+impl WallpaperSetter for SwwwSetter {
+    fn name(&self) -> &'static str {
+        "swww"
+    }
+
+    fn set(&self, path: &Path) -> Result<()> {
+        run_command("swww", &["img", "--resize", wayland_fit().swww_resize(), path_str(path)?])?;
+        Ok(())
+    }
+
+    // swww query 每行对应一个显示器，例如 "eDP-1: 1920x1080, scale: 1, currently displaying: image: /a.jpg"
+    fn current(&self) -> Result<Option<PathBuf>> {
+        let output = run_command("swww", &["query"])?;
+        Ok(output
+            .lines()
+            .find_map(|line| line.split_once("image: "))
+            .map(|(_, path)| PathBuf::from(path.trim())))
+    }
+
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        let output = run_command("swww", &["query"])?;
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let (name, rest) = line.split_once(": ")?;
+                let (width, height) = parse_size(rest.split(',').next()?)?;
+                Some(Monitor { id: name.trim().to_string(), width, height })
+            })
+            .collect())
+    }
+
+    fn set_monitor(&self, monitor: &Monitor, path: &Path) -> Result<()> {
+        run_command("swww", &["img", "-o", &monitor.id, "--resize", wayland_fit().swww_resize(), path_str(path)?])?;
+        Ok(())
+    }
+
+    // 用新的缩放方式重新显示每个显示器上的图片
+    fn set_fit(&self, fit: Fit) -> Result<()> {
+        if !remember_wayland_fit(fit) {
+            return Ok(());
+        }
+        let output = run_command("swww", &["query"])?;
+        for line in output.lines() {
+            let (Some((name, _)), Some((_, path))) = (line.split_once(": "), line.split_once("image: ")) else {
+                continue;
+            };
+            run_command("swww", &["img", "-o", name.trim(), "--resize", fit.swww_resize(), path.trim()])?;
+        }
+        Ok(())
+    }
+}
+
+// Hyprland 通过 hyprctl 控制 hyprpaper：先预加载图片，再应用到所有显示器，最后释放不再使用的图片
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+struct HyprpaperSetter;
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+impl WallpaperSetter for HyprpaperSetter {
+    fn name(&self) -> &'static str {
+        "hyprpaper"
+    }
+
+    fn set(&self, path: &Path) -> Result<()> {
+        let path = path_str(path)?;
+        run_command("hyprctl", &["hyprpaper", "preload", path])?;
+        // 显示器名留空表示所有显示器
+        run_command("hyprctl", &["hyprpaper", "wallpaper", &format!(",{}{}", wayland_fit().hyprpaper_prefix(), path)])?;
+        let _ = run_command("hyprctl", &["hyprpaper", "unload", "unused"]);
+        Ok(())
+    }
+
+    // listactive 每行形如 "eDP-1 = /a.jpg"
+    fn current(&self) -> Result<Option<PathBuf>> {
+        let output = run_command("hyprctl", &["hyprpaper", "listactive"])?;
+        Ok(output
+            .lines()
+            .find_map(|line| line.split_once(" = "))
+            .map(|(_, path)| PathBuf::from(strip_hyprpaper_prefix(path.trim()))))
+    }
+
+    // hyprctl monitors -j 返回的宽高是物理像素
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        #[derive(serde::Deserialize)]
+        struct HyprMonitor {
+            name: String,
+            width: u32,
+            height: u32,
+        }
+
+        let output = run_command("hyprctl", &["monitors", "-j"])?;
+        let monitors: Vec<HyprMonitor> = serde_json::from_str(&output)?;
+        Ok(monitors
+            .into_iter()
+            .map(|monitor| Monitor { id: monitor.name, width: monitor.width, height: monitor.height })
+            .collect())
+    }
+
+    fn set_monitor(&self, monitor: &Monitor, path: &Path) -> Result<()> {
+        let path = path_str(path)?;
+        run_command("hyprctl", &["hyprpaper", "preload", path])?;
+        run_command("hyprctl", &["hyprpaper", "wallpaper", &format!("{},{}{}", monitor.id, wayland_fit().hyprpaper_prefix(), path)])?;
+        let _ = run_command("hyprctl", &["hyprpaper", "unload", "unused"]);
+        Ok(())
+    }
+
+    // 已预加载的图片不用重新加载，按新的前缀重新指定到每个显示器
+    fn set_fit(&self, fit: Fit) -> Result<()> {
+        if !remember_wayland_fit(fit) {
+            return Ok(());
+        }
+        let output = run_command("hyprctl", &["hyprpaper", "listactive"])?;
+        for (name, path) in output.lines().filter_map(|line| line.split_once(" = ")) {
+            let path = strip_hyprpaper_prefix(path.trim());
+            run_command("hyprctl", &["hyprpaper", "wallpaper", &format!("{},{}{}", name.trim(), fit.hyprpaper_prefix(), path)])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn strip_hyprpaper_prefix(path: &str) -> &str {
+    path.strip_prefix("contain:").or_else(|| path.strip_prefix("tile:")).unwrap_or(path)
+}
+
+// swaybg 没有 IPC，只能结束旧进程后重新启动一个；新进程不随本程序退出
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+struct SwaybgSetter;
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+impl WallpaperSetter for SwaybgSetter {
+    fn name(&self) -> &'static str {
+        "swaybg"
+    }
+
+    fn set(&self, path: &Path) -> Result<()> {
+        let path = path_str(path)?;
+        // 没有正在运行的 swaybg 时 pkill 返回失败，忽略即可
+        let _ = run_command("pkill", &["-x", "swaybg"]);
+        Command::new("swaybg")
+            .args(["-m", wayland_fit().swaybg_mode(), "-i", path])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| Error::OsSet(format!("swaybg failed: {}", e)))?;
+        Ok(())
+    }
+
+    // 从正在运行的 swaybg 的 /proc/<pid>/cmdline 中取出 -i 参数
+    fn current(&self) -> Result<Option<PathBuf>> {
+        let Ok(output) = run_command("pgrep", &["-x", "swaybg"]) else {
+            return Ok(None);
+        };
+        let Some(pid) = output.lines().next().map(str::trim) else {
+            return Ok(None);
+        };
+        match std::fs::read(format!("/proc/{}/cmdline", pid)) {
+            Ok(cmdline) => Ok(parse_swaybg_image(&cmdline)),
+            // 进程恰好在 pgrep 之后退出
+            Err(_) => Ok(None),
+        }
+    }
+
+    // 用新的模式重新启动 swaybg 显示当前图片
+    fn set_fit(&self, fit: Fit) -> Result<()> {
+        if !remember_wayland_fit(fit) {
+            return Ok(());
+        }
+        match self.current()? {
+            Some(path) => self.set(&path),
+            None => Ok(()),
+        }
+    }
+}
+
+// cmdline 中的参数以 NUL 分隔，路径中的空格原样保留
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn parse_swaybg_image(cmdline: &[u8]) -> Option<PathBuf> {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let args: Vec<&[u8]> = cmdline.split(|byte| *byte == 0).collect();
+    args.windows(2)
+        .find(|pair| pair[0] == b"-i" || pair[0] == b"--image")
+        .map(|pair| PathBuf::from(OsStr::from_bytes(pair[1])))
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn has_command(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+// wlroots 系合成器（sway、Hyprland 等）没有统一的壁纸设置，按正在运行的守护进程依次选择
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn detect_wayland_setter() -> Option<Box<dyn WallpaperSetter>> {
+    if has_command("swww") && run_command("swww", &["query"]).is_ok() {
+        return Some(Box::new(SwwwSetter));
+    }
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some()
+        && has_command("hyprctl")
+        && run_command("hyprctl", &["hyprpaper", "listloaded"]).is_ok()
+    {
+        return Some(Box::new(HyprpaperSetter));
+    }
+    if has_command("swaybg") {
+        return Some(Box::new(SwaybgSetter));
+    }
+    None
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn is_wayland_session() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+        || std::env::var("XDG_SESSION_TYPE").map(|session| session == "wayland").unwrap_or(false)
+}
+
+#[cfg(target_os = "macos")]
+pub fn detect_setter() -> Option<Box<dyn WallpaperSetter>> {
+    Some(Box::new(MacosSetter))
+}
+
+#[cfg(target_os = "windows")]
+pub fn detect_setter() -> Option<Box<dyn WallpaperSetter>> {
+    Some(Box::new(WindowsSetter))
+}
+
+// 根据 XDG_CURRENT_DESKTOP 选择后端，例如 "ubuntu:GNOME"、"X-Cinnamon"；
+// 不属于这些桌面环境的 Wayland 会话再尝试 swww/hyprpaper/swaybg
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn detect_setter() -> Option<Box<dyn WallpaperSetter>> {
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default().to_lowercase();
+    let desktops: Vec<&str> = desktop.split(':').collect();
+    let has = |name: &str| desktops.iter().any(|d| d.trim_start_matches("x-") == name);
+
+    if has("kde") {
+        Some(Box::new(KdeSetter))
+    } else if has("xfce") {
+        Some(Box::new(XfceSetter))
+    } else if has("mate") {
+        Some(Box::new(MateSetter))
+    } else if has("cinnamon") {
+        Some(Box::new(GsettingsSetter { schema: "org.cinnamon.desktop.background" }))
+    } else if has("gnome") || has("unity") || has("budgie") || has("pantheon") {
+        Some(Box::new(GsettingsSetter { schema: "org.gnome.desktop.background" }))
+    } else if is_wayland_session() {
+        detect_wayland_setter()
+    } else {
+        None
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn percent_decode_path(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = if bytes[i] == b'%' { encoded.get(i + 1..i + 3) } else { None };
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn percent_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(all(test, not(any(target_os = "windows", target_os = "macos"))))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_encode_path() {
+        assert_eq!(percent_encode_path("/home/user/a_UHD.jpg"), "/home/user/a_UHD.jpg");
+        assert_eq!(percent_encode_path("/home/user/My Pictures/a.jpg"), "/home/user/My%20Pictures/a.jpg");
+        assert_eq!(percent_encode_path("/home/user/壁纸/a#1.jpg"), "/home/user/%E5%A3%81%E7%BA%B8/a%231.jpg");
+    }
+
+    #[test]
+    fn test_percent_decode_path() {
+        assert_eq!(percent_decode_path("/home/user/My%20Pictures/a.jpg"), "/home/user/My Pictures/a.jpg");
+        assert_eq!(percent_decode_path("/home/user/%E5%A3%81%E7%BA%B8"), "/home/user/壁纸");
+        // 不完整的转义原样保留
+        assert_eq!(percent_decode_path("/tmp/100%"), "/tmp/100%");
+        assert_eq!(percent_decode_path("/tmp/%zz"), "/tmp/%zz");
+        let path = "/home/user/My Pictures/壁纸 #1.jpg";
+        assert_eq!(percent_decode_path(&percent_encode_path(path)), path);
+    }
+
+    #[test]
+    fn test_parse_swaybg_image() {
+        let cmdline = b"swaybg\0-m\0fill\0-i\0/home/user/My Pictures/a.jpg\0";
+        assert_eq!(parse_swaybg_image(cmdline), Some(PathBuf::from("/home/user/My Pictures/a.jpg")));
+        assert_eq!(parse_swaybg_image(b"swaybg\0--image\0/tmp/b.jpg\0"), Some(PathBuf::from("/tmp/b.jpg")));
+        assert_eq!(parse_swaybg_image(b"swaybg\0-c\0#000000\0"), None);
+    }
+}